@@ -0,0 +1,31 @@
+//! Feeds arbitrary custom IDs and picked values through the parsing the bot
+//! does on every component click, checking it never panics and never hands
+//! back an index out of bounds
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+#[path = "../../src/custom_id.rs"]
+mod custom_id;
+
+fuzz_target!(|input: (&str, Vec<String>, u8)| {
+    let (id, values, len) = input;
+    let len = usize::from(len);
+
+    if let Some((prefix, _)) = custom_id::flow(id) {
+        assert!(!prefix.contains(':'));
+    }
+    if let Ok((title, _)) = custom_id::field(id) {
+        assert!(!title.contains(':'));
+    }
+
+    let picked = custom_id::indices(&values, len);
+    assert!(picked.iter().all(|&i| i < len));
+    assert!(picked.windows(2).all(|w| w[0] < w[1]));
+    if let Some(first) = values.first().and_then(|v| custom_id::index(v, len)) {
+        assert!(first < len);
+        // Toggling twice gives back what was picked
+        let toggled = custom_id::toggle(&picked, first);
+        assert_eq!(custom_id::toggle(&toggled, first), picked);
+    }
+});
@@ -0,0 +1,223 @@
+//! Queued sender for plain channel messages (public request confirmations, audit posts)
+//!
+//! Interaction flows hand their messages to an [Announcer] instead of posting
+//! directly. A single background task drains the queue, so a burst of completed
+//! requests (e.g. a bulk import) is merged into as few messages as possible and
+//! sent one at a time through twilight's rate limiter. A 429 that slips past the
+//! limiter is waited out and retried rather than failing the flow. Messages
+//! for a channel with a request log thread are posted in the thread.
+use crate::{
+    discord::MAX_MESSAGE_LENGTH, discord_error::DiscordFailure, text, threads::RequestThreads,
+};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+use twilight_http::{Client as HttpClient, api_error::ApiError, error::ErrorType};
+use twilight_model::id::{Id, marker::ChannelMarker};
+
+/// How many times to retry a message that was rate limited before giving up
+const MAX_RATELIMIT_RETRIES: usize = 3;
+
+/// Messages queued beyond this are dropped with a warning rather than growing without bound
+const QUEUE_CAPACITY: usize = 256;
+
+#[derive(Debug)]
+struct Announcement {
+    channel_id: Id<ChannelMarker>,
+    content: String,
+}
+
+#[derive(Debug, Clone)]
+/// Handle for queueing messages to be posted to channels
+pub struct Announcer {
+    tx: mpsc::Sender<Announcement>,
+}
+
+impl Announcer {
+    /// Start the background sender task and return a handle to it
+    pub fn spawn(client: Arc<HttpClient>, threads: Option<Arc<RequestThreads>>) -> Self {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(run(rx, client, threads));
+        Self { tx }
+    }
+
+    /// Queue `content` to be posted in `channel_id`. Delivery is best-effort;
+    /// failures are logged by the sender task, never returned to the caller.
+    pub fn send(&self, channel_id: Id<ChannelMarker>, content: impl Into<String>) {
+        let announcement = Announcement {
+            channel_id,
+            content: content.into(),
+        };
+        if let Err(e) = self.tx.try_send(announcement) {
+            warn!(channel_id = %channel_id, error = %e, "Dropping channel message, send queue unavailable");
+        }
+    }
+}
+
+/// The sender task: wait for a message, then sweep up everything else that
+/// queued behind it and post the batch
+async fn run(
+    mut rx: mpsc::Receiver<Announcement>,
+    client: Arc<HttpClient>,
+    threads: Option<Arc<RequestThreads>>,
+) {
+    while let Some(first) = rx.recv().await {
+        let mut pending = vec![first];
+        while let Ok(next) = rx.try_recv() {
+            pending.push(next);
+        }
+        if pending.len() > 1 {
+            debug!(count = pending.len(), "Batching queued channel messages");
+        }
+
+        for (channel_id, content) in batch(pending) {
+            let Some(threads) = &threads else {
+                post_with_retry(&client, channel_id, &content).await;
+                continue;
+            };
+            let target = threads.target(channel_id).await;
+            let failure = post_with_retry(&client, target, &content).await;
+            if target != channel_id && failure == Some(DiscordFailure::UnknownChannel) {
+                info!(channel_id = %channel_id, thread_id = %target, "Request log thread is gone, starting a new one");
+                threads.forget(channel_id).await;
+                post_with_retry(&client, threads.target(channel_id).await, &content).await;
+            }
+        }
+    }
+}
+
+/// Merge queued messages bound for the same channel, newline separated and
+/// split to fit Discord's message length. Channels keep the order in which
+/// they first appeared, as do messages within a channel. A message too long
+/// by itself is split between its lines, and lines too long are truncated.
+fn batch(pending: Vec<Announcement>) -> Vec<(Id<ChannelMarker>, String)> {
+    let mut batches: Vec<(Id<ChannelMarker>, String)> = Vec::new();
+    for Announcement {
+        channel_id,
+        content,
+    } in pending
+    {
+        let pieces = if content.chars().count() <= MAX_MESSAGE_LENGTH {
+            vec![content]
+        } else {
+            debug!(channel_id = %channel_id, "Splitting a channel message too long to post");
+            content
+                .lines()
+                .map(|line| text::truncate(line, MAX_MESSAGE_LENGTH))
+                .collect()
+        };
+        for piece in pieces {
+            // Only the newest batch for a channel can still take more lines
+            match batches.iter_mut().rev().find(|(id, _)| *id == channel_id) {
+                Some((_, body))
+                    if body.chars().count() + 1 + piece.chars().count() <= MAX_MESSAGE_LENGTH =>
+                {
+                    body.push('\n');
+                    body.push_str(&piece);
+                }
+                // Discord won't post an empty message
+                _ if piece.is_empty() => {}
+                _ => batches.push((channel_id, piece)),
+            }
+        }
+    }
+    batches
+}
+
+/// Post a message, waiting out rate limits. Returns why it couldn't be
+/// posted, when that's a known cause.
+async fn post_with_retry(
+    client: &HttpClient,
+    channel_id: Id<ChannelMarker>,
+    content: &str,
+) -> Option<DiscordFailure> {
+    for attempt in 0..=MAX_RATELIMIT_RETRIES {
+        let Err(e) = client.create_message(channel_id).content(content).await else {
+            return None;
+        };
+
+        if let ErrorType::Response {
+            error: ApiError::Ratelimited(ratelimit),
+            ..
+        } = e.kind()
+            && attempt < MAX_RATELIMIT_RETRIES
+        {
+            debug!(
+                channel_id = %channel_id,
+                retry_after = ratelimit.retry_after,
+                global = ratelimit.global,
+                "Rate limited posting channel message, retrying"
+            );
+            tokio::time::sleep(Duration::from_secs_f64(ratelimit.retry_after.max(0.0))).await;
+            continue;
+        }
+
+        let failure = DiscordFailure::from_http(&e);
+        match &failure {
+            Some(failure) => warn!(
+                channel_id = %channel_id,
+                error = %e,
+                "Could not post a channel message. {}",
+                failure.guidance(Some(channel_id))
+            ),
+            None => warn!(
+                channel_id = %channel_id,
+                error = ?e,
+                "Could not post a channel message. Set public_followup = false \
+                 to disable channel announcements."
+            ),
+        }
+        return failure;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(channel: u64, content: &str) -> Announcement {
+        Announcement {
+            channel_id: Id::new(channel),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn batch_merges_per_channel_in_order() {
+        let batches = batch(vec![msg(1, "a"), msg(2, "b"), msg(1, "c")]);
+        assert_eq!(
+            batches,
+            vec![
+                (Id::new(1), "a\nc".to_string()),
+                (Id::new(2), "b".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn batch_splits_at_message_limit() {
+        let long = "x".repeat(MAX_MESSAGE_LENGTH - 1);
+        let batches = batch(vec![msg(1, &long), msg(1, "y"), msg(1, "z")]);
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].1, long);
+        assert_eq!(batches[1].1, "y\nz");
+    }
+
+    #[test]
+    fn batch_splits_messages_too_long_by_themselves() {
+        let line = "x".repeat(MAX_MESSAGE_LENGTH / 2);
+        let long = format!("{line}\n{line}\n\n{}", "y".repeat(MAX_MESSAGE_LENGTH + 10));
+        let batches = batch(vec![msg(1, &long), msg(1, "z")]);
+        let bodies: Vec<_> = batches.iter().map(|(_, body)| body.as_str()).collect();
+        assert!(
+            bodies
+                .iter()
+                .all(|body| body.chars().count() <= MAX_MESSAGE_LENGTH)
+        );
+        assert_eq!(bodies[0], line);
+        assert_eq!(bodies[1], format!("{line}\n"));
+        assert!(bodies[2].starts_with("yyy") && bodies[2].ends_with('…'));
+        assert_eq!(bodies[3], "z");
+    }
+}
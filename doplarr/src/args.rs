@@ -1,8 +1,169 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Parser)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     #[arg(value_name = "FILE", default_value = "config.toml")]
     pub config_file: Option<PathBuf>,
 }
+
+#[derive(Subcommand, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Walk through connecting Discord and the backends, then write a config
+    Setup {
+        #[arg(value_name = "FILE", default_value = "config.toml")]
+        config_file: PathBuf,
+    },
+    /// Look after the state file the request log is kept in
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+    /// Bring past requests into the request log from elsewhere
+    Import {
+        #[command(subcommand)]
+        command: ImportCommand,
+    },
+}
+
+#[derive(Subcommand, Debug, PartialEq, Eq)]
+pub enum DbCommand {
+    /// Take requests older than the `[retention]` settings allow out of the
+    /// request log, then exit. Run it while the bot is stopped.
+    Prune {
+        #[arg(value_name = "FILE", default_value = "config.toml")]
+        config_file: PathBuf,
+
+        /// Prune requests older than this many months (of 30 days), instead
+        /// of the configured `months`
+        #[arg(long, value_name = "N")]
+        months: Option<u32>,
+
+        /// Delete the pruned requests instead of archiving them
+        #[arg(long)]
+        purge: bool,
+    },
+    /// Take everything about a Discord user out of the request log and its
+    /// archive, then exit. Run it while the bot is stopped.
+    Forget {
+        /// Their Discord user ID
+        #[arg(value_name = "USER_ID")]
+        user_id: u64,
+
+        #[arg(value_name = "FILE", default_value = "config.toml")]
+        config_file: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug, PartialEq, Eq)]
+pub enum ImportCommand {
+    /// Import the request history of the Overseerr/Jellyseerr instance at
+    /// URL into the request log, then exit
+    Seerr(SeerrImport),
+}
+
+#[derive(Args, Debug, PartialEq, Eq)]
+pub struct SeerrImport {
+    #[arg(value_name = "URL")]
+    pub url: String,
+
+    #[arg(value_name = "FILE", default_value = "config.toml")]
+    pub config_file: PathBuf,
+
+    /// API key of the instance
+    #[arg(
+        long,
+        value_name = "KEY",
+        env = "SEERR_API_KEY",
+        hide_env_values = true
+    )]
+    pub api_key: String,
+
+    /// Discord server the imported requests were made for, so they show up in
+    /// its /export-requests
+    #[arg(long, value_name = "ID")]
+    pub guild: Option<u64>,
+
+    /// Media command to log imported movie requests under
+    #[arg(long, value_name = "NAME", default_value = "movie")]
+    pub movie_command: String,
+
+    /// Media command to log imported TV requests under
+    #[arg(long, value_name = "NAME", default_value = "series")]
+    pub series_command: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn setup_is_a_subcommand() {
+        let cli = Cli::try_parse_from(["doplarr", "setup"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Command::Setup {
+                config_file: "config.toml".into()
+            })
+        );
+        let cli = Cli::try_parse_from(["doplarr", "my-config.toml"]).unwrap();
+        assert_eq!(cli.command, None);
+        assert_eq!(cli.config_file, Some("my-config.toml".into()));
+        let cli = Cli::try_parse_from(["doplarr", "db", "prune", "--months", "6"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Command::Db {
+                command: DbCommand::Prune {
+                    config_file: "config.toml".into(),
+                    months: Some(6),
+                    purge: false,
+                }
+            })
+        );
+        let cli = Cli::try_parse_from(["doplarr", "db", "forget", "42"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Command::Db {
+                command: DbCommand::Forget {
+                    user_id: 42,
+                    config_file: "config.toml".into(),
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn seerr_import_is_a_subcommand() {
+        let cli = Cli::try_parse_from([
+            "doplarr",
+            "import",
+            "seerr",
+            "http://overseerr:5055",
+            "--api-key",
+            "KEY",
+            "--guild",
+            "7",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Command::Import {
+                command: ImportCommand::Seerr(SeerrImport {
+                    url: "http://overseerr:5055".into(),
+                    config_file: "config.toml".into(),
+                    api_key: "KEY".into(),
+                    guild: Some(7),
+                    movie_command: "movie".into(),
+                    series_command: "series".into(),
+                })
+            })
+        );
+        // The old top-level flags are gone
+        assert!(
+            Cli::try_parse_from(["doplarr", "--import-seerr", "http://overseerr:5055"]).is_err()
+        );
+    }
+}
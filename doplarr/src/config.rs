@@ -1,30 +1,588 @@
+use crate::config_check;
 use anyhow::Context;
 use radarr_api::models::{MonitorTypes as RadarrMonitor, MovieStatusType};
 use serde::{Deserialize, Serialize};
 use sonarr_api::models::SeriesTypes;
-use std::fs;
+use std::{collections::BTreeMap, fs};
 
 #[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq)]
 pub struct Config {
     pub log_level: Option<String>,
+    /// Where changes made through admin commands (e.g. request bans) are
+    /// saved. Default: doplarr-state.json in the working directory
+    pub state_file: Option<String>,
+    /// How long requests stay in the request log
+    pub retention: Option<Retention>,
     pub public_followup: Option<bool>,
+    /// How the public confirmation is posted in the request's own channel
+    pub followup_style: Option<FollowupStyle>,
+    /// Channels to post public request confirmations to. When absent, the
+    /// confirmation goes to the channel the request was made in.
+    pub followup_channels: Option<Vec<FollowupChannel>>,
+    /// Busy channels whose request messages go into a thread in them
+    pub request_threads: Option<Vec<RequestThread>>,
+    /// Overrides for the messages shown to requesters
+    pub messages: Option<Messages>,
+    /// Named presets of request details, picked with the `profile` option of
+    /// `/request` (e.g. `[profiles.anime]`)
+    pub profiles: Option<BTreeMap<String, Profile>>,
+    /// Themes requesters can file a request under (e.g. "Kids"), recorded
+    /// with the request and tagged on the title in the backend
+    pub categories: Option<Vec<Category>>,
+    /// Tag requested titles in Radarr/Sonarr with the requester's Discord
+    /// username after this prefix (e.g. "req-" tags "req-alice"; "" for the
+    /// bare username). Titles aren't tagged by requester when absent.
+    pub requester_tag_prefix: Option<String>,
+    /// Post a daily digest of upcoming releases
+    pub digest: Option<Digest>,
+    /// Serve resized poster images from the bot instead of linking them directly
+    pub thumbnails: Option<Thumbnails>,
+    /// Spoiler handling for plot overviews, by server or channel
+    pub overviews: Option<Vec<OverviewRule>>,
+    /// Roles (by ID) that see adult search results outside NSFW channels
+    pub nsfw_roles: Option<Vec<u64>>,
+    /// Roles (by ID) that can ask for better copies of titles in the library
+    /// with `/upgrade`. The command isn't offered when absent.
+    pub upgrade_roles: Option<Vec<u64>>,
+    /// Commands or webhooks to run around requests
+    pub hooks: Option<Vec<Hook>>,
+    /// Which requesters need an admin's approval, by role
+    pub approval: Option<Approval>,
+    /// How requests' status is kept current after they're made
+    pub tracking: Option<Tracking>,
+    /// Extra detail shown on search results
+    pub enrichment: Option<Enrichment>,
+    /// Warnings about request flows piling up
+    pub watchdog: Option<Watchdog>,
+    /// Similar titles offered under a successful request
+    pub suggestions: Option<Suggestions>,
+    /// Movie search through TMDB while Radarr's own lookup is failing
+    pub search_fallback: Option<SearchFallback>,
+    /// Post a health report to admins every time the bot starts
+    pub startup_report: Option<StartupReport>,
+    /// Optional subsystems to start. When absent, every configured one
+    /// starts; when set, the sections of unlisted ones are ignored.
+    pub features: Option<Vec<Feature>>,
+    /// Make backend calls slow or fail on purpose, for testing. Never set
+    /// this on a bot people use.
+    pub debug: Option<FaultInjection>,
     pub discord_token: String,
     pub backends: Vec<Backend>,
+    /// Problems found in the file it was loaded from, which didn't stop it
+    /// loading (e.g. unknown keys)
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+/// A channel that public request confirmations are fanned out to
+pub struct FollowupChannel {
+    pub channel_id: u64,
+    /// Only announce requests made through these media commands (e.g. "movie").
+    /// When absent, every request is announced here.
+    pub media: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+/// A channel whose request confirmations and other bot posts go into one
+/// long-lived thread the bot keeps in it
+pub struct RequestThread {
+    pub channel_id: u64,
+    /// What the thread is called. Default: "Request log"
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+/// Something to run at one point of every request, given the request as JSON
+pub struct Hook {
+    pub on: HookEvent,
+    #[serde(flatten)]
+    pub action: HookAction,
+    /// How long the hook gets to finish (default: 10)
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// When a [Hook] runs
+pub enum HookEvent {
+    /// Before the request goes to the backend; failing cancels it
+    BeforeSubmit,
+    /// After the backend accepted the request
+    Success,
+    /// After the backend rejected the request or errored
+    Failure,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+#[serde(untagged)]
+/// What a [Hook] does
+pub enum HookAction {
+    /// Run with `sh -c`, the request on stdin
+    Command { command: String },
+    /// POST the request to this URL
+    Webhook { url: String },
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+/// Graduated trust for requesters: roles whose requests go straight through,
+/// roles whose requests wait for an admin, and roles that can only suggest
+pub struct Approval {
+    /// Where requests awaiting approval, and suggestions, are posted for
+    /// admins, for a bot in a single server. Use `channels` instead with more.
+    pub channel_id: Option<u64>,
+    /// Each server's own approval channel, so one server's requests never
+    /// reach another's admins. Servers not listed can't have requests approved.
+    pub channels: Option<Vec<ApprovalChannel>>,
+    /// Tier of requesters with none of the roles below (default: auto)
+    pub default: Option<ApprovalTier>,
+    pub auto_roles: Option<Vec<u64>>,
+    pub required_roles: Option<Vec<u64>>,
+    pub suggest_roles: Option<Vec<u64>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+/// A server's approval channel
+pub struct ApprovalChannel {
+    pub guild_id: u64,
+    pub channel_id: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// How much a requester is trusted to request on their own
+pub enum ApprovalTier {
+    /// Requests are sent to the backend right away
+    #[default]
+    Auto,
+    /// Requests wait for an admin to approve them
+    Required,
+    /// Requests are only passed on to the admins, who add them by hand
+    Suggest,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone)]
+/// Following requests through the download pipeline, by polling the backends'
+/// download queues and, optionally, from Radarr/Sonarr webhooks
+pub struct Tracking {
+    /// How often to check on requests that aren't available yet, in minutes
+    /// (default: 10)
+    pub poll_minutes: Option<u64>,
+    /// Address to receive Radarr/Sonarr webhooks on, e.g. "0.0.0.0:8181".
+    /// No webhook server runs when absent.
+    pub webhook_listen: Option<String>,
+    /// Password the Radarr/Sonarr Webhook connections send (any username);
+    /// required with `webhook_listen`, and other webhooks are turned away
+    pub webhook_password: Option<String>,
+    /// Channel to tell admins about requests that missed their need-by date.
+    /// Overdue titles are still searched for again when absent.
+    pub admin_channel_id: Option<u64>,
+    /// Hours a request can go with nothing grabbed before the backend is
+    /// asked to search for it again, and again after as long once more. No
+    /// searches are triggered when absent.
+    pub research_hours: Option<u64>,
+    /// How many times each request is searched for again at most (default: 3)
+    pub research_limit: Option<u32>,
+    /// Minutes a download can go without progress before admins are told in
+    /// `admin_channel_id`, along with downloads the backend flags with an
+    /// error. No download alerts when absent.
+    pub stalled_minutes: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// An optional subsystem that can be switched on in `features`
+pub enum Feature {
+    /// `[approval]`
+    Approval,
+    /// `[[hooks]]`
+    Hooks,
+    /// The webhook server of `[tracking]`
+    Webhooks,
+    /// `[digest]`
+    Digest,
+    /// `[thumbnails]`
+    Thumbnails,
+    /// `[enrichment]`
+    Enrichment,
+    /// `[suggestions]`
+    Suggestions,
+    /// `[search_fallback]`
+    SearchFallback,
+    /// `[startup_report]`
+    StartupReport,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone)]
+/// Artificial latency and random failures added to backend calls
+pub struct FaultInjection {
+    /// Delay every backend call by this much, in milliseconds (default: 0)
+    pub latency_ms: Option<u64>,
+    /// Delay calls by up to this much more, picked at random per call, in
+    /// milliseconds (default: 0)
+    pub jitter_ms: Option<u64>,
+    /// Fail this share of backend calls, in percent (default: 0)
+    pub failure_percent: Option<u8>,
+    /// Only affect these backend instances, by name (default: all of them)
+    pub backends: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone)]
+/// Extra detail added to search results, whichever backend found them
+pub struct Enrichment {
+    /// Show each result's parental rating (e.g. "PG-13") when the backend
+    /// knows it (default: false)
+    pub parental_ratings: Option<bool>,
+    /// TMDB API key (v3), to show each result's TMDB user rating
+    pub tmdb_api_key: Option<String>,
+    /// Mark results that are already in a Plex library
+    pub plex: Option<Plex>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+/// A Plex server whose libraries search results are checked against
+pub struct Plex {
+    /// e.g. "http://plex:32400"
+    pub url: String,
+    /// Plex token (X-Plex-Token) of an account that can see the libraries
+    pub token: String,
+    /// How often to re-read the libraries, in minutes (default: 30)
+    pub refresh_minutes: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+/// A daily post of what's airing and releasing, from the backends' calendars
+pub struct Digest {
+    pub channel_id: u64,
+    /// Time of day to post, as "HH:MM" in UTC (or in `utc_offset`)
+    pub time: String,
+    /// How many days ahead to cover, counting today (default: 7)
+    pub days: Option<u8>,
+    /// The server's time zone as an offset from UTC, e.g. "-05:00", for the
+    /// posting time and which day things air on. Air times are shown in each
+    /// reader's own time zone regardless.
+    pub utc_offset: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone)]
+/// When to warn that request flows are piling up
+pub struct Watchdog {
+    /// Where to post the warnings, besides the log
+    pub channel_id: Option<u64>,
+    /// Warn when more flows than this are in progress at once (default: 50)
+    pub max_in_progress: Option<usize>,
+    /// Warn when more flows than this are abandoned in one minute (default: 10)
+    pub max_abandoned: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+/// Taking old requests out of the request log, so the state file doesn't grow
+/// without bound
+pub struct Retention {
+    /// Requests older than this many months (of 30 days) leave the log
+    pub months: u32,
+    /// What happens to them (default: archive)
+    pub mode: Option<RetentionMode>,
+    /// Where archived requests are appended, one JSON object per line
+    /// (default: the state file's name, ending in .archive.jsonl)
+    pub archive_file: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// What [Retention] does with requests past their time
+pub enum RetentionMode {
+    /// Move them to the archive file
+    #[default]
+    Archive,
+    /// Delete them
+    Purge,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+/// Where the report on backends, command registration, and config warnings
+/// goes once the bot has connected
+pub struct StartupReport {
+    pub channel_id: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+/// "You might also like" buttons on the success card, each requesting a
+/// title TMDB recommends based on the one just requested
+pub struct Suggestions {
+    /// TMDB API key (v3), where the recommendations come from
+    pub tmdb_api_key: String,
+    /// How many titles to suggest, 3 to 5 (default: 3)
+    pub count: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+/// Searching TMDB instead when a Radarr instance answers but its title lookup
+/// fails, with the requests queued until Radarr can add them
+pub struct SearchFallback {
+    /// TMDB API key (v3), where the fallback results come from
+    pub tmdb_api_key: String,
+    /// How often queued requests are tried again, in minutes (default: 5)
+    pub retry_minutes: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+/// How a public request confirmation is posted
+pub enum FollowupStyle {
+    /// A message from the bot, which needs "Send Messages" in the channel
+    #[default]
+    Message,
+    /// A public reply to the request's interaction, which works without it
+    Interaction,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+/// How a request card shows the plot overview
+pub enum OverviewMode {
+    #[default]
+    Show,
+    /// Behind a spoiler tag, revealed by clicking it
+    Spoiler,
+    /// Not at all
+    Hide,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+/// Where an [OverviewMode] applies. A channel rule beats a server rule, which
+/// beats a rule with neither (the default for everywhere else).
+pub struct OverviewRule {
+    pub guild_id: Option<u64>,
+    pub channel_id: Option<u64>,
+    pub mode: OverviewMode,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+/// The bot's poster proxy, which must be reachable by Discord
+pub struct Thumbnails {
+    /// Address to listen on, e.g. "0.0.0.0:8180"
+    pub listen: String,
+    /// Public base URL the listener is reachable at, e.g. "https://doplarr.example.com"
+    pub public_url: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone)]
+/// A preset of request details, applied before the request form is shown.
+/// Presetting every field a request asks for skips the form entirely.
+pub struct Profile {
+    /// Only offer this profile on these media commands (e.g. "series").
+    /// When absent, it's offered on every command.
+    pub media: Option<Vec<String>>,
+    /// Field to preset (`root_folder`, `quality_profile`, `language_profile`,
+    /// `series_type`, `season_folder`, `monitor`, `availability`, `tags`, or
+    /// `is_4k`) to the option to pick, as labelled in Discord (e.g.
+    /// "/tv/anime", "HD-1080p", "Anime")
+    #[serde(flatten)]
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Profile {
+    pub fn applies_to(&self, media: &str) -> bool {
+        self.media
+            .as_ref()
+            .is_none_or(|m| m.iter().any(|x| x == media))
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+/// A category offered on the request form
+pub struct Category {
+    /// Shown to requesters and recorded with the request, e.g. "Family Movie Night"
+    pub name: String,
+    /// Tag put on the title in Radarr/Sonarr. Default: the name in lowercase,
+    /// with dashes for anything but letters and digits ("family-movie-night")
+    pub tag: Option<String>,
+    /// Only offer this category on these media commands (e.g. "movie").
+    /// When absent, it's offered on every command.
+    pub media: Option<Vec<String>>,
+}
+
+/// `text` as a Radarr/Sonarr tag label: lowercase, with dashes for anything
+/// but letters and digits ("Family Movie Night" is "family-movie-night")
+pub fn tag_label(text: &str) -> String {
+    let slug: String = text
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    slug.split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+impl Category {
+    pub fn tag(&self) -> String {
+        match &self.tag {
+            Some(tag) => tag.clone(),
+            None => tag_label(&self.name),
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
 pub struct Backend {
     pub media: String,
+    /// Identifies this backend instance in logs and admin commands (e.g.
+    /// "radarr-4k"). Defaults to the media command name.
+    pub name: Option<String>,
+    /// Only serve these guilds (server IDs), e.g. to send a family server's
+    /// requests to a family Radarr. When absent, the backend serves every
+    /// guild that has no backend of its own for the media command.
+    pub guilds: Option<Vec<u64>>,
     pub config: BackendConfig,
+    /// When the backend's server is down for scheduled maintenance. Requests
+    /// to it then stop with a message saying when it'll be back.
+    pub maintenance: Option<Vec<MaintenanceWindow>>,
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
+/// A recurring time a backend is down for maintenance, in UTC
+pub struct MaintenanceWindow {
+    /// The days it starts on, e.g. ["sun"]. Default: every day
+    pub days: Option<Vec<String>>,
+    /// "HH:MM"
+    pub start: String,
+    /// "HH:MM", earlier than `start` for a window running past midnight
+    pub end: String,
+}
+
+impl Backend {
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.media)
+    }
+
+    /// What kind of titles it serves, `None` when it can serve either
+    pub fn kind(&self) -> Option<MediaKind> {
+        match &self.config {
+            BackendConfig::Radarr { .. } => Some(MediaKind::Movie),
+            BackendConfig::Sonarr { .. } => Some(MediaKind::Tv),
+            BackendConfig::Seerr { media_filter, .. } => *media_filter,
+            BackendConfig::Plugin { .. } | BackendConfig::Combined { .. } => None,
+        }
+    }
+}
+
+pub const DEFAULT_TIMEOUT_MESSAGE: &str = "Interaction timed out, please try again";
+pub const DEFAULT_EARLY_STOP_MESSAGE: &str = "Already requested - nothing more to add";
+pub const DEFAULT_NO_RESULTS_MESSAGE: &str = "No results";
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone)]
+/// Custom text for the messages that end a request flow early. Each is a
+/// template: `{user}` becomes a mention of the requester, `{media}` the media
+/// command (e.g. "movie"), and `{query}` the search text. `{media}` and
+/// `{query}` are empty when the flow they belonged to is already gone.
+pub struct Messages {
+    /// Shown when the requester doesn't finish in time
+    pub timeout: Option<String>,
+    /// Shown when the selected media has already been requested
+    pub early_stop: Option<String>,
+    /// Shown when the search finds nothing
+    pub no_results: Option<String>,
+    /// Translations by Discord locale (e.g. "de", "pt-BR"), picked by the
+    /// requester's Discord language, then the server's. Messages a locale
+    /// leaves out fall back to the ones above.
+    pub locales: Option<BTreeMap<String, Messages>>,
+}
+
+/// Values substituted into [Messages] templates
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MessageVars<'a> {
+    pub user_id: Option<u64>,
+    pub media: &'a str,
+    pub query: &'a str,
+}
+
+impl Messages {
+    /// The messages to use for the first of `locales` (most preferred first)
+    /// that has them. A regional locale ("pt-BR") also matches its language
+    /// ("pt").
+    pub fn localized(&self, locales: &[&str]) -> Messages {
+        let candidates: Vec<_> = locales
+            .iter()
+            .flat_map(|l| [*l, l.split('-').next().unwrap_or(l)])
+            .filter_map(|l| self.locales.as_ref()?.get(l))
+            .chain([self])
+            .collect();
+        let pick = |field: fn(&Messages) -> &Option<String>| {
+            candidates.iter().find_map(|m| field(m).clone())
+        };
+        Messages {
+            timeout: pick(|m| &m.timeout),
+            early_stop: pick(|m| &m.early_stop),
+            no_results: pick(|m| &m.no_results),
+            locales: None,
+        }
+    }
+
+    pub fn timeout(&self, vars: MessageVars) -> String {
+        render_template(
+            self.timeout.as_deref().unwrap_or(DEFAULT_TIMEOUT_MESSAGE),
+            vars,
+        )
+    }
+
+    pub fn early_stop(&self, vars: MessageVars) -> String {
+        render_template(
+            self.early_stop
+                .as_deref()
+                .unwrap_or(DEFAULT_EARLY_STOP_MESSAGE),
+            vars,
+        )
+    }
+
+    pub fn no_results(&self, vars: MessageVars) -> String {
+        render_template(
+            self.no_results
+                .as_deref()
+                .unwrap_or(DEFAULT_NO_RESULTS_MESSAGE),
+            vars,
+        )
+    }
+}
+
+/// Fill in the `{user}`, `{media}`, and `{query}` placeholders of a message
+/// template. Any other text, including unknown `{...}`, is left as-is.
+fn render_template(template: &str, vars: MessageVars) -> String {
+    let user = vars
+        .user_id
+        .map(|id| format!("<@{id}>"))
+        .unwrap_or_default();
+    template
+        .replace("{user}", &user)
+        .replace("{media}", vars.media)
+        .replace("{query}", vars.query)
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
 #[serde(rename_all = "lowercase")]
 pub enum MediaKind {
     Movie,
     Tv,
 }
 
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+/// Something about a search result shown under its title in the results menu
+pub enum ResultField {
+    /// Release year, or first air year
+    Year,
+    /// e.g. "Released" or "Continuing"
+    Status,
+    /// The series' network, or the movie's studio
+    Network,
+    /// The movie's runtime, or a series' episode runtime
+    Runtime,
+    /// The first two genres
+    Genres,
+}
+
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
 /// All of the backend-specific configuration, passed to the backend constructors
 pub enum BackendConfig {
@@ -35,19 +593,36 @@ pub enum BackendConfig {
         quality_profile: Option<String>,
         rootfolder: Option<String>,
         minimum_availability: Option<MovieStatusType>,
+        /// Offer monitoring nothing (default: true). Picking it asks the
+        /// requester to confirm, as the movie then never downloads.
+        allow_unmonitored: Option<bool>,
+        /// Offer Radarr's tags on the request form (default: false)
+        allow_tags: Option<bool>,
+        /// What search results show under their titles, up to two fields
+        /// (default: year)
+        result_fields: Option<Vec<ResultField>>,
     },
     Sonarr {
         url: String,
         api_key: String,
         quality_profile: Option<String>,
+        /// Language profile to add series with (Sonarr v3 only; v4 picks
+        /// languages through custom formats)
+        language_profile: Option<String>,
         rootfolder: Option<String>,
         series_type: Option<SeriesTypes>,
         season_folders: Option<bool>,
-        /// Offer Season 0 (specials) in the season picker (default: false)
+        /// Ask whether to include Season 0 (specials), as a yes/no field of
+        /// its own (default: false)
         allow_specials: Option<bool>,
         /// Offer an "All Seasons" option that monitors all current and future
         /// seasons (default: true)
         allow_all_seasons: Option<bool>,
+        /// Offer Sonarr's tags on the request form (default: false)
+        allow_tags: Option<bool>,
+        /// What search results show under their titles, up to two fields
+        /// (default: year)
+        result_fields: Option<Vec<ResultField>>,
     },
     Seerr {
         url: String,
@@ -64,6 +639,21 @@ pub enum BackendConfig {
         /// Offer an "All Seasons" option in the season picker (default: true)
         allow_all_seasons: Option<bool>,
     },
+    /// An external program speaking doplarr's plugin protocol over stdio
+    Plugin {
+        /// Program to run, looked up on PATH when not a path
+        command: String,
+        args: Option<Vec<String>>,
+        /// How long the plugin gets to answer each call (default: 30)
+        timeout_secs: Option<u64>,
+    },
+    /// Several other backends searched as one: results from all of them are
+    /// merged, and each request goes to the instance its result came from
+    Combined {
+        /// Names of the backends to search, in order of preference when they
+        /// find the same title
+        backends: Vec<String>,
+    },
 }
 
 /// Starter config written when no config file exists and no migration
@@ -255,11 +845,258 @@ fn generate_from_env(is_set: impl Fn(&str) -> bool) -> Option<String> {
 }
 
 impl Config {
+    /// The tag marking a title as requested by the user with this Discord
+    /// username, when `requester_tag_prefix` is set
+    pub fn requester_tag(&self, username: &str) -> Option<String> {
+        let prefix = self.requester_tag_prefix.as_ref()?;
+        Some(tag_label(&format!("{prefix}{username}")))
+    }
+
+    /// The configured followup channels that should announce requests for
+    /// the given media command: `None` when none are configured, and empty
+    /// when none match.
+    pub fn followup_channels_for(&self, media: &str) -> Option<Vec<u64>> {
+        let channels = self.followup_channels.as_ref()?;
+        Some(
+            channels
+                .iter()
+                .filter(|c| {
+                    c.media
+                        .as_ref()
+                        .is_none_or(|kinds| kinds.iter().any(|k| k == media))
+                })
+                .map(|c| c.channel_id)
+                .collect(),
+        )
+    }
+
+    /// Whether the bot's posts in this channel go into a request log thread
+    pub fn has_request_thread(&self, channel_id: u64) -> bool {
+        self.request_threads
+            .iter()
+            .flatten()
+            .any(|t| t.channel_id == channel_id)
+    }
+
+    /// Every channel the bot is configured to post in on its own, outside of
+    /// interactions (followups, the digest, and approvals)
+    pub fn posting_channels(&self) -> Vec<u64> {
+        let mut channels: Vec<u64> = self
+            .followup_channels
+            .iter()
+            .flatten()
+            .map(|c| c.channel_id)
+            .chain(self.digest.as_ref().map(|d| d.channel_id))
+            .chain(self.approval.iter().flat_map(|a| {
+                a.channels
+                    .iter()
+                    .flatten()
+                    .map(|c| c.channel_id)
+                    .chain(a.channel_id)
+            }))
+            .chain(self.watchdog.as_ref().and_then(|w| w.channel_id))
+            .chain(self.startup_report.as_ref().map(|r| r.channel_id))
+            .chain(self.tracking.as_ref().and_then(|t| t.admin_channel_id))
+            .collect();
+        channels.sort_unstable();
+        channels.dedup();
+        channels
+    }
+
+    /// The approval tier of a requester with these roles. The most trusting
+    /// tier among their roles wins; everyone is trusted when `[approval]`
+    /// isn't configured.
+    pub fn approval_tier(&self, roles: &[u64]) -> ApprovalTier {
+        let Some(approval) = &self.approval else {
+            return ApprovalTier::Auto;
+        };
+        let has_any = |tier_roles: &Option<Vec<u64>>| {
+            tier_roles.iter().flatten().any(|role| roles.contains(role))
+        };
+        if has_any(&approval.auto_roles) {
+            ApprovalTier::Auto
+        } else if has_any(&approval.required_roles) {
+            ApprovalTier::Required
+        } else if has_any(&approval.suggest_roles) {
+            ApprovalTier::Suggest
+        } else {
+            approval.default.unwrap_or_default()
+        }
+    }
+
+    /// Where requests from `guild_id` awaiting approval are posted: the
+    /// server's own channel, or the one channel when servers don't have their own
+    pub fn approval_channel(&self, guild_id: Option<u64>) -> Option<u64> {
+        let approval = self.approval.as_ref()?;
+        match &approval.channels {
+            Some(channels) => channels
+                .iter()
+                .find(|c| Some(c.guild_id) == guild_id)
+                .map(|c| c.channel_id),
+            None => approval.channel_id,
+        }
+    }
+
+    /// How overviews are shown for a request made in `channel_id`
+    pub fn overview_mode(&self, guild_id: Option<u64>, channel_id: u64) -> OverviewMode {
+        self.overviews
+            .iter()
+            .flatten()
+            .filter_map(|r| {
+                let specificity = match (r.channel_id, r.guild_id) {
+                    (Some(c), _) if c == channel_id => 2,
+                    (Some(_), _) => return None,
+                    (None, Some(g)) if Some(g) == guild_id => 1,
+                    (None, Some(_)) => return None,
+                    (None, None) => 0,
+                };
+                Some((specificity, r.mode))
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map_or(OverviewMode::Show, |(_, mode)| mode)
+    }
+
+    /// What kind of titles a media command serves, `None` when it can serve
+    /// either (or isn't configured)
+    pub fn media_kind(&self, media: &str) -> Option<MediaKind> {
+        let backend = self.backends.iter().find(|b| b.media == media)?;
+        let BackendConfig::Combined { backends } = &backend.config else {
+            return backend.kind();
+        };
+        // Combined backends serve the kind all of their members agree on
+        let mut kinds = backends.iter().map(|name| {
+            self.backends
+                .iter()
+                .find(|b| b.name() == name)
+                .and_then(Backend::kind)
+        });
+        let first = kinds.next().flatten()?;
+        kinds.all(|k| k == Some(first)).then_some(first)
+    }
+
+    /// The profiles offered on the given media command, by name
+    pub fn profiles_for<'a>(
+        &'a self,
+        media: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a Profile)> {
+        self.profiles
+            .iter()
+            .flatten()
+            .filter(move |(_, p)| p.applies_to(media))
+            .map(|(name, p)| (name.as_str(), p))
+    }
+
+    /// The categories offered on the given media command
+    pub fn categories_for(&self, media: &str) -> Vec<Category> {
+        self.categories
+            .iter()
+            .flatten()
+            .filter(|c| {
+                c.media
+                    .as_ref()
+                    .is_none_or(|kinds| kinds.iter().any(|k| k == media))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Drop the sections of subsystems left out of `features`, so they aren't
+    /// started, returning a warning for each one that was configured
+    fn disable_unlisted_features(&mut self) -> Vec<String> {
+        let Some(features) = self.features.clone() else {
+            return Vec::new();
+        };
+        let mut disabled = Vec::new();
+        let mut gate = |feature: Feature, name: &str, configured: bool| {
+            let off = configured && !features.contains(&feature);
+            if off {
+                disabled.push(format!(
+                    "`{name}` is configured but isn't listed in `features`, so it's off"
+                ));
+            }
+            off
+        };
+        if gate(Feature::Approval, "approval", self.approval.is_some()) {
+            self.approval = None;
+        }
+        if gate(Feature::Hooks, "hooks", self.hooks.is_some()) {
+            self.hooks = None;
+        }
+        if let Some(tracking) = &mut self.tracking
+            && gate(
+                Feature::Webhooks,
+                "webhooks",
+                tracking.webhook_listen.is_some(),
+            )
+        {
+            tracking.webhook_listen = None;
+        }
+        if gate(Feature::Digest, "digest", self.digest.is_some()) {
+            self.digest = None;
+        }
+        if gate(Feature::Thumbnails, "thumbnails", self.thumbnails.is_some()) {
+            self.thumbnails = None;
+        }
+        if gate(Feature::Enrichment, "enrichment", self.enrichment.is_some()) {
+            self.enrichment = None;
+        }
+        if gate(
+            Feature::Suggestions,
+            "suggestions",
+            self.suggestions.is_some(),
+        ) {
+            self.suggestions = None;
+        }
+        if gate(
+            Feature::SearchFallback,
+            "search_fallback",
+            self.search_fallback.is_some(),
+        ) {
+            self.search_fallback = None;
+        }
+        if gate(
+            Feature::StartupReport,
+            "startup_report",
+            self.startup_report.is_some(),
+        ) {
+            self.startup_report = None;
+        }
+        disabled
+    }
+
     /// Parse a config from a TOML string, expanding `${VAR}` references first.
-    fn from_toml_str(content: &str, source: &str) -> anyhow::Result<Self> {
+    /// Every problem in the file is reported at once: keys nothing reads as
+    /// warnings on stderr, or alongside the parse error when it doesn't load.
+    pub(crate) fn from_toml_str(content: &str, source: &str) -> anyhow::Result<Self> {
         let expanded = expand_env_vars(content)
             .with_context(|| format!("Failed to expand environment variables in {source}"))?;
-        toml::from_str(&expanded).with_context(|| format!("Failed to parse TOML in {source}"))
+        let problems = toml::from_str(&expanded)
+            .map(|doc| config_check::check(&doc))
+            .unwrap_or_default();
+        match toml::from_str::<Self>(&expanded) {
+            Ok(mut config) => {
+                let mut warnings: Vec<_> = problems.iter().map(ToString::to_string).collect();
+                warnings.extend(config.disable_unlisted_features());
+                for warning in &warnings {
+                    eprintln!("Warning: {source}: {warning}");
+                }
+                config.warnings = warnings;
+                Ok(config)
+            }
+            Err(e) if problems.is_empty() => {
+                Err(e).with_context(|| format!("Failed to parse TOML in {source}"))
+            }
+            Err(e) => {
+                let list: Vec<_> = problems.iter().map(|p| format!("  - {p}")).collect();
+                Err(e).with_context(|| {
+                    format!(
+                        "Failed to load {source}, which has {} problem(s):\n{}",
+                        problems.len(),
+                        list.join("\n")
+                    )
+                })
+            }
+        }
     }
 
     pub fn from_file(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
@@ -332,6 +1169,8 @@ mod tests {
             discord_token: "abc123".to_string(),
             backends: vec![Backend {
                 media: "movie".to_string(),
+                name: None,
+                guilds: None,
                 config: BackendConfig::Radarr {
                     url: "http://1.2.3.4:7878".to_string(),
                     api_key: "abc123".to_string(),
@@ -339,10 +1178,39 @@ mod tests {
                     rootfolder: Some("/storage/movies".to_string()),
                     minimum_availability: Some(MovieStatusType::Announced),
                     quality_profile: None,
+                    allow_unmonitored: None,
+                    allow_tags: None,
+                    result_fields: None,
                 },
+                maintenance: None,
             }],
             log_level: None,
+            state_file: None,
+            retention: None,
             public_followup: None,
+            followup_style: None,
+            followup_channels: None,
+            request_threads: None,
+            messages: None,
+            profiles: None,
+            categories: None,
+            requester_tag_prefix: None,
+            digest: None,
+            thumbnails: None,
+            overviews: None,
+            nsfw_roles: None,
+            upgrade_roles: None,
+            hooks: None,
+            approval: None,
+            tracking: None,
+            enrichment: None,
+            watchdog: None,
+            suggestions: None,
+            search_fallback: None,
+            startup_report: None,
+            features: None,
+            debug: None,
+            warnings: Vec::new(),
         };
 
         assert_eq!(config, expected);
@@ -369,6 +1237,8 @@ mod tests {
             discord_token: "abc123".to_string(),
             backends: vec![Backend {
                 media: "media".to_string(),
+                name: None,
+                guilds: None,
                 config: BackendConfig::Seerr {
                     url: "http://1.2.3.4:5055".to_string(),
                     api_key: "abc123".to_string(),
@@ -377,14 +1247,445 @@ mod tests {
                     media_filter: None,
                     allow_all_seasons: None,
                 },
+                maintenance: None,
             }],
             log_level: None,
+            state_file: None,
+            retention: None,
             public_followup: None,
+            followup_style: None,
+            followup_channels: None,
+            request_threads: None,
+            messages: None,
+            profiles: None,
+            categories: None,
+            requester_tag_prefix: None,
+            digest: None,
+            thumbnails: None,
+            overviews: None,
+            nsfw_roles: None,
+            upgrade_roles: None,
+            hooks: None,
+            approval: None,
+            tracking: None,
+            enrichment: None,
+            watchdog: None,
+            suggestions: None,
+            search_fallback: None,
+            startup_report: None,
+            features: None,
+            debug: None,
+            warnings: Vec::new(),
         };
 
         assert_eq!(config, expected);
     }
 
+    #[test]
+    fn backend_name_defaults_to_media() {
+        let config: Config = toml::from_str(
+            r#"
+           discord_token = "abc123"
+
+           [[backends]]
+           media = "movie"
+           [backends.config.Radarr]
+           url = "http://radarr:7878"
+           api_key = "abc123"
+
+           [[backends]]
+           media = "movie_4k"
+           name = "radarr-4k"
+           [backends.config.Radarr]
+           url = "http://radarr-4k:7878"
+           api_key = "abc123"
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.backends[0].name(), "movie");
+        assert_eq!(config.backends[1].name(), "radarr-4k");
+    }
+
+    #[test]
+    fn sonarr_instances_keep_their_own_settings() {
+        let config: Config = toml::from_str(
+            r#"
+           discord_token = "abc123"
+
+           [[backends]]
+           media = "series"
+           [backends.config.Sonarr]
+           url = "http://sonarr:8989"
+           api_key = "abc123"
+           quality_profile = "WEB-1080p"
+           rootfolder = "/tv"
+
+           [[backends]]
+           media = "anime"
+           name = "sonarr-anime"
+           [backends.config.Sonarr]
+           url = "http://sonarr-anime:8989"
+           api_key = "def456"
+           quality_profile = "Anime"
+           rootfolder = "/anime"
+           series_type = "anime"
+        "#,
+        )
+        .unwrap();
+
+        let settings: Vec<_> = config
+            .backends
+            .iter()
+            .map(|b| match &b.config {
+                BackendConfig::Sonarr {
+                    quality_profile,
+                    rootfolder,
+                    series_type,
+                    ..
+                } => (
+                    b.name(),
+                    quality_profile.as_deref(),
+                    rootfolder.as_deref(),
+                    *series_type,
+                ),
+                _ => panic!("expected Sonarr"),
+            })
+            .collect();
+        assert_eq!(
+            settings,
+            [
+                ("series", Some("WEB-1080p"), Some("/tv"), None),
+                (
+                    "sonarr-anime",
+                    Some("Anime"),
+                    Some("/anime"),
+                    Some(SeriesTypes::Anime)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn followup_channels_route_by_media() {
+        let config: Config = toml::from_str(
+            r#"
+           discord_token = "abc123"
+           backends = []
+           followup_style = "interaction"
+
+           [[followup_channels]]
+           channel_id = 1
+
+           [[followup_channels]]
+           channel_id = 2
+           media = ["movie"]
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.followup_channels_for("movie"), Some(vec![1, 2]));
+        assert_eq!(config.followup_channels_for("series"), Some(vec![1]));
+        assert_eq!(Config::default().followup_channels_for("movie"), None);
+        assert_eq!(config.posting_channels(), vec![1, 2]);
+        assert_eq!(config.followup_style, Some(FollowupStyle::Interaction));
+    }
+
+    #[test]
+    fn followup_channels_that_dont_match_announce_nowhere() {
+        let config: Config = toml::from_str(
+            r#"
+           discord_token = "abc123"
+           backends = []
+
+           [[followup_channels]]
+           channel_id = 2
+           media = ["movie"]
+        "#,
+        )
+        .unwrap();
+
+        // Not the request's own channel, which is only for when none are set
+        assert_eq!(config.followup_channels_for("series"), Some(vec![]));
+    }
+
+    #[test]
+    fn approval_channels_are_per_server() {
+        let config: Config = toml::from_str(
+            r#"
+           discord_token = "abc123"
+           backends = []
+
+           [approval]
+           [[approval.channels]]
+           guild_id = 1
+           channel_id = 10
+
+           [[approval.channels]]
+           guild_id = 2
+           channel_id = 20
+        "#,
+        )
+        .unwrap();
+        assert_eq!(config.approval_channel(Some(1)), Some(10));
+        assert_eq!(config.approval_channel(Some(2)), Some(20));
+        // Never another server's channel
+        assert_eq!(config.approval_channel(Some(3)), None);
+        assert_eq!(config.approval_channel(None), None);
+        assert_eq!(config.posting_channels(), vec![10, 20]);
+
+        let single: Config = toml::from_str(
+            r#"
+           discord_token = "abc123"
+           backends = []
+
+           [approval]
+           channel_id = 10
+        "#,
+        )
+        .unwrap();
+        assert_eq!(single.approval_channel(Some(3)), Some(10));
+        assert_eq!(Config::default().approval_channel(Some(1)), None);
+    }
+
+    #[test]
+    fn overview_mode_prefers_the_most_specific_rule() {
+        let config: Config = toml::from_str(
+            r#"
+           discord_token = "abc123"
+           backends = []
+
+           [[overviews]]
+           mode = "spoiler"
+
+           [[overviews]]
+           guild_id = 1
+           mode = "hide"
+
+           [[overviews]]
+           channel_id = 10
+           mode = "show"
+           "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.overview_mode(Some(1), 10), OverviewMode::Show);
+        assert_eq!(config.overview_mode(Some(1), 11), OverviewMode::Hide);
+        assert_eq!(config.overview_mode(Some(2), 10), OverviewMode::Show);
+        assert_eq!(config.overview_mode(Some(2), 11), OverviewMode::Spoiler);
+        assert_eq!(config.overview_mode(None, 11), OverviewMode::Spoiler);
+        assert_eq!(
+            Config::default().overview_mode(Some(1), 10),
+            OverviewMode::Show
+        );
+    }
+
+    #[test]
+    fn profiles_parse_fields_and_filter_by_media() {
+        let config: Config = toml::from_str(
+            r#"
+           discord_token = "abc123"
+           backends = []
+
+           [profiles.anime]
+           media = ["series"]
+           root_folder = "/tv/anime"
+           series_type = "Anime"
+
+           [profiles.hd]
+           quality_profile = "HD-1080p"
+        "#,
+        )
+        .unwrap();
+
+        let series: Vec<_> = config.profiles_for("series").map(|(n, _)| n).collect();
+        assert_eq!(series, ["anime", "hd"]);
+        let movie: Vec<_> = config.profiles_for("movie").map(|(n, _)| n).collect();
+        assert_eq!(movie, ["hd"]);
+
+        let anime = &config.profiles.as_ref().unwrap()["anime"];
+        assert_eq!(anime.fields.len(), 2);
+        assert_eq!(anime.fields["series_type"], "Anime");
+    }
+
+    #[test]
+    fn categories_filter_by_media_and_tag_by_name() {
+        let config: Config = toml::from_str(
+            r#"
+            discord_token = "abc"
+            backends = []
+
+            [[categories]]
+            name = "Family Movie Night"
+            media = ["movie"]
+
+            [[categories]]
+            name = "Kids"
+            tag = "kids-shows"
+            "#,
+        )
+        .unwrap();
+        let tags = |media| -> Vec<String> {
+            config
+                .categories_for(media)
+                .iter()
+                .map(Category::tag)
+                .collect()
+        };
+        assert_eq!(tags("movie"), ["family-movie-night", "kids-shows"]);
+        assert_eq!(tags("series"), ["kids-shows"]);
+
+        assert_eq!(config.requester_tag("Alice_B"), None);
+        let config = Config {
+            requester_tag_prefix: Some("req-".into()),
+            ..config
+        };
+        assert_eq!(
+            config.requester_tag("Alice_B").as_deref(),
+            Some("req-alice-b")
+        );
+    }
+
+    #[test]
+    fn messages_render_templates_and_fall_back_to_defaults() {
+        let messages = Messages {
+            no_results: Some("Nothing for {query} in /request {media}, {user}. {unknown}".into()),
+            ..Default::default()
+        };
+        let vars = MessageVars {
+            user_id: Some(42),
+            media: "movie",
+            query: "Alien",
+        };
+        assert_eq!(
+            messages.no_results(vars),
+            "Nothing for Alien in /request movie, <@42>. {unknown}"
+        );
+        assert_eq!(messages.timeout(vars), DEFAULT_TIMEOUT_MESSAGE);
+    }
+
+    #[test]
+    fn approval_tier_follows_the_most_trusted_role() {
+        let config: Config = toml::from_str(
+            r#"
+           discord_token = "abc123"
+           backends = []
+
+           [approval]
+           channel_id = 9
+           default = "suggest"
+           auto_roles = [1]
+           required_roles = [2]
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.approval_tier(&[2, 1]), ApprovalTier::Auto);
+        assert_eq!(config.approval_tier(&[2]), ApprovalTier::Required);
+        assert_eq!(config.approval_tier(&[3]), ApprovalTier::Suggest);
+        assert_eq!(Config::default().approval_tier(&[]), ApprovalTier::Auto);
+        assert_eq!(config.posting_channels(), vec![9]);
+    }
+
+    #[test]
+    fn hooks_are_commands_or_webhooks() {
+        let config: Config = toml::from_str(
+            r#"
+           discord_token = "abc123"
+           backends = []
+
+           [[hooks]]
+           on = "before_submit"
+           command = "./check-quota.sh"
+
+           [[hooks]]
+           on = "success"
+           url = "http://wiki.local/hook"
+           timeout_secs = 5
+        "#,
+        )
+        .unwrap();
+        let hooks = config.hooks.unwrap();
+        assert_eq!(
+            hooks[0].action,
+            HookAction::Command {
+                command: "./check-quota.sh".into()
+            }
+        );
+        assert_eq!(hooks[1].on, HookEvent::Success);
+        assert_eq!(
+            hooks[1].action,
+            HookAction::Webhook {
+                url: "http://wiki.local/hook".into()
+            }
+        );
+    }
+
+    #[test]
+    fn features_switch_off_unlisted_subsystems() {
+        let toml = r#"
+           discord_token = "abc123"
+           backends = []
+           features = ["hooks"]
+
+           [[hooks]]
+           on = "success"
+           url = "http://wiki.local/hook"
+
+           [approval]
+           channel_id = 1
+
+           [tracking]
+           poll_minutes = 5
+           webhook_listen = "0.0.0.0:8181"
+        "#;
+        let config = Config::from_toml_str(toml, "test").unwrap();
+        assert!(config.hooks.is_some());
+        assert_eq!(config.approval, None);
+        let tracking = config.tracking.unwrap();
+        assert_eq!(tracking.poll_minutes, Some(5));
+        assert_eq!(tracking.webhook_listen, None);
+        assert_eq!(config.warnings.len(), 2);
+
+        // Everything configured starts without a list
+        let everything = Config::from_toml_str(&toml.replace("features", "#"), "test").unwrap();
+        assert!(everything.approval.is_some());
+        assert!(everything.warnings.is_empty());
+    }
+
+    #[test]
+    fn messages_follow_the_requesters_locale() {
+        let config: Config = toml::from_str(
+            r#"
+           discord_token = "abc123"
+           backends = []
+
+           [messages]
+           no_results = "No results"
+           timeout = "Timed out"
+
+           [messages.locales.pt]
+           no_results = "Sem resultados"
+
+           [messages.locales.de]
+           no_results = "Keine Ergebnisse"
+           timeout = "Zeit abgelaufen"
+        "#,
+        )
+        .unwrap();
+        let messages = config.messages.unwrap();
+        let vars = MessageVars::default();
+
+        // A regional locale matches its language, untranslated messages fall back
+        let pt = messages.localized(&["pt-BR"]);
+        assert_eq!(pt.no_results(vars), "Sem resultados");
+        assert_eq!(pt.timeout(vars), "Timed out");
+        // The user's locale first, then the server's
+        let fr_user = messages.localized(&["fr", "de"]);
+        assert_eq!(fr_user.timeout(vars), "Zeit abgelaufen");
+        assert_eq!(messages.localized(&[]).no_results(vars), "No results");
+    }
+
     #[test]
     fn expand_env_vars_substitutes_and_passes_through() {
         // PATH is reliably set in any environment we run tests in.
@@ -0,0 +1,728 @@
+//! Checking a whole config file against the options Doplarr reads
+//!
+//! serde stops at the first problem and quietly ignores keys it doesn't know,
+//! so a typo like `root_folder` for `rootfolder` does nothing at all. [check]
+//! walks the entire file instead, collecting every unknown key (with a
+//! suggestion when it's a near miss, or a real option that belongs in another
+//! table) and every missing required one, so they can all be fixed at once.
+//!
+//! [SCHEMA] mirrors the structs in [crate::config]; keep the two in step.
+use std::fmt;
+use toml::{Table, Value};
+
+/// The keys a table can have
+struct TableSchema {
+    fields: &'static [Field],
+    /// Any other key is allowed too (e.g. a profile's preset fields)
+    open: bool,
+    /// Exactly one of these must be set (e.g. a hook's command or URL)
+    one_of: &'static [&'static str],
+}
+
+struct Field {
+    name: &'static str,
+    required: bool,
+    shape: Shape,
+}
+
+enum Shape {
+    Value,
+    /// A Discord channel or server ID, which is never 0
+    Id,
+    Table(&'static TableSchema),
+    /// Tables under names the user picks, e.g. `[profiles.anime]`
+    Map(&'static TableSchema),
+    /// An array of tables, e.g. `[[backends]]`
+    Tables(&'static TableSchema),
+    /// A table holding exactly one of these, named after its kind
+    Variants(&'static [(&'static str, &'static TableSchema)]),
+}
+
+const fn table(fields: &'static [Field]) -> TableSchema {
+    TableSchema {
+        fields,
+        open: false,
+        one_of: &[],
+    }
+}
+
+const fn opt(name: &'static str) -> Field {
+    Field {
+        name,
+        required: false,
+        shape: Shape::Value,
+    }
+}
+
+const fn req(name: &'static str) -> Field {
+    Field {
+        name,
+        required: true,
+        shape: Shape::Value,
+    }
+}
+
+const fn req_id(name: &'static str) -> Field {
+    Field {
+        name,
+        required: true,
+        shape: Shape::Id,
+    }
+}
+
+const fn opt_id(name: &'static str) -> Field {
+    Field {
+        name,
+        required: false,
+        shape: Shape::Id,
+    }
+}
+
+const fn nested(name: &'static str, required: bool, shape: Shape) -> Field {
+    Field {
+        name,
+        required,
+        shape,
+    }
+}
+
+static SCHEMA: TableSchema = table(&[
+    opt("log_level"),
+    opt("state_file"),
+    nested("retention", false, Shape::Table(&RETENTION)),
+    opt("public_followup"),
+    opt("followup_style"),
+    nested("followup_channels", false, Shape::Tables(&FOLLOWUP_CHANNEL)),
+    nested("request_threads", false, Shape::Tables(&REQUEST_THREAD)),
+    nested("messages", false, Shape::Table(&MESSAGES)),
+    nested("profiles", false, Shape::Map(&PROFILE)),
+    nested("categories", false, Shape::Tables(&CATEGORY)),
+    opt("requester_tag_prefix"),
+    nested("digest", false, Shape::Table(&DIGEST)),
+    nested("thumbnails", false, Shape::Table(&THUMBNAILS)),
+    nested("overviews", false, Shape::Tables(&OVERVIEW)),
+    opt("nsfw_roles"),
+    opt("upgrade_roles"),
+    nested("hooks", false, Shape::Tables(&HOOK)),
+    nested("approval", false, Shape::Table(&APPROVAL)),
+    nested("tracking", false, Shape::Table(&TRACKING)),
+    nested("enrichment", false, Shape::Table(&ENRICHMENT)),
+    nested("watchdog", false, Shape::Table(&WATCHDOG)),
+    nested("suggestions", false, Shape::Table(&SUGGESTIONS)),
+    nested("search_fallback", false, Shape::Table(&SEARCH_FALLBACK)),
+    nested("startup_report", false, Shape::Table(&STARTUP_REPORT)),
+    opt("features"),
+    nested("debug", false, Shape::Table(&DEBUG)),
+    req("discord_token"),
+    nested("backends", true, Shape::Tables(&BACKEND)),
+]);
+
+static FOLLOWUP_CHANNEL: TableSchema = table(&[req_id("channel_id"), opt("media")]);
+
+static REQUEST_THREAD: TableSchema = table(&[req_id("channel_id"), opt("name")]);
+
+static MESSAGES: TableSchema = table(&[
+    opt("timeout"),
+    opt("early_stop"),
+    opt("no_results"),
+    nested("locales", false, Shape::Map(&MESSAGES)),
+]);
+
+static PROFILE: TableSchema = TableSchema {
+    open: true,
+    ..table(&[opt("media")])
+};
+
+static CATEGORY: TableSchema = table(&[req("name"), opt("tag"), opt("media")]);
+
+static DIGEST: TableSchema = table(&[
+    req_id("channel_id"),
+    req("time"),
+    opt("days"),
+    opt("utc_offset"),
+]);
+
+static THUMBNAILS: TableSchema = table(&[req("listen"), req("public_url")]);
+
+static OVERVIEW: TableSchema = table(&[opt_id("guild_id"), opt_id("channel_id"), req("mode")]);
+
+static HOOK: TableSchema = TableSchema {
+    one_of: &["command", "url"],
+    ..table(&[req("on"), opt("command"), opt("url"), opt("timeout_secs")])
+};
+
+static APPROVAL: TableSchema = TableSchema {
+    one_of: &["channel_id", "channels"],
+    ..table(&[
+        opt_id("channel_id"),
+        nested("channels", false, Shape::Tables(&APPROVAL_CHANNEL)),
+        opt("default"),
+        opt("auto_roles"),
+        opt("required_roles"),
+        opt("suggest_roles"),
+    ])
+};
+
+static APPROVAL_CHANNEL: TableSchema = table(&[req_id("guild_id"), req_id("channel_id")]);
+
+static TRACKING: TableSchema = table(&[
+    opt("poll_minutes"),
+    opt("webhook_listen"),
+    opt("webhook_password"),
+    opt_id("admin_channel_id"),
+    opt("research_hours"),
+    opt("research_limit"),
+    opt("stalled_minutes"),
+]);
+
+static ENRICHMENT: TableSchema = table(&[
+    opt("parental_ratings"),
+    opt("tmdb_api_key"),
+    nested("plex", false, Shape::Table(&PLEX)),
+]);
+
+static WATCHDOG: TableSchema = table(&[
+    opt_id("channel_id"),
+    opt("max_in_progress"),
+    opt("max_abandoned"),
+]);
+
+static SUGGESTIONS: TableSchema = table(&[req("tmdb_api_key"), opt("count")]);
+static SEARCH_FALLBACK: TableSchema = table(&[req("tmdb_api_key"), opt("retry_minutes")]);
+
+static STARTUP_REPORT: TableSchema = table(&[req_id("channel_id")]);
+
+static RETENTION: TableSchema = table(&[req("months"), opt("mode"), opt("archive_file")]);
+
+static PLEX: TableSchema = table(&[req("url"), req("token"), opt("refresh_minutes")]);
+
+static DEBUG: TableSchema = table(&[
+    opt("latency_ms"),
+    opt("jitter_ms"),
+    opt("failure_percent"),
+    opt("backends"),
+]);
+
+static BACKEND: TableSchema = table(&[
+    req("media"),
+    opt("name"),
+    opt("guilds"),
+    nested(
+        "config",
+        true,
+        Shape::Variants(&[
+            ("Radarr", &RADARR),
+            ("Sonarr", &SONARR),
+            ("Seerr", &SEERR),
+            ("Plugin", &PLUGIN),
+            ("Combined", &COMBINED),
+        ]),
+    ),
+    nested("maintenance", false, Shape::Tables(&MAINTENANCE_WINDOW)),
+]);
+
+static MAINTENANCE_WINDOW: TableSchema = table(&[opt("days"), req("start"), req("end")]);
+
+static RADARR: TableSchema = table(&[
+    req("url"),
+    req("api_key"),
+    opt("monitor_type"),
+    opt("quality_profile"),
+    opt("rootfolder"),
+    opt("minimum_availability"),
+    opt("allow_unmonitored"),
+    opt("allow_tags"),
+    opt("result_fields"),
+]);
+
+static SONARR: TableSchema = table(&[
+    req("url"),
+    req("api_key"),
+    opt("quality_profile"),
+    opt("language_profile"),
+    opt("rootfolder"),
+    opt("series_type"),
+    opt("season_folders"),
+    opt("allow_specials"),
+    opt("allow_all_seasons"),
+    opt("allow_tags"),
+    opt("result_fields"),
+]);
+
+static SEERR: TableSchema = table(&[
+    req("url"),
+    req("api_key"),
+    opt("fallback_user_id"),
+    opt("allow_4k"),
+    opt("media_filter"),
+    opt("allow_all_seasons"),
+]);
+
+static PLUGIN: TableSchema = table(&[req("command"), opt("args"), opt("timeout_secs")]);
+
+static COMBINED: TableSchema = table(&[req("backends")]);
+
+/// Something wrong with a config file, by the dotted path of the key
+#[derive(Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// A key nothing reads, with what was probably meant
+    Unknown { key: String, hint: Option<String> },
+    /// A required key that isn't there
+    Missing { key: String },
+    /// A table that needs exactly one of these keys
+    OneOf {
+        key: String,
+        options: Vec<&'static str>,
+    },
+    /// A key set to the wrong kind of thing, e.g. a value where a table goes
+    Expected { key: String, what: &'static str },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Unknown { key, hint: None } => write!(f, "unknown key `{key}`"),
+            Problem::Unknown {
+                key,
+                hint: Some(hint),
+            } => write!(f, "unknown key `{key}` ({hint})"),
+            Problem::Missing { key } => write!(f, "missing required key `{key}`"),
+            Problem::OneOf { key, options } => {
+                let options: Vec<_> = options.iter().map(|o| format!("`{o}`")).collect();
+                write!(f, "`{key}` needs exactly one of {}", options.join(", "))
+            }
+            Problem::Expected { key, what } => write!(f, "`{key}` should be {what}"),
+        }
+    }
+}
+
+/// Everything wrong with a parsed config file
+pub fn check(doc: &Table) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    check_table(&SCHEMA, doc, "", &mut problems);
+    problems
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+fn check_table(schema: &TableSchema, table: &Table, path: &str, out: &mut Vec<Problem>) {
+    for (key, value) in table {
+        let key_path = join(path, key);
+        match schema.fields.iter().find(|f| f.name == key) {
+            Some(field) => check_shape(&field.shape, value, &key_path, out),
+            None if schema.open => {}
+            None => out.push(Problem::Unknown {
+                hint: hint(key, schema.fields.iter().map(|f| f.name)),
+                key: key_path,
+            }),
+        }
+    }
+    for field in schema.fields.iter().filter(|f| f.required) {
+        if !table.contains_key(field.name) {
+            out.push(Problem::Missing {
+                key: join(path, field.name),
+            });
+        }
+    }
+    if !schema.one_of.is_empty()
+        && schema
+            .one_of
+            .iter()
+            .filter(|k| table.contains_key(**k))
+            .count()
+            != 1
+    {
+        out.push(Problem::OneOf {
+            key: path.to_string(),
+            options: schema.one_of.to_vec(),
+        });
+    }
+}
+
+fn check_shape(shape: &Shape, value: &Value, path: &str, out: &mut Vec<Problem>) {
+    let expected = |what| Problem::Expected {
+        key: path.to_string(),
+        what,
+    };
+    match (shape, value) {
+        (Shape::Value, _) => {}
+        // Anything but a number is for serde to reject
+        (Shape::Id, Value::Integer(id)) if *id <= 0 => out.push(expected("a Discord ID, not 0")),
+        (Shape::Id, _) => {}
+        (Shape::Table(schema), Value::Table(table)) => check_table(schema, table, path, out),
+        (Shape::Map(schema), Value::Table(entries)) => {
+            for (name, entry) in entries {
+                check_shape(&Shape::Table(schema), entry, &join(path, name), out);
+            }
+        }
+        (Shape::Tables(schema), Value::Array(items)) => {
+            for (i, item) in items.iter().enumerate() {
+                check_shape(&Shape::Table(schema), item, &format!("{path}[{i}]"), out);
+            }
+        }
+        (Shape::Variants(variants), Value::Table(table)) => {
+            let mut known = 0;
+            for (key, value) in table {
+                match variants.iter().find(|(name, _)| name == key) {
+                    Some((_, schema)) => {
+                        known += 1;
+                        check_shape(&Shape::Table(schema), value, &join(path, key), out);
+                    }
+                    None => out.push(Problem::Unknown {
+                        hint: hint(key, variants.iter().map(|(name, _)| *name)),
+                        key: join(path, key),
+                    }),
+                }
+            }
+            // A lone misspelled kind already says what's wrong
+            if known != 1 && !(known == 0 && table.len() == 1) {
+                out.push(Problem::OneOf {
+                    key: path.to_string(),
+                    options: variants.iter().map(|(name, _)| *name).collect(),
+                });
+            }
+        }
+        (Shape::Table(_) | Shape::Variants(_), _) => out.push(expected("a table")),
+        (Shape::Map(_), _) => out.push(expected("a table of named tables")),
+        (Shape::Tables(_), _) => out.push(expected("an array of tables, e.g. [[...]]")),
+    }
+}
+
+/// What an unknown key was probably meant to be: a near miss of one of the
+/// keys the table does have, or a real option that goes in another table
+fn hint(key: &str, candidates: impl Iterator<Item = &'static str>) -> Option<String> {
+    let normalize = |s: &str| s.to_lowercase().replace(['_', '-'], "");
+    let wanted = normalize(key);
+    let closest = candidates
+        .map(|c| (strsim::levenshtein(&wanted, &normalize(c)), c))
+        .min();
+    if let Some((distance, name)) = closest
+        && distance <= (key.len() / 3).max(1)
+    {
+        return Some(format!("did you mean `{name}`?"));
+    }
+    let mut homes = Vec::new();
+    find_homes(key, &SCHEMA, "", &mut homes);
+    (!homes.is_empty()).then(|| format!("it belongs under {}", homes.join(" or ")))
+}
+
+/// Where `key` is an option, as `[table]` headers (`top level` for the root)
+fn find_homes(key: &str, schema: &'static TableSchema, path: &str, homes: &mut Vec<String>) {
+    if schema.fields.iter().any(|f| f.name == key) {
+        homes.push(if path.is_empty() {
+            "the top level".to_string()
+        } else {
+            format!("`[{path}]`")
+        });
+    }
+    for field in schema.fields {
+        let path = join(path, field.name);
+        match &field.shape {
+            Shape::Value | Shape::Id => {}
+            Shape::Table(inner) | Shape::Tables(inner) => find_homes(key, inner, &path, homes),
+            // Named tables nest themselves (message locales); one level is plenty
+            Shape::Map(inner) if !std::ptr::eq(*inner, schema) => {
+                find_homes(key, inner, &format!("{path}.<name>"), homes)
+            }
+            Shape::Map(_) => {}
+            Shape::Variants(variants) => {
+                for (name, inner) in *variants {
+                    find_homes(key, inner, &join(&path, name), homes);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::*;
+    use radarr_api::models::{MonitorTypes, MovieStatusType};
+    use sonarr_api::models::SeriesTypes;
+    use std::collections::BTreeMap;
+
+    fn problems(content: &str) -> Vec<String> {
+        check(&toml::from_str(content).unwrap())
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn every_problem_in_the_file_is_reported() {
+        let found = problems(
+            r#"
+            discord_token = "abc"
+            log_levle = "debug"
+
+            [[backends]]
+            media = "movie"
+            [backends.config.Radarr]
+            uri = "http://localhost:7878"
+            api_key = "abc"
+            root_folder = "/movies"
+
+            [[backends]]
+            media = "series"
+            quality_profile = "HD-1080p"
+            [backends.config.Sonnar]
+            url = "http://localhost:8989"
+
+            [[hooks]]
+            on = "success"
+            "#,
+        );
+        // Keys are checked in order, tables before the keys they're missing
+        assert_eq!(
+            found,
+            [
+                "unknown key `backends[0].config.Radarr.root_folder` (did you mean `rootfolder`?)",
+                "unknown key `backends[0].config.Radarr.uri` (did you mean `url`?)",
+                "missing required key `backends[0].config.Radarr.url`",
+                "unknown key `backends[1].config.Sonnar` (did you mean `Sonarr`?)",
+                "unknown key `backends[1].quality_profile` (it belongs under \
+                 `[backends.config.Radarr]` or `[backends.config.Sonarr]`)",
+                "`hooks[0]` needs exactly one of `command`, `url`",
+                "unknown key `log_levle` (did you mean `log_level`?)",
+            ]
+        );
+    }
+
+    #[test]
+    fn zero_ids_are_reported() {
+        let found = problems(
+            r#"
+            discord_token = "abc"
+            backends = []
+
+            [[followup_channels]]
+            channel_id = 0
+
+            [approval]
+            [[approval.channels]]
+            guild_id = 1
+            channel_id = 0
+
+            [digest]
+            channel_id = 0
+            time = "08:00"
+            "#,
+        );
+        assert_eq!(
+            found,
+            [
+                "`approval.channels[0].channel_id` should be a Discord ID, not 0",
+                "`digest.channel_id` should be a Discord ID, not 0",
+                "`followup_channels[0].channel_id` should be a Discord ID, not 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn a_fully_set_config_has_no_problems() {
+        // Struct literals, so a new config field fails to compile here until
+        // it's set, and then this fails until SCHEMA knows it too
+        let messages = Messages {
+            timeout: Some("t".into()),
+            early_stop: Some("e".into()),
+            no_results: Some("n".into()),
+            locales: Some(BTreeMap::from([(
+                "de".into(),
+                Messages {
+                    timeout: Some("t".into()),
+                    early_stop: None,
+                    no_results: None,
+                    locales: None,
+                },
+            )])),
+        };
+        let backend = |media: &str, config| Backend {
+            media: media.into(),
+            name: Some(media.into()),
+            guilds: Some(vec![1]),
+            config,
+            maintenance: Some(vec![MaintenanceWindow {
+                days: Some(vec!["sun".into()]),
+                start: "23:00".into(),
+                end: "02:00".into(),
+            }]),
+        };
+        let config = Config {
+            log_level: Some("info".into()),
+            state_file: Some("state.json".into()),
+            retention: Some(Retention {
+                months: 12,
+                mode: Some(RetentionMode::Purge),
+                archive_file: Some("archive.jsonl".into()),
+            }),
+            public_followup: Some(true),
+            followup_style: Some(FollowupStyle::Interaction),
+            followup_channels: Some(vec![FollowupChannel {
+                channel_id: 1,
+                media: Some(vec!["movie".into()]),
+            }]),
+            request_threads: Some(vec![RequestThread {
+                channel_id: 1,
+                name: Some("Requests".into()),
+            }]),
+            messages: Some(messages),
+            profiles: Some(BTreeMap::from([(
+                "anime".into(),
+                Profile {
+                    media: Some(vec!["series".into()]),
+                    fields: BTreeMap::from([("series_type".into(), "Anime".into())]),
+                },
+            )])),
+            categories: Some(vec![Category {
+                name: "Kids".into(),
+                tag: Some("kids".into()),
+                media: Some(vec!["movie".into()]),
+            }]),
+            requester_tag_prefix: Some("req-".into()),
+            digest: Some(Digest {
+                channel_id: 1,
+                time: "09:00".into(),
+                days: Some(7),
+                utc_offset: Some("+10:00".into()),
+            }),
+            thumbnails: Some(Thumbnails {
+                listen: "0.0.0.0:8180".into(),
+                public_url: "https://example.com".into(),
+            }),
+            overviews: Some(vec![OverviewRule {
+                guild_id: Some(1),
+                channel_id: Some(2),
+                mode: OverviewMode::Hide,
+            }]),
+            nsfw_roles: Some(vec![1]),
+            upgrade_roles: Some(vec![1]),
+            hooks: Some(vec![Hook {
+                on: HookEvent::Success,
+                action: HookAction::Webhook {
+                    url: "http://hook".into(),
+                },
+                timeout_secs: Some(10),
+            }]),
+            approval: Some(Approval {
+                channel_id: None,
+                channels: Some(vec![ApprovalChannel {
+                    guild_id: 1,
+                    channel_id: 1,
+                }]),
+                default: Some(ApprovalTier::Required),
+                auto_roles: Some(vec![1]),
+                required_roles: Some(vec![2]),
+                suggest_roles: Some(vec![3]),
+            }),
+            tracking: Some(Tracking {
+                poll_minutes: Some(10),
+                webhook_listen: Some("0.0.0.0:8181".into()),
+                webhook_password: Some("hunter2".into()),
+                admin_channel_id: Some(1),
+                research_hours: Some(48),
+                research_limit: Some(3),
+                stalled_minutes: Some(60),
+            }),
+            enrichment: Some(Enrichment {
+                parental_ratings: Some(true),
+                tmdb_api_key: Some("key".into()),
+                plex: Some(Plex {
+                    url: "http://plex:32400".into(),
+                    token: "token".into(),
+                    refresh_minutes: Some(30),
+                }),
+            }),
+            watchdog: Some(Watchdog {
+                channel_id: Some(1),
+                max_in_progress: Some(50),
+                max_abandoned: Some(10),
+            }),
+            suggestions: Some(Suggestions {
+                tmdb_api_key: "key".into(),
+                count: Some(3),
+            }),
+            search_fallback: Some(SearchFallback {
+                tmdb_api_key: "key".into(),
+                retry_minutes: Some(5),
+            }),
+            startup_report: Some(StartupReport { channel_id: 1 }),
+            features: Some(vec![Feature::Approval, Feature::Webhooks]),
+            debug: Some(FaultInjection {
+                latency_ms: Some(1),
+                jitter_ms: Some(1),
+                failure_percent: Some(1),
+                backends: Some(vec!["movie".into()]),
+            }),
+            discord_token: "abc".into(),
+            backends: vec![
+                backend(
+                    "movie",
+                    BackendConfig::Radarr {
+                        url: "http://radarr".into(),
+                        api_key: "key".into(),
+                        monitor_type: Some(MonitorTypes::MovieOnly),
+                        quality_profile: Some("HD".into()),
+                        rootfolder: Some("/movies".into()),
+                        minimum_availability: Some(MovieStatusType::Released),
+                        allow_unmonitored: Some(false),
+                        allow_tags: Some(true),
+                        result_fields: Some(vec![ResultField::Year, ResultField::Genres]),
+                    },
+                ),
+                backend(
+                    "series",
+                    BackendConfig::Sonarr {
+                        url: "http://sonarr".into(),
+                        api_key: "key".into(),
+                        quality_profile: Some("HD".into()),
+                        language_profile: Some("English".into()),
+                        rootfolder: Some("/tv".into()),
+                        series_type: Some(SeriesTypes::Standard),
+                        season_folders: Some(true),
+                        allow_specials: Some(true),
+                        allow_all_seasons: Some(true),
+                        allow_tags: Some(true),
+                        result_fields: Some(vec![ResultField::Year, ResultField::Genres]),
+                    },
+                ),
+                backend(
+                    "media",
+                    BackendConfig::Seerr {
+                        url: "http://seerr".into(),
+                        api_key: "key".into(),
+                        fallback_user_id: Some(1),
+                        allow_4k: Some(true),
+                        media_filter: Some(MediaKind::Movie),
+                        allow_all_seasons: Some(true),
+                    },
+                ),
+                backend(
+                    "comic",
+                    BackendConfig::Plugin {
+                        command: "plugin".into(),
+                        args: Some(vec!["--flag".into()]),
+                        timeout_secs: Some(30),
+                    },
+                ),
+                backend(
+                    "movies",
+                    BackendConfig::Combined {
+                        backends: vec!["movie".into()],
+                    },
+                ),
+            ],
+            warnings: Vec::new(),
+        };
+        let doc = toml::Table::try_from(&config).unwrap();
+        assert_eq!(check(&doc), []);
+        let example = include_str!("../../config.example.toml");
+        assert_eq!(check(&toml::from_str(example).unwrap()), []);
+    }
+}
@@ -0,0 +1,82 @@
+//! Reading the custom IDs and picked values Discord sends back from components
+//!
+//! Our custom IDs are `<prefix>:<flow uuid>`, where a button in a grid of
+//! options adds the option's index to the prefix (`Seasons#3:<uuid>`). Any
+//! client can send any custom ID and values, so nothing here trusts them. This
+//! module doesn't use the rest of the crate, so the fuzz targets in
+//! `doplarr/fuzz` can build it on its own.
+use uuid::Uuid;
+
+/// The prefix and flow UUID of a custom ID, when it has both
+pub fn flow(custom_id: &str) -> Option<(&str, Uuid)> {
+    let (prefix, uuid) = custom_id.split_once(':')?;
+    Some((prefix, Uuid::parse_str(uuid).ok()?))
+}
+
+/// The form field a custom ID is for, by title, and the option a grid button
+/// toggles
+pub fn field(custom_id: &str) -> Result<(&str, Option<usize>), &'static str> {
+    let Some((id, _)) = custom_id.split_once(':') else {
+        return Err("custom id has no uuid suffix");
+    };
+    match id.split_once('#') {
+        Some((title, index)) => match index.parse() {
+            Ok(index) => Ok((title, Some(index))),
+            Err(_) => Err("button index is not a valid index"),
+        },
+        None => Ok((id, None)),
+    }
+}
+
+/// A picked value as an index into `len` options
+pub fn index(value: &str, len: usize) -> Option<usize> {
+    value.parse().ok().filter(|&i| i < len)
+}
+
+/// The picked values that are indices into `len` options, sorted and without
+/// repeats
+pub fn indices(values: &[String], len: usize) -> Vec<usize> {
+    let mut indices: Vec<_> = values.iter().filter_map(|v| index(v, len)).collect();
+    indices.sort_unstable();
+    indices.dedup();
+    indices
+}
+
+/// `selected` with option `index` flipped, kept sorted
+pub fn toggle(selected: &[usize], index: usize) -> Vec<usize> {
+    let mut toggled = selected.to_vec();
+    match toggled.binary_search(&index) {
+        Ok(pos) => {
+            toggled.remove(pos);
+        }
+        Err(pos) => toggled.insert(pos, index),
+    }
+    toggled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_ids_split_into_prefix_field_and_index() {
+        let uuid = Uuid::new_v4();
+        assert_eq!(flow(&format!("resume:{uuid}")), Some(("resume", uuid)));
+        assert_eq!(flow("resume:nope"), None);
+        assert_eq!(flow("resume"), None);
+
+        assert_eq!(field("Quality:x"), Ok(("Quality", None)));
+        assert_eq!(field("Seasons#3:x"), Ok(("Seasons", Some(3))));
+        assert!(field("Seasons#-1:x").is_err());
+        assert!(field("Seasons").is_err());
+    }
+
+    #[test]
+    fn picked_values_stay_in_bounds() {
+        let values = ["2", "0", "2", "9", "x", "-1"].map(String::from);
+        assert_eq!(indices(&values, 3), [0, 2]);
+        assert_eq!(index("3", 3), None);
+        assert_eq!(toggle(&[0, 2], 1), [0, 1, 2]);
+        assert_eq!(toggle(&[0, 1, 2], 1), [0, 2]);
+    }
+}
@@ -0,0 +1,286 @@
+//! Daily "airing today / releasing this week" digest
+//!
+//! A background task wakes at the configured time each day, asks every backend
+//! for its calendar, and posts the combined list through the [Announcer].
+use crate::{
+    announcer::Announcer,
+    config::Digest,
+    discord::escape_markdown,
+    providers::{CalendarEntry, registry::BackendRegistry},
+};
+use anyhow::Context;
+use time::{Date, OffsetDateTime, Time, UtcOffset};
+use tracing::{debug, info, warn};
+use twilight_model::id::{Id, marker::ChannelMarker};
+
+/// How far ahead the digest looks, counting today, when not configured
+const DEFAULT_DAYS: u8 = 7;
+
+/// Parse the "HH:MM" posting time
+fn parse_time(at: &str) -> anyhow::Result<Time> {
+    let (h, m) = at
+        .split_once(':')
+        .with_context(|| format!("Digest time \"{at}\" must be HH:MM"))?;
+    let (h, m) = (h.parse::<u8>()?, m.parse::<u8>()?);
+    Time::from_hms(h, m, 0).with_context(|| format!("Digest time \"{at}\" is not a valid time"))
+}
+
+/// Parse a "+HH:MM" or "-HH:MM" offset from UTC
+fn parse_offset(offset: &str) -> anyhow::Result<UtcOffset> {
+    let invalid = || format!("Digest utc_offset \"{offset}\" must be +HH:MM or -HH:MM");
+    let (sign, hm) = match offset.split_at_checked(1) {
+        Some(("+", hm)) => (1, hm),
+        Some(("-", hm)) => (-1, hm),
+        _ => anyhow::bail!(invalid()),
+    };
+    let (h, m) = hm.split_once(':').with_context(invalid)?;
+    let (h, m) = (
+        h.parse::<i8>().with_context(invalid)?,
+        m.parse::<i8>().with_context(invalid)?,
+    );
+    UtcOffset::from_hms(sign * h, sign * m, 0).with_context(invalid)
+}
+
+/// The day a release falls on where the server is: its exact time's day when
+/// known, or else the date the backend gave
+fn local_date(entry: &CalendarEntry, offset: UtcOffset) -> String {
+    entry
+        .at
+        .and_then(|at| OffsetDateTime::from_unix_timestamp(i64::try_from(at).ok()?).ok())
+        .map(|at| iso(at.to_offset(offset).date()))
+        .unwrap_or_else(|| entry.date.clone())
+}
+
+/// How long to sleep from `now` until the next `at`
+fn until_next(now: OffsetDateTime, at: Time) -> std::time::Duration {
+    let mut next = now.replace_time(at);
+    if next <= now {
+        next += time::Duration::DAY;
+    }
+    (next - now).unsigned_abs()
+}
+
+fn iso(date: Date) -> String {
+    format!(
+        "{:04}-{:02}-{:02}",
+        date.year(),
+        u8::from(date.month()),
+        date.day()
+    )
+}
+
+/// e.g. "Mon Mar 4"
+fn short_date(date: &str) -> String {
+    let parsed = (|| {
+        let mut parts = date.splitn(3, '-').map(str::parse::<i32>);
+        let (y, m, d) = (
+            parts.next()?.ok()?,
+            parts.next()?.ok()?,
+            parts.next()?.ok()?,
+        );
+        Date::from_calendar_date(y, u8::try_from(m).ok()?.try_into().ok()?, d as u8).ok()
+    })();
+    match parsed {
+        Some(d) => format!(
+            "{} {} {}",
+            &d.weekday().to_string()[..3],
+            &d.month().to_string()[..3],
+            d.day()
+        ),
+        None => date.to_string(),
+    }
+}
+
+/// The digest's lines: today's releases, then the rest of the window by date.
+/// Empty when nothing is coming up.
+fn render(mut entries: Vec<CalendarEntry>, today: &str) -> Vec<String> {
+    entries.sort();
+    // The same instance can sit behind several media commands
+    entries.dedup();
+
+    // Discord shows the timestamp in each reader's own time zone
+    let line = |e: &CalendarEntry| match e.at {
+        Some(at) => format!(
+            "- **{}** — {} · <t:{at}:t>",
+            escape_markdown(&e.title),
+            e.detail
+        ),
+        None => format!("- **{}** — {}", escape_markdown(&e.title), e.detail),
+    };
+    let (todays, later): (Vec<_>, Vec<_>) = entries.iter().partition(|e| e.date == today);
+    let mut lines = Vec::new();
+    if !todays.is_empty() {
+        lines.push("## Airing today".to_string());
+        lines.extend(todays.into_iter().map(line));
+    }
+    if !later.is_empty() {
+        lines.push("## Coming up this week".to_string());
+        let mut date = None;
+        for e in later {
+            if date != Some(&e.date) {
+                lines.push(format!("**{}**", short_date(&e.date)));
+                date = Some(&e.date);
+            }
+            lines.push(line(e));
+        }
+    }
+    lines
+}
+
+async fn post(
+    digest: &Digest,
+    channel_id: Id<ChannelMarker>,
+    offset: UtcOffset,
+    backends: &BackendRegistry,
+    announcer: &Announcer,
+) {
+    let today = OffsetDateTime::now_utc().to_offset(offset).date();
+    let days = digest.days.unwrap_or(DEFAULT_DAYS).max(1);
+    let end = today + time::Duration::days(i64::from(days) - 1);
+    // A day either side, for airings that land in the window once moved to
+    // the server's time zone
+    let (query_start, query_end) = (
+        iso(today.previous_day().unwrap_or(today)),
+        iso(end.next_day().unwrap_or(end)),
+    );
+    let (start, end) = (iso(today), iso(end));
+
+    let mut entries = Vec::new();
+    for (_, instance) in backends.iter() {
+        match instance.backend.calendar(&query_start, &query_end).await {
+            Ok(found) => entries.extend(found),
+            Err(e) => {
+                warn!(backend = instance.name, error = ?e, "Leaving backend out of the digest")
+            }
+        }
+    }
+    for entry in &mut entries {
+        entry.date = local_date(entry, offset);
+    }
+    // Backends may include the edges of the window differently
+    entries.retain(|e| (start.as_str()..=end.as_str()).contains(&e.date.as_str()));
+
+    let lines = render(entries, &start);
+    if lines.is_empty() {
+        info!("Nothing on the calendar, skipping digest");
+        return;
+    }
+    debug!(lines = lines.len(), "Posting calendar digest");
+    // Queued line by line so the announcer can split it at Discord's length limit
+    for line in lines {
+        announcer.send(channel_id, line);
+    }
+}
+
+/// Start posting the digest daily. Fails if the configured time or channel is
+/// invalid.
+pub fn spawn(
+    digest: Digest,
+    backends: BackendRegistry,
+    announcer: Announcer,
+) -> anyhow::Result<()> {
+    let at = parse_time(&digest.time)?;
+    let channel_id =
+        Id::new_checked(digest.channel_id).context("The digest's channel_id can't be 0")?;
+    let offset = match &digest.utc_offset {
+        Some(offset) => parse_offset(offset)?,
+        None => UtcOffset::UTC,
+    };
+    info!(time = %digest.time, %offset, channel_id = digest.channel_id, "Scheduling daily digest");
+    tokio::spawn(async move {
+        loop {
+            let now = OffsetDateTime::now_utc().to_offset(offset);
+            tokio::time::sleep(until_next(now, at)).await;
+            post(&digest, channel_id, offset, &backends, &announcer).await;
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::Month;
+
+    fn march_4th(h: u8, m: u8) -> OffsetDateTime {
+        Date::from_calendar_date(2024, Month::March, 4)
+            .unwrap()
+            .with_hms(h, m, 0)
+            .unwrap()
+            .assume_utc()
+    }
+
+    fn entry(date: &str, title: &str, detail: &str) -> CalendarEntry {
+        CalendarEntry {
+            date: date.into(),
+            at: None,
+            title: title.into(),
+            detail: detail.into(),
+        }
+    }
+
+    #[test]
+    fn next_run_is_today_or_tomorrow() {
+        let at = parse_time("09:30").unwrap();
+        let secs = |now| until_next(now, at).as_secs();
+        assert_eq!(secs(march_4th(9, 0)), 30 * 60);
+        assert_eq!(secs(march_4th(9, 30)), 24 * 3600);
+        assert_eq!(secs(march_4th(10, 30)), 23 * 3600);
+        assert!(parse_time("25:00").is_err());
+        assert!(parse_time("0930").is_err());
+    }
+
+    #[test]
+    fn render_groups_today_then_by_date() {
+        let lines = render(
+            vec![
+                entry("2024-03-06", "Dune", "Digital release"),
+                entry("2024-03-04", "Severance", "S02E05 · Trojan's Horse"),
+                entry("2024-03-06", "Dune", "Digital release"),
+                entry("2024-03-05", "Shogun", "S01E03"),
+            ],
+            "2024-03-04",
+        );
+        assert_eq!(
+            lines,
+            [
+                "## Airing today",
+                "- **Severance** — S02E05 · Trojan's Horse",
+                "## Coming up this week",
+                "**Tue Mar 5**",
+                "- **Shogun** — S01E03",
+                "**Wed Mar 6**",
+                "- **Dune** — Digital release",
+            ]
+        );
+        assert!(render(vec![], "2024-03-04").is_empty());
+    }
+
+    #[test]
+    fn airings_move_to_the_server_s_day_and_show_their_time() {
+        let offset = parse_offset("-05:00").unwrap();
+        assert_eq!(parse_offset("+05:30").unwrap().whole_minutes(), 330);
+        assert!(parse_offset("05:00").is_err());
+        assert!(parse_offset("+99:00").is_err());
+
+        // 02:00 UTC on the 5th is still the evening of the 4th in New York
+        let at = march_4th(0, 0).unix_timestamp() as u64 + 26 * 3600;
+        let mut late = entry("2024-03-05", "Severance", "S02E05");
+        late.at = Some(at);
+        assert_eq!(local_date(&late, offset), "2024-03-04");
+        assert_eq!(local_date(&late, UtcOffset::UTC), "2024-03-05");
+        assert_eq!(
+            local_date(&entry("2024-03-06", "Dune", "Digital release"), offset),
+            "2024-03-06"
+        );
+
+        late.date = local_date(&late, offset);
+        assert_eq!(
+            render(vec![late], "2024-03-04"),
+            [
+                "## Airing today",
+                &format!("- **Severance** — S02E05 · <t:{at}:t>"),
+            ]
+        );
+    }
+}
@@ -1,51 +1,171 @@
-use crate::providers::{
-    ALL_SEASONS_ID, DropdownOption, FieldType, MediaBackend, MediaDisplayInfo, RequestDetails,
-    SelectableId, SuccessMessage,
+use crate::{
+    announcer::Announcer,
+    config::{ApprovalTier, Category, FollowupStyle, MessageVars, Messages, OverviewMode, Profile},
+    custom_id,
+    enrich::{Candidate, Enrichers},
+    hooks::Hooks,
+    providers::{
+        ALL_SEASONS_ID, DownloadStatus, DropdownOption, FieldType, MediaBackend, MediaDisplayInfo,
+        MediaId, MediaItem, NO_TAG_ID, RequestDetails, Requester, SelectableId, SuccessMessage,
+        UserFacingError, registry::RegisteredBackend,
+    },
+    removals::Removal,
+    store::{
+        self, AuditRecord, Denial, RequestRecord, RequestStatus, Selection, StageTiming, Store,
+    },
+    suggestions::{Offer, Suggester},
+    text,
+    thumbnails::ThumbnailProxy,
+};
+use anyhow::{Context, bail};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use time::OffsetDateTime;
+use tokio::{
+    sync::{mpsc::Receiver, oneshot},
+    time::timeout,
 };
-use anyhow::Context;
-use std::{sync::Arc, time::Duration};
-use tokio::{sync::mpsc::Receiver, time::timeout};
-use tracing::{debug, info, trace, warn};
+use tracing::{Instrument, debug, info, trace, warn};
 use twilight_http::Client as HttpClient;
 use twilight_model::{
     application::{
         command::{Command, CommandType},
-        interaction::message_component::MessageComponentInteractionData,
+        interaction::{
+            message_component::MessageComponentInteractionData,
+            modal::{ModalInteractionComponent, ModalInteractionData},
+        },
     },
     channel::message::{
-        Component, MessageFlags,
-        component::{ActionRow, ButtonStyle, SelectMenuType, UnfurledMediaItem},
+        AllowedMentions, Component, MessageFlags,
+        component::{
+            ActionRow, ButtonStyle, Label, SelectDefaultValue, SelectMenuType, TextInput,
+            TextInputStyle, UnfurledMediaItem,
+        },
+    },
+    guild::Permissions,
+    http::{
+        attachment::Attachment,
+        interaction::{InteractionResponse, InteractionResponseType},
     },
-    http::interaction::{InteractionResponse, InteractionResponseType},
     id::{
         Id,
-        marker::{ApplicationMarker, ChannelMarker, InteractionMarker, UserMarker},
+        marker::{
+            ApplicationMarker, ChannelMarker, GuildMarker, InteractionMarker, MessageMarker,
+            UserMarker,
+        },
     },
 };
 use twilight_util::builder::{
     InteractionResponseDataBuilder,
-    command::{CommandBuilder, StringBuilder, SubCommandBuilder},
+    command::{
+        BooleanBuilder, CommandBuilder, IntegerBuilder, StringBuilder, SubCommandBuilder,
+        UserBuilder,
+    },
     message::{
-        ActionRowBuilder, ButtonBuilder, ContainerBuilder, SectionBuilder, SelectMenuBuilder,
-        SelectMenuOptionBuilder, SeparatorBuilder, TextDisplayBuilder, ThumbnailBuilder,
+        ActionRowBuilder, ButtonBuilder, ContainerBuilder, FileDisplayBuilder, SectionBuilder,
+        SelectMenuBuilder, SelectMenuOptionBuilder, SeparatorBuilder, TextDisplayBuilder,
+        ThumbnailBuilder,
     },
 };
 use uuid::Uuid;
 
 pub const TOP_LEVEL_COMMAND_NAME: &str = "request";
 pub const QUERY_COMMAND_NAME: &str = "query";
-pub const TIMEOUT_MESSAGE: &str = "Interaction timed out, please try again";
-pub const EARLY_STOP_MESSAGE: &str = "Already requested - nothing more to add";
+pub const QUICK_COMMAND_NAME: &str = "quick";
+pub const REMOVE_SUBCOMMAND_NAME: &str = "remove";
+pub const DELETE_FILES_OPTION: &str = "delete_files";
+pub const PROFILE_COMMAND_NAME: &str = "profile";
+pub const YEAR_COMMAND_NAME: &str = "year";
+pub const NEED_BY_OPTION: &str = "need_by";
+pub const BACKEND_COMMAND_NAME: &str = "backend";
+pub const BACKEND_NAME_OPTION: &str = "name";
+pub const TITLE_OPTION: &str = "title";
+pub const API_KEY_OPTION: &str = "api_key";
+pub const BAN_COMMAND_NAME: &str = "ban-requests";
+pub const UNBAN_COMMAND_NAME: &str = "unban-requests";
+pub const PING_COMMAND_NAME: &str = "ping-backends";
+pub const SEARCH_MISSING_COMMAND_NAME: &str = "search-missing";
+pub const MEDIA_OPTION: &str = "media";
+pub const EXPORT_COMMAND_NAME: &str = "export-requests";
+pub const FORMAT_OPTION: &str = "format";
+pub const SINCE_OPTION: &str = "since";
+pub const UNTIL_OPTION: &str = "until";
+pub const AUDIT_COMMAND_NAME: &str = "request-audit";
+pub const PENDING_COMMAND_NAME: &str = "pending";
+pub const REQUESTS_COMMAND_NAME: &str = "requests";
+pub const REPORT_COMMAND_NAME: &str = "report";
+pub const UPGRADE_COMMAND_NAME: &str = "upgrade";
+pub const FORGET_ME_COMMAND_NAME: &str = "forget-me";
+pub const FORGET_USER_COMMAND_NAME: &str = "forget-user";
+pub const QUALITY_PROFILE_OPTION: &str = "quality_profile";
+pub const ID_OPTION: &str = "id";
+pub const USER_OPTION: &str = "user";
+pub const REASON_OPTION: &str = "reason";
+/// Custom id prefix of the button on a timed-out flow that starts it over
+pub const RESUME_PREFIX: &str = "resume";
+/// Custom id prefix of the button on a "no results" message, and of the
+/// modal it opens, to search again with the query edited
+pub const RESEARCH_PREFIX: &str = "research";
+/// Custom id prefixes of the buttons admins decide on a request with. Deny's
+/// also names the modal asking why.
+pub const APPROVE_PREFIX: &str = "approve";
+pub const DENY_PREFIX: &str = "deny";
+/// Custom id prefix of the buttons requesting a title suggested on a success card
+pub const SIMILAR_PREFIX: &str = "similar";
+/// Custom id prefix of the buttons removing a title listed by `/request remove`
+pub const REMOVE_PREFIX: &str = "remove";
+/// Custom id prefix of the button confirming `/forget-me` or `/forget-user`
+pub const FORGET_PREFIX: &str = "forget";
+/// Custom id prefixes of the buttons on the warning about a risky pick
+const PROCEED_PREFIX: &str = "proceed";
+const BACK_PREFIX: &str = "back";
+/// Custom id prefix of the button previewing search results
+const PREVIEW_PREFIX: &str = "preview";
+/// Prefix of the "Also notify" user picker's custom ID on the request form
+const NOTIFY_PREFIX: &str = "notify";
+/// Prefix of the category picker's custom ID on the request form
+const CATEGORY_PREFIX: &str = "category";
 
 /// Discord's maximum number of options in a dropdown menu
 pub const MAX_DROPDOWN_OPTIONS: usize = 25;
 
+/// Discord's maximum number of choices for a command option
+pub const MAX_COMMAND_CHOICES: usize = 25;
+
+/// Discord's maximum number of subcommands in a command
+pub const MAX_SUBCOMMANDS: usize = 25;
+
+/// Discord's maximum character length of a dropdown option's label or description
+const MAX_OPTION_TEXT_LENGTH: usize = 100;
+
+/// Discord's maximum character length for a message's content
+pub const MAX_MESSAGE_LENGTH: usize = 2000;
+
 /// Discord's maximum character length for text content in components
 const MAX_TEXT_CONTENT_LENGTH: usize = 4000;
 
+/// Discord's maximum number of components in a message, counting nested ones
+const MAX_MESSAGE_COMPONENTS: usize = 40;
+
+/// Discord's maximum number of buttons in an action row
+const MAX_ROW_BUTTONS: usize = 5;
+
+/// Discord's maximum character length of a button's label
+const MAX_BUTTON_LABEL_LENGTH: usize = 80;
+
+/// How many people a requester can have notified besides themselves
+const MAX_SUBSCRIBERS: u8 = 5;
+
+/// How many of the top search results the Preview button cycles through
+const PREVIEWED_RESULTS: usize = 5;
+
 const ACCENT_COLOR: u32 = 0xCE4A28;
 
-fn escape_markdown(s: &str) -> String {
+pub fn escape_markdown(s: &str) -> String {
     s.replace('\\', "\\\\")
         .replace('*', "\\*")
         .replace('_', "\\_")
@@ -55,37 +175,396 @@ fn escape_markdown(s: &str) -> String {
 
 const INTERACTION_TIMEOUT_DURATION: Duration = Duration::from_secs(300);
 
-/// Truncate text to Discord's component text limit, respecting char boundaries
-fn truncate_text(text: &str) -> String {
-    if text.len() <= MAX_TEXT_CONTENT_LENGTH {
-        return text.to_string();
+/// How long Discord accepts an interaction's token for editing its message
+const TOKEN_LIFETIME: Duration = Duration::from_secs(15 * 60);
+
+/// Stop relying on a token this long before it expires, so the last edit
+/// still lands
+const TOKEN_MARGIN: Duration = Duration::from_secs(30);
+
+/// The token a flow edits its message with. Every click on the message comes
+/// with a token of its own that can edit it too, so the flow moves on to the
+/// newest one and only runs out if the user goes quiet.
+#[derive(Debug, Clone)]
+struct Anchor {
+    token: String,
+    issued: Instant,
+}
+
+impl Anchor {
+    fn new(token: String) -> Self {
+        Self {
+            token,
+            issued: Instant::now(),
+        }
     }
-    let mut end = MAX_TEXT_CONTENT_LENGTH - 3;
-    while !text.is_char_boundary(end) {
-        end -= 1;
+
+    /// How much longer the token can safely be used
+    fn remaining(&self) -> Duration {
+        TOKEN_LIFETIME
+            .saturating_sub(TOKEN_MARGIN)
+            .saturating_sub(self.issued.elapsed())
     }
-    format!("{}...", &text[..end])
 }
 
 /// Build the comand object, used to register with Discord what slash commands are available
-pub fn commands<T: AsRef<str>>(media_kinds: impl IntoIterator<Item = T>) -> Command {
+pub fn commands<T: AsRef<str>, P: AsRef<str>>(
+    media_kinds: impl IntoIterator<Item = (T, Vec<P>)>,
+) -> Command {
     let query = StringBuilder::new(QUERY_COMMAND_NAME, "search query").required(true);
+    let quick = BooleanBuilder::new(
+        QUICK_COMMAND_NAME,
+        "request the top result with default options, without the menus",
+    );
+    let year = IntegerBuilder::new(YEAR_COMMAND_NAME, "release year, to pick out remakes")
+        .min_value(1800)
+        .max_value(9999);
+    let need_by = StringBuilder::new(
+        NEED_BY_OPTION,
+        "date you need it by, e.g. friday or 2024-03-08, to chase it up if it's late",
+    );
     let mut request_command = CommandBuilder::new(
         TOP_LEVEL_COMMAND_NAME,
         "Request media",
         CommandType::ChatInput,
     );
-    for kind in media_kinds {
-        request_command = request_command.option(
+    let mut kinds = Vec::new();
+    for (kind, profiles) in media_kinds {
+        kinds.push(kind.as_ref().to_string());
+        let mut subcommand =
             SubCommandBuilder::new(kind.as_ref(), format!("Request {}", kind.as_ref()))
-                .option(query.clone()),
-        )
+                .option(query.clone())
+                .option(quick.clone())
+                .option(year.clone())
+                .option(need_by.clone());
+        // Only offered when the media command has profiles to pick from
+        if !profiles.is_empty() {
+            subcommand = subcommand.option(
+                StringBuilder::new(PROFILE_COMMAND_NAME, "preset request options").choices(
+                    profiles
+                        .iter()
+                        .map(|p| (p.as_ref().to_string(), p.as_ref().to_string())),
+                ),
+            );
+        }
+        request_command = request_command.option(subcommand)
     }
-    request_command.build()
+    // Everyone sees it, as it's part of `/request`; the handler checks for
+    // "Manage Server" before searching
+    request_command
+        .option(
+            SubCommandBuilder::new(
+                REMOVE_SUBCOMMAND_NAME,
+                "Remove a title from the library (admins only)",
+            )
+            .option(
+                StringBuilder::new(MEDIA_OPTION, "kind of media")
+                    .required(true)
+                    .choices(kinds.into_iter().map(|k| (k.clone(), k))),
+            )
+            .option(query)
+            .option(BooleanBuilder::new(
+                DELETE_FILES_OPTION,
+                "delete its files from disk too (default: keep them)",
+            )),
+        )
+        .build()
+}
+
+/// Build the admin command for taking backends in and out of service.
+/// Restricted to members with "Manage Server" by default; server owners can
+/// adjust who sees it under Server Settings → Integrations.
+pub fn backend_admin_command<T: AsRef<str>>(names: impl IntoIterator<Item = T>) -> Command {
+    let name = StringBuilder::new(BACKEND_NAME_OPTION, "backend instance")
+        .required(true)
+        .choices(
+            names
+                .into_iter()
+                .map(|n| (n.as_ref().to_string(), n.as_ref().to_string())),
+        );
+    CommandBuilder::new(
+        BACKEND_COMMAND_NAME,
+        "Manage request backends",
+        CommandType::ChatInput,
+    )
+    .default_member_permissions(Permissions::MANAGE_GUILD)
+    .option(SubCommandBuilder::new(
+        "list",
+        "Show every backend and whether it's enabled",
+    ))
+    .option(SubCommandBuilder::new(
+        "status",
+        "Show recent latency and error rates for every backend",
+    ))
+    .option(
+        SubCommandBuilder::new("disable", "Temporarily stop taking requests for a backend")
+            .option(name.clone()),
+    )
+    .option(
+        SubCommandBuilder::new("enable", "Resume taking requests for a backend")
+            .option(name.clone()),
+    )
+    .option(
+        SubCommandBuilder::new(
+            "refresh",
+            "Refresh metadata and rescan files, for one title or the whole library",
+        )
+        .option(name.clone())
+        .option(StringBuilder::new(
+            TITLE_OPTION,
+            "exact title in the library (default: everything)",
+        )),
+    )
+    .option(
+        SubCommandBuilder::new("rss-sync", "Check the indexers for new releases now")
+            .option(name.clone()),
+    )
+    .option(
+        SubCommandBuilder::new(
+            "rotate-key",
+            "Reconnect a backend with a new API key, without restarting",
+        )
+        .option(name)
+        .option(StringBuilder::new(API_KEY_OPTION, "the new API key").required(true)),
+    )
+    .build()
+}
+
+/// Build the admin commands for blocking users from making requests, and
+/// lifting the block. Restricted like [backend_admin_command].
+pub fn ban_commands() -> [Command; 2] {
+    let user = UserBuilder::new(USER_OPTION, "the user").required(true);
+    [
+        CommandBuilder::new(
+            BAN_COMMAND_NAME,
+            "Block a user from making requests",
+            CommandType::ChatInput,
+        )
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .option(user.clone())
+        .option(StringBuilder::new(
+            REASON_OPTION,
+            "shown to the user when their requests are rejected",
+        ))
+        .build(),
+        CommandBuilder::new(
+            UNBAN_COMMAND_NAME,
+            "Let a blocked user make requests again",
+            CommandType::ChatInput,
+        )
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .option(user)
+        .build(),
+    ]
+}
+
+/// Build the commands that take a user out of the request log: `/forget-me`
+/// for anyone, and `/forget-user` restricted like [backend_admin_command].
+/// Both ask for a click to confirm.
+pub fn forget_commands() -> [Command; 2] {
+    [
+        CommandBuilder::new(
+            FORGET_ME_COMMAND_NAME,
+            "Delete your request history and everything else the bot keeps about you",
+            CommandType::ChatInput,
+        )
+        .build(),
+        CommandBuilder::new(
+            FORGET_USER_COMMAND_NAME,
+            "Delete a user's request history and everything else the bot keeps about them",
+            CommandType::ChatInput,
+        )
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .option(UserBuilder::new(USER_OPTION, "the user").required(true))
+        .build(),
+    ]
+}
+
+/// Build the admin command that checks every backend is reachable.
+/// Restricted like [backend_admin_command].
+pub fn ping_command() -> Command {
+    CommandBuilder::new(
+        PING_COMMAND_NAME,
+        "Check that every backend is reachable, and how fast it responds",
+        CommandType::ChatInput,
+    )
+    .default_member_permissions(Permissions::MANAGE_GUILD)
+    .build()
+}
+
+/// Build the admin command that has backends search for everything missing,
+/// optionally limited to one media command. Restricted like [backend_admin_command].
+pub fn search_missing_command<T: AsRef<str>>(media_kinds: impl IntoIterator<Item = T>) -> Command {
+    CommandBuilder::new(
+        SEARCH_MISSING_COMMAND_NAME,
+        "Search the indexers for every monitored title that's still missing",
+        CommandType::ChatInput,
+    )
+    .default_member_permissions(Permissions::MANAGE_GUILD)
+    .option(
+        StringBuilder::new(MEDIA_OPTION, "only this media command (default: all)").choices(
+            media_kinds
+                .into_iter()
+                .map(|m| (m.as_ref().to_string(), m.as_ref().to_string())),
+        ),
+    )
+    .build()
+}
+
+/// Build the admin command that exports this server's request log as a file.
+/// Restricted like [backend_admin_command].
+pub fn export_command() -> Command {
+    CommandBuilder::new(
+        EXPORT_COMMAND_NAME,
+        "Export this server's requests as a CSV or JSON file",
+        CommandType::ChatInput,
+    )
+    .default_member_permissions(Permissions::MANAGE_GUILD)
+    .option(
+        StringBuilder::new(FORMAT_OPTION, "file format (default: csv)")
+            .choices([("csv", "csv"), ("json", "json")]),
+    )
+    .option(UserBuilder::new(USER_OPTION, "only this user's requests"))
+    .option(StringBuilder::new(
+        SINCE_OPTION,
+        "only requests from this day on, as YYYY-MM-DD (UTC)",
+    ))
+    .option(StringBuilder::new(
+        UNTIL_OPTION,
+        "only requests up to and including this day, as YYYY-MM-DD (UTC)",
+    ))
+    .build()
+}
+
+/// Build the command that shows requesters their latest requests and where
+/// they stand. Open to everyone, like `/request`.
+pub fn requests_command() -> Command {
+    CommandBuilder::new(
+        REQUESTS_COMMAND_NAME,
+        "Show your latest requests and where they stand",
+        CommandType::ChatInput,
+    )
+    .build()
+}
+
+/// Build the command for asking for a better copy of a title that's already
+/// in the library. Only offered with `upgrade_roles`, which are checked when
+/// it's used.
+pub fn upgrade_command<T: AsRef<str>>(media_kinds: impl IntoIterator<Item = T>) -> Command {
+    CommandBuilder::new(
+        UPGRADE_COMMAND_NAME,
+        "Get a better copy of a title that's already in the library",
+        CommandType::ChatInput,
+    )
+    .option(
+        StringBuilder::new(MEDIA_OPTION, "the media command it was requested with")
+            .required(true)
+            .choices(
+                media_kinds
+                    .into_iter()
+                    .map(|m| (m.as_ref().to_string(), m.as_ref().to_string())),
+            ),
+    )
+    .option(StringBuilder::new(TITLE_OPTION, "exact title in the library").required(true))
+    .option(StringBuilder::new(
+        QUALITY_PROFILE_OPTION,
+        "quality profile to move it to (default: keep its own)",
+    ))
+    .build()
+}
+
+/// Build the admin command that lists the requests waiting for approval.
+/// Restricted like [backend_admin_command].
+pub fn pending_command() -> Command {
+    CommandBuilder::new(
+        PENDING_COMMAND_NAME,
+        "List the requests waiting for an admin's approval",
+        CommandType::ChatInput,
+    )
+    .default_member_permissions(Permissions::MANAGE_GUILD)
+    .build()
+}
+
+/// Build the admin command that shows where request flows spend their time.
+/// Restricted like [backend_admin_command].
+pub fn report_command() -> Command {
+    CommandBuilder::new(
+        REPORT_COMMAND_NAME,
+        "Show how long requesters spend on each step of a request",
+        CommandType::ChatInput,
+    )
+    .default_member_permissions(Permissions::MANAGE_GUILD)
+    .build()
+}
+
+/// Build the admin command that shows what happened to a submitted request.
+/// Restricted like [backend_admin_command].
+pub fn audit_command() -> Command {
+    CommandBuilder::new(
+        AUDIT_COMMAND_NAME,
+        "Show the record of a submitted request, or list the latest ones",
+        CommandType::ChatInput,
+    )
+    .default_member_permissions(Permissions::MANAGE_GUILD)
+    .option(StringBuilder::new(
+        ID_OPTION,
+        "the request's reference ID (default: list the latest)",
+    ))
+    .build()
+}
+
+/// Respond to a slash command with a short message only the invoking user can see
+pub async fn respond_ephemeral_message(
+    client: &Arc<HttpClient>,
+    application_id: Id<ApplicationMarker>,
+    interaction_id: Id<InteractionMarker>,
+    interaction_token: &str,
+    content: &str,
+) -> anyhow::Result<()> {
+    let component = ContainerBuilder::new()
+        .accent_color(Some(ACCENT_COLOR))
+        .component(TextDisplayBuilder::new(content).build())
+        .build()
+        .into();
+    respond_ephemeral_component(
+        client,
+        application_id,
+        interaction_id,
+        interaction_token,
+        component,
+    )
+    .await
+}
+
+/// Respond to a slash command with a component only the invoking user can see
+pub async fn respond_ephemeral_component(
+    client: &Arc<HttpClient>,
+    application_id: Id<ApplicationMarker>,
+    interaction_id: Id<InteractionMarker>,
+    interaction_token: &str,
+    component: Component,
+) -> anyhow::Result<()> {
+    client
+        .interaction(application_id)
+        .create_response(
+            interaction_id,
+            interaction_token,
+            &InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .flags(MessageFlags::IS_COMPONENTS_V2 | MessageFlags::EPHEMERAL)
+                        .components(vec![component])
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+    Ok(())
 }
 
 /// Updates an existing interaction with a new component (ephemeral and supporting V2 components)
-async fn update_interaction_component(
+pub async fn update_interaction_component(
     client: &Arc<HttpClient>,
     application_id: Id<ApplicationMarker>,
     interaction_token: &str,
@@ -101,7 +580,7 @@ async fn update_interaction_component(
 }
 
 /// Responds to an interaction with an updated message, using a comonent as the body (ephemeral / supporting V2 components)
-async fn respond_interaction_component(
+pub async fn respond_interaction_component(
     client: &Arc<HttpClient>,
     application_id: Id<ApplicationMarker>,
     interaction_id: Id<InteractionMarker>,
@@ -129,7 +608,7 @@ async fn respond_interaction_component(
 
 /// Acknowledge a component interaction without changing the message, so Discord
 /// doesn't show "interaction failed" for events we intentionally ignore
-async fn ack_component(
+pub async fn ack_component(
     client: &Arc<HttpClient>,
     application_id: Id<ApplicationMarker>,
     interaction_id: Id<InteractionMarker>,
@@ -176,9 +655,36 @@ pub async fn send_thinking(
 
 /// Convert a vector of [DropdownOption] into a discord Select Menu, keyed by the vec index.
 /// `selected_indices` marks those options as default so Discord preserves the selection on re-render.
+/// Fit option labels and descriptions to Discord's limits, which reject the
+/// whole menu when any one runs over. Labels that only collide because they
+/// were shortened get a counter, so the options stay distinguishable.
+fn fit_dropdown_options(options: &mut [DropdownOption]) {
+    let mut seen = HashSet::new();
+    for option in options.iter_mut() {
+        if let Some(description) = &mut option.description {
+            *description = text::truncate(description, MAX_OPTION_TEXT_LENGTH);
+        }
+        let fitted = text::truncate(&option.title, MAX_OPTION_TEXT_LENGTH);
+        if fitted == option.title {
+            seen.insert(fitted);
+            continue;
+        }
+        let mut label = fitted;
+        let mut n = 2;
+        while seen.contains(&label) {
+            let suffix = format!(" ({n})");
+            let room = MAX_OPTION_TEXT_LENGTH - suffix.chars().count();
+            label = format!("{}{suffix}", text::truncate(&option.title, room));
+            n += 1;
+        }
+        seen.insert(label.clone());
+        option.title = label;
+    }
+}
+
 /// When `max_values` is `Some(n)`, the menu allows selecting 1–n items (multi-select).
 fn dropdown_options_to_select_menu<T: AsRef<str>>(
-    options: Vec<DropdownOption>,
+    mut options: Vec<DropdownOption>,
     selected_indices: &[usize],
     id: T,
     uuid: Uuid,
@@ -197,6 +703,7 @@ fn dropdown_options_to_select_menu<T: AsRef<str>>(
         menu = menu.min_values(1).max_values(max);
     }
 
+    fit_dropdown_options(&mut options);
     for (i, option) in options.into_iter().enumerate() {
         let mut menu_option = SelectMenuOptionBuilder::new(option.title, i.to_string())
             .default(selected_indices.contains(&i));
@@ -209,27 +716,62 @@ fn dropdown_options_to_select_menu<T: AsRef<str>>(
     ActionRowBuilder::new().component(menu.build()).build()
 }
 
-/// Using the result payload from a search, create a dropdown that will select a search result
-pub async fn update_search_results_component(
+/// Using the result payload from a search, create a dropdown that will select
+/// a search result, with a button previewing the top results one at a time.
+/// `preview`, when given, is the result shown above the dropdown, by its
+/// index. `notice`, when given, is shown as a small note under the dropdown.
+fn build_search_results_component(
     uuid: Uuid,
-    options: Vec<DropdownOption>,
-    client: &Arc<HttpClient>,
-    application_id: Id<ApplicationMarker>,
-    interaction_token: &str,
-) -> anyhow::Result<()> {
-    let dropdown = dropdown_options_to_select_menu(options, &[], "result", uuid, None, false, None);
-
-    let component = ContainerBuilder::new()
+    options: &[DropdownOption],
+    notice: Option<&str>,
+    preview: Option<(usize, &MediaDisplayInfo)>,
+    overview: OverviewMode,
+) -> Component {
+    let previewed = options.len().min(PREVIEWED_RESULTS);
+    let mut container = ContainerBuilder::new()
         .accent_color(Some(ACCENT_COLOR))
         .component(TextDisplayBuilder::new("# Search Results").build())
-        .component(SeparatorBuilder::new().build())
-        .component(dropdown)
+        .component(SeparatorBuilder::new().build());
+    if let Some((index, display_info)) = preview {
+        container = media_header(container, display_info, overview).component(
+            TextDisplayBuilder::new(format!(
+                "-# Result {} of {previewed}. Pick it from the menu to request it.",
+                index + 1
+            ))
+            .build(),
+        );
+    }
+    let label = match preview {
+        Some(_) => "Next preview",
+        None => "Preview",
+    };
+    let footer = match notice {
+        Some(notice) => format!("-# {notice}\n{}", expiry_note()),
+        None => expiry_note(),
+    };
+    container
+        .component(dropdown_options_to_select_menu(
+            options.to_vec(),
+            &[],
+            "result",
+            uuid,
+            None,
+            false,
+            None,
+        ))
+        .component(
+            ActionRowBuilder::new()
+                .component(
+                    ButtonBuilder::new(ButtonStyle::Secondary)
+                        .label(label)
+                        .custom_id(format!("{PREVIEW_PREFIX}:{uuid}"))
+                        .build(),
+                )
+                .build(),
+        )
+        .component(TextDisplayBuilder::new(footer).build())
         .build()
-        .into();
-
-    // And update the interaction with discord
-    update_interaction_component(client, application_id, interaction_token, component).await?;
-    Ok(())
+        .into()
 }
 
 pub async fn update_string_message(
@@ -247,29 +789,21 @@ pub async fn update_string_message(
     Ok(())
 }
 
-pub async fn update_timeout(
+/// Update a deferred response with a short message and a file attached below it
+pub async fn update_file_message(
+    content: &str,
+    filename: &str,
+    file: Vec<u8>,
     client: &Arc<HttpClient>,
     application_id: Id<ApplicationMarker>,
     interaction_token: &str,
 ) -> anyhow::Result<()> {
-    update_string_message(TIMEOUT_MESSAGE, client, application_id, interaction_token).await
-}
-
-fn build_request_component(
-    uuid: Uuid,
-    display_info: &MediaDisplayInfo,
-    request_details: &[RequestDetails],
-    user_selectable_fields: &std::collections::HashSet<String>,
-    submitting: bool,
-) -> Component {
-    // Build the container that holds everything
-    let mut container = ContainerBuilder::new().accent_color(Some(ACCENT_COLOR));
-
-    // Build the media overview
-    if let Some(thumbnail_url) = &display_info.thumbnail_url {
-        let mut section = SectionBuilder::new(
-            ThumbnailBuilder::new(UnfurledMediaItem {
-                url: thumbnail_url.clone(),
+    let component = ContainerBuilder::new()
+        .accent_color(Some(ACCENT_COLOR))
+        .component(TextDisplayBuilder::new(content).build())
+        .component(
+            FileDisplayBuilder::new(UnfurledMediaItem {
+                url: format!("attachment://{filename}"),
                 proxy_url: None,
                 height: None,
                 width: None,
@@ -277,51 +811,688 @@ fn build_request_component(
             })
             .build(),
         )
-        .component(
-            TextDisplayBuilder::new(format!("# {}", escape_markdown(&display_info.title))).build(),
-        );
-
-        // Only add subtitle if it exists
-        if let Some(subtitle) = &display_info.subtitle {
-            section = section.component(
-                TextDisplayBuilder::new(format!("-# {}", escape_markdown(subtitle))).build(),
-            );
-        }
+        .build()
+        .into();
+    client
+        .interaction(application_id)
+        .update_response(interaction_token)
+        .components(Some(&[component]))
+        .attachments(&[Attachment::from_bytes(filename.to_string(), file, 0)])
+        .flags(MessageFlags::IS_COMPONENTS_V2 | MessageFlags::EPHEMERAL)
+        .await?;
+    Ok(())
+}
 
-        let overview = display_info
-            .description
-            .as_deref()
-            .filter(|s| !s.is_empty())
-            .map_or("*Overview unavailable.*", |s| s);
-        section = section.component(TextDisplayBuilder::new(truncate_text(overview)).build());
+/// The message left on a timed-out flow, with a button to start the same
+/// request over instead of retyping the command
+fn build_timeout_component(content: &str, uuid: Uuid) -> Component {
+    ContainerBuilder::new()
+        .accent_color(Some(ACCENT_COLOR))
+        .component(TextDisplayBuilder::new(content).build())
+        .component(
+            ActionRowBuilder::new()
+                .component(
+                    ButtonBuilder::new(ButtonStyle::Secondary)
+                        .label("Start over with same query")
+                        .custom_id(format!("{RESUME_PREFIX}:{uuid}"))
+                        .build(),
+                )
+                .build(),
+        )
+        .build()
+        .into()
+}
 
-        container = container.component(section.build());
-    } else {
-        container = container.component(
-            TextDisplayBuilder::new(format!("# {}", escape_markdown(&display_info.title))).build(),
-        );
-        if let Some(subtitle) = &display_info.subtitle {
-            container = container.component(
-                TextDisplayBuilder::new(format!("-# {}", escape_markdown(subtitle))).build(),
-            );
-        }
-        let overview = display_info
-            .description
-            .as_deref()
-            .filter(|s| !s.is_empty())
-            .map_or("*Overview unavailable.*", |s| s);
-        container = container.component(TextDisplayBuilder::new(truncate_text(overview)).build());
-    }
+/// The message left when a search finds nothing, with a button to edit the
+/// query and search again instead of retyping the command
+fn build_no_results_component(content: &str, uuid: Uuid) -> Component {
+    ContainerBuilder::new()
+        .accent_color(Some(ACCENT_COLOR))
+        .component(TextDisplayBuilder::new(content).build())
+        .component(
+            ActionRowBuilder::new()
+                .component(
+                    ButtonBuilder::new(ButtonStyle::Secondary)
+                        .label("Edit search")
+                        .custom_id(format!("{RESEARCH_PREFIX}:{uuid}"))
+                        .build(),
+                )
+                .build(),
+        )
+        .build()
+        .into()
+}
 
-    // Build the additional options
-    // Show dropdowns that still need selection, and text for completed selections
-    let mut selections_remaining = false;
+/// Respond to the "Edit search" button with a modal holding the query that
+/// found nothing, ready to be edited
+#[allow(deprecated)] // TextInput::label, superseded by the Label around it
+pub async fn respond_research_modal(
+    client: &Arc<HttpClient>,
+    application_id: Id<ApplicationMarker>,
+    interaction_id: Id<InteractionMarker>,
+    interaction_token: &str,
+    uuid: Uuid,
+    query: &str,
+) -> anyhow::Result<()> {
+    let input = TextInput {
+        id: None,
+        custom_id: QUERY_COMMAND_NAME.to_string(),
+        label: None,
+        max_length: None,
+        min_length: Some(1),
+        placeholder: None,
+        required: Some(true),
+        style: TextInputStyle::Short,
+        value: Some(query.to_string()),
+    };
+    let label = Label {
+        id: None,
+        label: "Search for".to_string(),
+        description: None,
+        component: Box::new(Component::TextInput(input)),
+    };
+    client
+        .interaction(application_id)
+        .create_response(
+            interaction_id,
+            interaction_token,
+            &InteractionResponse {
+                kind: InteractionResponseType::Modal,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .custom_id(format!("{RESEARCH_PREFIX}:{uuid}"))
+                        .title("Search again")
+                        .components([Component::Label(label)])
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+    Ok(())
+}
 
-    for detail in request_details {
-        // Only show fields that were user-selectable (had multiple options initially)
-        let is_user_selectable = detail
-            .metadata
-            .as_ref()
+/// Respond to an admin's "Deny" click with a modal asking why, which is
+/// passed on to the requester
+#[allow(deprecated)] // TextInput::label, superseded by the Label around it
+pub async fn respond_denial_modal(
+    client: &Arc<HttpClient>,
+    application_id: Id<ApplicationMarker>,
+    interaction_id: Id<InteractionMarker>,
+    interaction_token: &str,
+    uuid: Uuid,
+) -> anyhow::Result<()> {
+    let input = TextInput {
+        id: None,
+        custom_id: REASON_OPTION.to_string(),
+        label: None,
+        max_length: Some(MAX_DENIAL_REASON_LENGTH),
+        min_length: Some(1),
+        placeholder: Some("e.g. We already have it in another edition".to_string()),
+        required: Some(true),
+        style: TextInputStyle::Short,
+        value: None,
+    };
+    let label = Label {
+        id: None,
+        label: "Reason".to_string(),
+        description: Some("Shown to the requester".to_string()),
+        component: Box::new(Component::TextInput(input)),
+    };
+    client
+        .interaction(application_id)
+        .create_response(
+            interaction_id,
+            interaction_token,
+            &InteractionResponse {
+                kind: InteractionResponseType::Modal,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .custom_id(format!("{DENY_PREFIX}:{uuid}"))
+                        .title("Deny request")
+                        .components([Component::Label(label)])
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+    Ok(())
+}
+
+/// The longest reason an admin can give for a denial
+const MAX_DENIAL_REASON_LENGTH: u16 = 300;
+
+/// The text typed into a modal's input, if it isn't blank
+fn modal_input(data: &ModalInteractionData, custom_id: &str) -> Option<String> {
+    fn find<'a>(components: &'a [ModalInteractionComponent], custom_id: &str) -> Option<&'a str> {
+        components.iter().find_map(|component| match component {
+            ModalInteractionComponent::TextInput(input) if input.custom_id == custom_id => {
+                Some(input.value.as_str())
+            }
+            ModalInteractionComponent::Label(label) => {
+                find(std::slice::from_ref(&label.component), custom_id)
+            }
+            ModalInteractionComponent::ActionRow(row) => find(&row.components, custom_id),
+            _ => None,
+        })
+    }
+    let text = find(&data.components, custom_id)?.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// The query typed into the "Search again" modal, if it isn't blank
+pub fn modal_query(data: &ModalInteractionData) -> Option<String> {
+    modal_input(data, QUERY_COMMAND_NAME)
+}
+
+/// Why an admin denied a request, from the "Deny request" modal
+pub fn modal_reason(data: &ModalInteractionData) -> Option<String> {
+    modal_input(data, REASON_OPTION)
+}
+
+/// The options the user picked that need a go-ahead before requesting, as
+/// the field, the option, and why. Admin-configured values aren't the user's
+/// pick, so they're never questioned.
+fn pick_warnings<'a>(
+    details: &'a [RequestDetails],
+    user_selectable_fields: &HashSet<String>,
+) -> Vec<(&'a str, &'a str, &'a str)> {
+    details
+        .iter()
+        .filter(|d| {
+            d.metadata
+                .as_ref()
+                .is_some_and(|m| user_selectable_fields.contains(m))
+        })
+        .flat_map(|d| {
+            d.selected_options()
+                .filter_map(|o| Some((d.title.as_str(), o.title.as_str(), o.warning.as_deref()?)))
+        })
+        .collect()
+}
+
+/// Asks the user to confirm picks with consequences (e.g. monitoring nothing)
+/// before requesting, or go back and change them
+fn build_warning_component(uuid: Uuid, warnings: &[(&str, &str, &str)]) -> Component {
+    let mut text = "### Are you sure?".to_string();
+    for (field, option, warning) in warnings {
+        text.push_str(&format!(
+            "\n**{}: {}** — {warning}",
+            escape_markdown(field),
+            escape_markdown(option)
+        ));
+    }
+    ContainerBuilder::new()
+        .accent_color(Some(ACCENT_COLOR))
+        .component(TextDisplayBuilder::new(text).build())
+        .component(
+            ActionRowBuilder::new()
+                .component(
+                    ButtonBuilder::new(ButtonStyle::Danger)
+                        .label("Request anyway")
+                        .custom_id(format!("{PROCEED_PREFIX}:{uuid}"))
+                        .build(),
+                )
+                .component(
+                    ButtonBuilder::new(ButtonStyle::Secondary)
+                        .label("Go back")
+                        .custom_id(format!("{BACK_PREFIX}:{uuid}"))
+                        .build(),
+                )
+                .build(),
+        )
+        .component(TextDisplayBuilder::new(expiry_note()).build())
+        .build()
+        .into()
+}
+
+/// The card admins see for a request from someone who isn't trusted to
+/// request on their own. Mentions in it never ping anyone.
+fn build_approval_component(request: &ApprovalRequest, card: ApprovalCard) -> Component {
+    let heading = match &card {
+        ApprovalCard::Suggested => "### Suggestion".to_string(),
+        ApprovalCard::Waiting(_) => "### Approval needed".to_string(),
+        ApprovalCard::Decided(ApprovalDecision::Approved(admin)) => {
+            format!("### Approved by <@{admin}>")
+        }
+        ApprovalCard::Decided(ApprovalDecision::Denied(admin, reason)) => {
+            format!("### Denied by <@{admin}>\n> {}", escape_markdown(reason))
+        }
+        ApprovalCard::Expired => "### Expired without a decision".to_string(),
+    };
+    let mut container = ContainerBuilder::new()
+        .accent_color(Some(ACCENT_COLOR))
+        .component(
+            TextDisplayBuilder::new(format!("{heading}\n{}", approval_text(request))).build(),
+        );
+    if let ApprovalCard::Waiting(uuid) = card {
+        container = container.component(approval_buttons(uuid));
+    }
+    container.build().into()
+}
+
+/// What was requested, for whom, and when
+fn approval_text(request: &ApprovalRequest) -> String {
+    let mut text = format!(
+        "**{}** for <@{}> · `/request {}` · <t:{}:R>",
+        escape_markdown(&request.title),
+        request.user_id,
+        request.media,
+        request.at
+    );
+    if let Some(details) = &request.details {
+        text.push_str(&format!("\n-# {details}"));
+    }
+    text
+}
+
+fn approval_buttons(uuid: Uuid) -> ActionRow {
+    ActionRowBuilder::new()
+        .component(
+            ButtonBuilder::new(ButtonStyle::Success)
+                .label("Approve")
+                .custom_id(format!("{APPROVE_PREFIX}:{uuid}"))
+                .build(),
+        )
+        .component(
+            ButtonBuilder::new(ButtonStyle::Danger)
+                .label("Deny")
+                .custom_id(format!("{DENY_PREFIX}:{uuid}"))
+                .build(),
+        )
+        .build()
+}
+
+/// Most requests `/pending` lists with their buttons, keeping the message
+/// within Discord's component limit
+const MAX_PENDING_LISTED: usize = 8;
+
+/// The requests waiting for approval, oldest first, each with its own
+/// Approve and Deny buttons
+pub fn build_pending_component(pending: &[(Uuid, ApprovalRequest)]) -> Component {
+    let mut container = ContainerBuilder::new()
+        .accent_color(Some(ACCENT_COLOR))
+        .component(
+            TextDisplayBuilder::new(format!("### Waiting for approval ({})", pending.len()))
+                .build(),
+        );
+    if pending.is_empty() {
+        container = container
+            .component(TextDisplayBuilder::new("Nothing is waiting for approval.").build());
+    }
+    for (uuid, request) in pending.iter().take(MAX_PENDING_LISTED) {
+        container = container
+            .component(TextDisplayBuilder::new(approval_text(request)).build())
+            .component(approval_buttons(*uuid));
+    }
+    if pending.len() > MAX_PENDING_LISTED {
+        container = container.component(
+            TextDisplayBuilder::new(format!(
+                "-# …and {} more, listed once these are decided",
+                pending.len() - MAX_PENDING_LISTED
+            ))
+            .build(),
+        );
+    }
+    container.build().into()
+}
+
+/// Most library titles `/request remove` lists, in two rows of buttons
+pub const MAX_REMOVALS_LISTED: usize = 2 * MAX_ROW_BUTTONS;
+
+/// The library titles a `/request remove` search found, each with a button
+/// that removes it
+pub fn build_removals_component(query: &str, listed: &[(Uuid, Removal)]) -> Component {
+    let mut container = ContainerBuilder::new()
+        .accent_color(Some(ACCENT_COLOR))
+        .component(TextDisplayBuilder::new("### Remove from the library").build());
+    let delete_files = listed.iter().any(|(_, r)| r.delete_files);
+    let text = if listed.is_empty() {
+        format!(
+            "Nothing in the library matches \"{}\".",
+            escape_markdown(query)
+        )
+    } else if delete_files {
+        "Click a title to remove it and delete its files. This can't be undone.".to_string()
+    } else {
+        "Click a title to remove it. Its files stay on disk.".to_string()
+    };
+    container = container.component(TextDisplayBuilder::new(text).build());
+    for row in listed.chunks(MAX_ROW_BUTTONS) {
+        container = container.component(
+            row.iter()
+                .fold(ActionRowBuilder::new(), |row, (id, removal)| {
+                    row.component(
+                        ButtonBuilder::new(ButtonStyle::Danger)
+                            .label(text::truncate(&removal.label, MAX_BUTTON_LABEL_LENGTH))
+                            .custom_id(format!("{REMOVE_PREFIX}:{id}"))
+                            .build(),
+                    )
+                })
+                .build(),
+        );
+    }
+    container.build().into()
+}
+
+/// Asks to confirm forgetting `user_id`, which is the one asking when
+/// `themselves`
+pub fn build_forget_component(id: Uuid, user_id: Id<UserMarker>, themselves: bool) -> Component {
+    let (whose, label) = if themselves {
+        ("your".to_string(), "Forget me")
+    } else {
+        (format!("<@{user_id}>'s"), "Forget them")
+    };
+    ContainerBuilder::new()
+        .accent_color(Some(ACCENT_COLOR))
+        .component(
+            TextDisplayBuilder::new(format!(
+                "### Forget {whose} requests?\nThis deletes {whose} request history, audit \
+                 records, saved name, queued requests and places on Also notify lists, on \
+                 every server this bot is in. Titles already requested stay in the library, \
+                 and bans stay. This can't be undone."
+            ))
+            .build(),
+        )
+        .component(
+            ActionRowBuilder::new()
+                .component(
+                    ButtonBuilder::new(ButtonStyle::Danger)
+                        .label(label)
+                        .custom_id(format!("{FORGET_PREFIX}:{id}"))
+                        .build(),
+                )
+                .build(),
+        )
+        .build()
+        .into()
+}
+
+/// Post an approval card to the admins' channel
+async fn post_approval_card(
+    client: &Arc<HttpClient>,
+    channel_id: Id<ChannelMarker>,
+    component: Component,
+) -> anyhow::Result<Id<MessageMarker>> {
+    let message = client
+        .create_message(channel_id)
+        .flags(MessageFlags::IS_COMPONENTS_V2)
+        .components(&[component])
+        .allowed_mentions(Some(&AllowedMentions::default()))
+        .await?
+        .model()
+        .await?;
+    Ok(message.id)
+}
+
+/// Settle an approval card that was decided from elsewhere (e.g. `/pending`)
+pub async fn update_approval_card(
+    client: &Arc<HttpClient>,
+    (channel_id, message_id): ApprovalCardRef,
+    request: &ApprovalRequest,
+    decision: ApprovalDecision,
+) -> anyhow::Result<()> {
+    client
+        .update_message(channel_id, message_id)
+        .components(Some(&[build_approval_component(
+            request,
+            ApprovalCard::Decided(decision),
+        )]))
+        .await?;
+    Ok(())
+}
+
+/// Settle an approval card in place, in response to the admin's button click
+pub async fn respond_approval_decision(
+    client: &Arc<HttpClient>,
+    application_id: Id<ApplicationMarker>,
+    interaction_id: Id<InteractionMarker>,
+    interaction_token: &str,
+    request: &ApprovalRequest,
+    decision: ApprovalDecision,
+) -> anyhow::Result<()> {
+    client
+        .interaction(application_id)
+        .create_response(
+            interaction_id,
+            interaction_token,
+            &InteractionResponse {
+                kind: InteractionResponseType::UpdateMessage,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .flags(MessageFlags::IS_COMPONENTS_V2)
+                        .allowed_mentions(AllowedMentions::default())
+                        .components(vec![build_approval_component(
+                            request,
+                            ApprovalCard::Decided(decision),
+                        )])
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+    Ok(())
+}
+
+/// Render a multi-select detail as rows of toggle buttons, one per option.
+/// Selected options are blurple and ones already on the backend green.
+/// Each button's custom id carries its option index after a `#`.
+fn options_to_button_grid(detail: &RequestDetails, uuid: Uuid, disabled: bool) -> Vec<ActionRow> {
+    let buttons: Vec<_> = detail
+        .options
+        .iter()
+        .enumerate()
+        .map(|(i, option)| {
+            let style = if detail.selected_indices.contains(&i) {
+                ButtonStyle::Primary
+            } else if option.present {
+                ButtonStyle::Success
+            } else {
+                ButtonStyle::Secondary
+            };
+            let label = match option.id {
+                Some(SelectableId::Integer(n)) if n >= 0 => format!("S{n}"),
+                _ => option.title.clone(),
+            };
+            ButtonBuilder::new(style)
+                .label(label)
+                .custom_id(format!("{}#{i}:{uuid}", detail.title))
+                .disabled(disabled)
+                .build()
+        })
+        .collect();
+    buttons
+        .chunks(MAX_ROW_BUTTONS)
+        .map(|row| {
+            row.iter()
+                .cloned()
+                .fold(ActionRowBuilder::new(), |b, button| b.component(button))
+                .build()
+        })
+        .collect()
+}
+
+/// Count a component and everything nested in it, as Discord does for its
+/// per-message limit. Every component serializes with a `type` field.
+fn component_count(component: &Component) -> usize {
+    fn count(value: &serde_json::Value) -> usize {
+        match value {
+            serde_json::Value::Object(map) => {
+                usize::from(map.get("type").is_some_and(|t| t.is_number()))
+                    + map.values().map(count).sum::<usize>()
+            }
+            serde_json::Value::Array(items) => items.iter().map(count).sum(),
+            _ => 0,
+        }
+    }
+    serde_json::to_value(component).map_or(0, |v| count(&v))
+}
+
+/// The overview as shown on the request card, or `None` when it's hidden
+fn overview_text(description: Option<&str>, mode: OverviewMode) -> Option<String> {
+    let Some(overview) = description.filter(|s| !s.is_empty()) else {
+        return (mode != OverviewMode::Hide).then(|| "*Overview unavailable.*".to_string());
+    };
+    match mode {
+        OverviewMode::Show => Some(text::truncate(overview, MAX_TEXT_CONTENT_LENGTH)),
+        // Leave room for the markers, which must survive truncation
+        OverviewMode::Spoiler => Some(format!(
+            "||{}||",
+            text::truncate(overview, MAX_TEXT_CONTENT_LENGTH - 4)
+        )),
+        OverviewMode::Hide => None,
+    }
+}
+
+/// The media's poster, title, subtitle and overview, as the top of a card
+fn media_header(
+    mut container: ContainerBuilder,
+    display_info: &MediaDisplayInfo,
+    overview: OverviewMode,
+) -> ContainerBuilder {
+    if let Some(thumbnail_url) = &display_info.thumbnail_url {
+        let mut section = SectionBuilder::new(
+            ThumbnailBuilder::new(UnfurledMediaItem {
+                url: thumbnail_url.clone(),
+                proxy_url: None,
+                height: None,
+                width: None,
+                content_type: None,
+            })
+            .build(),
+        )
+        .component(
+            TextDisplayBuilder::new(format!("# {}", escape_markdown(&display_info.title))).build(),
+        );
+
+        // Only add subtitle if it exists
+        if let Some(subtitle) = &display_info.subtitle {
+            section = section.component(
+                TextDisplayBuilder::new(format!("-# {}", escape_markdown(subtitle))).build(),
+            );
+        }
+
+        if let Some(overview) = overview_text(display_info.description.as_deref(), overview) {
+            section = section.component(TextDisplayBuilder::new(overview).build());
+        }
+
+        container = container.component(section.build());
+    } else {
+        container = container.component(
+            TextDisplayBuilder::new(format!("# {}", escape_markdown(&display_info.title))).build(),
+        );
+        if let Some(subtitle) = &display_info.subtitle {
+            container = container.component(
+                TextDisplayBuilder::new(format!("-# {}", escape_markdown(subtitle))).build(),
+            );
+        }
+        if let Some(overview) = overview_text(display_info.description.as_deref(), overview) {
+            container = container.component(TextDisplayBuilder::new(overview).build());
+        }
+    }
+    container
+}
+
+/// Build the request form, showing multi-selects (season pickers) as button
+/// grids unless that would take the message past Discord's component limit
+fn build_request_component(
+    uuid: Uuid,
+    display_info: &MediaDisplayInfo,
+    overview: OverviewMode,
+    request_details: &[RequestDetails],
+    user_selectable_fields: &HashSet<String>,
+    extras: &FormExtras,
+    submitting: bool,
+) -> Component {
+    let with_grids = render_request_component(
+        uuid,
+        display_info,
+        overview,
+        request_details,
+        user_selectable_fields,
+        extras,
+        submitting,
+        true,
+    );
+    if component_count(&with_grids) <= MAX_MESSAGE_COMPONENTS {
+        return with_grids;
+    }
+    render_request_component(
+        uuid,
+        display_info,
+        overview,
+        request_details,
+        user_selectable_fields,
+        extras,
+        submitting,
+        false,
+    )
+}
+
+/// The "Also notify" picker, showing who's picked so far
+fn notify_menu(uuid: Uuid, subscribers: &[Id<UserMarker>], disabled: bool) -> ActionRow {
+    let menu = SelectMenuBuilder::new(format!("{NOTIFY_PREFIX}:{uuid}"), SelectMenuType::User)
+        .placeholder("Nobody else")
+        .min_values(0)
+        .max_values(MAX_SUBSCRIBERS)
+        .default_values(
+            subscribers
+                .iter()
+                .map(|&id| SelectDefaultValue::User(id))
+                .collect(),
+        )
+        .disabled(disabled);
+    ActionRowBuilder::new().component(menu.build()).build()
+}
+
+/// The category picker, which can be cleared again to file it under none
+fn category_menu(
+    uuid: Uuid,
+    categories: &[Category],
+    picked: Option<usize>,
+    disabled: bool,
+) -> ActionRow {
+    let mut menu =
+        SelectMenuBuilder::new(format!("{CATEGORY_PREFIX}:{uuid}"), SelectMenuType::Text)
+            .placeholder("None")
+            .min_values(0)
+            .max_values(1)
+            .disabled(disabled);
+    for (i, category) in categories.iter().take(MAX_DROPDOWN_OPTIONS).enumerate() {
+        menu = menu.option(
+            SelectMenuOptionBuilder::new(
+                text::truncate(&category.name, MAX_OPTION_TEXT_LENGTH),
+                i.to_string(),
+            )
+            .default(picked == Some(i)),
+        );
+    }
+    ActionRowBuilder::new().component(menu.build()).build()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_request_component(
+    uuid: Uuid,
+    display_info: &MediaDisplayInfo,
+    overview: OverviewMode,
+    request_details: &[RequestDetails],
+    user_selectable_fields: &HashSet<String>,
+    extras: &FormExtras,
+    submitting: bool,
+    button_grids: bool,
+) -> Component {
+    // Build the container that holds everything
+    let container = ContainerBuilder::new().accent_color(Some(ACCENT_COLOR));
+    let mut container = media_header(container, display_info, overview);
+
+    // Build the additional options
+    // Show dropdowns that still need selection, and text for completed selections
+    let mut selections_remaining = false;
+
+    for detail in request_details {
+        // Only show fields that were user-selectable (had multiple options initially)
+        let is_user_selectable = detail
+            .metadata
+            .as_ref()
             .map(|m| user_selectable_fields.contains(m))
             .unwrap_or(false);
 
@@ -334,6 +1505,22 @@ fn build_request_component(
             if detail.selected_indices.is_empty() {
                 selections_remaining = true;
             }
+            if button_grids
+                && detail.field_type == FieldType::MultiSelect
+                && detail.options.len() <= MAX_DROPDOWN_OPTIONS
+            {
+                let mut heading = field_heading(detail);
+                if detail.options.iter().any(|o| o.present) {
+                    heading.push_str("\n-# Green: already downloaded");
+                }
+                container = container
+                    .component(SeparatorBuilder::new().build())
+                    .component(TextDisplayBuilder::new(heading).build());
+                for row in options_to_button_grid(detail, uuid, submitting) {
+                    container = container.component(row);
+                }
+                continue;
+            }
             let max_values = (detail.field_type == FieldType::MultiSelect)
                 .then(|| (detail.options.len() as u8).min(MAX_DROPDOWN_OPTIONS as u8));
             let row = dropdown_options_to_select_menu(
@@ -347,8 +1534,12 @@ fn build_request_component(
             );
             container = container
                 .component(SeparatorBuilder::new().build())
-                .component(TextDisplayBuilder::new(format!("### {}", detail.title)).build())
+                .component(TextDisplayBuilder::new(field_heading(detail)).build())
                 .component(row);
+            if let Some(note) = detail.selected_option().and_then(|o| o.note.as_deref()) {
+                container =
+                    container.component(TextDisplayBuilder::new(format!("-# {note}")).build());
+            }
         } else if detail.options.len() == 1 {
             // Admin-configured single option — show as text, no user choice needed
             let selection = detail.options.first().unwrap().title.clone();
@@ -360,6 +1551,33 @@ fn build_request_component(
         }
     }
 
+    if !extras.categories.is_empty() {
+        container = container
+            .component(SeparatorBuilder::new().build())
+            .component(
+                TextDisplayBuilder::new("### Category\n-# Optional, to file the request under")
+                    .build(),
+            )
+            .component(category_menu(
+                uuid,
+                &extras.categories,
+                extras.category,
+                submitting,
+            ));
+    }
+
+    if let Some(subscribers) = &extras.subscribers {
+        container = container
+            .component(SeparatorBuilder::new().build())
+            .component(
+                TextDisplayBuilder::new(
+                    "### Also notify\n-# Who else to mention once it's available",
+                )
+                .build(),
+            )
+            .component(notify_menu(uuid, subscribers, submitting));
+    }
+
     // Build the request button (disabled if selections still needed or already submitting)
     container = container.component(SeparatorBuilder::new().build());
     let request_button = ButtonBuilder::new(ButtonStyle::Primary)
@@ -373,16 +1591,87 @@ fn build_request_component(
         .build();
 
     container = container.component(ActionRowBuilder::new().component(request_button).build());
+    if !submitting {
+        container = container.component(TextDisplayBuilder::new(expiry_note()).build());
+    }
 
     container.build().into()
 }
 
-fn build_completion_component(message: &SuccessMessage) -> Component {
+/// A field's heading on the request form, with what the backend already has
+/// for it underneath
+fn field_heading(detail: &RequestDetails) -> String {
+    let mut heading = format!("### {}", detail.title);
+    for line in detail.status.iter().flat_map(|s| s.lines()) {
+        heading.push_str(&format!("\n-# {}", escape_markdown(line)));
+    }
+    heading
+}
+
+/// When the flow stops waiting on the user, as a Discord relative timestamp
+/// that counts down on its own ("Expires in 5 minutes"). Every card that
+/// waits is rendered right before the wait starts.
+fn expiry_note() -> String {
+    format!(
+        "-# Expires <t:{}:R>",
+        store::now() + INTERACTION_TIMEOUT_DURATION.as_secs()
+    )
+}
+
+/// How long after a request to check on its download, between checks. Stops
+/// early once something is grabbed, well before the interaction token expires.
+const DOWNLOAD_STATUS_CHECKS: [Duration; 4] = [
+    Duration::from_secs(20),
+    Duration::from_secs(40),
+    Duration::from_secs(60),
+    Duration::from_secs(120),
+];
+
+/// One line on the success card saying how the download is going
+fn download_status_text(status: &DownloadStatus) -> String {
+    match status {
+        DownloadStatus::Searching => "Searching indexers…".to_string(),
+        DownloadStatus::Grabbed { eta: None } => "Grabbed, downloading".to_string(),
+        DownloadStatus::Grabbed { eta: Some(eta) } => match eta.as_secs() / 60 {
+            0 => "Grabbed, almost done".to_string(),
+            mins @ 1..60 => format!("Grabbed, ETA ~{mins} min"),
+            mins => format!("Grabbed, ETA ~{} h {} min", mins / 60, mins % 60),
+        },
+        DownloadStatus::Downloaded => "Already downloaded".to_string(),
+    }
+}
+
+/// Longest [SuccessMessage::details] line, so it can't crowd out the rest
+const MAX_DETAILS_SUMMARY: usize = 300;
+
+/// The picked options as one line, for [SuccessMessage::details]
+fn selections_summary(selections: &[Selection]) -> Option<String> {
+    let parts: Vec<_> = selections
+        .iter()
+        .filter(|s| !s.values.is_empty())
+        .map(|s| format!("{}: {}", s.field, s.values.join(", ")))
+        .collect();
+    (!parts.is_empty())
+        .then(|| text::truncate(&escape_markdown(&parts.join(" · ")), MAX_DETAILS_SUMMARY))
+}
+
+fn build_completion_component(
+    message: &SuccessMessage,
+    status: Option<&DownloadStatus>,
+    similar: &[(Uuid, Offer)],
+) -> Component {
     let mut container = ContainerBuilder::new().accent_color(Some(ACCENT_COLOR));
 
     let heading =
         TextDisplayBuilder::new(format!("# {}", escape_markdown(&message.summary))).build();
-    let body = TextDisplayBuilder::new(&message.description).build();
+    let mut body = message.description.clone();
+    if let Some(details) = &message.details {
+        body.push_str(&format!("\n-# {details}"));
+    }
+    if let Some(status) = status {
+        body.push_str(&format!("\n-# {}", download_status_text(status)));
+    }
+    let body = TextDisplayBuilder::new(body).build();
 
     if let Some(thumbnail_url) = &message.thumbnail_url {
         let section = SectionBuilder::new(
@@ -403,360 +1692,2690 @@ fn build_completion_component(message: &SuccessMessage) -> Component {
         container = container.component(heading).component(body);
     }
 
-    container.build().into()
-}
-
-#[derive(Debug)]
-/// Data needed to start an interaction flow
-pub struct InteractionStart {
-    pub uuid: Uuid,
-    pub rx: Receiver<InteractionContinue>,
-    pub query: String,
-    /// The backend's media command (e.g. "movie", "series") this request targets.
-    /// Carried for log correlation when multiple backends are configured.
-    pub media: String,
-    pub interaction_id: Id<InteractionMarker>,
-    pub application_id: Id<ApplicationMarker>,
-    pub token: String,
-    pub user_id: Id<UserMarker>,
-    pub channel_id: Id<ChannelMarker>,
+    if !similar.is_empty() {
+        container = container
+            .component(TextDisplayBuilder::new("-# You might also like").build())
+            .component(
+                similar
+                    .iter()
+                    .take(MAX_ROW_BUTTONS)
+                    .fold(ActionRowBuilder::new(), |row, (id, offer)| {
+                        row.component(
+                            ButtonBuilder::new(ButtonStyle::Secondary)
+                                .label(text::truncate(&offer.label(), MAX_BUTTON_LABEL_LENGTH))
+                                .custom_id(format!("{SIMILAR_PREFIX}:{id}"))
+                                .build(),
+                        )
+                    })
+                    .build(),
+            );
+    }
+
+    container.build().into()
+}
+
+#[derive(Debug)]
+/// Data needed to start an interaction flow
+pub struct InteractionStart {
+    pub uuid: Uuid,
+    pub rx: Receiver<InteractionContinue>,
+    pub query: String,
+    /// Request the top search result with default details, only showing the
+    /// form when some detail has no default
+    pub quick: bool,
+    /// Defaults profile picked by the requester, with its name
+    pub profile: Option<(String, Profile)>,
+    /// Release year given by the requester, to rank matching results first
+    pub year: Option<i32>,
+    /// Unix timestamp the requester needs it by, logged with the request
+    pub need_by: Option<u64>,
+    /// The backend's media command (e.g. "movie", "series") this request targets.
+    /// Carried for log correlation when multiple backends are configured.
+    pub media: String,
+    pub interaction_id: Id<InteractionMarker>,
+    pub application_id: Id<ApplicationMarker>,
+    pub token: String,
+    pub user_id: Id<UserMarker>,
+    pub channel_id: Id<ChannelMarker>,
+    pub guild_id: Option<Id<GuildMarker>>,
+    /// Started over from a timed-out flow's button rather than a slash
+    /// command, so the flow takes over that message instead of a new one
+    pub resumed: bool,
+}
+
+/// How a request flow ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowOutcome {
+    /// Ran to completion, successfully or by an expected early exit
+    Finished,
+    /// The user stopped responding and the flow timed out
+    Abandoned,
+    /// The search found nothing; the message offers to edit the query
+    NoResults,
+}
+
+#[derive(Debug)]
+/// Data needed to continue an interaction flow
+pub struct InteractionContinue {
+    pub data: Box<MessageComponentInteractionData>,
+    pub interaction_id: Id<InteractionMarker>,
+    pub token: String,
+}
+
+/// Server-configured settings that shape a request flow
+pub struct FlowOptions {
+    pub public_followup: bool,
+    pub followup_style: FollowupStyle,
+    /// Channels to announce the request in, which may be none of them; the
+    /// request's own channel when none are configured
+    pub followup_channels: Option<Vec<Id<ChannelMarker>>>,
+    pub messages: Messages,
+    pub thumbnails: Option<Arc<ThumbnailProxy>>,
+    /// How the plot overview is shown in this channel
+    pub overview: OverviewMode,
+    /// Show adult search results: the channel is NSFW or the requester has
+    /// one of the `nsfw_roles`
+    pub allow_adult: bool,
+    pub hooks: Arc<Hooks>,
+    /// Run over search results before they're shown
+    pub enrichers: Arc<Enrichers>,
+    /// Where the request goes past an admin first. None when the requester
+    /// is trusted to request on their own.
+    pub approval: Option<ApprovalRoute>,
+    /// Offers similar titles on the success card, when `[suggestions]` is set
+    pub suggester: Option<Arc<Suggester>>,
+    /// Categories the requester can file the request under
+    pub categories: Vec<Category>,
+    /// Tag naming the requester, put on the title with the category's
+    pub requester_tag: Option<String>,
+}
+
+/// How long media stays claimed after it was requested. A second requester
+/// inside this window is told who beat them to it, instead of getting the
+/// backend's error for a duplicate.
+const REQUEST_CLAIM_DURATION: Duration = Duration::from_secs(120);
+
+/// Media being requested right now or just now, by backend instance and
+/// [MediaBackend::media_id], with who requested it and until when it's theirs.
+/// Shared by all request flows.
+#[derive(Debug, Clone, Default)]
+pub struct RequestClaims(Arc<Mutex<HashMap<ClaimKey, Claim>>>);
+
+/// Backend instance name and the media's IDs
+type ClaimKey = (String, MediaId);
+
+/// Who claimed the media, and when the claim runs out
+type Claim = (Id<UserMarker>, Instant);
+
+impl RequestClaims {
+    /// Claim media for `user_id` for `lasting`, or learn who already holds it
+    fn claim(
+        &self,
+        key: ClaimKey,
+        user_id: Id<UserMarker>,
+        lasting: Duration,
+    ) -> Result<(), Id<UserMarker>> {
+        let mut claims = self.0.lock().expect("request claims lock poisoned");
+        let now = Instant::now();
+        claims.retain(|_, (_, until)| *until > now);
+        // The same title can come with more or fewer IDs from different searches
+        let held = claims
+            .iter()
+            .find(|((name, ids), _)| *name == key.0 && ids.matches(&key.1));
+        match held {
+            Some((_, &(holder, _))) => Err(holder),
+            None => {
+                claims.insert(key, (user_id, now + lasting));
+                Ok(())
+            }
+        }
+    }
+
+    /// Keep `user_id`'s claim for another `lasting` from now, e.g. once an
+    /// admin approved the request it was waiting on
+    fn extend(&self, key: &ClaimKey, user_id: Id<UserMarker>, lasting: Duration) {
+        let mut claims = self.0.lock().expect("request claims lock poisoned");
+        if let Some((holder, until)) = claims.get_mut(key)
+            && *holder == user_id
+        {
+            *until = Instant::now() + lasting;
+        }
+    }
+
+    /// Give up `user_id`'s claim, e.g. because the request failed. A claim
+    /// someone else took since is left alone.
+    fn release(&self, key: &ClaimKey, user_id: Id<UserMarker>) {
+        let mut claims = self.0.lock().expect("request claims lock poisoned");
+        if claims
+            .get(key)
+            .is_some_and(|(holder, _)| *holder == user_id)
+        {
+            claims.remove(key);
+        }
+    }
+}
+
+/// How long a request waits for an admin's decision before it's dropped
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(3 * 24 * 60 * 60);
+
+/// An admin's answer to a request awaiting approval, with who gave it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApprovalDecision {
+    Approved(Id<UserMarker>),
+    /// With the reason the admin gave, for the requester
+    Denied(Id<UserMarker>, String),
+}
+
+/// A request as shown to admins on its approval card
+#[derive(Debug, Clone)]
+pub struct ApprovalRequest {
+    pub user_id: Id<UserMarker>,
+    pub guild_id: Option<Id<GuildMarker>>,
+    /// The media command it was made through
+    pub media: String,
+    pub title: String,
+    /// The picked options, as on the success card
+    pub details: Option<String>,
+    /// When it was submitted, in unix seconds
+    pub at: u64,
+}
+
+/// Where an approval card is in its life
+#[derive(Debug, Clone)]
+enum ApprovalCard {
+    /// From a suggest-only requester; there's nothing to decide
+    Suggested,
+    /// Waiting for an admin, with the buttons to decide by
+    Waiting(Uuid),
+    Decided(ApprovalDecision),
+    Expired,
+}
+
+/// How a requester's requests reach the backend, when not directly
+#[derive(Debug, Clone)]
+pub struct ApprovalRoute {
+    pub tier: ApprovalTier,
+    /// Where approval cards are posted; `None` when the requester's server
+    /// has no approval channel
+    pub channel_id: Option<Id<ChannelMarker>>,
+    pub pending: PendingApprovals,
+}
+
+/// Where a request's approval card was posted: channel and message
+pub type ApprovalCardRef = (Id<ChannelMarker>, Id<MessageMarker>);
+
+/// Requests waiting for an admin, by flow, each with the channel its flow
+/// waits on for the decision. Shared by all request flows, the approval
+/// buttons, and `/pending`. Lost on restart, like the flows themselves.
+#[derive(Debug, Clone, Default)]
+pub struct PendingApprovals(Arc<Mutex<HashMap<Uuid, PendingApproval>>>);
+
+#[derive(Debug)]
+struct PendingApproval {
+    request: ApprovalRequest,
+    /// Set once the card is posted
+    card: Option<ApprovalCardRef>,
+    decision: oneshot::Sender<ApprovalDecision>,
+}
+
+impl PendingApprovals {
+    /// Put a request up for approval, returning where its decision arrives
+    fn add(&self, uuid: Uuid, request: ApprovalRequest) -> oneshot::Receiver<ApprovalDecision> {
+        let (tx, rx) = oneshot::channel();
+        self.0
+            .lock()
+            .expect("pending approvals lock poisoned")
+            .insert(
+                uuid,
+                PendingApproval {
+                    request,
+                    card: None,
+                    decision: tx,
+                },
+            );
+        rx
+    }
+
+    /// Note where a waiting request's card was posted
+    fn set_card(&self, uuid: &Uuid, card: ApprovalCardRef) {
+        if let Some(pending) = self
+            .0
+            .lock()
+            .expect("pending approvals lock poisoned")
+            .get_mut(uuid)
+        {
+            pending.card = Some(card);
+        }
+    }
+
+    /// Settle a waiting request from `guild_id`, handing the decision to its
+    /// flow. Returns the request and where its card is, or None when it was
+    /// already decided, expired, is from before a restart, or is another
+    /// server's.
+    pub fn decide(
+        &self,
+        uuid: Uuid,
+        guild_id: Option<Id<GuildMarker>>,
+        decision: ApprovalDecision,
+    ) -> Option<(ApprovalRequest, Option<ApprovalCardRef>)> {
+        let mut approvals = self.0.lock().expect("pending approvals lock poisoned");
+        if approvals.get(&uuid)?.request.guild_id != guild_id {
+            return None;
+        }
+        let pending = approvals.remove(&uuid)?;
+        // The flow removes its entry before it stops listening, so it's there
+        let _ = pending.decision.send(decision);
+        Some((pending.request, pending.card))
+    }
+
+    /// Whether a request from `guild_id` is still waiting for an admin
+    pub fn is_waiting(&self, uuid: &Uuid, guild_id: Option<Id<GuildMarker>>) -> bool {
+        self.0
+            .lock()
+            .expect("pending approvals lock poisoned")
+            .get(uuid)
+            .is_some_and(|p| p.request.guild_id == guild_id)
+    }
+
+    /// The requests from a server waiting for an admin, oldest first
+    pub fn list(&self, guild_id: Option<Id<GuildMarker>>) -> Vec<(Uuid, ApprovalRequest)> {
+        let mut pending: Vec<_> = self
+            .0
+            .lock()
+            .expect("pending approvals lock poisoned")
+            .iter()
+            .filter(|(_, p)| p.request.guild_id == guild_id)
+            .map(|(uuid, p)| (*uuid, p.request.clone()))
+            .collect();
+        pending.sort_by_key(|(_, request)| request.at);
+        pending
+    }
+
+    fn remove(&self, uuid: &Uuid) {
+        self.0
+            .lock()
+            .expect("pending approvals lock poisoned")
+            .remove(uuid);
+    }
+}
+
+/// Everything a request flow needs that stays fixed from step to step
+struct FlowContext {
+    uuid: Uuid,
+    discord_http: Arc<HttpClient>,
+    application_id: Id<ApplicationMarker>,
+    /// The newest token that can edit the flow's message, starting with the
+    /// slash command's
+    anchor: Mutex<Anchor>,
+    user_id: Id<UserMarker>,
+    channel_id: Id<ChannelMarker>,
+    guild_id: Option<Id<GuildMarker>>,
+    /// The media command this flow was started from
+    media: String,
+    query: String,
+    quick: bool,
+    profile: Option<(String, Profile)>,
+    year: Option<i32>,
+    need_by: Option<u64>,
+    instance: RegisteredBackend,
+    announcer: Announcer,
+    store: Arc<Store>,
+    claims: RequestClaims,
+    options: FlowOptions,
+    /// How long each backend call took, for the audit record
+    stages: Mutex<Vec<StageTiming>>,
+    /// How long the requester took at each wait, for `/report`
+    waits: Mutex<Waits>,
+}
+
+/// The points where a flow waits on the requester
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wait {
+    /// Picking a search result
+    Result,
+    /// Filling in the request form, up to clicking Request
+    Details,
+    /// Going ahead despite a warning
+    Warning,
+}
+
+impl Wait {
+    /// The [FLOW_STAGES] entry the wait counts toward
+    fn stage(self) -> &'static str {
+        match self {
+            Wait::Result => "select",
+            Wait::Details => "details",
+            Wait::Warning => "confirm",
+        }
+    }
+}
+
+impl fmt::Display for Wait {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Wait::Result => "search result selection",
+            Wait::Details => "detail selection",
+            Wait::Warning => "warning confirmation",
+        })
+    }
+}
+
+/// What the requester spent their time on in one flow
+#[derive(Debug, Default)]
+struct Waits {
+    taken: Vec<StageTiming>,
+    /// Where they stopped responding, if they did
+    abandoned_at: Option<Wait>,
+}
+
+/// The stages of a request flow reported by `/report`, in order, with how
+/// they're labelled there
+pub const FLOW_STAGES: [(&str, &str); 5] = [
+    ("search", "Searching"),
+    ("select", "Picking a result"),
+    ("details", "Filling in details"),
+    ("confirm", "Confirming a warning"),
+    ("submit", "Submitting"),
+];
+
+/// A flow's time per [FLOW_STAGES] stage: the backend calls it waited on,
+/// plus the requester's own time
+fn flow_stages(calls: &[StageTiming], waits: &[StageTiming]) -> Vec<StageTiming> {
+    let stage_of = |call: &str| match call {
+        "search" | "enrich" => Some("search"),
+        "details" => Some("details"),
+        "request" => Some("submit"),
+        _ => None,
+    };
+    let timings: Vec<(&str, u64)> = calls
+        .iter()
+        .filter_map(|t| Some((stage_of(&t.stage)?, t.millis)))
+        .chain(waits.iter().map(|t| (t.stage.as_str(), t.millis)))
+        .collect();
+    FLOW_STAGES
+        .iter()
+        .filter_map(|(stage, _)| {
+            let mut spent = timings.iter().filter(|(s, _)| s == stage).peekable();
+            spent.peek()?;
+            Some(StageTiming {
+                stage: stage.to_string(),
+                millis: spent.map(|(_, millis)| millis).sum(),
+            })
+        })
+        .collect()
+}
+
+/// The request form shown once the user has picked a search result
+struct RequestForm {
+    selection: Box<dyn MediaItem>,
+    display_info: MediaDisplayInfo,
+    overview: OverviewMode,
+    details: Vec<RequestDetails>,
+    /// Metadata keys of the fields rendered for the user: ones they must choose
+    /// from (multiple options), plus ones the backend wants reviewed regardless
+    user_selectable_fields: HashSet<String>,
+    extras: FormExtras,
+}
+
+/// What the request form asks for besides the backend's details
+#[derive(Debug, Default)]
+struct FormExtras {
+    /// Who else is mentioned once the title is available. `None` where
+    /// there's nobody else to pick (outside a server).
+    subscribers: Option<Vec<Id<UserMarker>>>,
+    /// The categories offered on this media command
+    categories: Vec<Category>,
+    /// Index of the category picked, if any
+    category: Option<usize>,
+}
+
+impl RequestForm {
+    fn render(&self, uuid: Uuid, submitting: bool) -> Component {
+        build_request_component(
+            uuid,
+            &self.display_info,
+            self.overview,
+            &self.details,
+            &self.user_selectable_fields,
+            &self.extras,
+            submitting,
+        )
+    }
+
+    /// Apply a component event to the form: the "Also notify" picker, the
+    /// category picker, or a detail
+    fn apply(
+        &mut self,
+        data: &MessageComponentInteractionData,
+        requester: Id<UserMarker>,
+    ) -> FormEvent {
+        if data.custom_id.starts_with(&format!("{CATEGORY_PREFIX}:")) {
+            return match picked_category(data, self.extras.categories.len()) {
+                Ok(category) => {
+                    self.extras.category = category;
+                    FormEvent::Updated
+                }
+                Err(reason) => FormEvent::Ignored(reason),
+            };
+        }
+        if !data.custom_id.starts_with(&format!("{NOTIFY_PREFIX}:")) {
+            return apply_form_event(&mut self.details, data);
+        }
+        let Some(subscribers) = &mut self.extras.subscribers else {
+            return FormEvent::Ignored("nobody else can be notified here");
+        };
+        *subscribers = picked_subscribers(data, requester);
+        FormEvent::Updated
+    }
+}
+
+/// The category picked out of `offered`, or `None` when the picker was cleared
+fn picked_category(
+    data: &MessageComponentInteractionData,
+    offered: usize,
+) -> Result<Option<usize>, &'static str> {
+    let Some(value) = data.values.first() else {
+        return Ok(None);
+    };
+    match custom_id::index(value, offered) {
+        Some(i) => Ok(Some(i)),
+        None => Err("category index out of bounds"),
+    }
+}
+
+/// The people picked in the "Also notify" picker, leaving out the requester
+/// (who's always mentioned) and bots
+fn picked_subscribers(
+    data: &MessageComponentInteractionData,
+    requester: Id<UserMarker>,
+) -> Vec<Id<UserMarker>> {
+    let is_bot = |id: Id<UserMarker>| {
+        data.resolved
+            .as_ref()
+            .and_then(|r| r.users.get(&id))
+            .is_some_and(|u| u.bot)
+    };
+    data.values
+        .iter()
+        .filter_map(|v| v.parse().ok())
+        .filter_map(Id::new_checked)
+        .filter(|&id| id != requester && !is_bot(id))
+        .take(MAX_SUBSCRIBERS.into())
+        .collect()
+}
+
+/// What the search results card shows, kept to redraw it for each preview
+struct ResultsCard {
+    options: Vec<DropdownOption>,
+    notice: Option<String>,
+    /// Index of the result being previewed
+    preview: Option<usize>,
+}
+
+/// The states of a request flow. Each step consumes the current state and
+/// produces the next one, until the flow is [FlowState::Done].
+enum FlowState {
+    /// Search results are shown; waiting for the user to pick one, or to
+    /// preview another
+    AwaitingResult {
+        results: Vec<Box<dyn MediaItem>>,
+        card: ResultsCard,
+    },
+    /// The request form is shown; waiting for detail selections or the Request button
+    CollectingDetails(RequestForm),
+    /// The user picked something with consequences and was warned about it;
+    /// waiting for them to go ahead anyway or back to the form
+    Warned(RequestForm),
+    /// The Request button was clicked; the click still needs acknowledging
+    Confirming {
+        form: RequestForm,
+        click: InteractionContinue,
+    },
+    /// Every detail is collected; submit the request to the backend
+    Submitting(RequestForm),
+    /// The flow has run to completion (successfully, or by an expected early exit)
+    Done,
+    /// The user stopped responding; the message offers to start over
+    Abandoned,
+    /// The search found nothing; the message offers to edit the query
+    NoResults,
+}
+
+impl FlowState {
+    fn name(&self) -> &'static str {
+        match self {
+            FlowState::AwaitingResult { .. } => "AwaitingResult",
+            FlowState::CollectingDetails(_) => "CollectingDetails",
+            FlowState::Warned(_) => "Warned",
+            FlowState::Confirming { .. } => "Confirming",
+            FlowState::Submitting(_) => "Submitting",
+            FlowState::Done => "Done",
+            FlowState::Abandoned => "Abandoned",
+            FlowState::NoResults => "NoResults",
+        }
+    }
+}
+
+/// How a component event on the request form was handled
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum FormEvent {
+    /// A detail selection changed; the form should be re-rendered
+    Updated,
+    /// The Request button was clicked
+    Submit,
+    /// A stale or malformed event (e.g. a second click on a dropdown we already collapsed)
+    Ignored(&'static str),
+}
+
+/// Map a search-result dropdown event to an index into the results
+fn selected_result_index(
+    data: &MessageComponentInteractionData,
+    result_count: usize,
+) -> Option<usize> {
+    data.values
+        .first()
+        .and_then(|v| custom_id::index(v, result_count))
+}
+
+/// Preselect the options a defaults profile names. Fields the request doesn't
+/// ask for (e.g. add-time settings for a series that already exists) are
+/// skipped; naming an option the field doesn't offer is an error.
+fn apply_profile(
+    details: &mut [RequestDetails],
+    name: &str,
+    profile: &Profile,
+) -> anyhow::Result<()> {
+    for (key, value) in &profile.fields {
+        let Some(detail) = details.iter_mut().find(|d| {
+            d.metadata
+                .as_deref()
+                .and_then(|m| m.split_once(':'))
+                .is_some_and(|(_, field)| field == key)
+        }) else {
+            debug!(profile = name, field = %key, "Profile field not part of this request");
+            continue;
+        };
+        let Some(idx) = detail
+            .options
+            .iter()
+            .position(|o| o.title.eq_ignore_ascii_case(value))
+        else {
+            let available: Vec<_> = detail.options.iter().map(|o| o.title.as_str()).collect();
+            bail!(UserFacingError(format!(
+                "Profile \"{name}\" sets {} to \"{value}\", which isn't available here. Options: {}",
+                detail.title,
+                available.join(", ")
+            )));
+        };
+        debug!(profile = name, field = %key, value = %value, "Applied profile field");
+        detail.selected_indices = vec![idx];
+    }
+    Ok(())
+}
+
+/// Whether every detail has a value (a selection or an admin-configured
+/// default), so the request could be submitted without asking the user
+pub(crate) fn details_complete(details: &[RequestDetails]) -> bool {
+    details
+        .iter()
+        .all(|d| d.selected_option().is_some() || !d.selected_indices.is_empty())
+}
+
+/// Move results released within a year of `year` to the front, keeping the
+/// backend's relevance order otherwise. Release dates drift a year between
+/// regions and databases, so an exact match would miss some.
+fn rank_by_year(results: &mut [Box<dyn MediaItem>], year: i32) {
+    // `false` sorts first
+    results.sort_by_key(|r| r.year().is_none_or(|y| (y - year).abs() > 1));
+}
+
+/// Apply a component event to the request form's details
+pub(crate) fn apply_form_event(
+    details: &mut [RequestDetails],
+    data: &MessageComponentInteractionData,
+) -> FormEvent {
+    if data.custom_id.starts_with("request:") {
+        return FormEvent::Submit;
+    }
+
+    // Buttons in a grid append the index of the option they toggle
+    let (title, toggled) = match custom_id::field(&data.custom_id) {
+        Ok(field) => field,
+        Err(reason) => return FormEvent::Ignored(reason),
+    };
+    let Some(detail) = details.iter_mut().find(|x| x.title == title) else {
+        return FormEvent::Ignored("no detail matching custom id");
+    };
+
+    if detail.field_type == FieldType::MultiSelect {
+        let mut indices: Vec<usize> = match toggled {
+            Some(i) if i >= detail.options.len() => {
+                return FormEvent::Ignored("button index out of bounds");
+            }
+            Some(i) => custom_id::toggle(&detail.selected_indices, i),
+            None => custom_id::indices(&data.values, detail.options.len()),
+        };
+
+        // An "All Seasons"-style option (or the "None" of a tags field) is
+        // mutually exclusive with the rest. Discord can't enforce that natively, so we reconcile on
+        // re-render: selecting it clears the others; selecting another
+        // while it's active drops it.
+        let exclusive = detail.options.iter().position(|o| {
+            matches!(
+                o.id,
+                Some(SelectableId::Integer(ALL_SEASONS_ID | NO_TAG_ID))
+            )
+        });
+        if let Some(excl) = exclusive
+            && indices.contains(&excl)
+            && indices.len() > 1
+        {
+            if detail.selected_indices.contains(&excl) {
+                // It was already on and the user added a specific option
+                indices.retain(|&i| i != excl);
+            } else {
+                // The user just turned it on
+                indices = vec![excl];
+            }
+        }
+
+        debug!(detail = %title, count = indices.len(), "User updated multi-select");
+        detail.selected_indices = indices;
+    } else {
+        let Some(value) = data.values.first() else {
+            return FormEvent::Ignored("no option was picked");
+        };
+        let Some(option_idx) = custom_id::index(value, detail.options.len()) else {
+            return FormEvent::Ignored("selection is not an index in bounds");
+        };
+        debug!(
+            detail = %title,
+            selected = %detail.options[option_idx].title,
+            "User selected detail option"
+        );
+        detail.selected_indices = vec![option_idx];
+    }
+    FormEvent::Updated
+}
+
+/// The event Discord sends when these options of a form field are picked
+#[cfg(test)]
+pub(crate) fn pick_event(title: &str, indices: &[usize]) -> MessageComponentInteractionData {
+    MessageComponentInteractionData {
+        custom_id: format!("{title}:{}", Uuid::nil()),
+        component_type: twilight_model::channel::message::component::ComponentType::TextSelectMenu,
+        resolved: None,
+        values: indices.iter().map(ToString::to_string).collect(),
+    }
+}
+
+impl FlowContext {
+    /// The newest token the flow was given, and when
+    fn anchor(&self) -> Anchor {
+        self.anchor.lock().expect("anchor lock poisoned").clone()
+    }
+
+    fn token(&self) -> String {
+        self.anchor().token
+    }
+
+    /// Replace the request's card, unless its token is too old to do so
+    async fn edit_card(&self, component: Component) -> anyhow::Result<()> {
+        let anchor = self.anchor();
+        if anchor.remaining().is_zero() {
+            bail!("The interaction token has expired");
+        }
+        update_interaction_component(
+            &self.discord_http,
+            self.application_id,
+            &anchor.token,
+            component,
+        )
+        .await
+    }
+
+    fn message_vars(&self) -> MessageVars<'_> {
+        MessageVars {
+            user_id: Some(self.user_id.get()),
+            media: &self.media,
+            query: &self.query,
+        }
+    }
+
+    /// Add where this flow's time went to its server's `/report` totals
+    async fn record_stages(&self) {
+        let Some(guild_id) = self.guild_id else {
+            return;
+        };
+        let stages = flow_stages(
+            &self.stages.lock().expect("stage timings lock poisoned"),
+            &self.waits.lock().expect("waits lock poisoned").taken,
+        );
+        let abandoned_at = self
+            .waits
+            .lock()
+            .expect("waits lock poisoned")
+            .abandoned_at
+            .map(Wait::stage);
+        if let Err(e) = self
+            .store
+            .record_flow(guild_id.get(), &stages, abandoned_at)
+            .await
+        {
+            warn!(error = ?e, "Failed to save the flow's stage timings");
+        }
+    }
+
+    /// Run a backend call, noting how long it took for the audit record
+    async fn timed<T>(&self, stage: &str, call: impl Future<Output = T>) -> T {
+        let start = Instant::now();
+        let result = call.await;
+        self.stages
+            .lock()
+            .expect("stage timings lock poisoned")
+            .push(StageTiming {
+                stage: stage.to_string(),
+                millis: start.elapsed().as_millis() as u64,
+            });
+        result
+    }
+
+    /// Stop the flow with a maintenance message if an admin has disabled the backend
+    fn ensure_available(&self) -> anyhow::Result<()> {
+        if !self.instance.is_enabled() {
+            info!(
+                backend = self.instance.name,
+                "Backend is disabled, stopping flow"
+            );
+            bail!(UserFacingError(format!(
+                "Requests for {} are temporarily disabled for maintenance. Please try again later.",
+                self.media
+            )));
+        }
+        if let Some(until) = self.instance.maintenance_until(OffsetDateTime::now_utc()) {
+            info!(
+                backend = self.instance.name,
+                "Backend is in a maintenance window, stopping flow"
+            );
+            bail!(UserFacingError(format!(
+                "Requests for {} are down for scheduled maintenance until <t:{until}:t> (<t:{until}:R>). Please try again then.",
+                self.media,
+                until = until.unix_timestamp()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Wait for the user's next component event. `None` means they abandoned
+    /// the flow, which is a normal outcome, not an error; the message has
+    /// already been updated to say so.
+    async fn next_event(
+        &self,
+        rx: &mut Receiver<InteractionContinue>,
+        wait: Wait,
+    ) -> anyhow::Result<Option<InteractionContinue>> {
+        let start = Instant::now();
+        // Give up while the message can still be edited to say so
+        let remaining = self.anchor().remaining();
+        if remaining < INTERACTION_TIMEOUT_DURATION {
+            debug!(
+                ?remaining,
+                "Interaction token expires before the step would time out"
+            );
+        }
+        match timeout(INTERACTION_TIMEOUT_DURATION.min(remaining), rx.recv()).await {
+            Ok(Some(val)) => {
+                trace!(data = ?val, "Got the next interaction");
+                *self.anchor.lock().expect("anchor lock poisoned") = Anchor::new(val.token.clone());
+                self.waits
+                    .lock()
+                    .expect("waits lock poisoned")
+                    .taken
+                    .push(StageTiming {
+                        stage: wait.stage().to_string(),
+                        millis: start.elapsed().as_millis() as u64,
+                    });
+                Ok(Some(val))
+            }
+            Ok(None) | Err(_) => {
+                info!("User abandoned the interaction at {wait}");
+                self.waits.lock().expect("waits lock poisoned").abandoned_at = Some(wait);
+                update_interaction_component(
+                    &self.discord_http,
+                    self.application_id,
+                    &self.token(),
+                    build_timeout_component(
+                        &self.options.messages.timeout(self.message_vars()),
+                        self.uuid,
+                    ),
+                )
+                .await?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Acknowledge the slash command, run the search, and show the results
+    async fn start(
+        &self,
+        interaction_id: Id<InteractionMarker>,
+        resumed: bool,
+    ) -> anyhow::Result<FlowState> {
+        let query = &self.query;
+        // Send the "thinking" ack so we can take some time to actually perform the request
+        // This is done over the HTTP client connection. A resumed flow instead
+        // defers an update to the timed-out message it was started from.
+        if resumed {
+            ack_component(
+                &self.discord_http,
+                self.application_id,
+                interaction_id,
+                &self.token(),
+            )
+            .await?;
+        } else {
+            send_thinking(
+                &self.discord_http,
+                self.application_id,
+                interaction_id,
+                &self.token(),
+            )
+            .await?;
+        }
+        self.ensure_available()?;
+
+        debug!(query = %query, "Performing search");
+        let mut results = self
+            .timed("search", self.instance.backend.search(query))
+            .await?;
+        info!(count = results.len(), "Search completed");
+
+        // Check if there were no results
+        if results.is_empty() {
+            info!("No search results found");
+            update_interaction_component(
+                &self.discord_http,
+                self.application_id,
+                &self.token(),
+                build_no_results_component(
+                    &self.options.messages.no_results(self.message_vars()),
+                    self.uuid,
+                ),
+            )
+            .await?;
+            return Ok(FlowState::NoResults);
+        }
+
+        let hidden_adult = if self.options.allow_adult {
+            0
+        } else {
+            let before = results.len();
+            results.retain(|r| !r.is_adult());
+            before - results.len()
+        };
+        if hidden_adult > 0 {
+            info!(
+                count = hidden_adult,
+                "Hid adult results outside an NSFW channel"
+            );
+            if results.is_empty() {
+                update_string_message(
+                    "Only adult results matched, which are only shown in NSFW channels.",
+                    &self.discord_http,
+                    self.application_id,
+                    &self.token(),
+                )
+                .await?;
+                return Ok(FlowState::Done);
+            }
+        }
+
+        if let Some(year) = self.year {
+            rank_by_year(&mut results, year);
+            debug!(year, "Ranked search results by year");
+        }
+
+        if self.quick {
+            // Search results come back best match first
+            let selection = results.swap_remove(0);
+            info!(selection = ?selection, "Quick request, using the top search result");
+            let Some(form) = self.prepare_form(selection).await? else {
+                return Ok(FlowState::Done);
+            };
+            if details_complete(&form.details) {
+                return Ok(FlowState::Submitting(form));
+            }
+            info!("Quick request is missing details without defaults, showing the form");
+            update_interaction_component(
+                &self.discord_http,
+                self.application_id,
+                &self.token(),
+                form.render(self.uuid, false),
+            )
+            .await?;
+            return Ok(FlowState::CollectingDetails(form));
+        }
+
+        // Discord allows a maximum of 25 options in a dropdown
+        if results.len() > MAX_DROPDOWN_OPTIONS {
+            info!(
+                "Truncating {} results to {} for Discord dropdown",
+                results.len(),
+                MAX_DROPDOWN_OPTIONS
+            );
+            results.truncate(MAX_DROPDOWN_OPTIONS);
+        }
+
+        let dropdown_options = self
+            .instance
+            .backend
+            .to_dropdown_options(results.as_slice());
+        let mut candidates: Vec<_> = results
+            .into_iter()
+            .zip(dropdown_options)
+            .map(|(item, option)| Candidate {
+                id: self.instance.backend.media_id(&*item),
+                item,
+                option,
+            })
+            .collect();
+        self.timed("enrich", self.options.enrichers.run(&mut candidates))
+            .await;
+        let (results, dropdown_options): (Vec<_>, Vec<_>) =
+            candidates.into_iter().map(|c| (c.item, c.option)).unzip();
+
+        // Now update the interaction with all of the options that result from the search
+        trace!("Showing search results to user");
+        let notice = (hidden_adult > 0).then(|| {
+            format!("{hidden_adult} adult result(s) hidden, search in an NSFW channel to see them")
+        });
+        update_interaction_component(
+            &self.discord_http,
+            self.application_id,
+            &self.token(),
+            build_search_results_component(
+                self.uuid,
+                &dropdown_options,
+                notice.as_deref(),
+                None,
+                self.options.overview,
+            ),
+        )
+        .await?;
+
+        let card = ResultsCard {
+            options: dropdown_options,
+            notice,
+            preview: None,
+        };
+        Ok(FlowState::AwaitingResult { results, card })
+    }
+
+    /// Check the picked search result can be requested and fetch the details
+    /// needed to request it. `None` means the flow stopped early; the message
+    /// has already been updated to say why.
+    async fn prepare_form(
+        &self,
+        selection: Box<dyn MediaItem>,
+    ) -> anyhow::Result<Option<RequestForm>> {
+        // Now check the early stop criteria
+        if self.instance.backend.early_stop(&*selection) {
+            info!("Stopping early - media already requested");
+            update_string_message(
+                &self.options.messages.early_stop(self.message_vars()),
+                &self.discord_http,
+                self.application_id,
+                &self.token(),
+            )
+            .await?;
+            return Ok(None);
+        }
+        debug!("Selection has not been requested, continuing interaction");
+
+        // Now, we need to collect the additional information needed to perform the request
+        debug!("Fetching additional details required");
+        let mut details = self
+            .timed(
+                "details",
+                self.instance.backend.additional_details(&*selection),
+            )
+            .await?;
+        trace!(details = ?details, "Request details");
+        if let Some((name, profile)) = &self.profile {
+            apply_profile(&mut details, name, profile)?;
+        }
+
+        // Preselected fields (backend defaults, or set by the profile) are
+        // settled; only ask about the rest
+        let user_selectable_fields = details
+            .iter()
+            .filter(|detail| {
+                (detail.options.len() > 1 && detail.selected_indices.is_empty())
+                    || detail.always_show
+            })
+            .filter_map(|detail| detail.metadata.as_ref())
+            .cloned()
+            .collect();
+
+        let mut display_info = self.instance.backend.display_info(&*selection);
+        display_info.thumbnail_url = self.proxied(display_info.thumbnail_url);
+        Ok(Some(RequestForm {
+            display_info,
+            overview: self.options.overview,
+            selection,
+            details,
+            user_selectable_fields,
+            extras: FormExtras {
+                subscribers: self.guild_id.map(|_| Vec::new()),
+                categories: self.options.categories.clone(),
+                category: None,
+            },
+        }))
+    }
+
+    /// Point a poster at the thumbnail proxy, when one is configured
+    fn proxied(&self, thumbnail_url: Option<String>) -> Option<String> {
+        match &self.options.thumbnails {
+            Some(proxy) => thumbnail_url.map(|url| proxy.url_for(&url)),
+            None => thumbnail_url,
+        }
+    }
+
+    /// Advance the flow by one state
+    async fn step(
+        &self,
+        state: FlowState,
+        rx: &mut Receiver<InteractionContinue>,
+    ) -> anyhow::Result<FlowState> {
+        trace!(state = state.name(), "Stepping interaction flow");
+        if !matches!(state, FlowState::Done) {
+            self.ensure_available()?;
+        }
+        match state {
+            FlowState::AwaitingResult {
+                mut results,
+                mut card,
+            } => {
+                debug!("Waiting for user to select a search result");
+                let Some(next) = self.next_event(rx, Wait::Result).await? else {
+                    return Ok(FlowState::Abandoned);
+                };
+
+                if let Some((PREVIEW_PREFIX, _)) = next.data.custom_id.split_once(':') {
+                    let previewed = results.len().min(PREVIEWED_RESULTS);
+                    let index = card.preview.map_or(0, |i| (i + 1) % previewed);
+                    debug!(index, "Previewing a search result");
+                    let mut display_info = self.instance.backend.display_info(&*results[index]);
+                    display_info.thumbnail_url = self.proxied(display_info.thumbnail_url);
+                    respond_interaction_component(
+                        &self.discord_http,
+                        self.application_id,
+                        next.interaction_id,
+                        &next.token,
+                        build_search_results_component(
+                            self.uuid,
+                            &card.options,
+                            card.notice.as_deref(),
+                            Some((index, &display_info)),
+                            self.options.overview,
+                        ),
+                    )
+                    .await?;
+                    card.preview = Some(index);
+                    return Ok(FlowState::AwaitingResult { results, card });
+                }
+
+                // Use the value from this payload to get the index into the search results
+                let selection_idx = selected_result_index(&next.data, results.len())
+                    .context("Search result selection didn't map to a valid result")?;
+                let selection = results.swap_remove(selection_idx);
+                info!(index = selection_idx, "User made selection");
+                trace!(selection = ?selection, "Selection details");
+
+                let Some(form) = self.prepare_form(selection).await? else {
+                    return Ok(FlowState::Done);
+                };
+
+                respond_interaction_component(
+                    &self.discord_http,
+                    self.application_id,
+                    next.interaction_id,
+                    &next.token,
+                    form.render(self.uuid, false),
+                )
+                .await?;
+
+                Ok(FlowState::CollectingDetails(form))
+            }
+            FlowState::CollectingDetails(mut form) => {
+                debug!("Waiting for user to select a detail option");
+                let Some(next) = self.next_event(rx, Wait::Details).await? else {
+                    return Ok(FlowState::Abandoned);
+                };
+
+                match form.apply(&next.data, self.user_id) {
+                    FormEvent::Submit => {
+                        info!("User clicked Request button, all details collected");
+                        let warnings = pick_warnings(&form.details, &form.user_selectable_fields);
+                        if warnings.is_empty() {
+                            return Ok(FlowState::Confirming { form, click: next });
+                        }
+                        debug!(
+                            count = warnings.len(),
+                            "Asking the user to confirm their picks"
+                        );
+                        respond_interaction_component(
+                            &self.discord_http,
+                            self.application_id,
+                            next.interaction_id,
+                            &next.token,
+                            build_warning_component(self.uuid, &warnings),
+                        )
+                        .await?;
+                        Ok(FlowState::Warned(form))
+                    }
+                    FormEvent::Ignored(reason) => {
+                        debug!(data = ?next.data, reason = reason, "Ignoring component event");
+                        ack_component(
+                            &self.discord_http,
+                            self.application_id,
+                            next.interaction_id,
+                            &next.token,
+                        )
+                        .await?;
+                        Ok(FlowState::CollectingDetails(form))
+                    }
+                    FormEvent::Updated => {
+                        // Update the component to show the selection
+                        respond_interaction_component(
+                            &self.discord_http,
+                            self.application_id,
+                            next.interaction_id,
+                            &next.token,
+                            form.render(self.uuid, false),
+                        )
+                        .await?;
+                        trace!("Updated component with selection");
+
+                        if form
+                            .details
+                            .iter()
+                            .all(|x| x.options.len() == 1 || !x.selected_indices.is_empty())
+                        {
+                            debug!(
+                                "All details have been selected, waiting for final Request button click"
+                            );
+                        }
+                        Ok(FlowState::CollectingDetails(form))
+                    }
+                }
+            }
+            FlowState::Warned(form) => {
+                let Some(next) = self.next_event(rx, Wait::Warning).await? else {
+                    return Ok(FlowState::Abandoned);
+                };
+                match next.data.custom_id.split_once(':') {
+                    Some((PROCEED_PREFIX, _)) => {
+                        info!("User confirmed their picks despite the warning");
+                        Ok(FlowState::Confirming { form, click: next })
+                    }
+                    Some((BACK_PREFIX, _)) => {
+                        respond_interaction_component(
+                            &self.discord_http,
+                            self.application_id,
+                            next.interaction_id,
+                            &next.token,
+                            form.render(self.uuid, false),
+                        )
+                        .await?;
+                        Ok(FlowState::CollectingDetails(form))
+                    }
+                    _ => {
+                        debug!(data = ?next.data, "Ignoring component event on the warning");
+                        ack_component(
+                            &self.discord_http,
+                            self.application_id,
+                            next.interaction_id,
+                            &next.token,
+                        )
+                        .await?;
+                        Ok(FlowState::Warned(form))
+                    }
+                }
+            }
+            FlowState::Confirming { form, click } => {
+                // Acknowledge the button click immediately (before 3-second timeout),
+                // disabling everything so it can't be clicked again while we submit
+                respond_interaction_component(
+                    &self.discord_http,
+                    self.application_id,
+                    click.interaction_id,
+                    &click.token,
+                    form.render(self.uuid, true),
+                )
+                .await?;
+                Ok(FlowState::Submitting(form))
+            }
+            FlowState::Submitting(form) => {
+                self.submit(form).await?;
+                Ok(FlowState::Done)
+            }
+            FlowState::Done => Ok(FlowState::Done),
+            FlowState::Abandoned => Ok(FlowState::Abandoned),
+            FlowState::NoResults => Ok(FlowState::NoResults),
+        }
+    }
+
+    /// Perform the request with the backend and announce it
+    async fn submit(&self, form: RequestForm) -> anyhow::Result<()> {
+        let RequestForm {
+            selection,
+            details,
+            extras,
+            ..
+        } = form;
+        let subscribers = extras.subscribers.unwrap_or_default();
+        let category = extras
+            .category
+            .and_then(|i| extras.categories.into_iter().nth(i));
+
+        info!("All options collected, performing request");
+        trace!(options = ?details, "Collected options");
+
+        // Perform the actual request
+        let mut success_msg = self.instance.backend.success_message(&details, &*selection);
+        success_msg.thumbnail_url = self.proxied(success_msg.thumbnail_url);
+        let media_id = self.instance.backend.media_id(&*selection);
+        let selection_kind = selection.kind();
+        // Two people can reach this for the same media at once; the second
+        // request would only fail at the backend
+        let claim = media_id
+            .clone()
+            .filter(|id| !id.is_empty())
+            .map(|id| (self.instance.name.clone(), id));
+        // A request waiting on an admin keeps its claim until they decide
+        let claim_lasts = match &self.options.approval {
+            Some(_) => APPROVAL_TIMEOUT + REQUEST_CLAIM_DURATION,
+            None => REQUEST_CLAIM_DURATION,
+        };
+        if let Some(claim) = &claim
+            && let Err(holder) = self.claims.claim(claim.clone(), self.user_id, claim_lasts)
+        {
+            info!(holder = %holder, "Media was requested by someone else just now");
+            return Err(UserFacingError(format!(
+                "**{}** was already requested by <@{holder}> just now.",
+                escape_markdown(&success_msg.summary)
+            ))
+            .into());
+        }
+        let selections = details
+            .iter()
+            .map(Selection::picked)
+            .chain(category.as_ref().map(|category| Selection {
+                field: "Category".into(),
+                values: vec![category.name.clone()],
+            }))
+            .chain((!subscribers.is_empty()).then(|| Selection {
+                field: "Also notify".into(),
+                values: subscribers.iter().map(|id| format!("<@{id}>")).collect(),
+            }))
+            .collect::<Vec<_>>();
+        success_msg.details = selections_summary(&selections);
+        let mut audit = AuditRecord {
+            id: self.uuid.to_string(),
+            at: store::now(),
+            user_id: self.user_id.get(),
+            guild_id: self.guild_id.map(|id| id.get()),
+            media: self.media.clone(),
+            backend: self.instance.name.clone(),
+            query: self.query.clone(),
+            title: success_msg.summary.clone(),
+            selections,
+            error: None,
+            stages: vec![],
+            denied: None,
+        };
+        if let Err(e) = self.options.hooks.before_submit(&audit).await {
+            if let Some(claim) = &claim {
+                self.claims.release(claim, self.user_id);
+            }
+            return Err(e);
+        }
+        let mut approved_by = None;
+        if let Some(route) = &self.options.approval {
+            let approval = self.approval(route, &success_msg, &audit).await;
+            if !matches!(approval, Ok(Some(_)))
+                && let Some(claim) = &claim
+            {
+                self.claims.release(claim, self.user_id);
+            }
+            let Some(admin) = approval? else {
+                return Ok(());
+            };
+            approved_by = Some(admin);
+            // The backend may have been disabled or gone into maintenance
+            // while the request waited
+            if let Err(e) = self.ensure_available() {
+                if let Some(claim) = &claim {
+                    self.claims.release(claim, self.user_id);
+                }
+                self.show_outcome(
+                    &success_msg,
+                    &format!("Approved by <@{admin}>, but not requested: {e}"),
+                )
+                .await;
+                return Ok(());
+            }
+            if let Some(claim) = &claim {
+                self.claims
+                    .extend(claim, self.user_id, REQUEST_CLAIM_DURATION);
+            }
+        }
+        let tags: Vec<String> = category
+            .iter()
+            .map(Category::tag)
+            .chain(self.options.requester_tag.clone())
+            .collect();
+        let requester = Requester {
+            user_id: self.user_id.get(),
+            guild_id: self.guild_id.map(|id| id.get()),
+            media: self.media.clone(),
+            tags: tags.clone(),
+        };
+        let result = self
+            .timed(
+                "request",
+                self.instance
+                    .backend
+                    .request(details, selection, &requester),
+            )
+            .await;
+        audit.error = result.as_ref().err().map(|e| format!("{e:#}"));
+        audit.stages = self
+            .stages
+            .lock()
+            .expect("stage timings lock poisoned")
+            .clone();
+        self.options.hooks.after_submit(audit.clone());
+        if let Err(e) = self.store.record_audit(audit).await {
+            warn!(error = ?e, "Failed to save audit record");
+        }
+        if result.is_err()
+            && let Some(claim) = &claim
+        {
+            self.claims.release(claim, self.user_id);
+        }
+        // Per-server totals are bookkeeping, not worth failing the request over
+        if let Some(guild_id) = self.guild_id
+            && let Err(e) = self
+                .store
+                .record_request(guild_id.get(), result.is_ok())
+                .await
+        {
+            warn!(error = ?e, "Failed to record request totals");
+        }
+        result?;
+        info!("Request completed successfully");
+        // The request went through either way; a missing tag is for an admin to fix
+        if let Some(id) = &media_id
+            && !tags.is_empty()
+            && let Err(e) = self.instance.backend.apply_tags(id, &tags).await
+        {
+            warn!(error = ?e, ?tags, "Failed to tag the requested title");
+        }
+        if let Err(e) = self
+            .store
+            .log_request(RequestRecord {
+                status: if approved_by.is_some() {
+                    RequestStatus::Approved
+                } else {
+                    RequestStatus::Requested
+                },
+                channel_id: Some(self.channel_id.get()),
+                subscribers: subscribers.iter().map(|id| id.get()).collect(),
+                category: category.as_ref().map(|c| c.name.clone()),
+                need_by: self.need_by,
+                ..RequestRecord::new(
+                    self.user_id.get(),
+                    self.guild_id.map(|id| id.get()),
+                    self.media.clone(),
+                    success_msg.summary.clone(),
+                    media_id.clone().unwrap_or_default(),
+                )
+            })
+            .await
+        {
+            warn!(error = ?e, "Failed to log request");
+        }
+
+        let similar = match (&self.options.suggester, &media_id, selection_kind) {
+            (
+                Some(suggester),
+                Some(MediaId {
+                    tmdb: Some(tmdb), ..
+                }),
+                Some(kind),
+            ) => {
+                suggester
+                    .offer(&self.media, *tmdb, kind, self.user_id)
+                    .await
+            }
+            _ => vec![],
+        };
+
+        // Update the message with success, using the newest token we were given
+        let shown = self
+            .edit_card(build_completion_component(&success_msg, None, &similar))
+            .await;
+        match (shown, approved_by) {
+            (Ok(()), _) => {}
+            // An approval can take longer than the token lives
+            (Err(e), Some(admin)) => {
+                debug!(error = ?e, "Couldn't update the request card, mentioning the requester instead");
+                self.announcer.send(
+                    self.channel_id,
+                    format!(
+                        "<@{}> **{}** was approved by <@{admin}> and requested.",
+                        self.user_id,
+                        escape_markdown(&success_msg.summary)
+                    ),
+                );
+            }
+            (Err(e), None) => return Err(e.context("Failed to send success response")),
+        }
+
+        // Queue the public message for the configured channels (or the request's
+        // own channel when none are configured).
+        // Plain content only: it's the one thing OS notification previews render.
+        //
+        // This is best-effort and must NOT fail the interaction: the request
+        // already succeeded and the user has already seen the success message
+        // above. The announcer logs delivery failures on its own.
+        if self.options.public_followup {
+            let mut content = format!(
+                "{} requested by <@{}>",
+                escape_markdown(&success_msg.summary),
+                self.user_id
+            );
+            if let Some(details) = &success_msg.details {
+                content.push_str(&format!("\n-# {details}"));
+            }
+            match &self.options.followup_channels {
+                None => self.announce_here(content).await,
+                Some(targets) => {
+                    for target in targets {
+                        self.announcer.send(*target, content.clone());
+                    }
+                }
+            }
+        }
+
+        // The flow is done, so follow the download on its own
+        if let Some(id) = media_id {
+            tokio::spawn(
+                watch_download(
+                    id,
+                    success_msg,
+                    similar,
+                    Arc::clone(&self.instance.backend),
+                    Arc::clone(&self.discord_http),
+                    self.application_id,
+                    self.anchor(),
+                )
+                .in_current_span(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Take a request past the admins first. Returns the admin who approved
+    /// it, or None when it stops here: it was only a suggestion, or it was
+    /// denied or expired. The requester is told either way, and a denial is
+    /// audited with the admin's reason.
+    async fn approval(
+        &self,
+        route: &ApprovalRoute,
+        message: &SuccessMessage,
+        audit: &AuditRecord,
+    ) -> anyhow::Result<Option<Id<UserMarker>>> {
+        let request = ApprovalRequest {
+            user_id: self.user_id,
+            guild_id: self.guild_id,
+            media: self.media.clone(),
+            title: message.summary.clone(),
+            details: message.details.clone(),
+            at: store::now(),
+        };
+        let Some(channel_id) = route.channel_id else {
+            bail!(UserFacingError(
+                "Requests here need an admin's go-ahead, but this server has no approval \
+                 channel set up. Ask an admin to add one."
+                    .to_string()
+            ));
+        };
+        if route.tier == ApprovalTier::Suggest {
+            post_approval_card(
+                &self.discord_http,
+                channel_id,
+                build_approval_component(&request, ApprovalCard::Suggested),
+            )
+            .await
+            .context("Failed to pass the suggestion on to the admins")?;
+            info!("Passed the request on to the admins as a suggestion");
+            self.show_outcome(
+                message,
+                "Suggested to the admins, who'll take it from here.",
+            )
+            .await;
+            return Ok(None);
+        }
+
+        let mut decision = route.pending.add(self.uuid, request.clone());
+        let card = match post_approval_card(
+            &self.discord_http,
+            channel_id,
+            build_approval_component(&request, ApprovalCard::Waiting(self.uuid)),
+        )
+        .await
+        {
+            Ok(card) => card,
+            Err(e) => {
+                route.pending.remove(&self.uuid);
+                return Err(e.context("Failed to ask the admins for approval"));
+            }
+        };
+        route.pending.set_card(&self.uuid, (channel_id, card));
+        info!("Waiting for an admin to approve the request");
+        self.show_outcome(
+            message,
+            &format!(
+                "Waiting for an admin to approve this request.\n-# Expires <t:{}:R>",
+                request.at + APPROVAL_TIMEOUT.as_secs()
+            ),
+        )
+        .await;
+
+        let decision = match timeout(APPROVAL_TIMEOUT, &mut decision).await {
+            Ok(decision) => decision.ok(),
+            Err(_) => {
+                route.pending.remove(&self.uuid);
+                // An admin may have decided just as it expired
+                decision.try_recv().ok()
+            }
+        };
+        match decision {
+            Some(ApprovalDecision::Approved(admin)) => {
+                info!(admin = %admin, "Request was approved");
+                Ok(Some(admin))
+            }
+            Some(ApprovalDecision::Denied(admin, reason)) => {
+                info!(admin = %admin, "Request was denied");
+                let audit = AuditRecord {
+                    denied: Some(Denial {
+                        admin_id: admin.get(),
+                        reason: reason.clone(),
+                    }),
+                    stages: self
+                        .stages
+                        .lock()
+                        .expect("stage timings lock poisoned")
+                        .clone(),
+                    ..audit.clone()
+                };
+                if let Err(e) = self.store.record_audit(audit).await {
+                    warn!(error = ?e, "Failed to save audit record");
+                }
+                self.show_outcome(
+                    message,
+                    &format!("Denied by <@{admin}>: {}", escape_markdown(&reason)),
+                )
+                .await;
+                Ok(None)
+            }
+            None => {
+                info!("Request expired waiting for approval");
+                if let Err(e) = self
+                    .discord_http
+                    .update_message(channel_id, card)
+                    .components(Some(&[build_approval_component(
+                        &request,
+                        ApprovalCard::Expired,
+                    )]))
+                    .await
+                {
+                    debug!(error = ?e, "Failed to mark the approval card expired");
+                }
+                self.show_outcome(
+                    message,
+                    "No admin got to this request in time. Please request it again later.",
+                )
+                .await;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Post the public confirmation in the request's own channel, as a reply
+    /// to the interaction when configured to (and its token still works)
+    async fn announce_here(&self, content: String) {
+        let anchor = self.anchor();
+        if self.options.followup_style == FollowupStyle::Interaction {
+            if anchor.remaining().is_zero() {
+                debug!("Interaction token expired, announcing with a message instead");
+            } else {
+                match self
+                    .discord_http
+                    .interaction(self.application_id)
+                    .create_followup(&anchor.token)
+                    .content(&content)
+                    .await
+                {
+                    Ok(_) => return,
+                    Err(e) => {
+                        warn!(error = ?e, "Failed to reply with the announcement, sending a message instead")
+                    }
+                }
+            }
+        }
+        self.announcer.send(self.channel_id, content);
+    }
+
+    /// Show the requester where their request stands, on its card, or with a
+    /// mention in the request's channel once the card can't be edited anymore
+    /// (Discord allows that for 15 minutes)
+    async fn show_outcome(&self, message: &SuccessMessage, note: &str) {
+        let card = SuccessMessage {
+            description: note.to_string(),
+            ..message.clone()
+        };
+        if let Err(e) = self
+            .edit_card(build_completion_component(&card, None, &[]))
+            .await
+        {
+            debug!(error = ?e, "Couldn't update the request card, mentioning the requester instead");
+            self.announcer.send(
+                self.channel_id,
+                format!(
+                    "<@{}> **{}**: {note}",
+                    self.user_id,
+                    escape_markdown(&message.summary)
+                ),
+            );
+        }
+    }
 }
 
-#[derive(Debug)]
-/// Data needed to continue an interaction flow
-pub struct InteractionContinue {
-    pub data: Box<MessageComponentInteractionData>,
-    pub interaction_id: Id<InteractionMarker>,
-    pub token: String,
+/// Check on a fresh request a few times, showing on its success card whether
+/// a release has been grabbed yet and when it should be done
+async fn watch_download(
+    id: MediaId,
+    message: SuccessMessage,
+    similar: Vec<(Uuid, Offer)>,
+    backend: Arc<dyn MediaBackend>,
+    discord_http: Arc<HttpClient>,
+    application_id: Id<ApplicationMarker>,
+    anchor: Anchor,
+) {
+    let mut shown = None;
+    for delay in DOWNLOAD_STATUS_CHECKS {
+        tokio::time::sleep(delay).await;
+        if anchor.remaining().is_zero() {
+            debug!("The success card can't be edited anymore");
+            return;
+        }
+        let status = match backend.download_status(&id).await {
+            Ok(Some(status)) => status,
+            Ok(None) => return,
+            Err(e) => {
+                debug!(error = ?e, "Stopped checking on the download");
+                return;
+            }
+        };
+        if shown.as_ref() != Some(&status) {
+            trace!(status = ?status, "Download status changed");
+            if let Err(e) = update_interaction_component(
+                &discord_http,
+                application_id,
+                &anchor.token,
+                build_completion_component(&message, Some(&status), &similar),
+            )
+            .await
+            {
+                debug!(error = ?e, "Failed to show the download status");
+                return;
+            }
+        }
+        if status != DownloadStatus::Searching {
+            return;
+        }
+        shown = Some(status);
+    }
 }
 
 /// The coroutine that runs the request interaction to completion
 ///
 /// Wrapped in a span so every log emitted during the flow - including those
 /// from the backend providers - is tagged with the interaction's uuid, the
-/// requesting user, and the targeted media command. This is what makes a
-/// reporter's log readable when several requests overlap.
+/// requesting user, their server, and the targeted media command. This is
+/// what makes a reporter's log readable when several requests overlap, and
+/// lets operators running one bot for several servers tell them apart.
 #[tracing::instrument(
     name = "interaction",
     skip_all,
-    fields(uuid = %start.uuid, user_id = %start.user_id, media = %start.media),
+    fields(
+        uuid = %start.uuid,
+        user_id = %start.user_id,
+        guild_id = ?start.guild_id.map(|id| id.get()),
+        media = %start.media,
+    ),
 )]
 pub async fn run_interaction(
     start: InteractionStart,
     discord_http: Arc<HttpClient>,
-    backend: Arc<dyn MediaBackend>,
-    public_followup: bool,
-) -> anyhow::Result<()> {
+    instance: RegisteredBackend,
+    announcer: Announcer,
+    store: Arc<Store>,
+    claims: RequestClaims,
+    options: FlowOptions,
+) -> anyhow::Result<FlowOutcome> {
     // Destructure some some of the starting data
     let InteractionStart {
         uuid,
         mut rx,
         query,
-        media: _,
+        quick,
+        profile,
+        year,
+        need_by,
+        media,
         interaction_id,
         application_id,
         token,
         user_id,
         channel_id,
+        guild_id,
+        resumed,
     } = start;
 
     info!(query = %query, "Starting interaction flow");
 
-    // Send the "thinking" ack so we can take some time to actually perform the request
-    // This is done over the HTTP client connection
-    send_thinking(&discord_http, application_id, interaction_id, &token).await?;
+    let ctx = FlowContext {
+        uuid,
+        discord_http,
+        application_id,
+        anchor: Mutex::new(Anchor::new(token)),
+        user_id,
+        channel_id,
+        guild_id,
+        media,
+        query,
+        quick,
+        profile,
+        year,
+        need_by,
+        instance,
+        announcer,
+        store,
+        claims,
+        options,
+        stages: Mutex::default(),
+        waits: Mutex::default(),
+    };
+
+    let mut state = ctx.start(interaction_id, resumed).await?;
+    let outcome = loop {
+        state = match state {
+            FlowState::Done => {
+                info!("Interaction flow completed successfully");
+                break FlowOutcome::Finished;
+            }
+            FlowState::Abandoned => break FlowOutcome::Abandoned,
+            FlowState::NoResults => break FlowOutcome::NoResults,
+            state => ctx.step(state, &mut rx).await?,
+        };
+    };
+    ctx.record_stages().await;
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use twilight_model::channel::message::component::ComponentType;
 
-    debug!(query = %query, "Performing search");
-    let mut results = backend.search(&query).await?;
-    info!(count = results.len(), "Search completed");
+    fn event(custom_id: &str, values: &[&str]) -> MessageComponentInteractionData {
+        MessageComponentInteractionData {
+            custom_id: custom_id.to_string(),
+            component_type: ComponentType::TextSelectMenu,
+            resolved: None,
+            values: values.iter().map(|v| v.to_string()).collect(),
+        }
+    }
 
-    // Check if there were no results
-    if results.is_empty() {
-        info!("No search results found");
-        update_string_message("No results", &discord_http, application_id, &token).await?;
-        return Ok(());
+    fn field(title: &str, ids: &[i32], field_type: FieldType) -> RequestDetails {
+        RequestDetails {
+            title: title.to_string(),
+            options: ids
+                .iter()
+                .map(|&n| DropdownOption {
+                    title: n.to_string(),
+                    description: None,
+                    id: Some(SelectableId::Integer(n)),
+                    present: false,
+                    note: None,
+                    warning: None,
+                })
+                .collect(),
+            selected_indices: vec![],
+            metadata: Some(title.to_string()),
+            field_type,
+            always_show: false,
+            status: None,
+        }
     }
 
-    // Discord allows a maximum of 25 options in a dropdown
-    if results.len() > MAX_DROPDOWN_OPTIONS {
-        info!(
-            "Truncating {} results to {} for Discord dropdown",
-            results.len(),
-            MAX_DROPDOWN_OPTIONS
+    #[test]
+    fn flow_time_is_grouped_into_report_stages() {
+        let timing = |stage: &str, millis| StageTiming {
+            stage: stage.into(),
+            millis,
+        };
+        let calls = [
+            timing("search", 300),
+            timing("enrich", 100),
+            timing("details", 200),
+            timing("request", 500),
+        ];
+        let waits = [
+            timing(Wait::Result.stage(), 4000),
+            timing(Wait::Details.stage(), 1000),
+            timing(Wait::Details.stage(), 2000),
+        ];
+        assert_eq!(
+            flow_stages(&calls, &waits),
+            [
+                timing("search", 400),
+                timing("select", 4000),
+                timing("details", 3200),
+                timing("submit", 500),
+            ]
         );
-        results.truncate(MAX_DROPDOWN_OPTIONS);
     }
 
-    // Now update the interaction with all of the options that result from the search
-    trace!("Showing search results to user");
-    let dropdown_options = backend.to_dropdown_options(results.as_slice());
-    update_search_results_component(
-        uuid,
-        dropdown_options,
-        &discord_http,
-        application_id,
-        &token,
-    )
-    .await?;
-
-    // Now wait for the user to select an option, which will come in on the channel
-    // An abandoned interaction is a normal outcome, not an error
-    debug!("Waiting for user to select a search result");
-    let mut next = match timeout(INTERACTION_TIMEOUT_DURATION, rx.recv()).await {
-        Ok(Some(val)) => val,
-        Ok(None) | Err(_) => {
-            info!("User abandoned the interaction at search result selection");
-            update_timeout(&discord_http, application_id, &token).await?;
-            return Ok(());
-        }
-    };
-    trace!(data = ?next, "Got the next interaction");
+    #[test]
+    fn tokens_are_given_up_before_discord_expires_them() {
+        let fresh = Anchor::new("token".into());
+        assert!(fresh.remaining() > INTERACTION_TIMEOUT_DURATION);
+        let aged = |minutes: u64| Anchor {
+            token: "token".into(),
+            issued: Instant::now()
+                .checked_sub(Duration::from_secs(minutes * 60))
+                .unwrap(),
+        };
+        assert!(aged(12).remaining() < Duration::from_secs(3 * 60));
+        assert!(aged(15).remaining().is_zero());
+    }
 
-    // Use the value from this next payload to get the index into the search results to process
-    let selection_idx: usize = next
-        .data
-        .values
-        .first()
-        .and_then(|v| v.parse().ok())
-        .filter(|idx| *idx < results.len())
-        .context("Search result selection didn't map to a valid result")?;
-
-    let selection = results.remove(selection_idx);
-    info!(index = selection_idx, "User made selection");
-    trace!(selection = ?selection, "Selection details");
-
-    // Now check the early stop critera
-    if backend.early_stop(&*selection) {
-        info!("Stopping early - media already requested");
-        update_string_message(EARLY_STOP_MESSAGE, &discord_http, application_id, &token).await?;
-        return Ok(());
-    }
-    debug!("Selection has not been requested, continuing interaction");
-
-    // Now, we need to collect the additional information needed to perform the request
-    debug!("Fetching additional details required");
-    let mut additional_details = backend.additional_details(&*selection).await?;
-    trace!(details = ?additional_details, "Request details");
-
-    // Track which fields to show in the UI: ones the user must choose from
-    // (multiple options), plus ones the backend wants reviewed regardless
-    let user_selectable_fields: std::collections::HashSet<_> = additional_details
-        .iter()
-        .filter(|detail| detail.options.len() > 1 || detail.always_show)
-        .filter_map(|detail| detail.metadata.as_ref())
-        .cloned()
-        .collect();
+    #[test]
+    fn result_index_must_be_in_bounds() {
+        assert_eq!(
+            selected_result_index(&event("result:x", &["2"]), 3),
+            Some(2)
+        );
+        assert_eq!(selected_result_index(&event("result:x", &["3"]), 3), None);
+        assert_eq!(
+            selected_result_index(&event("result:x", &["nope"]), 3),
+            None
+        );
+    }
 
-    let display_info = backend.display_info(&*selection);
-    let mut request_container = build_request_component(
-        uuid,
-        &display_info,
-        &additional_details,
-        &user_selectable_fields,
-        false,
-    );
+    #[test]
+    fn also_notify_leaves_out_the_requester() {
+        let picked = event("notify:x", &["2", "1", "nope", "3", "4", "5", "6", "7"]);
+        let subscribers = picked_subscribers(&picked, Id::new(1));
+        assert_eq!(subscribers, [2, 3, 4, 5, 6].map(Id::new));
+        assert!(picked_subscribers(&event("notify:x", &[]), Id::new(1)).is_empty());
+    }
 
-    respond_interaction_component(
-        &discord_http,
-        application_id,
-        next.interaction_id,
-        &next.token,
-        request_container,
-    )
-    .await?;
+    #[test]
+    fn category_can_be_picked_and_cleared() {
+        assert_eq!(
+            picked_category(&event("category:x", &["1"]), 2),
+            Ok(Some(1))
+        );
+        assert_eq!(picked_category(&event("category:x", &[]), 2), Ok(None));
+        assert!(picked_category(&event("category:x", &["2"]), 2).is_err());
+        assert!(picked_category(&event("category:x", &["kids"]), 2).is_err());
+    }
 
-    // Collect all the selections
-    loop {
-        debug!("Waiting for user to select a detail option");
-        next = match timeout(INTERACTION_TIMEOUT_DURATION, rx.recv()).await {
-            Ok(Some(val)) => val,
-            Ok(None) | Err(_) => {
-                info!("User abandoned the interaction at detail selection");
-                update_timeout(&discord_http, application_id, &token).await?;
-                return Ok(());
-            }
+    #[test]
+    fn long_option_text_is_shortened_and_kept_distinct() {
+        let option = |title: String, description: Option<String>| DropdownOption {
+            title,
+            description,
+            id: None,
+            present: false,
+            note: None,
+            warning: None,
+        };
+        let long = "word ".repeat(30);
+        let mut options = vec![
+            option(format!("{long}one"), Some("x".repeat(150))),
+            option(format!("{long}two"), None),
+            option("Dune".into(), None),
+            option("Dune".into(), None),
+        ];
+        fit_dropdown_options(&mut options);
+        for o in &options {
+            assert!(o.title.chars().count() <= MAX_OPTION_TEXT_LENGTH);
+        }
+        assert_eq!(
+            options[0].description.as_ref().unwrap().chars().count(),
+            MAX_OPTION_TEXT_LENGTH
+        );
+        assert_ne!(options[0].title, options[1].title);
+        assert!(options[1].title.ends_with("… (2)"));
+        // Titles that were identical to begin with are left alone
+        assert_eq!(options[2].title, options[3].title);
+    }
+
+    #[test]
+    fn media_is_claimed_by_the_first_requester() {
+        let claims = RequestClaims::default();
+        let tmdb = |id| MediaId {
+            tmdb: Some(id),
+            ..Default::default()
+        };
+        let claim =
+            |key: &ClaimKey, user| claims.claim(key.clone(), Id::new(user), REQUEST_CLAIM_DURATION);
+        let key = ("radarr".to_string(), tmdb(603));
+        assert_eq!(claim(&key, 1), Ok(()));
+        assert_eq!(claim(&key, 2), Err(Id::new(1)));
+        let more_ids = MediaId {
+            imdb: Some("tt0133093".into()),
+            ..tmdb(603)
         };
-        trace!(data = ?next, "Got interaction from additional details");
+        assert_eq!(claim(&("radarr".into(), more_ids), 2), Err(Id::new(1)));
+        // Other media, or the same title on another backend, is unaffected
+        assert_eq!(claim(&("radarr".into(), tmdb(604)), 2), Ok(()));
+        assert_eq!(claim(&("radarr-4k".into(), tmdb(603)), 2), Ok(()));
+        // Only the holder can give it up
+        claims.release(&key, Id::new(2));
+        assert_eq!(claim(&key, 2), Err(Id::new(1)));
+        claims.release(&key, Id::new(1));
+        assert_eq!(claim(&key, 2), Ok(()));
+        // A late release from the first requester leaves the new claim be
+        claims.release(&key, Id::new(1));
+        assert_eq!(claim(&key, 3), Err(Id::new(2)));
+    }
 
-        // Check if this was the final "Request" button click
-        if next.data.custom_id.starts_with("request:") {
-            info!("User clicked Request button, all details collected");
+    #[test]
+    fn claims_last_as_long_as_asked() {
+        let claims = RequestClaims::default();
+        let key = (
+            "radarr".to_string(),
+            MediaId {
+                tmdb: Some(603),
+                ..Default::default()
+            },
+        );
+        // e.g. a request that's waiting on an admin
+        assert_eq!(
+            claims.claim(key.clone(), Id::new(1), APPROVAL_TIMEOUT),
+            Ok(())
+        );
+        assert_eq!(
+            claims.claim(key.clone(), Id::new(2), REQUEST_CLAIM_DURATION),
+            Err(Id::new(1))
+        );
+        // Once decided, it runs out like any other
+        claims.extend(&key, Id::new(1), Duration::ZERO);
+        assert_eq!(
+            claims.claim(key.clone(), Id::new(2), REQUEST_CLAIM_DURATION),
+            Ok(())
+        );
+        // Someone else's claim can't be extended
+        claims.extend(&key, Id::new(1), APPROVAL_TIMEOUT);
+        claims.extend(&key, Id::new(2), Duration::ZERO);
+        assert_eq!(
+            claims.claim(key, Id::new(3), REQUEST_CLAIM_DURATION),
+            Ok(())
+        );
+    }
 
-            // Acknowledge the button click immediately (before 3-second timeout),
-            // disabling everything so it can't be clicked again while we submit
-            respond_interaction_component(
-                &discord_http,
-                application_id,
-                next.interaction_id,
-                &next.token,
-                build_request_component(
-                    uuid,
-                    &display_info,
-                    &additional_details,
-                    &user_selectable_fields,
-                    true,
-                ),
+    #[test]
+    fn selections_are_summarized_on_one_line() {
+        let selection = |field: &str, values: &[&str]| Selection {
+            field: field.into(),
+            values: values.iter().map(|v| v.to_string()).collect(),
+        };
+        assert_eq!(
+            selections_summary(&[
+                selection("Quality Profile", &["HD-1080p"]),
+                selection("Seasons", &["Season 1", "Season 2"]),
+                selection("Tags", &[]),
+                selection("Root Folder", &["/tv_shows"]),
+            ])
+            .as_deref(),
+            Some(
+                "Quality Profile: HD-1080p · Seasons: Season 1, Season 2 · Root Folder: /tv\\_shows"
             )
-            .await?;
+        );
+        assert_eq!(selections_summary(&[]), None);
+    }
 
-            break;
-        }
+    #[test]
+    fn request_button_submits() {
+        let mut details = vec![field("Quality", &[1, 2], FieldType::Dropdown)];
+        assert_eq!(
+            apply_form_event(&mut details, &event("request:x", &[])),
+            FormEvent::Submit
+        );
+    }
 
-        // Map the response back to one of our details, ignoring stale or malformed
-        // events (e.g. a second click on a dropdown we already collapsed)
-        let stale = 'event: {
-            let Some((title, _)) = next.data.custom_id.split_once(':') else {
-                break 'event Some("custom id has no uuid suffix");
-            };
-            let Some(detail_idx) = additional_details.iter().position(|x| x.title == title) else {
-                break 'event Some("no detail matching custom id");
-            };
+    #[test]
+    fn dropdown_selection_updates_and_stale_events_are_ignored() {
+        let mut details = vec![field("Quality", &[1, 2], FieldType::Dropdown)];
+        assert_eq!(
+            apply_form_event(&mut details, &event("Quality:x", &["1"])),
+            FormEvent::Updated
+        );
+        assert_eq!(details[0].selected_indices, vec![1]);
 
-            if additional_details[detail_idx].field_type == FieldType::MultiSelect {
-                let detail = &mut additional_details[detail_idx];
-                let mut indices: Vec<usize> = next
-                    .data
-                    .values
-                    .iter()
-                    .filter_map(|v| v.parse().ok())
-                    .filter(|&i| i < detail.options.len())
-                    .collect();
-
-                // An "All Seasons"-style option is mutually exclusive with the
-                // rest. Discord can't enforce that natively, so we reconcile on
-                // re-render: selecting it clears the others; selecting another
-                // while it's active drops it.
-                let exclusive = detail.options.iter().position(
-                    |o| matches!(o.id, Some(SelectableId::Integer(n)) if n == ALL_SEASONS_ID),
-                );
-                if let Some(excl) = exclusive
-                    && indices.contains(&excl)
-                    && indices.len() > 1
-                {
-                    if detail.selected_indices.contains(&excl) {
-                        // It was already on and the user added a specific option
-                        indices.retain(|&i| i != excl);
-                    } else {
-                        // The user just turned it on
-                        indices = vec![excl];
-                    }
-                }
+        assert!(matches!(
+            apply_form_event(&mut details, &event("Quality:x", &["5"])),
+            FormEvent::Ignored(_)
+        ));
+        assert!(matches!(
+            apply_form_event(&mut details, &event("Unknown:x", &["0"])),
+            FormEvent::Ignored(_)
+        ));
+        assert!(matches!(
+            apply_form_event(&mut details, &event("nouuid", &["0"])),
+            FormEvent::Ignored(_)
+        ));
+        // Stale events leave the existing selection alone
+        assert_eq!(details[0].selected_indices, vec![1]);
+    }
 
-                debug!(detail = %title, count = indices.len(), "User updated multi-select");
-                detail.selected_indices = indices;
-            } else {
-                let Some(option_idx) = next
-                    .data
-                    .values
-                    .first()
-                    .and_then(|v| v.parse::<usize>().ok())
-                else {
-                    break 'event Some("selection value is not a valid index");
-                };
-                if option_idx >= additional_details[detail_idx].options.len() {
-                    break 'event Some("selection index out of bounds");
-                }
-                debug!(
-                    detail = %title,
-                    selected = %additional_details[detail_idx].options[option_idx].title,
-                    "User selected detail option"
-                );
-                additional_details[detail_idx].selected_indices = vec![option_idx];
-            }
-            None
+    #[test]
+    fn all_seasons_is_mutually_exclusive() {
+        let mut details = vec![field(
+            "Seasons",
+            &[ALL_SEASONS_ID, 1, 2],
+            FieldType::MultiSelect,
+        )];
+
+        apply_form_event(&mut details, &event("Seasons:x", &["1", "2"]));
+        assert_eq!(details[0].selected_indices, vec![1, 2]);
+
+        // Turning "All Seasons" on clears the specific picks
+        apply_form_event(&mut details, &event("Seasons:x", &["0", "1", "2"]));
+        assert_eq!(details[0].selected_indices, vec![0]);
+
+        // Adding a specific pick while it's on drops "All Seasons"
+        apply_form_event(&mut details, &event("Seasons:x", &["0", "2"]));
+        assert_eq!(details[0].selected_indices, vec![2]);
+
+        // A tags field's "None" works the same way
+        let mut tags = field("Tags", &[NO_TAG_ID, 3, 5], FieldType::MultiSelect);
+        tags.selected_indices = vec![0];
+        let mut details = vec![tags];
+        apply_form_event(&mut details, &event("Tags:x", &["0", "1", "2"]));
+        assert_eq!(details[0].selected_indices, vec![1, 2]);
+        apply_form_event(&mut details, &event("Tags:x", &["0", "1", "2"]));
+        assert_eq!(details[0].selected_indices, vec![0]);
+    }
+
+    #[test]
+    fn season_buttons_toggle_one_season_at_a_time() {
+        let mut details = vec![field(
+            "Seasons",
+            &[ALL_SEASONS_ID, 1, 2],
+            FieldType::MultiSelect,
+        )];
+
+        apply_form_event(&mut details, &event("Seasons#2:x", &[]));
+        apply_form_event(&mut details, &event("Seasons#1:x", &[]));
+        assert_eq!(details[0].selected_indices, vec![1, 2]);
+
+        apply_form_event(&mut details, &event("Seasons#2:x", &[]));
+        assert_eq!(details[0].selected_indices, vec![1]);
+
+        // "All Seasons" stays exclusive when toggled as a button
+        apply_form_event(&mut details, &event("Seasons#0:x", &[]));
+        assert_eq!(details[0].selected_indices, vec![0]);
+        apply_form_event(&mut details, &event("Seasons#2:x", &[]));
+        assert_eq!(details[0].selected_indices, vec![2]);
+
+        assert!(matches!(
+            apply_form_event(&mut details, &event("Seasons#3:x", &[])),
+            FormEvent::Ignored(_)
+        ));
+    }
+
+    #[test]
+    fn timeout_message_offers_to_start_over() {
+        let uuid = Uuid::new_v4();
+        let rendered = serde_json::to_string(&build_timeout_component("Timed out", uuid)).unwrap();
+        assert!(rendered.contains(&format!("\"{RESUME_PREFIX}:{uuid}\"")));
+        assert!(rendered.contains("Timed out"));
+    }
+
+    #[test]
+    fn no_results_offers_to_edit_the_query() {
+        let uuid = Uuid::new_v4();
+        let rendered =
+            serde_json::to_string(&build_no_results_component("Nothing found", uuid)).unwrap();
+        assert!(rendered.contains(&format!("\"{RESEARCH_PREFIX}:{uuid}\"")));
+
+        let submitted: ModalInteractionData = serde_json::from_value(serde_json::json!({
+            "custom_id": format!("{RESEARCH_PREFIX}:{uuid}"),
+            "components": [{
+                "type": 18,
+                "id": 1,
+                "component": {"type": 4, "id": 2, "custom_id": "query", "value": " Dune Part Two "}
+            }]
+        }))
+        .unwrap();
+        assert_eq!(modal_query(&submitted).as_deref(), Some("Dune Part Two"));
+    }
+
+    #[test]
+    fn search_results_preview_one_result_at_a_time() {
+        let uuid = Uuid::new_v4();
+        let options: Vec<_> = ["Dune", "Dune: Part Two"]
+            .map(|title| DropdownOption {
+                title: title.into(),
+                ..Default::default()
+            })
+            .into();
+        let render = |preview| {
+            serde_json::to_string(&build_search_results_component(
+                uuid,
+                &options,
+                None,
+                preview,
+                OverviewMode::Spoiler,
+            ))
+            .unwrap()
         };
+        let unpreviewed = render(None);
+        assert!(unpreviewed.contains(&format!("\"{PREVIEW_PREFIX}:{uuid}\"")));
+        assert!(unpreviewed.contains("\"Preview\""));
 
-        if let Some(reason) = stale {
-            debug!(data = ?next.data, reason = reason, "Ignoring component event");
-            ack_component(
-                &discord_http,
-                application_id,
-                next.interaction_id,
-                &next.token,
-            )
-            .await?;
-            continue;
+        let display_info = MediaDisplayInfo {
+            title: "Dune: Part Two".into(),
+            subtitle: Some("2024".into()),
+            description: Some("Paul unites with the Fremen.".into()),
+            thumbnail_url: Some("https://example.com/poster.jpg".into()),
+        };
+        let previewed = render(Some((1, &display_info)));
+        assert!(previewed.contains("poster.jpg"));
+        assert!(previewed.contains("||Paul unites with the Fremen.||"));
+        assert!(previewed.contains("Result 2 of 2"));
+        assert!(previewed.contains("\"Next preview\""));
+    }
+
+    #[test]
+    fn season_grid_falls_back_to_a_menu_past_the_component_limit() {
+        let display_info = MediaDisplayInfo {
+            title: "Show".into(),
+            subtitle: None,
+            description: None,
+            thumbnail_url: None,
+        };
+        let render = |seasons: &RequestDetails| {
+            let details = [
+                field("Quality", &[1, 2], FieldType::Dropdown),
+                seasons.clone(),
+            ];
+            let fields = details.iter().filter_map(|d| d.metadata.clone()).collect();
+            let component = build_request_component(
+                Uuid::nil(),
+                &display_info,
+                OverviewMode::Show,
+                &details,
+                &fields,
+                &FormExtras::default(),
+                false,
+            );
+            serde_json::to_string(&component).unwrap()
+        };
+
+        let short = field("Seasons", &[1, 2, 3], FieldType::MultiSelect);
+        let rendered = render(&short);
+        assert!(rendered.contains("Seasons#2:"));
+        assert!(rendered.contains("\"label\":\"S3\""));
+
+        let long = field(
+            "Seasons",
+            &(1..=25).collect::<Vec<_>>(),
+            FieldType::MultiSelect,
+        );
+        let rendered = render(&long);
+        assert!(!rendered.contains("Seasons#"));
+        assert!(rendered.contains("\"custom_id\":\"Seasons:"));
+    }
+
+    #[test]
+    fn approvals_are_decided_once_by_the_buttons() {
+        let request = ApprovalRequest {
+            user_id: Id::new(1),
+            guild_id: Some(Id::new(10)),
+            media: "movie".into(),
+            title: "Dune (2021)".into(),
+            details: None,
+            at: 0,
+        };
+        let uuid = Uuid::new_v4();
+        let pending = PendingApprovals::default();
+        let mut decision = pending.add(uuid, request.clone());
+        let card = |card| serde_json::to_string(&build_approval_component(&request, card)).unwrap();
+        assert!(card(ApprovalCard::Waiting(uuid)).contains(&format!("{APPROVE_PREFIX}:{uuid}")));
+
+        let approved = ApprovalDecision::Approved(Id::new(2));
+        // Another server's admins can't decide on it
+        assert!(!pending.is_waiting(&uuid, Some(Id::new(20))));
+        assert!(
+            pending
+                .decide(uuid, Some(Id::new(20)), approved.clone())
+                .is_none()
+        );
+        assert!(pending.decide(uuid, None, approved.clone()).is_none());
+        assert!(decision.try_recv().is_err());
+        assert!(pending.is_waiting(&uuid, Some(Id::new(10))));
+        assert!(
+            pending
+                .decide(uuid, Some(Id::new(10)), approved.clone())
+                .is_some()
+        );
+        assert_eq!(decision.try_recv().unwrap(), approved);
+        // The card's buttons can be clicked again before it's updated
+        assert!(!pending.is_waiting(&uuid, Some(Id::new(10))));
+        assert!(
+            pending
+                .decide(uuid, Some(Id::new(10)), approved.clone())
+                .is_none()
+        );
+        assert!(!card(ApprovalCard::Decided(approved)).contains("custom_id"));
+    }
+
+    #[test]
+    fn denials_carry_the_admins_reason() {
+        let uuid = Uuid::new_v4();
+        let submitted: ModalInteractionData = serde_json::from_value(serde_json::json!({
+            "custom_id": format!("{DENY_PREFIX}:{uuid}"),
+            "components": [{
+                "type": 18,
+                "id": 1,
+                "component": {"type": 4, "id": 2, "custom_id": "reason", "value": " Already in 4K "}
+            }]
+        }))
+        .unwrap();
+        let reason = modal_reason(&submitted).unwrap();
+        assert_eq!(reason, "Already in 4K");
+        // Not mistaken for a search query, or the other way round
+        assert_eq!(modal_query(&submitted), None);
+
+        let request = ApprovalRequest {
+            user_id: Id::new(1),
+            guild_id: None,
+            media: "movie".into(),
+            title: "Dune (2021)".into(),
+            details: None,
+            at: 0,
+        };
+        let card = serde_json::to_string(&build_approval_component(
+            &request,
+            ApprovalCard::Decided(ApprovalDecision::Denied(
+                Id::new(2),
+                "*Already* in 4K".into(),
+            )),
+        ))
+        .unwrap();
+        assert!(card.contains(r"Denied by <@2>\n> \\*Already\\* in 4K"));
+    }
+
+    #[test]
+    fn pending_lists_a_servers_requests_oldest_first() {
+        let request = |guild: u64, at| ApprovalRequest {
+            user_id: Id::new(1),
+            guild_id: Some(Id::new(guild)),
+            media: "movie".into(),
+            title: format!("Movie {at}"),
+            details: None,
+            at,
+        };
+        let pending = PendingApprovals::default();
+        let (newer, older, elsewhere) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let _newer = pending.add(newer, request(1, 20));
+        let _older = pending.add(older, request(1, 10));
+        let _elsewhere = pending.add(elsewhere, request(2, 0));
+
+        let listed = pending.list(Some(Id::new(1)));
+        let uuids: Vec<_> = listed.iter().map(|(uuid, _)| *uuid).collect();
+        assert_eq!(uuids, vec![older, newer]);
+        let rendered = serde_json::to_string(&build_pending_component(&listed)).unwrap();
+        assert!(rendered.contains("Waiting for approval (2)"));
+        assert!(rendered.contains(&format!("{DENY_PREFIX}:{older}")));
+    }
+
+    #[test]
+    fn removals_list_a_button_per_library_title() {
+        let removal = |n| Removal {
+            media: "movie".into(),
+            label: format!("Movie {n}"),
+            id: MediaId {
+                backend_id: Some(n),
+                ..Default::default()
+            },
+            delete_files: true,
+            admin: Id::new(1),
+        };
+        let listed: Vec<_> = (0..7).map(|n| (Uuid::new_v4(), removal(n))).collect();
+        let rendered = serde_json::to_string(&build_removals_component("movie", &listed)).unwrap();
+        assert!(rendered.contains("delete its files"));
+        for (id, _) in &listed {
+            assert!(rendered.contains(&format!("\"{REMOVE_PREFIX}:{id}\"")));
         }
 
-        // Update the component to show the selection
-        request_container = build_request_component(
-            uuid,
-            &display_info,
-            &additional_details,
-            &user_selectable_fields,
-            false,
+        let none = serde_json::to_string(&build_removals_component("Dune", &[])).unwrap();
+        assert!(none.contains("Nothing in the library matches"));
+    }
+
+    #[test]
+    fn waiting_form_says_when_it_expires() {
+        let display_info = MediaDisplayInfo {
+            title: "Movie".into(),
+            subtitle: None,
+            description: None,
+            thumbnail_url: None,
+        };
+        let details = [field("Quality", &[1, 2], FieldType::Dropdown)];
+        let fields = details.iter().filter_map(|d| d.metadata.clone()).collect();
+        let render = |submitting| {
+            let component = build_request_component(
+                Uuid::nil(),
+                &display_info,
+                OverviewMode::Show,
+                &details,
+                &fields,
+                &FormExtras::default(),
+                submitting,
+            );
+            serde_json::to_string(&component).unwrap()
+        };
+        let deadline = store::now() + INTERACTION_TIMEOUT_DURATION.as_secs();
+        let waiting = render(false);
+        assert!(
+            waiting.contains(&format!("Expires <t:{deadline}:R>"))
+                || waiting.contains(&format!("Expires <t:{}:R>", deadline + 1))
         );
+        // Nothing to wait on while the request is being sent
+        assert!(!render(true).contains("Expires"));
+    }
 
-        respond_interaction_component(
-            &discord_http,
-            application_id,
-            next.interaction_id,
-            &next.token,
-            request_container,
-        )
-        .await?;
-        trace!("Updated component with selection");
+    #[test]
+    fn risky_picks_need_confirming() {
+        let mut details = [
+            field("Monitor", &[1, 2], FieldType::Dropdown),
+            field("Quality", &[1], FieldType::Dropdown),
+        ];
+        for detail in &mut details {
+            detail.options[0].warning = Some("It never downloads.".into());
+        }
+        let monitor = HashSet::from(["Monitor".to_string()]);
+        assert_eq!(pick_warnings(&details, &monitor), []);
 
-        // Check if all details have been resolved
-        if additional_details
-            .iter()
-            .all(|x| x.options.len() == 1 || !x.selected_indices.is_empty())
-        {
-            debug!("All details have been selected, waiting for final Request button click");
+        details[0].selected_indices = vec![0];
+        let warnings = pick_warnings(&details, &monitor);
+        // The admin-configured quality isn't questioned
+        assert_eq!(warnings, [("Monitor", "1", "It never downloads.")]);
+        let card = serde_json::to_string(&build_warning_component(Uuid::nil(), &warnings)).unwrap();
+        assert!(card.contains("**Monitor: 1** — It never downloads."));
+        assert!(card.contains(&format!("\"{PROCEED_PREFIX}:{}\"", Uuid::nil())));
+        assert!(card.contains(&format!("\"{BACK_PREFIX}:{}\"", Uuid::nil())));
+    }
+
+    #[test]
+    fn download_status_reads_naturally() {
+        let grabbed = |secs| DownloadStatus::Grabbed {
+            eta: Some(Duration::from_secs(secs)),
+        };
+        assert_eq!(
+            download_status_text(&DownloadStatus::Searching),
+            "Searching indexers…"
+        );
+        assert_eq!(
+            download_status_text(&grabbed(35 * 60 + 20)),
+            "Grabbed, ETA ~35 min"
+        );
+        assert_eq!(
+            download_status_text(&grabbed(2 * 3600 + 5 * 60)),
+            "Grabbed, ETA ~2 h 5 min"
+        );
+        assert_eq!(download_status_text(&grabbed(30)), "Grabbed, almost done");
+    }
+
+    #[test]
+    fn overview_can_be_spoilered_or_hidden() {
+        let long = "x".repeat(MAX_TEXT_CONTENT_LENGTH);
+        let spoiler = overview_text(Some(&long), OverviewMode::Spoiler).unwrap();
+        assert!(spoiler.starts_with("||") && spoiler.ends_with("…||"));
+        assert!(spoiler.chars().count() <= MAX_TEXT_CONTENT_LENGTH);
+
+        assert_eq!(overview_text(Some("plot"), OverviewMode::Hide), None);
+        assert_eq!(overview_text(None, OverviewMode::Hide), None);
+        assert_eq!(
+            overview_text(Some(""), OverviewMode::Spoiler).as_deref(),
+            Some("*Overview unavailable.*")
+        );
+    }
+
+    #[test]
+    fn quick_request_needs_every_detail_resolved() {
+        let default = field("Root Folder", &[1], FieldType::Dropdown);
+        let mut quality = field("Quality", &[1, 2], FieldType::Dropdown);
+        let mut seasons = field("Seasons", &[ALL_SEASONS_ID, 1], FieldType::MultiSelect);
+        assert!(details_complete(std::slice::from_ref(&default)));
+        assert!(!details_complete(&[default.clone(), quality.clone()]));
+
+        quality.selected_indices = vec![1];
+        assert!(details_complete(&[default.clone(), quality.clone()]));
+
+        // A multi-select has no implicit default
+        assert!(!details_complete(&[quality.clone(), seasons.clone()]));
+        seasons.selected_indices = vec![0];
+        assert!(details_complete(&[default, quality, seasons]));
+    }
+
+    #[test]
+    fn profile_preselects_named_options() {
+        let mut details = vec![
+            field("Root Folder", &[1, 2], FieldType::Dropdown),
+            field("Series Type", &[1, 2, 3], FieldType::Dropdown),
+        ];
+        details[0].metadata = Some("sonarr:root_folder".into());
+        details[1].metadata = Some("sonarr:series_type".into());
+        let mut profile = Profile::default();
+        profile.fields.insert("root_folder".into(), "2".into());
+        profile.fields.insert("season_folder".into(), "Yes".into());
+
+        apply_profile(&mut details, "anime", &profile).unwrap();
+        assert_eq!(details[0].selected_indices, vec![1]);
+        assert!(details[1].selected_indices.is_empty());
+
+        profile.fields.insert("series_type".into(), "4".into());
+        let err = apply_profile(&mut details, "anime", &profile).unwrap_err();
+        assert!(err.downcast_ref::<UserFacingError>().is_some());
+    }
+
+    #[derive(Debug)]
+    struct Release(&'static str, Option<i32>);
+
+    impl MediaItem for Release {
+        fn to_dropdown(&self) -> DropdownOption {
+            DropdownOption {
+                title: self.0.to_string(),
+                description: None,
+                id: None,
+                present: false,
+                note: None,
+                warning: None,
+            }
+        }
+        fn year(&self) -> Option<i32> {
+            self.1
+        }
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+        fn into_any(self: Box<Self>) -> Box<dyn std::any::Any> {
+            self
         }
     }
 
-    info!("All options collected, performing request");
-    trace!(options = ?additional_details, "Collected options");
+    #[test]
+    fn year_ranks_close_releases_first_in_original_order() {
+        let mut results: Vec<Box<dyn MediaItem>> = vec![
+            Box::new(Release("a", Some(1984))),
+            Box::new(Release("b", None)),
+            Box::new(Release("c", Some(2020))),
+            Box::new(Release("d", Some(2024))),
+            Box::new(Release("e", Some(2021))),
+        ];
+        rank_by_year(&mut results, 2021);
+        let order: Vec<_> = results.iter().map(|r| r.to_dropdown().title).collect();
+        assert_eq!(order, ["c", "e", "a", "b", "d"]);
+    }
 
-    // Perform the actual request
-    let success_msg = backend.success_message(&additional_details, &*selection);
-    backend
-        .request(additional_details, selection, user_id.get())
-        .await?;
-    info!("Request completed successfully");
+    /// Compare a rendered component with its golden file under
+    /// `tests/golden`, or rewrite the file when `UPDATE_GOLDEN` is set.
+    /// Relative timestamps are zeroed, as they count from now.
+    fn assert_golden(name: &str, component: &Component) {
+        let json = serde_json::to_string_pretty(component).unwrap();
+        let mut parts = json.split("<t:");
+        let mut rendered = parts.next().unwrap_or_default().to_string();
+        for part in parts {
+            let rest = part.find(":R>").map_or(part, |end| &part[end..]);
+            rendered.push_str("<t:0");
+            rendered.push_str(rest);
+        }
+        rendered.push('\n');
+        let path = format!("{}/tests/golden/{name}.json", env!("CARGO_MANIFEST_DIR"));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, &rendered).unwrap();
+            return;
+        }
+        let golden = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Can't read {path} ({e}), run with UPDATE_GOLDEN=1"));
+        assert!(
+            golden == rendered,
+            "{name} renders differently from {path}:\n{rendered}\n\
+             Run with UPDATE_GOLDEN=1 if the change is intended"
+        );
+    }
 
-    // Update the message with success (using original token since we already responded to button click)
-    update_interaction_component(
-        &discord_http,
-        application_id,
-        &token,
-        build_completion_component(&success_msg),
-    )
-    .await
-    .context("Failed to send success response")?;
-
-    // Send public message to channel if configured.
-    // Plain content only: it's the one thing OS notification previews render.
-    //
-    // A failure here must NOT fail the interaction. The request already
-    // succeeded and the user has already seen the success message above; the
-    // public announcement is best-effort. The common cause is the bot lacking
-    // "View Channel"/"Send Messages" permission in this channel (403 Missing
-    // Access), which we'd otherwise mis-surface to the user as a "Backend
-    // authentication error" overwriting their success message.
-    if public_followup {
-        let content = format!(
-            "{} requested by <@{}>",
-            escape_markdown(&success_msg.summary),
-            user_id
-        );
-        if let Err(e) = discord_http
-            .create_message(channel_id)
-            .content(&content)
-            .await
-        {
-            warn!(
-                channel_id = %channel_id,
-                error = ?e,
-                "Could not post the public request confirmation, but the request \
-                 itself succeeded. Ensure the bot has the \"View Channel\" and \
-                 \"Send Messages\" permissions in this channel, or set \
-                 public_followup = false to disable channel announcements."
-            );
+    fn golden_display_info() -> MediaDisplayInfo {
+        MediaDisplayInfo {
+            title: "Dune: Part Two".into(),
+            subtitle: Some("2024 · 166 min".into()),
+            description: Some("Paul Atreides unites with Chani and the Fremen.".into()),
+            thumbnail_url: Some("https://example.com/poster.jpg".into()),
         }
     }
 
-    info!("Interaction flow completed successfully");
-    Ok(())
+    #[test]
+    fn search_results_match_golden() {
+        let options = [
+            DropdownOption {
+                title: "Dune: Part Two".into(),
+                description: Some("2024".into()),
+                ..Default::default()
+            },
+            DropdownOption {
+                title: format!("Dune {}", "and sand ".repeat(20)),
+                description: Some("Already requested".into()),
+                present: true,
+                ..Default::default()
+            },
+        ];
+        let render = |notice, preview| {
+            build_search_results_component(
+                Uuid::nil(),
+                &options,
+                notice,
+                preview,
+                OverviewMode::Spoiler,
+            )
+        };
+        assert_golden("search_results", &render(None, None));
+        let display_info = golden_display_info();
+        assert_golden(
+            "search_results_preview",
+            &render(Some("Showing 2 of 40 results"), Some((0, &display_info))),
+        );
+    }
+
+    #[test]
+    fn request_form_matches_golden() {
+        let mut quality = field("Quality", &[1, 2], FieldType::Dropdown);
+        quality.selected_indices = vec![1];
+        let details = [
+            quality,
+            field("Root Folder", &[1], FieldType::Dropdown),
+            field("Seasons", &[1, 2, 3], FieldType::MultiSelect),
+        ];
+        let fields = ["Quality", "Seasons"].map(String::from).into();
+        let extras = FormExtras {
+            subscribers: Some(vec![Id::new(2)]),
+            categories: vec![Category {
+                name: "Family Movie Night".into(),
+                tag: None,
+                media: None,
+            }],
+            category: Some(0),
+        };
+        let render = |extras: &FormExtras, submitting| {
+            build_request_component(
+                Uuid::nil(),
+                &golden_display_info(),
+                OverviewMode::Show,
+                &details,
+                &fields,
+                extras,
+                submitting,
+            )
+        };
+        assert_golden("request_form", &render(&FormExtras::default(), false));
+        assert_golden("request_form_extras", &render(&extras, false));
+        assert_golden("request_form_submitting", &render(&extras, true));
+    }
+
+    #[test]
+    fn completion_matches_golden() {
+        let message = SuccessMessage {
+            summary: "Dune: Part Two (2024)".into(),
+            description: "Requested! It'll show up once it's downloaded.".into(),
+            thumbnail_url: Some("https://example.com/poster.jpg".into()),
+            details: Some("Quality: 2 · Root Folder: 1".into()),
+        };
+        assert_golden(
+            "completion",
+            &build_completion_component(&message, None, &[]),
+        );
+        let status = DownloadStatus::Grabbed {
+            eta: Some(Duration::from_secs(90 * 60)),
+        };
+        let plain = SuccessMessage {
+            thumbnail_url: None,
+            details: None,
+            ..message
+        };
+        assert_golden(
+            "completion_downloading",
+            &build_completion_component(&plain, Some(&status), &[]),
+        );
+    }
 }
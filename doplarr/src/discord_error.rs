@@ -0,0 +1,146 @@
+//! Telling apart the ways a Discord API call fails
+//!
+//! Nearly every failure talking to Discord that isn't a bug comes down to a
+//! few causes: the bot can't see or post in a channel, the interaction's token
+//! expired, or a rate limit outlasted twilight's limiter. [DiscordFailure]
+//! names them, so logs can tell an admin what to fix and users get a message
+//! that fits, instead of a raw HTTP error.
+use twilight_http::{api_error::ApiError, error::ErrorType};
+use twilight_model::id::{Id, marker::ChannelMarker};
+
+// Discord's JSON error codes for the failures we can give guidance on
+const UNKNOWN_CHANNEL: u64 = 10003;
+const UNKNOWN_WEBHOOK: u64 = 10015;
+const UNKNOWN_INTERACTION: u64 = 10062;
+const MISSING_ACCESS: u64 = 50001;
+const MISSING_PERMISSIONS: u64 = 50013;
+const INVALID_WEBHOOK_TOKEN: u64 = 50027;
+
+/// A Discord API failure with a known cause
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiscordFailure {
+    /// The interaction's token is no longer valid (Discord allows 15 minutes),
+    /// so nothing more can be said through it
+    TokenExpired,
+    /// The bot can't see the channel, or lacks a permission in it
+    MissingPermissions,
+    /// The channel was deleted, or the ID is wrong
+    UnknownChannel,
+    /// Rate limited beyond what twilight's limiter absorbed
+    RateLimited { retry_after: f64 },
+}
+
+impl DiscordFailure {
+    /// Classify a twilight HTTP error, if its cause is one we know
+    pub fn from_http(error: &twilight_http::Error) -> Option<Self> {
+        match error.kind() {
+            ErrorType::Response { error, .. } => Self::from_api(error),
+            _ => None,
+        }
+    }
+
+    /// Classify the first twilight HTTP error in an error's chain
+    pub fn find(error: &anyhow::Error) -> Option<Self> {
+        error
+            .chain()
+            .find_map(|e| e.downcast_ref::<twilight_http::Error>())
+            .and_then(Self::from_http)
+    }
+
+    fn from_api(error: &ApiError) -> Option<Self> {
+        match error {
+            ApiError::Ratelimited(ratelimit) => Some(Self::RateLimited {
+                retry_after: ratelimit.retry_after,
+            }),
+            ApiError::General(e) => match e.code {
+                UNKNOWN_INTERACTION | UNKNOWN_WEBHOOK | INVALID_WEBHOOK_TOKEN => {
+                    Some(Self::TokenExpired)
+                }
+                MISSING_ACCESS | MISSING_PERMISSIONS => Some(Self::MissingPermissions),
+                UNKNOWN_CHANNEL => Some(Self::UnknownChannel),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// What an admin can do about it, for the logs
+    pub fn guidance(&self, channel_id: Option<Id<ChannelMarker>>) -> String {
+        let channel =
+            channel_id.map_or_else(|| "the channel".to_string(), |id| format!("channel {id}"));
+        match self {
+            Self::TokenExpired => "The interaction expired before the bot finished responding; \
+                                   the user has to run the command again"
+                .to_string(),
+            Self::MissingPermissions => format!(
+                "The bot lacks the \"View Channel\" or \"Send Messages\" permission in {channel}"
+            ),
+            Self::UnknownChannel => {
+                format!("{channel} doesn't exist (anymore); check the configured channel IDs")
+            }
+            Self::RateLimited { retry_after } => {
+                format!("Discord is rate limiting the bot, retry after {retry_after:.1} s")
+            }
+        }
+    }
+
+    /// What to tell the user whose interaction failed. None when the
+    /// interaction can't be written to anymore.
+    pub fn user_message(&self) -> Option<String> {
+        match self {
+            Self::TokenExpired => None,
+            Self::MissingPermissions | Self::UnknownChannel => Some(
+                "The bot is missing permissions it needs here. Please contact your administrator."
+                    .to_string(),
+            ),
+            Self::RateLimited { retry_after } => Some(format!(
+                "Discord is rate limiting the bot. Please try again <t:{}:R>.",
+                crate::store::now() + retry_after.max(0.0).ceil() as u64
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(body: &str) -> Option<DiscordFailure> {
+        DiscordFailure::from_api(&serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn api_errors_are_classified_by_code() {
+        assert_eq!(
+            classify(r#"{"code": 50013, "message": "Missing Permissions"}"#),
+            Some(DiscordFailure::MissingPermissions)
+        );
+        assert_eq!(
+            classify(r#"{"code": 10062, "message": "Unknown interaction"}"#),
+            Some(DiscordFailure::TokenExpired)
+        );
+        assert_eq!(
+            classify(
+                r#"{"global": false, "message": "You are being rate limited.", "retry_after": 1.5}"#
+            ),
+            Some(DiscordFailure::RateLimited { retry_after: 1.5 })
+        );
+        assert_eq!(
+            classify(r#"{"code": 50035, "message": "Invalid Form Body"}"#),
+            None
+        );
+    }
+
+    #[test]
+    fn rate_limited_users_are_told_when_to_try_again() {
+        let at = crate::store::now() + 2;
+        let message = DiscordFailure::RateLimited { retry_after: 1.5 }
+            .user_message()
+            .unwrap();
+        assert!(
+            message.ends_with(&format!("try again <t:{at}:R>."))
+                || message.ends_with(&format!("try again <t:{}:R>.", at + 1)),
+            "{message}"
+        );
+    }
+}
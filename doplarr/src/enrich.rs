@@ -0,0 +1,544 @@
+//! Extra detail on search results, whichever backend found them
+//!
+//! Before search results are shown, they go through an ordered list of
+//! [Enricher]s: duplicates are dropped first, then each configured enricher
+//! adds what it knows to the results' descriptions (parental rating, TMDB
+//! rating, whether it's already on Plex). An enricher that fails is logged and
+//! skipped, so the results are still shown, just without its detail.
+use crate::{
+    config::{Enrichment, MediaKind, Plex},
+    providers::{DropdownOption, MediaId, MediaItem, cache::Refreshing},
+};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, de::DeserializeOwned};
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use tokio::{task::JoinSet, time::timeout};
+use tracing::{debug, warn};
+
+const TMDB_API: &str = "https://api.themoviedb.org/3";
+
+/// How often to re-read the Plex libraries (default)
+const DEFAULT_PLEX_REFRESH: Duration = Duration::from_secs(30 * 60);
+
+/// How long each enricher gets before the results are shown without its detail
+const ENRICH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A search result on its way to the dropdown
+pub struct Candidate {
+    pub item: Box<dyn MediaItem>,
+    pub option: DropdownOption,
+    /// From [crate::providers::MediaBackend::media_id]
+    pub id: Option<MediaId>,
+}
+
+impl Candidate {
+    /// Add a detail to the end of the option's description
+    pub fn annotate(&mut self, detail: &str) {
+        match &mut self.option.description {
+            Some(description) if !description.is_empty() => {
+                description.push_str(" · ");
+                description.push_str(detail);
+            }
+            _ => self.option.description = Some(detail.to_string()),
+        }
+    }
+
+    /// Whether both are the same title, as far as their IDs tell
+    fn same_title(&self, other: &Self) -> bool {
+        self.item.kind() == other.item.kind()
+            && matches!((&self.id, &other.id), (Some(a), Some(b)) if a.matches(b))
+    }
+}
+
+/// One step of the pipeline, free to change, annotate, or drop candidates
+#[async_trait]
+pub trait Enricher: Send + Sync {
+    /// Identifies it in logs
+    fn name(&self) -> &'static str;
+
+    async fn enrich(&self, candidates: &mut Vec<Candidate>) -> Result<()>;
+}
+
+/// Drops results that name a title already further up the list
+struct Dedup;
+
+#[async_trait]
+impl Enricher for Dedup {
+    fn name(&self) -> &'static str {
+        "dedup"
+    }
+
+    async fn enrich(&self, candidates: &mut Vec<Candidate>) -> Result<()> {
+        let mut kept: Vec<Candidate> = Vec::with_capacity(candidates.len());
+        for candidate in candidates.drain(..) {
+            if !kept.iter().any(|k| k.same_title(&candidate)) {
+                kept.push(candidate);
+            }
+        }
+        *candidates = kept;
+        Ok(())
+    }
+}
+
+/// Adds the certification the backend reports, e.g. "PG-13"
+struct ParentalRatings;
+
+#[async_trait]
+impl Enricher for ParentalRatings {
+    fn name(&self) -> &'static str {
+        "parental rating"
+    }
+
+    async fn enrich(&self, candidates: &mut Vec<Candidate>) -> Result<()> {
+        for candidate in candidates.iter_mut() {
+            if let Some(rating) = candidate.item.certification() {
+                candidate.annotate(&rating);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Adds the TMDB user rating, e.g. "★ 7.8"
+struct TmdbRatings {
+    api_key: String,
+    client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct TmdbTitle {
+    vote_average: Option<f64>,
+    #[serde(default)]
+    vote_count: u64,
+}
+
+#[derive(Deserialize)]
+struct TmdbFound {
+    #[serde(default)]
+    movie_results: Vec<TmdbTitle>,
+    #[serde(default)]
+    tv_results: Vec<TmdbTitle>,
+}
+
+impl TmdbRatings {
+    /// Where to look a title up: by TMDB ID when we know which list it's in,
+    /// otherwise through IDs TMDB can find it by
+    fn url(&self, id: &MediaId, kind: Option<MediaKind>) -> Option<String> {
+        let key = &self.api_key;
+        match (kind, id.tmdb, &id.imdb, id.tvdb) {
+            (Some(MediaKind::Movie), Some(tmdb), _, _) => {
+                Some(format!("{TMDB_API}/movie/{tmdb}?api_key={key}"))
+            }
+            (Some(MediaKind::Tv), Some(tmdb), _, _) => {
+                Some(format!("{TMDB_API}/tv/{tmdb}?api_key={key}"))
+            }
+            (_, _, Some(imdb), _) => Some(format!(
+                "{TMDB_API}/find/{imdb}?external_source=imdb_id&api_key={key}"
+            )),
+            (_, _, _, Some(tvdb)) => Some(format!(
+                "{TMDB_API}/find/{tvdb}?external_source=tvdb_id&api_key={key}"
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// The title's rating from a TMDB title or find response, when it has votes
+fn tmdb_rating(body: &[u8], kind: Option<MediaKind>) -> Option<f64> {
+    let title = match serde_json::from_slice::<TmdbFound>(body) {
+        Ok(found) if !found.movie_results.is_empty() || !found.tv_results.is_empty() => {
+            let (movies, shows) = (found.movie_results, found.tv_results);
+            match kind {
+                Some(MediaKind::Movie) => movies.into_iter().next(),
+                Some(MediaKind::Tv) => shows.into_iter().next(),
+                None => movies.into_iter().chain(shows).next(),
+            }?
+        }
+        _ => serde_json::from_slice::<TmdbTitle>(body).ok()?,
+    };
+    (title.vote_count > 0).then_some(title.vote_average?)
+}
+
+async fn fetch_json<T: DeserializeOwned>(request: reqwest::RequestBuilder) -> Result<T> {
+    let bytes = request.send().await?.error_for_status()?.bytes().await?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+#[async_trait]
+impl Enricher for TmdbRatings {
+    fn name(&self) -> &'static str {
+        "TMDB rating"
+    }
+
+    async fn enrich(&self, candidates: &mut Vec<Candidate>) -> Result<()> {
+        let mut lookups = JoinSet::new();
+        for (i, candidate) in candidates.iter().enumerate() {
+            let kind = candidate.item.kind();
+            let Some(url) = candidate.id.as_ref().and_then(|id| self.url(id, kind)) else {
+                continue;
+            };
+            let request = self.client.get(url);
+            lookups.spawn(async move {
+                let response = request.send().await?;
+                // Not every title is on TMDB
+                if response.status() == reqwest::StatusCode::NOT_FOUND {
+                    return anyhow::Ok((i, None));
+                }
+                let body = response.error_for_status()?.bytes().await?;
+                Ok((i, tmdb_rating(&body, kind)))
+            });
+        }
+        let mut failed = None;
+        while let Some(lookup) = lookups.join_next().await {
+            match lookup? {
+                Ok((i, Some(rating))) => candidates[i].annotate(&format!("★ {rating:.1}")),
+                Ok((_, None)) => {}
+                Err(e) => failed = Some(e),
+            }
+        }
+        // e.g. a bad API key, which every lookup runs into
+        match failed {
+            Some(e) => Err(e.context("Some TMDB lookups failed")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Which titles are in the Plex libraries, by their external IDs
+#[derive(Debug, Default, PartialEq, Eq)]
+struct PlexIndex {
+    /// e.g. "imdb://tt0111161", "tmdb://278"
+    movies: HashSet<String>,
+    shows: HashSet<String>,
+}
+
+impl PlexIndex {
+    fn contains(&self, id: &MediaId, kind: Option<MediaKind>) -> bool {
+        let guids = [
+            id.imdb.as_ref().map(|id| format!("imdb://{id}")),
+            id.tmdb.map(|id| format!("tmdb://{id}")),
+            id.tvdb.map(|id| format!("tvdb://{id}")),
+        ];
+        let libraries = match kind {
+            Some(MediaKind::Movie) => vec![&self.movies],
+            Some(MediaKind::Tv) => vec![&self.shows],
+            None => vec![&self.movies, &self.shows],
+        };
+        guids
+            .iter()
+            .flatten()
+            .any(|guid| libraries.iter().any(|library| library.contains(guid)))
+    }
+}
+
+#[derive(Deserialize)]
+struct PlexResponse<T> {
+    #[serde(rename = "MediaContainer")]
+    container: T,
+}
+
+#[derive(Deserialize)]
+struct PlexSections {
+    #[serde(rename = "Directory", default)]
+    sections: Vec<PlexSection>,
+}
+
+#[derive(Deserialize)]
+struct PlexSection {
+    key: String,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Deserialize)]
+struct PlexItems {
+    #[serde(rename = "Metadata", default)]
+    items: Vec<PlexItem>,
+}
+
+#[derive(Deserialize)]
+struct PlexItem {
+    #[serde(rename = "Guid", default)]
+    guids: Vec<PlexGuid>,
+}
+
+#[derive(Deserialize)]
+struct PlexGuid {
+    id: String,
+}
+
+/// Reads the Plex libraries for [PlexLibrary]
+struct PlexReader {
+    config: Plex,
+    client: reqwest::Client,
+}
+
+impl PlexReader {
+    fn get(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .get(format!("{}{path}", self.config.url.trim_end_matches('/')))
+            .header("X-Plex-Token", &self.config.token)
+            .header(reqwest::header::ACCEPT, "application/json")
+    }
+
+    /// Read every movie and show library's external IDs
+    async fn read_libraries(&self) -> Result<PlexIndex> {
+        let sections: PlexResponse<PlexSections> = fetch_json(self.get("/library/sections"))
+            .await
+            .context("Failed to list Plex libraries")?;
+        let mut index = PlexIndex::default();
+        for section in sections.container.sections {
+            let library = match section.kind.as_str() {
+                "movie" => &mut index.movies,
+                "show" => &mut index.shows,
+                _ => continue,
+            };
+            let items: PlexResponse<PlexItems> = fetch_json(self.get(&format!(
+                "/library/sections/{}/all?includeGuids=1",
+                section.key
+            )))
+            .await
+            .with_context(|| format!("Failed to read Plex library {}", section.key))?;
+            library.extend(
+                items
+                    .container
+                    .items
+                    .into_iter()
+                    .flat_map(|item| item.guids)
+                    .map(|guid| guid.id),
+            );
+        }
+        debug!(
+            movies = index.movies.len(),
+            shows = index.shows.len(),
+            "Read the Plex libraries"
+        );
+        Ok(index)
+    }
+}
+
+/// Marks results that are already in a Plex library. The libraries are read
+/// in the background, since reading them all can take a while; results are
+/// marked from the last read, and not at all until the first one is in.
+struct PlexLibrary {
+    index: Refreshing<Option<PlexIndex>>,
+}
+
+impl PlexLibrary {
+    fn new(config: Plex, client: reqwest::Client) -> Self {
+        let refresh = config
+            .refresh_minutes
+            .map_or(DEFAULT_PLEX_REFRESH, |m| Duration::from_secs(m.max(1) * 60));
+        let reader = Arc::new(PlexReader { config, client });
+        let index = Refreshing::lazy("Plex libraries", refresh, None, move || {
+            let reader = Arc::clone(&reader);
+            async move { reader.read_libraries().await.map(Some) }
+        });
+        // Start the first read now, so it's in by the first search
+        index.get();
+        Self { index }
+    }
+}
+
+#[async_trait]
+impl Enricher for PlexLibrary {
+    fn name(&self) -> &'static str {
+        "Plex availability"
+    }
+
+    async fn enrich(&self, candidates: &mut Vec<Candidate>) -> Result<()> {
+        let index = self.index.get();
+        let Some(index) = index.as_ref() else {
+            debug!("The Plex libraries haven't been read yet");
+            return Ok(());
+        };
+        for candidate in candidates.iter_mut() {
+            if candidate
+                .id
+                .as_ref()
+                .is_some_and(|id| index.contains(id, candidate.item.kind()))
+            {
+                candidate.annotate("On Plex");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The enrichers to run, in order
+pub struct Enrichers(Vec<Box<dyn Enricher>>);
+
+impl Default for Enrichers {
+    fn default() -> Self {
+        Self(vec![Box::new(Dedup)])
+    }
+}
+
+impl Enrichers {
+    pub fn new(config: Option<&Enrichment>, client: reqwest::Client) -> Self {
+        let mut enrichers = Self::default();
+        let Some(config) = config else {
+            return enrichers;
+        };
+        if config.parental_ratings.unwrap_or(false) {
+            enrichers.0.push(Box::new(ParentalRatings));
+        }
+        if let Some(api_key) = &config.tmdb_api_key {
+            enrichers.0.push(Box::new(TmdbRatings {
+                api_key: api_key.clone(),
+                client: client.clone(),
+            }));
+        }
+        if let Some(plex) = &config.plex {
+            enrichers
+                .0
+                .push(Box::new(PlexLibrary::new(plex.clone(), client)));
+        }
+        enrichers
+    }
+
+    /// Run every enricher over the results, in order, each for at most
+    /// [ENRICH_TIMEOUT]
+    pub async fn run(&self, candidates: &mut Vec<Candidate>) {
+        for enricher in &self.0 {
+            match timeout(ENRICH_TIMEOUT, enricher.enrich(candidates)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    warn!(error = ?e, enricher = enricher.name(), "Search result enricher failed");
+                }
+                Err(_) => warn!(
+                    enricher = enricher.name(),
+                    "Search result enricher took too long, showing the results without it"
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::any::Any;
+
+    #[derive(Debug)]
+    struct Title(MediaKind, Option<&'static str>);
+
+    impl MediaItem for Title {
+        fn to_dropdown(&self) -> DropdownOption {
+            DropdownOption::default()
+        }
+
+        fn kind(&self) -> Option<MediaKind> {
+            Some(self.0)
+        }
+
+        fn certification(&self) -> Option<String> {
+            self.1.map(str::to_string)
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn into_any(self: Box<Self>) -> Box<dyn Any> {
+            self
+        }
+    }
+
+    fn candidate(kind: MediaKind, tmdb: i32, rating: Option<&'static str>) -> Candidate {
+        Candidate {
+            item: Box::new(Title(kind, rating)),
+            option: DropdownOption {
+                title: format!("{tmdb}"),
+                description: Some("2021".into()),
+                ..Default::default()
+            },
+            id: Some(MediaId {
+                tmdb: Some(tmdb),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn enrichers_run_in_order_over_deduplicated_results() {
+        let enrichers = Enrichers::new(
+            Some(&Enrichment {
+                parental_ratings: Some(true),
+                ..Default::default()
+            }),
+            reqwest::Client::new(),
+        );
+        let mut candidates = vec![
+            candidate(MediaKind::Movie, 438631, Some("PG-13")),
+            candidate(MediaKind::Movie, 438631, Some("PG-13")),
+            // TMDB numbers series apart from movies
+            candidate(MediaKind::Tv, 438631, None),
+        ];
+        enrichers.run(&mut candidates).await;
+        let descriptions: Vec<_> = candidates
+            .iter()
+            .map(|c| c.option.description.as_deref().unwrap())
+            .collect();
+        assert_eq!(descriptions, ["2021 · PG-13", "2021"]);
+    }
+
+    struct Stuck;
+
+    #[async_trait]
+    impl Enricher for Stuck {
+        fn name(&self) -> &'static str {
+            "stuck"
+        }
+
+        async fn enrich(&self, _: &mut Vec<Candidate>) -> Result<()> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_enrichers_are_cut_off() {
+        let enrichers = Enrichers(vec![Box::new(Stuck), Box::new(ParentalRatings)]);
+        let mut candidates = vec![candidate(MediaKind::Movie, 438631, Some("PG-13"))];
+        let started = tokio::time::Instant::now();
+        enrichers.run(&mut candidates).await;
+        assert_eq!(started.elapsed(), ENRICH_TIMEOUT);
+        assert_eq!(
+            candidates[0].option.description.as_deref(),
+            Some("2021 · PG-13")
+        );
+    }
+
+    #[test]
+    fn tmdb_ratings_come_from_titles_or_finds() {
+        let title = br#"{"id": 438631, "vote_average": 7.78, "vote_count": 12000}"#;
+        assert_eq!(tmdb_rating(title, Some(MediaKind::Movie)), Some(7.78));
+        let found =
+            br#"{"movie_results": [], "tv_results": [{"vote_average": 9.5, "vote_count": 3}]}"#;
+        assert_eq!(tmdb_rating(found, Some(MediaKind::Tv)), Some(9.5));
+        assert_eq!(tmdb_rating(found, Some(MediaKind::Movie)), None);
+        let unrated = br#"{"vote_average": 0.0, "vote_count": 0}"#;
+        assert_eq!(tmdb_rating(unrated, None), None);
+    }
+
+    #[test]
+    fn plex_index_matches_by_any_id_in_the_right_library() {
+        let index = PlexIndex {
+            movies: HashSet::from(["tmdb://438631".to_string()]),
+            shows: HashSet::from(["tvdb://81189".to_string()]),
+        };
+        let dune = MediaId {
+            tmdb: Some(438631),
+            imdb: Some("tt1160419".into()),
+            ..Default::default()
+        };
+        assert!(index.contains(&dune, Some(MediaKind::Movie)));
+        assert!(!index.contains(&dune, Some(MediaKind::Tv)));
+        let breaking_bad = MediaId {
+            tvdb: Some(81189),
+            ..Default::default()
+        };
+        assert!(index.contains(&breaking_bad, None));
+    }
+}
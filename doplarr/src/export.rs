@@ -0,0 +1,211 @@
+//! Request log exports, for reporting outside Discord or moving to another
+//! request tool (e.g. Overseerr)
+use crate::store::RequestRecord;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use time::{Date, Month, OffsetDateTime};
+
+/// Requesters' latest display names, by user ID
+pub type Names = BTreeMap<u64, String>;
+
+/// A record as exported, with the requester's current name next to their ID
+#[derive(Serialize)]
+struct Row<'a> {
+    #[serde(flatten)]
+    record: &'a RequestRecord,
+    user_name: Option<&'a str>,
+}
+
+/// File formats `/export-requests` can produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// A YYYY-MM-DD date
+pub fn parse_calendar_date(date: &str) -> Option<Date> {
+    let mut parts = date.splitn(3, '-');
+    let (y, m, d) = (
+        parts.next()?.parse::<i32>().ok()?,
+        parts.next()?.parse::<u8>().ok()?,
+        parts.next()?.parse::<u8>().ok()?,
+    );
+    Date::from_calendar_date(y, Month::try_from(m).ok()?, d).ok()
+}
+
+/// Unix timestamp of midnight UTC at the start of a YYYY-MM-DD date
+pub fn parse_date(date: &str) -> Option<u64> {
+    let date = parse_calendar_date(date)?;
+    u64::try_from(date.midnight().assume_utc().unix_timestamp()).ok()
+}
+
+/// Unix timestamp of an ISO 8601 UTC time, e.g. "2024-03-04T18:30:00.000Z"
+pub fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let date = parse_date(timestamp.get(..10)?)?;
+    let mut hms = timestamp.get(11..19)?.splitn(3, ':').map(str::parse::<u64>);
+    let (h, m, s) = (hms.next()?.ok()?, hms.next()?.ok()?, hms.next()?.ok()?);
+    Some(date + (h * 60 + m) * 60 + s)
+}
+
+/// e.g. "2024-03-04T18:30:00Z"
+fn iso_datetime(timestamp: u64) -> String {
+    let Some(t) = i64::try_from(timestamp)
+        .ok()
+        .and_then(|t| OffsetDateTime::from_unix_timestamp(t).ok())
+    else {
+        return timestamp.to_string();
+    };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        t.year(),
+        u8::from(t.month()),
+        t.day(),
+        t.hour(),
+        t.minute(),
+        t.second()
+    )
+}
+
+/// Quote a CSV field when it needs it
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn to_csv(records: &[RequestRecord], names: &Names) -> String {
+    let mut out = String::from(
+        "requested_at,user_id,user_name,guild_id,media,title,tmdb_id,tvdb_id,imdb_id,status,category\n",
+    );
+    for r in records {
+        let row = [
+            iso_datetime(r.requested_at),
+            r.user_id.to_string(),
+            names
+                .get(&r.user_id)
+                .map(|n| csv_field(n))
+                .unwrap_or_default(),
+            r.guild_id.map(|id| id.to_string()).unwrap_or_default(),
+            csv_field(&r.media),
+            csv_field(&r.title),
+            r.ids.tmdb.map(|id| id.to_string()).unwrap_or_default(),
+            r.ids.tvdb.map(|id| id.to_string()).unwrap_or_default(),
+            r.ids.imdb.as_deref().map(csv_field).unwrap_or_default(),
+            r.status.label().to_string(),
+            r.category.as_deref().map(csv_field).unwrap_or_default(),
+        ];
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// The export file's contents
+pub fn render(records: &[RequestRecord], names: &Names, format: Format) -> anyhow::Result<Vec<u8>> {
+    Ok(match format {
+        Format::Csv => to_csv(records, names).into_bytes(),
+        Format::Json => {
+            let rows: Vec<_> = records
+                .iter()
+                .map(|record| Row {
+                    record,
+                    user_name: names.get(&record.user_id).map(String::as_str),
+                })
+                .collect();
+            serde_json::to_vec_pretty(&rows)?
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{providers::MediaId, store::RequestStatus};
+
+    #[test]
+    fn dates_parse_to_midnight_utc() {
+        assert_eq!(parse_date("1970-01-02"), Some(86_400));
+        assert_eq!(parse_date("2024-03-04"), Some(1_709_510_400));
+        assert_eq!(parse_date("2024-13-01"), None);
+        assert_eq!(parse_date("yesterday"), None);
+    }
+
+    #[test]
+    fn seerr_timestamps_parse() {
+        assert_eq!(
+            parse_timestamp("2024-03-04T18:30:00.000Z"),
+            Some(1_709_577_000)
+        );
+        assert_eq!(parse_timestamp("2024-03-04"), None);
+    }
+
+    #[test]
+    fn csv_quotes_titles_that_need_it() {
+        let records = [
+            RequestRecord {
+                requested_at: 1_709_577_000,
+                user_id: 10,
+                guild_id: Some(1),
+                media: "movie".into(),
+                title: "Crouching Tiger, Hidden Dragon (2000)".into(),
+                ids: MediaId {
+                    tmdb: Some(146),
+                    imdb: Some("tt0190332".into()),
+                    ..Default::default()
+                },
+                legacy_media_id: None,
+                status: RequestStatus::Available,
+                channel_id: None,
+                subscribers: Vec::new(),
+                category: Some("Family Movie Night".into()),
+                need_by: None,
+                escalated: false,
+                searches: 0,
+                searched_at: None,
+            },
+            RequestRecord {
+                requested_at: 0,
+                user_id: 20,
+                guild_id: None,
+                media: "series".into(),
+                title: "The \"Office\"".into(),
+                ids: MediaId::default(),
+                legacy_media_id: None,
+                status: RequestStatus::Requested,
+                channel_id: None,
+                subscribers: Vec::new(),
+                category: None,
+                need_by: None,
+                escalated: false,
+                searches: 0,
+                searched_at: None,
+            },
+        ];
+        assert_eq!(
+            to_csv(&records, &Names::from([(10, "Ana".to_string())])),
+            "requested_at,user_id,user_name,guild_id,media,title,tmdb_id,tvdb_id,imdb_id,status,category\n\
+             2024-03-04T18:30:00Z,10,Ana,1,movie,\"Crouching Tiger, Hidden Dragon (2000)\",146,,tt0190332,available,Family Movie Night\n\
+             1970-01-01T00:00:00Z,20,,,series,\"The \"\"Office\"\"\",,,,requested,\n"
+        );
+    }
+}
@@ -0,0 +1,834 @@
+//! Admin commands: `/backend` to list, inspect, disable, and re-enable backends
+//! at runtime, trigger their maintenance tasks, and rotate their API keys,
+//! `/ping-backends` to check
+//! they're reachable, `/search-missing` after an indexer outage,
+//! `/export-requests` to download the request log, `/request-audit` to debug
+//! submitted requests, `/pending` to decide on requests waiting for approval,
+//! `/ban-requests`/`/unban-requests` to block users, and `/forget-user` to
+//! delete what's kept about someone. `/forget-me`, the same for anyone about
+//! themselves, is here to share its confirm button.
+use super::{BotState, Handler};
+use crate::{
+    custom_id, discord, export,
+    providers::{Maintenance, registry::RegisteredBackend},
+    retention,
+    store::{AuditRecord, Ban, StageTotals},
+    watchdog::FlowAges,
+};
+use async_trait::async_trait;
+use std::{collections::BTreeMap, sync::Arc, time::Instant};
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
+use twilight_model::{
+    application::interaction::{
+        Interaction,
+        application_command::{CommandData, CommandDataOption, CommandOptionValue},
+        message_component::MessageComponentInteractionData,
+    },
+    id::{
+        Id,
+        marker::{GuildMarker, UserMarker},
+    },
+};
+
+pub struct AdminHandler;
+
+/// The value of a subcommand's string option
+fn string_option<'a>(options: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
+    options.iter().find_map(|o| match &o.value {
+        CommandOptionValue::String(v) if o.name == name => Some(v.as_str()),
+        _ => None,
+    })
+}
+
+/// How many requests `/request-audit` lists without an ID
+const RECENT_AUDITS: usize = 10;
+
+/// Backend errors can be long; keep the record within one message
+const MAX_AUDIT_ERROR: usize = 1500;
+
+fn outcome(record: &AuditRecord) -> &'static str {
+    if record.denied.is_some() {
+        "denied"
+    } else if record.error.is_some() {
+        "**failed**"
+    } else {
+        "accepted"
+    }
+}
+
+/// One request's full audit record
+fn render_audit(record: &AuditRecord) -> String {
+    let mut lines = vec![
+        format!("### Request `{}`", record.id),
+        format!(
+            "<t:{}:f> · <@{}> · `/request {}` \"{}\" on `{}`",
+            record.at,
+            record.user_id,
+            record.media,
+            discord::escape_markdown(&record.query),
+            record.backend
+        ),
+        format!(
+            "**{}** · {}",
+            discord::escape_markdown(&record.title),
+            outcome(record)
+        ),
+    ];
+    for selection in &record.selections {
+        let values = if selection.values.is_empty() {
+            "(none)".to_string()
+        } else {
+            selection.values.join(", ")
+        };
+        lines.push(format!("- {}: {values}", selection.field));
+    }
+    if !record.stages.is_empty() {
+        let stages: Vec<_> = record
+            .stages
+            .iter()
+            .map(|s| format!("{} {} ms", s.stage, s.millis))
+            .collect();
+        lines.push(format!("-# {}", stages.join(" · ")));
+    }
+    if let Some(denial) = &record.denied {
+        lines.push(format!(
+            "Denied by <@{}>: {}",
+            denial.admin_id,
+            discord::escape_markdown(&denial.reason)
+        ));
+    }
+    if let Some(error) = &record.error {
+        let error: String = error.chars().take(MAX_AUDIT_ERROR).collect();
+        lines.push(format!("```\n{}\n```", error.replace("```", "'''")));
+    }
+    lines.join("\n")
+}
+
+/// Average time per request flow stage, and where requesters give up
+fn render_report(stages: &BTreeMap<String, StageTotals>) -> String {
+    if stages.is_empty() {
+        return "No request flows yet.".to_string();
+    }
+    let mut lines = vec!["### Time per request step".to_string()];
+    for (stage, label) in discord::FLOW_STAGES {
+        let Some(totals) = stages.get(stage) else {
+            continue;
+        };
+        let mut line = match totals.average() {
+            Some(average) => format!(
+                "- {label}: {:.1} s on average, over {} request(s)",
+                average.as_secs_f64(),
+                totals.completed
+            ),
+            None => format!("- {label}"),
+        };
+        if totals.abandoned > 0 {
+            line.push_str(&format!(" · **{} gave up here**", totals.abandoned));
+        }
+        lines.push(line);
+    }
+    lines.push(
+        "-# Slow picking? Requesters can skip it with `quick`. Slow details? \
+         Set them in the backend's config or a profile, and they aren't asked."
+            .to_string(),
+    );
+    lines.join("\n")
+}
+
+/// Search term for the lookup half of a ping; any term exercises the
+/// backend's connection to its metadata provider
+const PING_LOOKUP_TERM: &str = "test";
+
+/// Round-trip to one backend: its status endpoint, then a lookup. Returns
+/// the report line for it.
+async fn ping_backend(backend: RegisteredBackend) -> String {
+    let start = Instant::now();
+    let version = match backend.backend.version().await {
+        Ok(version) => version,
+        Err(e) => return format!("- `{}` **unreachable**: {e}", backend.name),
+    };
+    let status_ms = start.elapsed().as_millis();
+
+    let start = Instant::now();
+    let lookup = backend.backend.search(PING_LOOKUP_TERM).await;
+    let lookup_ms = start.elapsed().as_millis();
+
+    let version = version.map(|v| format!(" v{v}")).unwrap_or_default();
+    match lookup {
+        Ok(_) => format!(
+            "- `{}`{version} ok · status {status_ms} ms, lookup {lookup_ms} ms",
+            backend.name
+        ),
+        Err(e) => format!(
+            "- `{}`{version} **lookup failed** after status {status_ms} ms: {e}",
+            backend.name
+        ),
+    }
+}
+
+impl AdminHandler {
+    /// Render every backend instance serving the guild, with its command and status
+    fn list(state: &BotState, guild_id: Option<Id<GuildMarker>>) -> String {
+        let mut lines = vec!["### Backends".to_string()];
+        for (media, backend) in state.backends.for_guild(guild_id.map(|id| id.get())) {
+            let status = if backend.is_enabled() {
+                "enabled"
+            } else {
+                "**disabled**"
+            };
+            lines.push(format!(
+                "- `{}` → /request {media} ({status})",
+                backend.name
+            ));
+        }
+        lines.join("\n")
+    }
+
+    /// Render the rolling call statistics of every backend instance serving the
+    /// guild, and the guild's request totals
+    async fn status(state: &BotState, guild_id: Option<Id<GuildMarker>>) -> String {
+        let mut lines = vec!["### Backend status".to_string()];
+        for (_, backend) in state.backends.for_guild(guild_id.map(|id| id.get())) {
+            lines.push(format!("**`{}`**", backend.name));
+            let summaries = backend.stats.summaries();
+            if summaries.is_empty() {
+                lines.push("- No calls yet".to_string());
+            }
+            for (op, summary) in summaries {
+                lines.push(format!("- {op}: {summary}"));
+            }
+            for (cache, stats) in backend.backend.cache_stats() {
+                lines.push(format!("- cached {cache}: {stats}"));
+            }
+        }
+        if let Some(guild_id) = guild_id {
+            let counts = state.store.request_counts(guild_id.get()).await;
+            lines.push("### Requests from this server".to_string());
+            lines.push(format!(
+                "{} submitted, {} failed",
+                counts.submitted, counts.failed
+            ));
+        }
+        lines.push("### Request flows (all servers)".to_string());
+        lines.push(FlowAges::of(&state.interactions).await.to_string());
+        lines.push(format!(
+            "-# State file schema v{}",
+            state.store.schema_version().await
+        ));
+        lines.join("\n")
+    }
+
+    /// Ping every backend instance serving the guild at once, reporting them
+    /// in the same order as `/backend list`
+    async fn ping(state: &BotState, guild_id: Option<Id<GuildMarker>>) -> String {
+        let mut pings = JoinSet::new();
+        for (i, (_, backend)) in state
+            .backends
+            .for_guild(guild_id.map(|id| id.get()))
+            .enumerate()
+        {
+            let backend = backend.clone();
+            pings.spawn(async move { (i, ping_backend(backend).await) });
+        }
+        let mut results = pings.join_all().await;
+        results.sort_unstable_by_key(|(i, _)| *i);
+
+        let mut lines = vec!["### Backend connectivity".to_string()];
+        lines.extend(results.into_iter().map(|(_, line)| line));
+        lines.join("\n")
+    }
+
+    async fn ping_command(state: &Arc<BotState>, interaction: &Interaction) -> anyhow::Result<()> {
+        info!(user_id = ?interaction.author_id(), "Admin pinged backends");
+        // Lookups can take longer than Discord waits for a first response
+        discord::send_thinking(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+        )
+        .await?;
+        let reply = Self::ping(state, interaction.guild_id).await;
+        discord::update_string_message(
+            &reply,
+            &state.discord_http,
+            state.application_id,
+            &interaction.token,
+        )
+        .await
+    }
+
+    /// Queue a maintenance task on a backend. Listing a large library to
+    /// find a title can be slow, so the reply is deferred.
+    async fn maintenance(
+        state: &BotState,
+        interaction: &Interaction,
+        name: &str,
+        task: Maintenance,
+        title: Option<&str>,
+    ) -> anyhow::Result<()> {
+        info!(
+            backend = name,
+            task = ?task,
+            title,
+            user_id = ?interaction.author_id(),
+            "Admin triggered backend maintenance"
+        );
+        discord::send_thinking(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+        )
+        .await?;
+        let guild_id = interaction.guild_id.map(|id| id.get());
+        let reply = match state.backends.named(name, guild_id) {
+            Ok(backend) => match backend.backend.maintenance(task, title).await {
+                Ok(queued) => format!("`{name}`: {queued}"),
+                Err(e) => {
+                    warn!(backend = name, error = ?e, "Backend maintenance failed");
+                    format!("`{name}`: {e}")
+                }
+            },
+            Err(e) => e.to_string(),
+        };
+        discord::update_string_message(
+            &reply,
+            &state.discord_http,
+            state.application_id,
+            &interaction.token,
+        )
+        .await
+    }
+
+    /// Reconnect a backend with a new API key. Connecting checks the key, so
+    /// the reply is deferred.
+    async fn rotate_key(
+        state: &BotState,
+        interaction: &Interaction,
+        name: &str,
+        api_key: &str,
+    ) -> anyhow::Result<()> {
+        // Never the key itself, wherever it ends up in the logs
+        state.secrets.add(api_key);
+        info!(
+            backend = name,
+            user_id = ?interaction.author_id(),
+            "Admin rotating backend API key"
+        );
+        discord::send_thinking(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+        )
+        .await?;
+        let guild_id = interaction.guild_id.map(|id| id.get());
+        let reply = match state.backends.rotate_key(name, guild_id, api_key).await {
+            Ok(()) => {
+                info!(backend = name, "Reconnected backend with its new API key");
+                format!(
+                    "`{name}` is now using the new API key. Put it in the config file \
+                     too, or the old one comes back when the bot restarts."
+                )
+            }
+            Err(e) => {
+                warn!(backend = name, error = ?e, "Backend API key rotation failed");
+                format!("`{name}`: {e:#}")
+            }
+        };
+        discord::update_string_message(
+            &reply,
+            &state.discord_http,
+            state.application_id,
+            &interaction.token,
+        )
+        .await
+    }
+
+    /// Have every backend serving the guild (or just the one behind `media`)
+    /// search for its missing titles. One instance can sit behind several
+    /// media commands, so each is only asked once.
+    async fn search_missing(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        data: &CommandData,
+    ) -> anyhow::Result<()> {
+        let media = string_option(&data.options, discord::MEDIA_OPTION);
+        info!(media, user_id = ?interaction.author_id(), "Admin triggered missing search");
+        discord::send_thinking(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+        )
+        .await?;
+
+        let mut lines = vec!["### Missing search".to_string()];
+        let mut asked = Vec::new();
+        for (kind, backend) in state
+            .backends
+            .for_guild(interaction.guild_id.map(|id| id.get()))
+        {
+            if media.is_some_and(|m| m != kind) || asked.contains(&backend.name) {
+                continue;
+            }
+            asked.push(backend.name.clone());
+            let outcome = match backend
+                .backend
+                .maintenance(Maintenance::SearchMissing, None)
+                .await
+            {
+                Ok(queued) => queued,
+                Err(e) => {
+                    warn!(backend = backend.name, error = ?e, "Missing search failed");
+                    e.to_string()
+                }
+            };
+            lines.push(format!("- `{}`: {outcome}", backend.name));
+        }
+        discord::update_string_message(
+            &lines.join("\n"),
+            &state.discord_http,
+            state.application_id,
+            &interaction.token,
+        )
+        .await
+    }
+
+    fn set_enabled(
+        state: &BotState,
+        guild_id: Option<Id<GuildMarker>>,
+        name: &str,
+        enabled: bool,
+    ) -> String {
+        let verb = if enabled { "enabled" } else { "disabled" };
+        match state
+            .backends
+            .set_enabled(name, guild_id.map(|id| id.get()), enabled)
+        {
+            Ok(true) => format!("Backend `{name}` {verb}."),
+            Ok(false) => format!("Backend `{name}` was already {verb}."),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    async fn backend_command(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        data: &CommandData,
+    ) -> anyhow::Result<()> {
+        let Some(subcommand) = data.options.first() else {
+            warn!(data = ?data, "Backend command had no subcommand");
+            return Ok(());
+        };
+        let options = match &subcommand.value {
+            CommandOptionValue::SubCommand(options) => options.as_slice(),
+            _ => &[],
+        };
+        let name = string_option(options, discord::BACKEND_NAME_OPTION);
+
+        let task = match subcommand.name.as_str() {
+            "refresh" => Some(Maintenance::Refresh),
+            "rss-sync" => Some(Maintenance::RssSync),
+            _ => None,
+        };
+        if let (Some(task), Some(name)) = (task, name) {
+            let title = string_option(options, discord::TITLE_OPTION);
+            return Self::maintenance(state, interaction, name, task, title).await;
+        }
+        if subcommand.name == "rotate-key"
+            && let Some(name) = name
+            && let Some(api_key) = string_option(options, discord::API_KEY_OPTION)
+        {
+            return Self::rotate_key(state, interaction, name, api_key).await;
+        }
+
+        let (reply, changed) = match (subcommand.name.as_str(), name) {
+            ("list", _) => (Self::list(state, interaction.guild_id), false),
+            ("status", _) => (Self::status(state, interaction.guild_id).await, false),
+            (action @ ("enable" | "disable"), Some(name)) => {
+                info!(
+                    backend = name,
+                    action,
+                    user_id = ?interaction.author_id(),
+                    "Admin toggled backend"
+                );
+                (
+                    Self::set_enabled(state, interaction.guild_id, name, action == "enable"),
+                    true,
+                )
+            }
+            _ => {
+                warn!(data = ?data, "Backend command body didn't match what we expected");
+                return Ok(());
+            }
+        };
+
+        discord::respond_ephemeral_message(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+            &reply,
+        )
+        .await?;
+
+        // Show or hide the backend's /request subcommand everywhere
+        if changed {
+            state.refresh_commands().await;
+        }
+        Ok(())
+    }
+
+    async fn export_command(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        data: &CommandData,
+    ) -> anyhow::Result<()> {
+        let format = string_option(&data.options, discord::FORMAT_OPTION)
+            .and_then(export::Format::parse)
+            .unwrap_or(export::Format::Csv);
+        let user = data.options.iter().find_map(|o| match o.value {
+            CommandOptionValue::User(id) if o.name == discord::USER_OPTION => Some(id.get()),
+            _ => None,
+        });
+        // `until` is inclusive, so it ends at the following midnight
+        let dates = (
+            string_option(&data.options, discord::SINCE_OPTION).map(|d| (d, export::parse_date(d))),
+            string_option(&data.options, discord::UNTIL_OPTION)
+                .map(|d| (d, export::parse_date(d).map(|t| t + 86_400))),
+        );
+        let (since, until) = match dates {
+            (Some((d, None)), _) | (_, Some((d, None))) => {
+                return discord::respond_ephemeral_message(
+                    &state.discord_http,
+                    state.application_id,
+                    interaction.id,
+                    &interaction.token,
+                    &format!("\"{d}\" isn't a date, use YYYY-MM-DD."),
+                )
+                .await;
+            }
+            (since, until) => (
+                since.and_then(|(_, t)| t).unwrap_or(0),
+                until.and_then(|(_, t)| t).unwrap_or(u64::MAX),
+            ),
+        };
+        discord::send_thinking(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+        )
+        .await?;
+
+        let guild_id = interaction.guild_id.map(|id| id.get());
+        let records = state
+            .store
+            .requests(|r| {
+                r.guild_id == guild_id
+                    && user.is_none_or(|u| r.user_id == u)
+                    && (since..until).contains(&r.requested_at)
+            })
+            .await;
+        info!(
+            count = records.len(),
+            format = format.extension(),
+            user_id = ?interaction.author_id(),
+            "Admin exported requests"
+        );
+        if records.is_empty() {
+            return discord::update_string_message(
+                "No requests match.",
+                &state.discord_http,
+                state.application_id,
+                &interaction.token,
+            )
+            .await;
+        }
+        let file = export::render(&records, &state.store.user_names().await, format)?;
+        discord::update_file_message(
+            &format!("{} request(s)", records.len()),
+            &format!("requests.{}", format.extension()),
+            file,
+            &state.discord_http,
+            state.application_id,
+            &interaction.token,
+        )
+        .await
+    }
+
+    async fn audit_command(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        data: &CommandData,
+    ) -> anyhow::Result<()> {
+        let guild_id = interaction.guild_id.map(|id| id.get());
+        let reply = match string_option(&data.options, discord::ID_OPTION) {
+            Some(id) => match state.store.audit(id.trim()).await {
+                // Admins only see their own server's requests
+                Some(record) if record.guild_id == guild_id => render_audit(&record),
+                _ => format!("No request with ID `{}` here.", id.trim()),
+            },
+            None => {
+                let records = state.store.recent_audits(guild_id, RECENT_AUDITS).await;
+                if records.is_empty() {
+                    "No requests submitted yet.".to_string()
+                } else {
+                    let mut lines = vec!["### Latest requests".to_string()];
+                    lines.extend(records.iter().map(|r| {
+                        format!(
+                            "- <t:{}:R> `{}` <@{}> {} · {}",
+                            r.at,
+                            r.id,
+                            r.user_id,
+                            discord::escape_markdown(&r.title),
+                            outcome(r)
+                        )
+                    }));
+                    lines.join("\n")
+                }
+            }
+        };
+        discord::respond_ephemeral_message(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+            &reply,
+        )
+        .await
+    }
+
+    async fn report_command(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+    ) -> anyhow::Result<()> {
+        let reply = match interaction.guild_id {
+            Some(guild_id) => render_report(&state.store.flow_stages(guild_id.get()).await),
+            None => "Reports are kept per server.".to_string(),
+        };
+        discord::respond_ephemeral_message(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+            &reply,
+        )
+        .await
+    }
+
+    async fn pending_command(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+    ) -> anyhow::Result<()> {
+        discord::respond_ephemeral_component(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+            discord::build_pending_component(&state.approvals.list(interaction.guild_id)),
+        )
+        .await
+    }
+
+    async fn ban_command(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        data: &CommandData,
+    ) -> anyhow::Result<()> {
+        let user = data.options.iter().find_map(|o| match o.value {
+            CommandOptionValue::User(id) if o.name == discord::USER_OPTION => Some(id),
+            _ => None,
+        });
+        let reason = data.options.iter().find_map(|o| match &o.value {
+            CommandOptionValue::String(v) if o.name == discord::REASON_OPTION => Some(v.clone()),
+            _ => None,
+        });
+        // Bans are per server, so one server's admins can't block anyone elsewhere
+        let (Some(user), Some(admin), Some(guild_id)) =
+            (user, interaction.author_id(), interaction.guild_id)
+        else {
+            warn!(data = ?data, "Ban command body didn't match what we expected");
+            return Ok(());
+        };
+
+        let reply = if data.name == discord::BAN_COMMAND_NAME {
+            Self::ban(state, guild_id, user, admin, reason).await
+        } else {
+            Self::unban(state, guild_id, user).await
+        };
+        discord::respond_ephemeral_message(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+            &reply,
+        )
+        .await
+    }
+
+    async fn ban(
+        state: &BotState,
+        guild_id: Id<GuildMarker>,
+        user: Id<UserMarker>,
+        admin: Id<UserMarker>,
+        reason: Option<String>,
+    ) -> String {
+        info!(user_id = %user, admin_id = %admin, guild_id = %guild_id, reason = ?reason, "Banning user from requests");
+        match state
+            .store
+            .add_ban(guild_id.get(), user.get(), Ban::new(reason, admin.get()))
+            .await
+        {
+            Ok(false) => format!("<@{user}> can no longer make requests."),
+            Ok(true) => format!("Updated the ban on <@{user}>."),
+            Err(e) => {
+                error!(error = ?e, "Failed to save ban");
+                "Couldn't save the ban, check the bot's logs.".to_string()
+            }
+        }
+    }
+
+    /// Ask whoever ran `/forget-me` or `/forget-user` to confirm, with a
+    /// button only they can use
+    async fn forget_command(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        data: &CommandData,
+    ) -> anyhow::Result<()> {
+        let user = data.options.iter().find_map(|o| match o.value {
+            CommandOptionValue::User(id) if o.name == discord::USER_OPTION => Some(id),
+            _ => None,
+        });
+        let Some(asked_by) = interaction.author_id() else {
+            return Ok(());
+        };
+        let user = if data.name == discord::FORGET_ME_COMMAND_NAME {
+            asked_by
+        } else if let Some(user) = user {
+            user
+        } else {
+            warn!(data = ?data, "Forget command body didn't match what we expected");
+            return Ok(());
+        };
+        let id = state.forgets.ask(user, asked_by);
+        discord::respond_ephemeral_component(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+            discord::build_forget_component(id, user, user == asked_by),
+        )
+        .await
+    }
+
+    /// Forget a user once the button `/forget-me` or `/forget-user` offered
+    /// is clicked. Rewriting the archive can take a while, so the reply is
+    /// deferred.
+    async fn confirm_forget(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        id: uuid::Uuid,
+    ) -> anyhow::Result<()> {
+        let asked_by = interaction.author_id();
+        let Some(user) = asked_by.and_then(|by| state.forgets.take(id, by)) else {
+            discord::respond_ephemeral_message(
+                &state.discord_http,
+                state.application_id,
+                interaction.id,
+                &interaction.token,
+                "This can't be confirmed anymore, please run the command again.",
+            )
+            .await?;
+            return Ok(());
+        };
+        discord::ack_component(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+        )
+        .await?;
+        let reply = match retention::forget_user(&state.store, &state.config, user.get()).await {
+            Ok((forgotten, archived)) => {
+                info!(user_id = %user, asked_by = ?asked_by, "Forgot a user");
+                format!(
+                    "Forgot <@{user}>: {} request(s) and {} audit record(s) in the log, and \
+                     {archived} archived request(s).",
+                    forgotten.requests, forgotten.audits
+                )
+            }
+            Err(e) => {
+                error!(user_id = %user, error = ?e, "Failed to forget a user");
+                "Couldn't forget them, check the bot's logs.".to_string()
+            }
+        };
+        discord::update_string_message(
+            &reply,
+            &state.discord_http,
+            state.application_id,
+            &interaction.token,
+        )
+        .await
+    }
+
+    async fn unban(state: &BotState, guild_id: Id<GuildMarker>, user: Id<UserMarker>) -> String {
+        match state.store.remove_ban(guild_id.get(), user.get()).await {
+            Ok(true) => {
+                info!(user_id = %user, guild_id = %guild_id, "Lifted request ban");
+                format!("<@{user}> can make requests again.")
+            }
+            Ok(false) => format!("<@{user}> wasn't banned."),
+            Err(e) => {
+                error!(error = ?e, "Failed to save ban removal");
+                "Couldn't save the change, check the bot's logs.".to_string()
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Handler for AdminHandler {
+    async fn application_command(
+        &self,
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        data: &CommandData,
+    ) -> anyhow::Result<()> {
+        match data.name.as_str() {
+            discord::BACKEND_COMMAND_NAME => Self::backend_command(state, interaction, data).await,
+            discord::BAN_COMMAND_NAME | discord::UNBAN_COMMAND_NAME => {
+                Self::ban_command(state, interaction, data).await
+            }
+            discord::PING_COMMAND_NAME => Self::ping_command(state, interaction).await,
+            discord::EXPORT_COMMAND_NAME => Self::export_command(state, interaction, data).await,
+            discord::AUDIT_COMMAND_NAME => Self::audit_command(state, interaction, data).await,
+            discord::PENDING_COMMAND_NAME => Self::pending_command(state, interaction).await,
+            discord::REPORT_COMMAND_NAME => Self::report_command(state, interaction).await,
+            discord::SEARCH_MISSING_COMMAND_NAME => {
+                Self::search_missing(state, interaction, data).await
+            }
+            discord::FORGET_ME_COMMAND_NAME | discord::FORGET_USER_COMMAND_NAME => {
+                Self::forget_command(state, interaction, data).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn component(
+        &self,
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        data: &MessageComponentInteractionData,
+    ) -> anyhow::Result<()> {
+        match custom_id::flow(&data.custom_id) {
+            Some((discord::FORGET_PREFIX, id)) => {
+                Self::confirm_forget(state, interaction, id).await
+            }
+            _ => Ok(()),
+        }
+    }
+}
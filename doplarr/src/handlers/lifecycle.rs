@@ -0,0 +1,136 @@
+//! Connection lifecycle: announcing the connection, registering commands,
+//! checking the bot can post where it's configured to, and feeding what it
+//! finds to the startup report
+use super::{BotState, Handler};
+use async_trait::async_trait;
+use std::sync::{Arc, OnceLock};
+use tracing::{debug, error, info, warn};
+use twilight_model::{
+    gateway::payload::incoming::{GuildCreate, Ready},
+    guild::{Guild, Permissions},
+    id::{
+        Id,
+        marker::{ChannelMarker, UserMarker},
+    },
+};
+use twilight_util::permission_calculator::PermissionCalculator;
+
+/// What the bot needs in a channel to post in it unprompted
+const POSTING_PERMISSIONS: Permissions =
+    Permissions::VIEW_CHANNEL.union(Permissions::SEND_MESSAGES);
+
+/// What the bot needs in a channel to keep a request log thread in it
+const THREAD_PERMISSIONS: Permissions = POSTING_PERMISSIONS
+    .union(Permissions::CREATE_PUBLIC_THREADS)
+    .union(Permissions::SEND_MESSAGES_IN_THREADS);
+
+#[derive(Default)]
+pub struct LifecycleHandler {
+    /// The bot's own user, learned from READY, which comes before any guild
+    bot_user: OnceLock<Id<UserMarker>>,
+}
+
+#[async_trait]
+impl Handler for LifecycleHandler {
+    async fn ready(&self, state: &Arc<BotState>, ready: &Ready) -> anyhow::Result<()> {
+        info!("Connected to Discord's server");
+        let _ = self.bot_user.set(ready.user.id);
+        if let Some(report) = &state.startup_report {
+            report.ready(state, ready.guilds.iter().map(|g| g.id));
+        }
+        Ok(())
+    }
+
+    // Discord sends one of these per guild after READY, and again whenever
+    // the bot joins a new guild, so this covers initial and runtime registration
+    async fn guild_create(&self, state: &Arc<BotState>, guild: &GuildCreate) -> anyhow::Result<()> {
+        let guild_id = guild.id();
+        state.guilds.lock().await.insert(guild_id);
+        let registered = state.register_commands(guild_id).await;
+        if let Err(e) = &registered {
+            error!(error = %e, guild_id = %guild_id, "Failed to register commands to guild");
+        }
+        if let (GuildCreate::Available(guild), Some(&bot_user)) = (guild, self.bot_user.get()) {
+            let threaded = state.config.request_threads.iter().flatten();
+            let channels: Vec<_> = state
+                .config
+                .posting_channels()
+                .into_iter()
+                .map(|id| (id, POSTING_PERMISSIONS))
+                .chain(threaded.map(|t| (t.channel_id, THREAD_PERMISSIONS)))
+                .filter_map(|(id, needed)| Some((Id::new_checked(id)?, needed)))
+                .collect();
+            for (channel_id, missing) in missing_permissions(guild, bot_user, &channels) {
+                warn!(
+                    guild_id = %guild_id,
+                    channel_id = %channel_id,
+                    missing = ?missing,
+                    "The bot can't post in a configured channel; grant it these permissions \
+                     there, or messages for this channel will be dropped"
+                );
+                if let Some(report) = &state.startup_report {
+                    let missing: Vec<_> = missing.iter_names().map(|(name, _)| name).collect();
+                    report.channel_warning(format!(
+                        "Can't post in <#{channel_id}>: missing {}",
+                        missing.join(", ")
+                    ));
+                }
+            }
+        }
+        // Recorded last, so the report has this guild's channel warnings too
+        if let Some(report) = &state.startup_report {
+            report.registered(guild_id, registered.map_err(|e| e.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// The configured `channels` in `guild` that the bot lacks the permissions
+/// it needs in, with what it lacks. Channels in other guilds are skipped.
+fn missing_permissions(
+    guild: &Guild,
+    bot_user: Id<UserMarker>,
+    channels: &[(Id<ChannelMarker>, Permissions)],
+) -> Vec<(Id<ChannelMarker>, Permissions)> {
+    // Discord always includes the bot's own member in GUILD_CREATE
+    let Some(member) = guild.members.iter().find(|m| m.user.id == bot_user) else {
+        debug!(guild_id = %guild.id, "Bot member missing from guild, skipping permission check");
+        return Vec::new();
+    };
+    let role_permissions = |id| {
+        guild
+            .roles
+            .iter()
+            .find(|r| r.id == id)
+            .map(|r| r.permissions)
+    };
+    let everyone = role_permissions(guild.id.cast()).unwrap_or(Permissions::empty());
+    let member_roles: Vec<_> = member
+        .roles
+        .iter()
+        .filter_map(|&id| Some((id, role_permissions(id)?)))
+        .collect();
+    let calculator = PermissionCalculator::new(guild.id, bot_user, everyone, &member_roles)
+        .owner_id(guild.owner_id);
+
+    let mut missing: Vec<(Id<ChannelMarker>, Permissions)> = Vec::new();
+    for &(channel_id, needed) in channels {
+        let Some(c) = guild.channels.iter().find(|c| c.id == channel_id) else {
+            continue;
+        };
+        let granted = calculator.clone().in_channel(
+            c.kind,
+            c.permission_overwrites.as_deref().unwrap_or_default(),
+        );
+        let lacking = needed.difference(granted);
+        if lacking.is_empty() {
+            continue;
+        }
+        // A channel both posted in and threaded is reported once
+        match missing.iter_mut().find(|(id, _)| *id == channel_id) {
+            Some((_, reported)) => *reported |= lacking,
+            None => missing.push((channel_id, lacking)),
+        }
+    }
+    missing
+}
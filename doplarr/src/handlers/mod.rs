@@ -0,0 +1,277 @@
+//! Gateway event handling
+//!
+//! The event loop in `main` hands every gateway event to the [Dispatcher], which
+//! fans it out by kind (ready, guild create, slash command, component,
+//! autocomplete, modal submit) to each registered [Handler]. A handler only
+//! implements the kinds it cares about; the rest default to no-ops. Adding a new
+//! command or interaction kind means adding a handler, not growing the loop.
+use crate::{
+    announcer::Announcer,
+    config::Config,
+    discord::{self, InteractionContinue, PendingApprovals, RequestClaims},
+    enrich::Enrichers,
+    hooks::Hooks,
+    providers::registry::BackendRegistry,
+    redact::Secrets,
+    removals::PendingRemovals,
+    retention::PendingForgets,
+    startup::Reporter,
+    store::Store,
+    suggestions::Suggester,
+    thumbnails::ThumbnailProxy,
+};
+use async_trait::async_trait;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Instant,
+};
+use tokio::sync::{Mutex, mpsc};
+use tracing::{debug, error, info, trace};
+use twilight_gateway::Event;
+use twilight_http::Client as HttpClient;
+use twilight_model::{
+    application::{
+        command::Command,
+        interaction::{
+            Interaction, InteractionData, InteractionType, application_command::CommandData,
+            message_component::MessageComponentInteractionData, modal::ModalInteractionData,
+        },
+    },
+    gateway::payload::incoming::{GuildCreate, Ready},
+    id::{
+        Id,
+        marker::{ApplicationMarker, GuildMarker, UserMarker},
+    },
+};
+
+pub mod admin;
+pub mod lifecycle;
+pub mod request;
+
+/// Each in-progress interaction flow -> (sender for its component events, the
+/// user who started it, start time)
+pub type InteractionMap =
+    Arc<Mutex<HashMap<uuid::Uuid, (mpsc::Sender<InteractionContinue>, Id<UserMarker>, Instant)>>>;
+
+/// Each timed-out request flow -> (what it was started with, who started it,
+/// when it timed out), so it can be started over from its timeout message
+pub type ResumableMap =
+    Mutex<HashMap<uuid::Uuid, (request::RequestParams, Id<UserMarker>, Instant)>>;
+
+/// Everything handlers share for the lifetime of the bot
+pub struct BotState {
+    pub config: Config,
+    pub discord_http: Arc<HttpClient>,
+    pub application_id: Id<ApplicationMarker>,
+    pub backends: BackendRegistry,
+    /// Guilds Discord has announced to us, so commands can be re-registered at runtime
+    pub guilds: Mutex<HashSet<Id<GuildMarker>>>,
+    pub interactions: InteractionMap,
+    pub resumable: ResumableMap,
+    pub request_claims: RequestClaims,
+    /// Requests waiting for an admin's approval
+    pub approvals: PendingApprovals,
+    /// Library titles listed by `/request remove`, until one is clicked
+    pub removals: PendingRemovals,
+    /// Users `/forget-me` or `/forget-user` was used on, until it's confirmed
+    pub forgets: PendingForgets,
+    pub hooks: Arc<Hooks>,
+    pub enrichers: Arc<Enrichers>,
+    pub announcer: Announcer,
+    pub store: Arc<Store>,
+    /// Poster proxy, when `[thumbnails]` is configured
+    pub thumbnails: Option<Arc<ThumbnailProxy>>,
+    /// Similar titles offered after a request, when `[suggestions]` is configured
+    pub suggester: Option<Arc<Suggester>>,
+    /// Kept out of the logs; keys given at runtime are added
+    pub secrets: Secrets,
+    /// Reports how startup went, when `[startup_report]` is configured
+    pub startup_report: Option<Arc<Reporter>>,
+}
+
+impl BotState {
+    /// The slash commands to register to a guild, covering the backends that
+    /// serve it. Disabled backends are left out of `/request`, which is omitted
+    /// entirely when none are enabled.
+    pub fn commands(&self, guild_id: Id<GuildMarker>) -> Vec<Command> {
+        let guild_id = Some(guild_id.get());
+        let mut commands = Vec::new();
+        if self.backends.enabled_media_kinds(guild_id).next().is_some() {
+            commands.push(discord::commands(
+                self.backends.enabled_media_kinds(guild_id).map(|media| {
+                    let profiles: Vec<_> =
+                        self.config.profiles_for(media).map(|(n, _)| n).collect();
+                    (media, profiles)
+                }),
+            ));
+        }
+        commands.push(discord::backend_admin_command(
+            self.backends
+                .for_guild(guild_id)
+                .map(|(_, b)| b.name.as_str()),
+        ));
+        commands.extend(discord::ban_commands());
+        commands.push(discord::requests_command());
+        commands.extend(discord::forget_commands());
+        if self.config.upgrade_roles.is_some() {
+            commands.push(discord::upgrade_command(
+                self.backends.enabled_media_kinds(guild_id),
+            ));
+        }
+        commands.push(discord::ping_command());
+        commands.push(discord::export_command());
+        commands.push(discord::audit_command());
+        commands.push(discord::report_command());
+        if self.config.approval.is_some() {
+            commands.push(discord::pending_command());
+        }
+        commands.push(discord::search_missing_command(
+            self.backends.for_guild(guild_id).map(|(media, _)| media),
+        ));
+        commands
+    }
+
+    /// Register the current set of commands to a guild, replacing what was
+    /// there. Returns how many were registered.
+    pub async fn register_commands(&self, guild_id: Id<GuildMarker>) -> anyhow::Result<usize> {
+        info!(guild_id = %guild_id, "Registering commands to guild");
+        let commands = self.commands(guild_id);
+        self.discord_http
+            .interaction(self.application_id)
+            .set_guild_commands(guild_id, &commands)
+            .await?;
+        Ok(commands.len())
+    }
+
+    /// Re-register commands to every known guild, e.g. after a backend is toggled
+    pub async fn refresh_commands(&self) {
+        let guilds: Vec<_> = self.guilds.lock().await.iter().copied().collect();
+        for guild_id in guilds {
+            if let Err(e) = self.register_commands(guild_id).await {
+                error!(error = %e, guild_id = %guild_id, "Failed to register commands to guild");
+            }
+        }
+    }
+}
+
+/// Handles some subset of gateway events. Every method defaults to doing nothing.
+#[async_trait]
+pub trait Handler: Send + Sync {
+    async fn ready(&self, _state: &Arc<BotState>, _ready: &Ready) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn guild_create(
+        &self,
+        _state: &Arc<BotState>,
+        _guild: &GuildCreate,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn application_command(
+        &self,
+        _state: &Arc<BotState>,
+        _interaction: &Interaction,
+        _data: &CommandData,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn component(
+        &self,
+        _state: &Arc<BotState>,
+        _interaction: &Interaction,
+        _data: &MessageComponentInteractionData,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn autocomplete(
+        &self,
+        _state: &Arc<BotState>,
+        _interaction: &Interaction,
+        _data: &CommandData,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn modal(
+        &self,
+        _state: &Arc<BotState>,
+        _interaction: &Interaction,
+        _data: &ModalInteractionData,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Routes gateway events to the registered handlers, in registration order
+pub struct Dispatcher {
+    state: Arc<BotState>,
+    handlers: Vec<Box<dyn Handler>>,
+}
+
+impl Dispatcher {
+    pub fn new(state: Arc<BotState>) -> Self {
+        Self {
+            state,
+            handlers: Vec::new(),
+        }
+    }
+
+    pub fn with_handler(mut self, handler: impl Handler + 'static) -> Self {
+        self.handlers.push(Box::new(handler));
+        self
+    }
+
+    /// Hand one event to every handler. A handler's error is logged and does not
+    /// stop the others, nor the event loop.
+    pub async fn dispatch(&self, event: &Event) {
+        for handler in &self.handlers {
+            let result = match event {
+                Event::Ready(ready) => handler.ready(&self.state, ready).await,
+                Event::GuildCreate(guild) => handler.guild_create(&self.state, guild).await,
+                Event::InteractionCreate(interaction) => {
+                    self.dispatch_interaction(handler.as_ref(), interaction)
+                        .await
+                }
+                Event::GatewayClose(_) => Ok(()), // Standard timeout, no need to log to debug or anything
+                _ => {
+                    debug!(event = ?event, "Got non-handled event");
+                    return;
+                }
+            };
+            if let Err(e) = result {
+                error!(error = ?e, kind = ?event.kind(), "Event handler failed");
+            }
+        }
+    }
+
+    async fn dispatch_interaction(
+        &self,
+        handler: &dyn Handler,
+        interaction: &Interaction,
+    ) -> anyhow::Result<()> {
+        trace!(data = ?interaction, "Got interaction event");
+        match (&interaction.kind, &interaction.data) {
+            (
+                InteractionType::ApplicationCommandAutocomplete,
+                Some(InteractionData::ApplicationCommand(data)),
+            ) => handler.autocomplete(&self.state, interaction, data).await,
+            (_, Some(InteractionData::ApplicationCommand(data))) => {
+                handler
+                    .application_command(&self.state, interaction, data)
+                    .await
+            }
+            (_, Some(InteractionData::MessageComponent(data))) => {
+                handler.component(&self.state, interaction, data).await
+            }
+            (_, Some(InteractionData::ModalSubmit(data))) => {
+                handler.modal(&self.state, interaction, data).await
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,1243 @@
+//! The `/request` command: starts a request flow and feeds it component events.
+//! Also `/requests`, for requesters to see theirs, `/upgrade`, to ask for a
+//! better copy of a title already in the library, and `/request remove`, for
+//! admins to take one out.
+use super::{BotState, Handler};
+use crate::{
+    config::{ApprovalTier, FollowupStyle, MessageVars, Messages},
+    custom_id,
+    discord::{self, ApprovalDecision, FlowOutcome, InteractionContinue},
+    discord_error::DiscordFailure,
+    providers::UserFacingError,
+    removals::Removal,
+    store::RequestStatus,
+    tracking,
+};
+use async_trait::async_trait;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, trace, warn};
+use twilight_model::{
+    application::interaction::{
+        Interaction,
+        application_command::{CommandData, CommandDataOption, CommandOptionValue},
+        message_component::MessageComponentInteractionData,
+        modal::ModalInteractionData,
+    },
+    guild::Permissions,
+    id::{Id, marker::UserMarker},
+};
+
+/// Sanitize error messages for Discord users while keeping full details in logs
+fn user_facing_error(err: &anyhow::Error) -> String {
+    if let Some(e) = err.downcast_ref::<UserFacingError>() {
+        return e.0.clone();
+    }
+
+    let err_msg = err.to_string().to_lowercase();
+
+    if err_msg.contains("timeout") || err_msg.contains("timed out") {
+        "Request timed out. The backend server may be slow or unavailable."
+    } else if err_msg.contains("connection") || err_msg.contains("connect") {
+        "Could not connect to the backend server. Please try again later."
+    } else if err_msg.contains("401")
+        || err_msg.contains("403")
+        || err_msg.contains("unauthorized")
+        || err_msg.contains("forbidden")
+    {
+        "Backend authentication error. Please contact your administrator."
+    } else if err_msg.contains("500") || err_msg.contains("502") || err_msg.contains("503") {
+        "The backend server encountered an error. Please try again later."
+    } else {
+        "An error occurred while processing your request. Please try again or contact your administrator."
+    }
+    .to_string()
+}
+
+/// The configured messages in the language of the user's Discord client, or
+/// else the server's
+fn localized_messages(state: &BotState, interaction: &Interaction) -> Messages {
+    let locales: Vec<_> = [&interaction.locale, &interaction.guild_locale]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect();
+    state
+        .config
+        .messages
+        .as_ref()
+        .map(|m| m.localized(&locales))
+        .unwrap_or_default()
+}
+
+/// How long a timed-out request can be started over from its message, or a
+/// search that found nothing edited and run again
+const RESUME_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// How many of their requests `/requests` shows a requester
+const LISTED_REQUESTS: usize = 10;
+
+/// What a request was started with, kept once it times out or finds nothing
+/// so the message's button can start it over
+#[derive(Debug, Clone)]
+pub struct RequestParams {
+    pub media: String,
+    pub query: String,
+    pub quick: bool,
+    /// Name of the defaults profile, resolved again when started over
+    pub profile: Option<String>,
+    pub year: Option<i32>,
+    /// Unix timestamp the requester needs it by
+    pub need_by: Option<u64>,
+}
+
+/// Why a click can't be handed to a request flow
+enum StaleClick {
+    /// The component's ID isn't one of ours, e.g. from an older version
+    Malformed,
+    /// The flow timed out, or belonged to a run before the bot restarted
+    Gone,
+}
+
+pub struct RequestHandler;
+
+impl RequestHandler {
+    /// Start a request flow for `interaction`: a `/request` command, or the
+    /// button on a timed-out flow when `resumed`
+    async fn begin(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        params: RequestParams,
+        resumed: bool,
+    ) -> anyhow::Result<()> {
+        let RequestParams {
+            media: media_kind,
+            query,
+            quick,
+            profile,
+            year,
+            need_by,
+        } = params.clone();
+
+        if let Some(user_id) = interaction.author_id()
+            && let Some(guild_id) = interaction.guild_id
+            && let Some(ban) = state.store.ban(guild_id.get(), user_id.get()).await
+        {
+            info!(user_id = %user_id, "Rejecting request from banned user");
+            let reply = match ban.reason {
+                Some(reason) => format!("You've been blocked from making requests: {reason}"),
+                None => "You've been blocked from making requests.".to_string(),
+            };
+            discord::respond_ephemeral_message(
+                &state.discord_http,
+                state.application_id,
+                interaction.id,
+                &interaction.token,
+                &reply,
+            )
+            .await?;
+            return Ok(());
+        }
+
+        // Keep the name in the request log current, since users can rename themselves
+        if let Some(user) = interaction.author()
+            && let Err(e) = state
+                .store
+                .remember_name(
+                    user.id.get(),
+                    user.global_name.as_deref().unwrap_or(&user.name),
+                )
+                .await
+        {
+            warn!(error = ?e, "Failed to save user's name");
+        }
+
+        let Some(backend) = state
+            .backends
+            .get(&media_kind, interaction.guild_id.map(|id| id.get()))
+        else {
+            warn!(
+                kind = media_kind,
+                "No backend configured for requested media kind"
+            );
+            return Ok(());
+        };
+
+        // Profile choices come from the config, so an unknown one is a stale command
+        let profile = match profile {
+            Some(name) => match state
+                .config
+                .profiles_for(&media_kind)
+                .find(|(n, _)| *n == name)
+            {
+                Some((_, p)) => Some((name, p.clone())),
+                None => {
+                    warn!(
+                        profile = name,
+                        kind = media_kind,
+                        "Unknown defaults profile"
+                    );
+                    discord::respond_ephemeral_message(
+                        &state.discord_http,
+                        state.application_id,
+                        interaction.id,
+                        &interaction.token,
+                        &format!("There's no \"{name}\" profile for /request {media_kind}."),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+            },
+            None => None,
+        };
+
+        let user_id = interaction
+            .author_id()
+            .expect("Interaction must have a user");
+
+        // Create the channel that we'll push data through
+        let (tx, rx) = mpsc::channel(1);
+
+        // Add this channel to our map of in-progress interactions
+        let uuid = uuid::Uuid::new_v4();
+        state
+            .interactions
+            .lock()
+            .await
+            .insert(uuid, (tx, user_id, Instant::now()));
+
+        // Build the start data
+        let start = discord::InteractionStart {
+            uuid,
+            rx,
+            query,
+            quick,
+            profile,
+            year,
+            need_by,
+            media: media_kind.clone(),
+            interaction_id: interaction.id,
+            application_id: state.application_id,
+            token: interaction.token.clone(),
+            user_id,
+            channel_id: interaction
+                .channel
+                .as_ref()
+                .expect("Interaction must have a channel")
+                .id,
+            guild_id: interaction.guild_id,
+            resumed,
+        };
+
+        // Adult results need an NSFW channel, or a role trusted with them anywhere
+        let nsfw_channel = interaction.channel.as_ref().and_then(|c| c.nsfw) == Some(true);
+        let nsfw_role = interaction.member.as_ref().is_some_and(|member| {
+            member.roles.iter().any(|role| {
+                state
+                    .config
+                    .nsfw_roles
+                    .iter()
+                    .flatten()
+                    .any(|id| *id == role.get())
+            })
+        });
+
+        let options = discord::FlowOptions {
+            public_followup: state.config.public_followup.unwrap_or(true),
+            // A reply to the interaction can't go into the request log thread
+            followup_style: if state.config.has_request_thread(start.channel_id.get()) {
+                FollowupStyle::Message
+            } else {
+                state.config.followup_style.unwrap_or_default()
+            },
+            followup_channels: state
+                .config
+                .followup_channels_for(&media_kind)
+                .map(|channels| channels.into_iter().filter_map(Id::new_checked).collect()),
+            messages: localized_messages(state, interaction),
+            thumbnails: state.thumbnails.clone(),
+            overview: state
+                .config
+                .overview_mode(start.guild_id.map(|id| id.get()), start.channel_id.get()),
+            allow_adult: nsfw_channel || nsfw_role,
+            hooks: Arc::clone(&state.hooks),
+            enrichers: Arc::clone(&state.enrichers),
+            approval: state.config.approval.as_ref().and_then(|_| {
+                let roles: Vec<u64> = interaction
+                    .member
+                    .iter()
+                    .flat_map(|member| member.roles.iter().map(|role| role.get()))
+                    .collect();
+                match state.config.approval_tier(&roles) {
+                    ApprovalTier::Auto => None,
+                    tier => Some(discord::ApprovalRoute {
+                        tier,
+                        channel_id: state
+                            .config
+                            .approval_channel(start.guild_id.map(|id| id.get()))
+                            .and_then(Id::new_checked),
+                        pending: state.approvals.clone(),
+                    }),
+                }
+            }),
+            suggester: state.suggester.clone(),
+            categories: state.config.categories_for(&media_kind),
+            requester_tag: interaction
+                .author()
+                .and_then(|user| state.config.requester_tag(&user.name)),
+        };
+
+        // Spawn the coroutine
+        let state = Arc::clone(state);
+        let user_id = start.user_id;
+        let channel_id = start.channel_id;
+        tokio::spawn(async move {
+            // Keep token for error handling
+            let interaction_token = start.token.clone();
+
+            // Run the flow in its own task so a panic is contained here
+            // instead of silently killing the interaction
+            let result = match tokio::spawn(discord::run_interaction(
+                start,
+                Arc::clone(&state.discord_http),
+                backend,
+                state.announcer.clone(),
+                Arc::clone(&state.store),
+                state.request_claims.clone(),
+                options,
+            ))
+            .await
+            {
+                Ok(result) => result,
+                Err(join_err) => Err(anyhow::anyhow!("Interaction task panicked: {join_err}")),
+            };
+
+            // Offered again by the timeout or "no results" message's button
+            if let Ok(FlowOutcome::Abandoned | FlowOutcome::NoResults) = result {
+                let mut resumable = state.resumable.lock().await;
+                resumable.retain(|_, (_, _, at)| at.elapsed() < RESUME_WINDOW);
+                resumable.insert(uuid, (params, user_id, Instant::now()));
+            }
+            if let Err(e) = result {
+                // A UserFacingError is an expected, user-actionable
+                // outcome (e.g. seasons already monitored), not a
+                // system failure - log it calmly. A failure talking to
+                // Discord gets guidance for the admin instead of a
+                // backtrace. Everything else is a real error worth an
+                // admin's attention.
+                let expected = e.downcast_ref::<UserFacingError>().is_some();
+                let discord_failure = DiscordFailure::find(&e);
+                if expected {
+                    info!(uuid = %uuid, reason = %e, "Interaction ended with a user-facing message");
+                } else if let Some(failure) = &discord_failure {
+                    warn!(
+                        uuid = %uuid,
+                        error = %e,
+                        "Interaction failed talking to Discord. {}",
+                        failure.guidance(Some(channel_id))
+                    );
+                } else {
+                    error!(uuid = %uuid, error = ?e, "Failed to run coroutine to completion");
+                }
+
+                // Show sanitized error to Discord user (no sensitive info),
+                // with the ID an admin can look it up by in /request-audit
+                // (nothing can reach them through an expired token)
+                let user_msg = match &discord_failure {
+                    Some(failure) => failure.user_message(),
+                    None => Some(user_facing_error(&e)),
+                };
+                if let Some(mut user_msg) = user_msg {
+                    if !expected {
+                        user_msg.push_str(&format!("\n-# Reference: `{uuid}`"));
+                    }
+                    if let Err(update_err) = discord::update_string_message(
+                        &user_msg,
+                        &state.discord_http,
+                        state.application_id,
+                        &interaction_token,
+                    )
+                    .await
+                    {
+                        warn!(uuid = %uuid, error = %update_err, "Failed to send error message to user");
+                    }
+                }
+            }
+
+            // Clean up the interaction from the map
+            state.interactions.lock().await.remove(&uuid);
+            debug!(uuid = %uuid, "Cleaned up completed interaction");
+        });
+
+        Ok(())
+    }
+
+    /// Start a timed-out request over from the button on its timeout message
+    async fn resume(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        uuid: uuid::Uuid,
+    ) -> anyhow::Result<()> {
+        let Some(params) = Self::resumable(state, interaction, uuid, true).await else {
+            debug!(uuid = %uuid, "Nothing to start over");
+            discord::respond_ephemeral_message(
+                &state.discord_http,
+                state.application_id,
+                interaction.id,
+                &interaction.token,
+                "This request can't be started over anymore, please run the command again.",
+            )
+            .await?;
+            return Ok(());
+        };
+        info!(
+            uuid = %uuid,
+            kind = params.media,
+            query = params.query,
+            user_id = ?interaction.author_id(),
+            "Starting a timed-out request over"
+        );
+        Self::begin(state, interaction, params, true).await
+    }
+
+    /// The parameters of a flow that can be started over, if `interaction`
+    /// comes from the user who started it and it isn't too late
+    async fn resumable(
+        state: &BotState,
+        interaction: &Interaction,
+        uuid: uuid::Uuid,
+        take: bool,
+    ) -> Option<RequestParams> {
+        let mut resumable = state.resumable.lock().await;
+        match resumable.get(&uuid) {
+            Some((params, user_id, at))
+                if Some(*user_id) == interaction.author_id() && at.elapsed() < RESUME_WINDOW =>
+            {
+                let params = params.clone();
+                if take {
+                    resumable.remove(&uuid);
+                }
+                Some(params)
+            }
+            _ => None,
+        }
+    }
+
+    /// Open the modal to edit a query that found nothing, from the button on
+    /// its "no results" message
+    async fn edit_search(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        uuid: uuid::Uuid,
+    ) -> anyhow::Result<()> {
+        // Left in place until the modal is submitted, as it can be dismissed
+        let Some(params) = Self::resumable(state, interaction, uuid, false).await else {
+            debug!(uuid = %uuid, "No search to edit");
+            discord::respond_ephemeral_message(
+                &state.discord_http,
+                state.application_id,
+                interaction.id,
+                &interaction.token,
+                "This search can't be edited anymore, please run the command again.",
+            )
+            .await?;
+            return Ok(());
+        };
+        discord::respond_research_modal(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+            uuid,
+            &params.query,
+        )
+        .await
+    }
+
+    /// Search again with the query edited in the modal
+    async fn search_again(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        uuid: uuid::Uuid,
+        query: String,
+    ) -> anyhow::Result<()> {
+        let Some(mut params) = Self::resumable(state, interaction, uuid, true).await else {
+            debug!(uuid = %uuid, "No search to run again");
+            discord::respond_ephemeral_message(
+                &state.discord_http,
+                state.application_id,
+                interaction.id,
+                &interaction.token,
+                "This search can't be edited anymore, please run the command again.",
+            )
+            .await?;
+            return Ok(());
+        };
+        info!(
+            uuid = %uuid,
+            kind = params.media,
+            previous = params.query,
+            query,
+            user_id = ?interaction.author_id(),
+            "Searching again with an edited query"
+        );
+        params.query = query;
+        Self::begin(state, interaction, params, true).await
+    }
+
+    /// Request a title suggested on a success card, from its button
+    async fn request_similar(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        id: uuid::Uuid,
+    ) -> anyhow::Result<()> {
+        let offer = state
+            .suggester
+            .as_ref()
+            .zip(interaction.author_id())
+            .and_then(|(suggester, user_id)| suggester.take(id, user_id));
+        let Some(offer) = offer else {
+            debug!(id = %id, "No suggestion to request");
+            discord::respond_ephemeral_message(
+                &state.discord_http,
+                state.application_id,
+                interaction.id,
+                &interaction.token,
+                "This suggestion can't be requested anymore, please use `/request`.",
+            )
+            .await?;
+            return Ok(());
+        };
+        info!(
+            kind = offer.media,
+            title = offer.title,
+            year = offer.year,
+            user_id = ?interaction.author_id(),
+            "Requesting a suggested title"
+        );
+        let params = RequestParams {
+            media: offer.media,
+            query: offer.title,
+            quick: true,
+            profile: None,
+            year: offer.year,
+            need_by: None,
+        };
+        Self::begin(state, interaction, params, false).await
+    }
+
+    /// Show the requester their latest requests in this server, newest first
+    async fn list_requests(state: &Arc<BotState>, interaction: &Interaction) -> anyhow::Result<()> {
+        let user_id = interaction.author_id().map(|id| id.get());
+        let guild_id = interaction.guild_id.map(|id| id.get());
+        let records = state
+            .store
+            .requests(|r| Some(r.user_id) == user_id && r.guild_id == guild_id)
+            .await;
+        let reply = if records.is_empty() {
+            "You haven't requested anything here yet.".to_string()
+        } else {
+            let mut lines = vec!["### Your requests".to_string()];
+            lines.extend(records.iter().rev().take(LISTED_REQUESTS).map(|r| {
+                format!(
+                    "- **{}** · {} · <t:{}:R>",
+                    discord::escape_markdown(&r.title),
+                    r.status.label(),
+                    r.requested_at
+                )
+            }));
+            lines.join("\n")
+        };
+        discord::respond_ephemeral_message(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+            &reply,
+        )
+        .await
+    }
+
+    /// Move a library title to another quality profile and search for a
+    /// better release, for members with one of the `upgrade_roles`
+    async fn upgrade(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        data: &CommandData,
+    ) -> anyhow::Result<()> {
+        let option = |name| {
+            data.options.iter().find_map(|o| match &o.value {
+                CommandOptionValue::String(v) if o.name == name => Some(v.as_str()),
+                _ => None,
+            })
+        };
+        let (Some(media), Some(title)) =
+            (option(discord::MEDIA_OPTION), option(discord::TITLE_OPTION))
+        else {
+            warn!(data = ?data, "Upgrade command body didn't match what we expected");
+            return Ok(());
+        };
+        let quality_profile = option(discord::QUALITY_PROFILE_OPTION);
+
+        let allowed = interaction.member.as_ref().is_some_and(|member| {
+            member.roles.iter().any(|role| {
+                state
+                    .config
+                    .upgrade_roles
+                    .iter()
+                    .flatten()
+                    .any(|id| *id == role.get())
+            })
+        });
+        let banned = match interaction.author_id().zip(interaction.guild_id) {
+            Some((user_id, guild_id)) => state
+                .store
+                .ban(guild_id.get(), user_id.get())
+                .await
+                .is_some(),
+            None => false,
+        };
+        let backend = state
+            .backends
+            .get(media, interaction.guild_id.map(|id| id.get()));
+        let backend = match backend {
+            Some(backend) if allowed && !banned => backend,
+            _ => {
+                let refusal = if allowed && !banned {
+                    format!("There's no /request {media} here anymore.")
+                } else {
+                    "You can't ask for upgrades here.".to_string()
+                };
+                discord::respond_ephemeral_message(
+                    &state.discord_http,
+                    state.application_id,
+                    interaction.id,
+                    &interaction.token,
+                    &refusal,
+                )
+                .await?;
+                return Ok(());
+            }
+        };
+
+        info!(
+            media,
+            title,
+            quality_profile,
+            user_id = ?interaction.author_id(),
+            "Got upgrade request"
+        );
+        discord::send_thinking(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+        )
+        .await?;
+        let reply = match backend.backend.upgrade(title, quality_profile).await {
+            Ok(queued) => queued,
+            Err(e) => {
+                warn!(backend = backend.name, error = ?e, "Upgrade failed");
+                user_facing_error(&e)
+            }
+        };
+        discord::update_string_message(
+            &reply,
+            &state.discord_http,
+            state.application_id,
+            &interaction.token,
+        )
+        .await
+    }
+
+    /// List the library titles matching a `/request remove` search, for an
+    /// admin to pick which to remove
+    async fn remove(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        options: &[CommandDataOption],
+    ) -> anyhow::Result<()> {
+        let option = |name| {
+            options.iter().find_map(|o| match &o.value {
+                CommandOptionValue::String(v) if o.name == name => Some(v.as_str()),
+                _ => None,
+            })
+        };
+        let (Some(media), Some(query)) = (
+            option(discord::MEDIA_OPTION),
+            option(discord::QUERY_COMMAND_NAME),
+        ) else {
+            warn!(options = ?options, "Remove command body didn't match what we expected");
+            return Ok(());
+        };
+        let delete_files = options.iter().any(|o| {
+            o.name == discord::DELETE_FILES_OPTION
+                && matches!(o.value, CommandOptionValue::Boolean(true))
+        });
+
+        let admin = interaction.author_id().filter(|_| {
+            interaction
+                .member
+                .as_ref()
+                .and_then(|member| member.permissions)
+                .is_some_and(|p| p.contains(Permissions::MANAGE_GUILD))
+        });
+        let backend = state
+            .backends
+            .get(media, interaction.guild_id.map(|id| id.get()));
+        let (admin, backend) = match (admin, backend) {
+            (Some(admin), Some(backend)) => (admin, backend),
+            (admin, _) => {
+                let refusal = match admin {
+                    Some(_) => format!("There's no /request {media} here anymore."),
+                    None => "Only admins can remove titles.".to_string(),
+                };
+                discord::respond_ephemeral_message(
+                    &state.discord_http,
+                    state.application_id,
+                    interaction.id,
+                    &interaction.token,
+                    &refusal,
+                )
+                .await?;
+                return Ok(());
+            }
+        };
+
+        info!(media, query, delete_files, admin = %admin, "Got remove command");
+        discord::send_thinking(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+        )
+        .await?;
+        let results = match backend.backend.search(query).await {
+            Ok(results) => results,
+            Err(e) => {
+                warn!(backend = backend.name, error = ?e, "Search for removal failed");
+                return discord::update_string_message(
+                    &user_facing_error(&e),
+                    &state.discord_http,
+                    state.application_id,
+                    &interaction.token,
+                )
+                .await;
+            }
+        };
+        // Only titles the backend has an ID for are in its library
+        let options = backend.backend.to_dropdown_options(&results);
+        let removals: Vec<_> = results
+            .iter()
+            .zip(options)
+            .filter_map(|(item, option)| {
+                let id = backend
+                    .backend
+                    .media_id(item.as_ref())
+                    .filter(|id| id.backend_id.is_some())?;
+                let label = match item.year() {
+                    Some(year) => format!("{} ({year})", option.title),
+                    None => option.title,
+                };
+                Some(Removal {
+                    media: media.to_string(),
+                    label,
+                    id,
+                    delete_files,
+                    admin,
+                })
+            })
+            .take(discord::MAX_REMOVALS_LISTED)
+            .collect();
+        let listed = state.removals.offer(removals);
+        discord::update_interaction_component(
+            &state.discord_http,
+            state.application_id,
+            &interaction.token,
+            discord::build_removals_component(query, &listed),
+        )
+        .await
+    }
+
+    /// Remove a title listed by `/request remove`, from its button
+    async fn confirm_removal(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        id: uuid::Uuid,
+    ) -> anyhow::Result<()> {
+        let removal = interaction
+            .author_id()
+            .and_then(|admin| state.removals.take(id, admin));
+        let backend = removal.as_ref().and_then(|r| {
+            state
+                .backends
+                .get(&r.media, interaction.guild_id.map(|id| id.get()))
+        });
+        let (Some(removal), Some(backend)) = (removal, backend) else {
+            debug!(id = %id, "No title to remove");
+            discord::respond_ephemeral_message(
+                &state.discord_http,
+                state.application_id,
+                interaction.id,
+                &interaction.token,
+                "This title can't be removed from here anymore, please use `/request remove` again.",
+            )
+            .await?;
+            return Ok(());
+        };
+        // Deleting files can take longer than Discord waits for an answer
+        discord::ack_component(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+        )
+        .await?;
+        let title = discord::escape_markdown(&removal.label);
+        let reply = match backend
+            .backend
+            .remove(&removal.id, removal.delete_files)
+            .await
+        {
+            Ok(()) => {
+                info!(
+                    backend = backend.name,
+                    title = removal.label,
+                    delete_files = removal.delete_files,
+                    admin = %removal.admin,
+                    "Removed a title from the library"
+                );
+                if let Err(e) = state
+                    .store
+                    .update_status(
+                        &removal.media,
+                        interaction.guild_id.map(|id| id.get()),
+                        &removal.id,
+                        RequestStatus::Removed,
+                    )
+                    .await
+                {
+                    warn!(error = ?e, "Failed to save request status");
+                }
+                if removal.delete_files {
+                    format!(
+                        "Removed **{title}** from {} and deleted its files.",
+                        backend.name
+                    )
+                } else {
+                    format!("Removed **{title}** from {}.", backend.name)
+                }
+            }
+            Err(e) => {
+                warn!(backend = backend.name, title = removal.label, error = ?e, "Removal failed");
+                user_facing_error(&e)
+            }
+        };
+        discord::update_string_message(
+            &reply,
+            &state.discord_http,
+            state.application_id,
+            &interaction.token,
+        )
+        .await
+    }
+
+    /// Tell whoever clicked a dead message why nothing happened. This answers
+    /// the click itself, since the message's own token may be long expired.
+    async fn explain_stale(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        why: StaleClick,
+    ) -> anyhow::Result<()> {
+        let message = match why {
+            StaleClick::Malformed => {
+                "This message doesn't work anymore. Use `/request` to start a new request."
+                    .to_string()
+            }
+            // The flow is gone, so only the user is known
+            StaleClick::Gone => localized_messages(state, interaction).timeout(MessageVars {
+                user_id: interaction.author_id().map(|id| id.get()),
+                ..Default::default()
+            }),
+        };
+        discord::respond_ephemeral_message(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+            &message,
+        )
+        .await
+    }
+
+    /// The admin deciding on a request, or None after telling a member who
+    /// can't that only admins can. Only members who can manage the server
+    /// decide, like the admin commands.
+    async fn deciding_admin(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+    ) -> anyhow::Result<Option<Id<UserMarker>>> {
+        let admin = interaction.author_id().filter(|_| {
+            interaction
+                .member
+                .as_ref()
+                .and_then(|member| member.permissions)
+                .is_some_and(|p| p.contains(Permissions::MANAGE_GUILD))
+        });
+        if admin.is_none() {
+            discord::respond_ephemeral_message(
+                &state.discord_http,
+                state.application_id,
+                interaction.id,
+                &interaction.token,
+                "Only admins can approve or deny requests.",
+            )
+            .await?;
+        }
+        Ok(admin)
+    }
+
+    async fn respond_not_waiting(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        uuid: uuid::Uuid,
+    ) -> anyhow::Result<()> {
+        debug!(uuid = %uuid, "No pending approval to decide");
+        discord::respond_ephemeral_message(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+            "This request was already decided, expired, or is from another server.",
+        )
+        .await
+    }
+
+    /// Ask the admin who clicked "Deny" why, in a modal; the request is only
+    /// denied once they submit it
+    async fn ask_denial_reason(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        uuid: uuid::Uuid,
+    ) -> anyhow::Result<()> {
+        if Self::deciding_admin(state, interaction).await?.is_none() {
+            return Ok(());
+        }
+        if !state.approvals.is_waiting(&uuid, interaction.guild_id) {
+            return Self::respond_not_waiting(state, interaction, uuid).await;
+        }
+        discord::respond_denial_modal(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+            uuid,
+        )
+        .await
+    }
+
+    /// Approve a request from the buttons on its approval card or in
+    /// `/pending`, or deny it from the modal its "Deny" button opened
+    async fn decide(
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        uuid: uuid::Uuid,
+        decision: impl FnOnce(Id<UserMarker>) -> ApprovalDecision,
+    ) -> anyhow::Result<()> {
+        let Some(admin) = Self::deciding_admin(state, interaction).await? else {
+            return Ok(());
+        };
+        let decision = decision(admin);
+        // Admins only decide on their own server's requests
+        let Some((request, card)) =
+            state
+                .approvals
+                .decide(uuid, interaction.guild_id, decision.clone())
+        else {
+            return Self::respond_not_waiting(state, interaction, uuid).await;
+        };
+        info!(uuid = %uuid, admin = %admin, decision = ?decision, "Admin decided on a request");
+        let clicked = interaction.message.as_ref().map(|m| m.id);
+        if let Some(card) = card
+            && Some(card.1) == clicked
+        {
+            return discord::respond_approval_decision(
+                &state.discord_http,
+                state.application_id,
+                interaction.id,
+                &interaction.token,
+                &request,
+                decision,
+            )
+            .await;
+        }
+        // Decided from `/pending`: refresh the list, and settle the card too
+        discord::respond_interaction_component(
+            &state.discord_http,
+            state.application_id,
+            interaction.id,
+            &interaction.token,
+            discord::build_pending_component(&state.approvals.list(interaction.guild_id)),
+        )
+        .await?;
+        if let Some(card) = card
+            && let Err(e) =
+                discord::update_approval_card(&state.discord_http, card, &request, decision).await
+        {
+            warn!(uuid = %uuid, error = ?e, "Failed to update the approval card");
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler for RequestHandler {
+    async fn application_command(
+        &self,
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        command_data: &CommandData,
+    ) -> anyhow::Result<()> {
+        if command_data.name == discord::REQUESTS_COMMAND_NAME {
+            return Self::list_requests(state, interaction).await;
+        }
+        if command_data.name == discord::UPGRADE_COMMAND_NAME {
+            return Self::upgrade(state, interaction, command_data).await;
+        }
+        if command_data.name != discord::TOP_LEVEL_COMMAND_NAME {
+            return Ok(());
+        }
+        debug!(data = ?command_data, "Got application command");
+        if let Some(subcommand) = command_data.options.first()
+            && subcommand.name == discord::REMOVE_SUBCOMMAND_NAME
+            && let CommandOptionValue::SubCommand(options) = &subcommand.value
+        {
+            return Self::remove(state, interaction, options).await;
+        }
+
+        // New interaction
+        // We now dispatch on the "name" of the interaction which selects the media kind, called with the query string
+        let params = if let Some(subcommand) = command_data.options.first()
+            && let CommandOptionValue::SubCommand(x) = &subcommand.value
+            && let Some(query) = x.iter().find_map(|o| match &o.value {
+                CommandOptionValue::String(v) if o.name == discord::QUERY_COMMAND_NAME => Some(v),
+                _ => None,
+            }) {
+            let quick = x.iter().any(|o| {
+                o.name == discord::QUICK_COMMAND_NAME
+                    && matches!(o.value, CommandOptionValue::Boolean(true))
+            });
+            let profile = x.iter().find_map(|o| match &o.value {
+                CommandOptionValue::String(v) if o.name == discord::PROFILE_COMMAND_NAME => {
+                    Some(v.clone())
+                }
+                _ => None,
+            });
+            let year = x.iter().find_map(|o| match o.value {
+                CommandOptionValue::Integer(v) if o.name == discord::YEAR_COMMAND_NAME => {
+                    i32::try_from(v).ok()
+                }
+                _ => None,
+            });
+            let need_by = match x.iter().find_map(|o| match &o.value {
+                CommandOptionValue::String(v) if o.name == discord::NEED_BY_OPTION => Some(v),
+                _ => None,
+            }) {
+                Some(date) => {
+                    match tracking::parse_need_by(date, time::OffsetDateTime::now_utc().date()) {
+                        Some(need_by) => Some(need_by),
+                        None => {
+                            discord::respond_ephemeral_message(
+                            &state.discord_http,
+                            state.application_id,
+                            interaction.id,
+                            &interaction.token,
+                            &format!(
+                                "\"{date}\" isn't a date to come. Try e.g. \"friday\", \"tomorrow\", or \"2024-03-08\"."
+                            ),
+                        )
+                        .await?;
+                            return Ok(());
+                        }
+                    }
+                }
+                None => None,
+            };
+            RequestParams {
+                media: subcommand.name.clone(),
+                query: query.clone(),
+                quick,
+                profile,
+                year,
+                need_by,
+            }
+        } else {
+            warn!(data = ?command_data, "Interaction body didn't match what we expected",);
+            return Ok(());
+        };
+        info!(
+            kind = params.media,
+            query = params.query,
+            quick = params.quick,
+            profile = params.profile,
+            year = params.year,
+            need_by = params.need_by,
+            user_id = ?interaction.author_id(),
+            guild_id = ?interaction.guild_id,
+            "Got search request"
+        );
+        Self::begin(state, interaction, params, false).await
+    }
+
+    async fn component(
+        &self,
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        component_data: &MessageComponentInteractionData,
+    ) -> anyhow::Result<()> {
+        debug!(data = ?component_data, "Got message component");
+        let parsed = custom_id::flow(&component_data.custom_id);
+        match parsed {
+            Some((discord::RESUME_PREFIX, uuid)) => {
+                return Self::resume(state, interaction, uuid).await;
+            }
+            Some((discord::RESEARCH_PREFIX, uuid)) => {
+                return Self::edit_search(state, interaction, uuid).await;
+            }
+            Some((discord::SIMILAR_PREFIX, id)) => {
+                return Self::request_similar(state, interaction, id).await;
+            }
+            Some((discord::REMOVE_PREFIX, id)) => {
+                return Self::confirm_removal(state, interaction, id).await;
+            }
+            Some((discord::APPROVE_PREFIX, uuid)) => {
+                return Self::decide(state, interaction, uuid, ApprovalDecision::Approved).await;
+            }
+            Some((discord::DENY_PREFIX, uuid)) => {
+                return Self::ask_denial_reason(state, interaction, uuid).await;
+            }
+            // Confirmed by the admin handler
+            Some((discord::FORGET_PREFIX, _)) => return Ok(()),
+            _ => {}
+        }
+        // This is a continuation of an interaction, send this update payload through the channel to the spawned coroutine
+        // Extract the UUID from the update message and push this new data into the associated channel to move that coroutine forward
+        let Some((_, uuid)) = parsed else {
+            warn!(
+                custom_id = component_data.custom_id,
+                "Unrecognized component"
+            );
+            return Self::explain_stale(state, interaction, StaleClick::Malformed).await;
+        };
+
+        let flow = state
+            .interactions
+            .lock()
+            .await
+            .get(&uuid)
+            .map(|(tx, user_id, _)| (tx.clone(), *user_id));
+        // Anyone who can see a flow's message can click it, e.g. once it's
+        // posted somewhere public; only its requester gets to steer it
+        if let Some((_, owner)) = flow
+            && interaction.author_id() != Some(owner)
+        {
+            debug!(uuid = %uuid, user_id = ?interaction.author_id(), "Ignoring someone else's request flow");
+            discord::respond_ephemeral_message(
+                &state.discord_http,
+                state.application_id,
+                interaction.id,
+                &interaction.token,
+                "This isn't your request. Use `/request` to start your own.",
+            )
+            .await?;
+            return Ok(());
+        }
+        let tx = flow.map(|(tx, _)| tx);
+        match tx {
+            Some(tx) => {
+                // Build the continuation data
+                let cont = InteractionContinue {
+                    data: Box::new(component_data.clone()),
+                    interaction_id: interaction.id,
+                    token: interaction.token.clone(),
+                };
+                // Try to send, distinguishing "coroutine busy" from "coroutine gone"
+                match tx.try_send(cont) {
+                    Ok(_) => {
+                        trace!("Sent continuation to interaction coroutine");
+                    }
+                    Err(mpsc::error::TrySendError::Full(_)) => {
+                        // The coroutine is still processing the previous event
+                        // (e.g. the user is clicking quickly); drop this one
+                        debug!(uuid = %uuid, "Interaction coroutine busy, dropping extra event");
+                    }
+                    Err(mpsc::error::TrySendError::Closed(_)) => {
+                        // Other side timed out
+                        warn!(uuid = %uuid, "Interaction coroutine timed out");
+                        // Remove the TX from the map
+                        state.interactions.lock().await.remove(&uuid);
+                        debug!(uuid = %uuid, "Removed timed out interaction from map");
+                        return Self::explain_stale(state, interaction, StaleClick::Gone).await;
+                    }
+                }
+            }
+            None => {
+                // User wanted to continue an interaction that we don't have an ID for, impling we cleaned it up from timeout
+                // Alternatively, a user continued an interaction from a previous run of the bot, which means we don't have any interaction to update!
+                warn!(uuid = %uuid, "No active interaction found for continuation");
+                return Self::explain_stale(state, interaction, StaleClick::Gone).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn modal(
+        &self,
+        state: &Arc<BotState>,
+        interaction: &Interaction,
+        data: &ModalInteractionData,
+    ) -> anyhow::Result<()> {
+        let Some((prefix @ (discord::RESEARCH_PREFIX | discord::DENY_PREFIX), _)) =
+            data.custom_id.split_once(':')
+        else {
+            return Ok(());
+        };
+        let Some((_, uuid)) = custom_id::flow(&data.custom_id) else {
+            warn!(custom_id = data.custom_id, "Unrecognized modal");
+            return Ok(());
+        };
+        if prefix == discord::DENY_PREFIX {
+            // Required in the modal, but a blank one gets past that
+            let Some(reason) = discord::modal_reason(data) else {
+                discord::respond_ephemeral_message(
+                    &state.discord_http,
+                    state.application_id,
+                    interaction.id,
+                    &interaction.token,
+                    "Give a reason for the denial.",
+                )
+                .await?;
+                return Ok(());
+            };
+            return Self::decide(state, interaction, uuid, |admin| {
+                ApprovalDecision::Denied(admin, reason)
+            })
+            .await;
+        }
+        let Some(query) = discord::modal_query(data) else {
+            discord::respond_ephemeral_message(
+                &state.discord_http,
+                state.application_id,
+                interaction.id,
+                &interaction.token,
+                "Type something to search for.",
+            )
+            .await?;
+            return Ok(());
+        };
+        Self::search_again(state, interaction, uuid, query).await
+    }
+}
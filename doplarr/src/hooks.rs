@@ -0,0 +1,273 @@
+//! Site-specific automation around requests (e.g. updating a wiki page)
+//!
+//! Each `[[hooks]]` entry runs a shell command or POSTs to a URL at one point
+//! of a request: just before it's sent to the backend, after it succeeded, or
+//! after it failed. The hook gets the request as JSON, on stdin or as the
+//! POST body. A failing `before_submit` hook cancels the request; the others
+//! run in the background and only log their failures. A webhook enforcing a
+//! quota can answer 429 with `Retry-After`, and the requester is told when
+//! they can request again.
+use crate::{
+    config::{Hook, HookAction, HookEvent},
+    providers::UserFacingError,
+    store::{self, AuditRecord},
+};
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::{process::Stdio, sync::Arc, time::Duration};
+use tokio::{io::AsyncWriteExt, process::Command};
+use tracing::{Instrument, debug, warn};
+
+/// How long a hook gets to finish (default)
+const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Told to the requester when a `before_submit` hook cancels their request
+/// without saying why
+const DEFAULT_CANCEL_MESSAGE: &str = "This request was stopped by a server rule.";
+
+/// Told to the requester when a `before_submit` webhook answers 429 without
+/// saying why
+const DEFAULT_QUOTA_MESSAGE: &str = "You've reached your request limit.";
+
+/// What a hook receives
+#[derive(Serialize)]
+struct Payload<'a> {
+    event: HookEvent,
+    #[serde(flatten)]
+    request: &'a AuditRecord,
+}
+
+/// The configured hooks, ready to run
+#[derive(Default)]
+pub struct Hooks {
+    hooks: Vec<Hook>,
+    client: reqwest::Client,
+}
+
+impl Hooks {
+    pub fn new(hooks: Vec<Hook>, client: reqwest::Client) -> Self {
+        Self { hooks, client }
+    }
+
+    /// Run the `before_submit` hooks in order. The first to fail cancels the
+    /// request, with what it printed (or answered) as the reason.
+    pub async fn before_submit(&self, request: &AuditRecord) -> Result<()> {
+        for hook in self.for_event(HookEvent::BeforeSubmit) {
+            if let Err(e) = self.run(hook, HookEvent::BeforeSubmit, request).await {
+                debug!(error = ?e, "before_submit hook cancelled the request");
+                let reason = e
+                    .downcast_ref::<UserFacingError>()
+                    .map_or(DEFAULT_CANCEL_MESSAGE.to_string(), |e| e.0.clone());
+                bail!(UserFacingError(reason));
+            }
+        }
+        Ok(())
+    }
+
+    /// Start the `success` or `failure` hooks without waiting for them
+    pub fn after_submit(self: &Arc<Self>, request: AuditRecord) {
+        let event = if request.error.is_none() {
+            HookEvent::Success
+        } else {
+            HookEvent::Failure
+        };
+        if self.for_event(event).next().is_none() {
+            return;
+        }
+        let hooks = Arc::clone(self);
+        tokio::spawn(
+            async move {
+                for hook in hooks.for_event(event) {
+                    if let Err(e) = hooks.run(hook, event, &request).await {
+                        warn!(error = ?e, event = ?event, "Request hook failed");
+                    }
+                }
+            }
+            .in_current_span(),
+        );
+    }
+
+    fn for_event(&self, event: HookEvent) -> impl Iterator<Item = &Hook> {
+        self.hooks.iter().filter(move |h| h.on == event)
+    }
+
+    async fn run(&self, hook: &Hook, event: HookEvent, request: &AuditRecord) -> Result<()> {
+        let payload = serde_json::to_vec(&Payload { event, request })?;
+        let limit = hook
+            .timeout_secs
+            .map_or(DEFAULT_HOOK_TIMEOUT, Duration::from_secs);
+        let run = async {
+            match &hook.action {
+                HookAction::Command { command } => run_command(command, &payload).await,
+                HookAction::Webhook { url } => self.post(url, payload).await,
+            }
+        };
+        tokio::time::timeout(limit, run)
+            .await
+            .context("Hook didn't finish in time")?
+    }
+
+    async fn post(&self, url: &str, payload: Vec<u8>) -> Result<()> {
+        let response = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(payload)
+            .send()
+            .await
+            .context("Failed to call hook URL")?;
+        let status = response.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            && let Some(wait) = retry_after(response.headers())
+        {
+            let body = response.text().await.unwrap_or_default();
+            let said = cooldown_message(body.trim(), store::now() + wait);
+            return reject(status.to_string(), &said);
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            reject(status.to_string(), body.trim())?;
+        }
+        Ok(())
+    }
+}
+
+/// Run a hook command with `sh -c`, the payload on its stdin
+async fn run_command(command: &str, payload: &[u8]) -> Result<()> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to start hook command")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that doesn't read its input closes the pipe early, which is fine
+        let _ = stdin.write_all(payload).await;
+    }
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        reject(output.status.to_string(), stdout.trim())?;
+    }
+    Ok(())
+}
+
+/// Seconds to wait from a `Retry-After` header. Only the delay form is
+/// understood, not an HTTP date.
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// What a quota hook said, followed by when the requester can try again
+/// (`at`, in Unix seconds) as a timestamp Discord shows relative to now
+fn cooldown_message(said: &str, at: u64) -> String {
+    let said = if said.is_empty() {
+        DEFAULT_QUOTA_MESSAGE
+    } else {
+        said
+    };
+    format!("{said}\nYou can request again <t:{at}:R>.")
+}
+
+/// Fail a hook, with what it said as a message for the requester
+fn reject(status: String, said: &str) -> Result<()> {
+    if said.is_empty() {
+        bail!("Hook failed ({status})");
+    }
+    Err(anyhow::Error::new(UserFacingError(said.to_string()))
+        .context(format!("Hook failed ({status})")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> AuditRecord {
+        AuditRecord {
+            id: "x".into(),
+            at: 0,
+            user_id: 1,
+            guild_id: None,
+            media: "movie".into(),
+            backend: "radarr".into(),
+            query: "dune".into(),
+            title: "Dune (2021)".into(),
+            selections: vec![],
+            error: None,
+            stages: vec![],
+            denied: None,
+        }
+    }
+
+    fn command(on: HookEvent, command: &str) -> Hook {
+        Hook {
+            on,
+            action: HookAction::Command {
+                command: command.into(),
+            },
+            timeout_secs: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn before_submit_hooks_can_cancel_with_a_reason() {
+        let allow = Hooks::new(
+            vec![command(
+                HookEvent::BeforeSubmit,
+                r#"grep -q '"event":"before_submit".*"title":"Dune (2021)"'"#,
+            )],
+            reqwest::Client::new(),
+        );
+        allow.before_submit(&request()).await.unwrap();
+
+        let deny = Hooks::new(
+            vec![command(
+                HookEvent::BeforeSubmit,
+                "echo 'No more 4K'; exit 1",
+            )],
+            reqwest::Client::new(),
+        );
+        let e = deny.before_submit(&request()).await.unwrap_err();
+        assert_eq!(e.downcast_ref::<UserFacingError>().unwrap().0, "No more 4K");
+
+        let silent = Hooks::new(
+            vec![command(HookEvent::BeforeSubmit, "exit 1")],
+            reqwest::Client::new(),
+        );
+        let e = silent.before_submit(&request()).await.unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<UserFacingError>().unwrap().0,
+            DEFAULT_CANCEL_MESSAGE
+        );
+    }
+
+    #[test]
+    fn quota_hooks_say_when_to_request_again() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(reqwest::header::RETRY_AFTER, "3600".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(3600));
+        headers.insert(
+            reqwest::header::RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), None);
+
+        assert_eq!(
+            cooldown_message("3 requests a day", 1700000000),
+            "3 requests a day\nYou can request again <t:1700000000:R>."
+        );
+        assert_eq!(
+            cooldown_message("", 1700000000),
+            "You've reached your request limit.\nYou can request again <t:1700000000:R>."
+        );
+    }
+}
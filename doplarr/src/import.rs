@@ -0,0 +1,58 @@
+//! One-off import of an Overseerr/Jellyseerr instance's request history into
+//! the request log, so a server moving to Doplarr keeps its past requests
+use crate::{
+    args::SeerrImport,
+    config::MediaKind,
+    export::parse_timestamp,
+    providers::seerr,
+    store::{RequestRecord, Store},
+};
+use tracing::{info, warn};
+
+/// Import from the instance given to `doplarr import seerr`
+pub async fn seerr(
+    import: &SeerrImport,
+    store: &Store,
+    client: reqwest::Client,
+) -> anyhow::Result<()> {
+    let url = import.url.as_str();
+    info!(url, "Importing request history from Seerr");
+    let (history, unlinked) = seerr::request_history(url, import.api_key.clone(), client).await?;
+    if unlinked > 0 {
+        warn!(
+            count = unlinked,
+            "Skipped requests from Seerr users without a linked Discord account"
+        );
+    }
+
+    let records: Vec<_> = history
+        .into_iter()
+        .map(|request| {
+            let media = match request.kind {
+                MediaKind::Movie => &import.movie_command,
+                MediaKind::Tv => &import.series_command,
+            };
+            let mut record = RequestRecord::new(
+                request.discord_id,
+                import.guild,
+                media.clone(),
+                request.title,
+                request.media_id,
+            );
+            // Keep when it was actually requested; fall back to now
+            if let Some(at) = request.created_at.as_deref().and_then(parse_timestamp) {
+                record.requested_at = at;
+            }
+            record
+        })
+        .collect();
+    let found = records.len();
+    let added = store.import_requests(records).await?;
+    info!(
+        found,
+        added,
+        skipped = found - added,
+        "Imported Seerr request history (skipped ones were already logged)"
+    );
+    Ok(())
+}
@@ -0,0 +1,166 @@
+//! Scheduled backend maintenance windows
+//!
+//! A backend's `[[backends.maintenance]]` windows are times (in UTC) when its
+//! server is expected to be down, e.g. for nightly backups. Requests made to
+//! it during a window stop straight away with a message saying when it'll be
+//! back, instead of waiting on a server that isn't answering.
+use crate::config::MaintenanceWindow;
+use anyhow::{Context, Result, bail};
+use time::{Duration, OffsetDateTime, Time, Weekday};
+
+/// Parse an "HH:MM" time of day
+fn parse_time(at: &str) -> Result<Time> {
+    let (h, m) = at
+        .split_once(':')
+        .with_context(|| format!("Maintenance time \"{at}\" must be HH:MM"))?;
+    let (h, m) = (h.parse::<u8>()?, m.parse::<u8>()?);
+    Time::from_hms(h, m, 0)
+        .with_context(|| format!("Maintenance time \"{at}\" is not a valid time"))
+}
+
+/// Parse a day of the week, by its full name or first three letters
+pub fn parse_day(day: &str) -> Result<Weekday> {
+    const DAYS: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+    let lower = day.to_lowercase();
+    DAYS.into_iter()
+        .find(|d| {
+            let name = d.to_string().to_lowercase();
+            lower == name || lower == name[..3]
+        })
+        .with_context(|| format!("\"{day}\" is not a day of the week"))
+}
+
+/// One parsed window
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Window {
+    /// The days it starts on, every day when empty
+    days: Vec<Weekday>,
+    start: Time,
+    /// At or before `start` when it runs past midnight
+    end: Time,
+}
+
+impl Window {
+    fn starts_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    /// When the window ends, if `now` falls in it
+    fn until(&self, now: OffsetDateTime) -> Option<OffsetDateTime> {
+        let wraps = self.end <= self.start;
+        let time = now.time();
+        // Started today
+        if self.starts_on(now.weekday()) && time >= self.start {
+            if !wraps && time < self.end {
+                return Some(now.replace_time(self.end));
+            }
+            if wraps {
+                return Some(now.replace_time(self.end) + Duration::DAY);
+            }
+        }
+        // Started yesterday and runs past midnight
+        if wraps && self.starts_on(now.weekday().previous()) && time < self.end {
+            return Some(now.replace_time(self.end));
+        }
+        None
+    }
+}
+
+/// A backend's maintenance windows
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Schedule(Vec<Window>);
+
+impl Schedule {
+    pub fn parse(windows: &[MaintenanceWindow]) -> Result<Self> {
+        windows
+            .iter()
+            .map(|w| {
+                let (start, end) = (parse_time(&w.start)?, parse_time(&w.end)?);
+                if start == end {
+                    bail!("A maintenance window can't start and end at {}", w.start);
+                }
+                let days = w.days.iter().flatten().map(|d| parse_day(d));
+                Ok(Window {
+                    days: days.collect::<Result<_>>()?,
+                    start,
+                    end,
+                })
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+
+    /// When maintenance ends, if `now` falls in a window. Back-to-back windows
+    /// count as one.
+    pub fn until(&self, now: OffsetDateTime) -> Option<OffsetDateTime> {
+        let mut until = self.0.iter().find_map(|w| w.until(now))?;
+        while let Some(later) = self.0.iter().filter_map(|w| w.until(until)).max() {
+            if later <= until {
+                break;
+            }
+            until = later;
+        }
+        Some(until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::{Date, Month};
+
+    /// A time in the first week of March 2024, which starts on a Friday
+    fn march(day: u8, h: u8, m: u8) -> OffsetDateTime {
+        Date::from_calendar_date(2024, Month::March, day)
+            .unwrap()
+            .with_hms(h, m, 0)
+            .unwrap()
+            .assume_utc()
+    }
+
+    fn window(days: Option<&[&str]>, start: &str, end: &str) -> MaintenanceWindow {
+        MaintenanceWindow {
+            days: days.map(|d| d.iter().map(|d| d.to_string()).collect()),
+            start: start.into(),
+            end: end.into(),
+        }
+    }
+
+    #[test]
+    fn windows_cover_their_days_and_run_past_midnight() {
+        let schedule = Schedule::parse(&[
+            window(None, "04:00", "04:30"),
+            window(Some(&["sun"]), "23:00", "02:00"),
+        ])
+        .unwrap();
+        let until = |t| schedule.until(t);
+        assert_eq!(until(march(5, 4, 10)), Some(march(5, 4, 30)));
+        assert_eq!(until(march(5, 4, 30)), None);
+        // Sunday night into Monday morning
+        assert_eq!(until(march(3, 23, 30)), Some(march(4, 2, 0)));
+        assert_eq!(until(march(4, 1, 59)), Some(march(4, 2, 0)));
+        // The Sunday window doesn't start on Mondays
+        assert_eq!(until(march(4, 23, 30)), None);
+        assert_eq!(until(march(5, 1, 0)), None);
+
+        // Back-to-back windows end together
+        let chained = Schedule::parse(&[
+            window(None, "01:00", "02:00"),
+            window(Some(&["Monday"]), "02:00", "03:00"),
+        ])
+        .unwrap();
+        assert_eq!(chained.until(march(4, 1, 15)), Some(march(4, 3, 0)));
+
+        assert!(Schedule::parse(&[window(Some(&["someday"]), "01:00", "02:00")]).is_err());
+        assert!(Schedule::parse(&[window(None, "25:00", "02:00")]).is_err());
+        assert!(Schedule::parse(&[window(None, "02:00", "02:00")]).is_err());
+    }
+}
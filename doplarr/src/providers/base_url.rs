@@ -0,0 +1,65 @@
+/// Shared handling of the configured URLs for Radarr and Sonarr
+///
+/// Both servers can sit under a path prefix (their "URL Base" setting, or a
+/// reverse proxy path like `https://host/radarr`). The generated clients paste
+/// `/api/v3/...` straight onto `Configuration::base_path`, so a trailing slash
+/// or an already-included API path gives URLs the server doesn't know.
+use anyhow::{Error, anyhow};
+use reqwest::StatusCode;
+
+/// The configured URL as a client `base_path`: the server's root, prefix
+/// included, with no trailing slash or API path
+pub fn normalize(url: &str) -> String {
+    let mut base = url.trim().trim_end_matches('/');
+    for suffix in ["/api/v3", "/api"] {
+        if let Some(stripped) = base.strip_suffix(suffix) {
+            base = stripped.trim_end_matches('/');
+            break;
+        }
+    }
+    base.to_string()
+}
+
+/// Whether a failed status check looks like the URL's path is wrong, rather
+/// than the server being down or the API key rejected. A wrong prefix either
+/// 404s or lands on a web page (e.g. a proxy's) that isn't the API's JSON.
+pub fn is_wrong_prefix(status: Option<StatusCode>, not_json: bool) -> bool {
+    not_json || status == Some(StatusCode::NOT_FOUND)
+}
+
+/// The error for a URL whose path doesn't reach the API
+pub fn wrong_prefix(service: &str, base_path: &str) -> Error {
+    anyhow!(
+        "{service} wasn't found at {base_path}/api/v3. Check the URL's path matches \
+         {service}'s URL Base setting (Settings > General), e.g. \
+         \"http://host:port/{prefix}\" when it's \"/{prefix}\"",
+        prefix = service.to_lowercase()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_keeps_the_prefix_and_drops_the_rest() {
+        assert_eq!(normalize("http://radarr:7878"), "http://radarr:7878");
+        assert_eq!(normalize("http://radarr:7878/"), "http://radarr:7878");
+        assert_eq!(normalize(" https://host/radarr// "), "https://host/radarr");
+        assert_eq!(
+            normalize("https://host/radarr/api/v3/"),
+            "https://host/radarr"
+        );
+        assert_eq!(normalize("https://host/sonarr/api"), "https://host/sonarr");
+        // Only a whole trailing segment is an API path
+        assert_eq!(normalize("https://host/myapi"), "https://host/myapi");
+    }
+
+    #[test]
+    fn only_404s_and_non_json_look_like_a_wrong_prefix() {
+        assert!(is_wrong_prefix(Some(StatusCode::NOT_FOUND), false));
+        assert!(is_wrong_prefix(Some(StatusCode::OK), true));
+        assert!(!is_wrong_prefix(Some(StatusCode::UNAUTHORIZED), false));
+        assert!(!is_wrong_prefix(None, false));
+    }
+}
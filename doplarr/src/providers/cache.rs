@@ -0,0 +1,261 @@
+//! Backend settings kept current without making requesters wait on them
+//!
+//! [Refreshing] holds something fetched from a backend that rarely changes,
+//! like Radarr's root folders and quality profiles. Reading it never waits on
+//! the network: once the value is older than its time-to-live, the read still
+//! gets it (a miss), and a fetch in the background replaces it for the next
+//! one. A failed fetch keeps the old value, so a backend that's briefly down
+//! doesn't take the choices away.
+use anyhow::Result;
+use std::{
+    fmt,
+    pin::Pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+/// How long fetched backend settings are used before fetching them again
+pub const SETTINGS_TTL: Duration = Duration::from_secs(10 * 60);
+
+type Fetch<T> = Box<dyn Fn() -> Pin<Box<dyn Future<Output = Result<T>> + Send>> + Send + Sync>;
+
+struct Inner<T> {
+    /// What's cached, for the logs and `/backend status`
+    name: &'static str,
+    ttl: Duration,
+    fetch: Fetch<T>,
+    /// The value, and when it was fetched (`None` for a placeholder)
+    value: Mutex<(Arc<T>, Option<Instant>)>,
+    refreshing: AtomicBool,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    failures: AtomicU64,
+}
+
+/// Clears a refetch-in-progress flag however the refetch ends, panics included
+struct Refetching<'a>(&'a AtomicBool);
+
+impl Drop for Refetching<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// A backend value, refetched in the background once it's older than its TTL
+pub struct Refreshing<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Clone for Refreshing<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Refreshing<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Refreshing")
+            .field("name", &self.inner.name)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How reads of a [Refreshing] value went
+pub struct CacheStats {
+    /// Reads of a value within its TTL
+    pub hits: u64,
+    /// Reads of an expired value, which started a refetch
+    pub misses: u64,
+    /// Refetches that failed, leaving the old value in place
+    pub failures: u64,
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} hits, {} misses", self.hits, self.misses)?;
+        if self.failures > 0 {
+            write!(f, ", {} failed refreshes", self.failures)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Send + Sync + 'static> Refreshing<T> {
+    /// Fetch the first value, failing like `fetch` does
+    pub async fn new<F, Fut>(name: &'static str, ttl: Duration, fetch: F) -> Result<Self>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+    {
+        let first = fetch().await?;
+        Ok(Self::with_value(
+            name,
+            ttl,
+            first,
+            Some(Instant::now()),
+            Box::new(move || Box::pin(fetch())),
+        ))
+    }
+
+    /// Serve `placeholder` until the first fetch, which the first read starts
+    /// in the background, for values too slow to fetch while anyone waits
+    pub fn lazy<F, Fut>(name: &'static str, ttl: Duration, placeholder: T, fetch: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+    {
+        Self::with_value(
+            name,
+            ttl,
+            placeholder,
+            None,
+            Box::new(move || Box::pin(fetch())),
+        )
+    }
+
+    fn with_value(
+        name: &'static str,
+        ttl: Duration,
+        value: T,
+        fetched: Option<Instant>,
+        fetch: Fetch<T>,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                name,
+                ttl,
+                fetch,
+                value: Mutex::new((Arc::new(value), fetched)),
+                refreshing: AtomicBool::new(false),
+                hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+                failures: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// A value that never expires, for tests without a backend
+    #[cfg(test)]
+    pub fn fixed(value: T) -> Self {
+        Self::with_value(
+            "fixed",
+            Duration::MAX,
+            value,
+            Some(Instant::now()),
+            Box::new(|| Box::pin(async { anyhow::bail!("fixed values aren't refetched") })),
+        )
+    }
+
+    /// The current value, starting a refetch if it has expired
+    pub fn get(&self) -> Arc<T> {
+        let (value, fetched) = self
+            .inner
+            .value
+            .lock()
+            .expect("cache lock poisoned")
+            .clone();
+        if fetched.is_some_and(|at| at.elapsed() < self.inner.ttl) {
+            self.inner.hits.fetch_add(1, Ordering::Relaxed);
+            return value;
+        }
+        self.inner.misses.fetch_add(1, Ordering::Relaxed);
+        if !self.inner.refreshing.swap(true, Ordering::AcqRel) {
+            let inner = Arc::clone(&self.inner);
+            tokio::spawn(async move {
+                let _refetching = Refetching(&inner.refreshing);
+                match (inner.fetch)().await {
+                    Ok(fresh) => {
+                        debug!(cache = inner.name, "Refreshed");
+                        *inner.value.lock().expect("cache lock poisoned") =
+                            (Arc::new(fresh), Some(Instant::now()));
+                    }
+                    Err(e) => {
+                        warn!(cache = inner.name, error = ?e, "Refresh failed, keeping the old value");
+                        inner.failures.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+        value
+    }
+}
+
+impl<T> Refreshing<T> {
+    pub fn name(&self) -> &'static str {
+        self.inner.name
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.inner.hits.load(Ordering::Relaxed),
+            misses: self.inner.misses.load(Ordering::Relaxed),
+            failures: self.inner.failures.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::bail;
+
+    #[tokio::test(start_paused = true)]
+    async fn expired_values_are_served_while_refetching() {
+        let fetches = AtomicU64::new(0);
+        let cache = Refreshing::new("fetch count", SETTINGS_TTL, move || {
+            let n = fetches.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match n {
+                    2 => bail!("backend down"),
+                    3 => panic!("fetch panicked"),
+                    _ => Ok(n),
+                }
+            }
+        })
+        .await
+        .unwrap();
+        // Lets a refetch started by the last read finish
+        let refetched = tokio::task::yield_now;
+
+        assert_eq!(*cache.get(), 0);
+        tokio::time::advance(SETTINGS_TTL).await;
+        // Expired, so the old value is served once more while refetching
+        assert_eq!(*cache.get(), 0);
+        refetched().await;
+        assert_eq!(*cache.get(), 1);
+
+        tokio::time::advance(SETTINGS_TTL).await;
+        assert_eq!(*cache.get(), 1);
+        refetched().await;
+        // The third fetch failed, which keeps the second value
+        assert_eq!(*cache.get(), 1);
+        refetched().await;
+        // The fourth panicked, which doesn't stop the next one
+        assert_eq!(*cache.get(), 1);
+        refetched().await;
+        assert_eq!(*cache.get(), 4);
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 3,
+                misses: 4,
+                failures: 1
+            }
+        );
+
+        let fresh = Refreshing::new("constant", SETTINGS_TTL, || async { Ok("x") })
+            .await
+            .unwrap();
+        fresh.get();
+        assert_eq!(fresh.stats().hits, 1);
+    }
+}
@@ -0,0 +1,403 @@
+//! One media command searching several backends at once
+//!
+//! A `Combined` backend owns no server of its own. It searches its member
+//! instances concurrently, merges their results (a title found by several of
+//! them is offered once), and labels each result with the instance a request
+//! for it goes to. Requesters then don't need to know which command maps to
+//! which server.
+use super::{
+    DownloadStatus, DropdownOption, Maintenance, MediaBackend, MediaDisplayInfo, MediaId,
+    MediaItem, QueueItem, RequestDetails, Requester, SuccessMessage, UserFacingError,
+    registry::RegisteredBackend,
+};
+use crate::config::MediaKind;
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use std::any::Any;
+use tokio::task::JoinSet;
+use tracing::warn;
+
+/// A search result, with the member instance that found it
+#[derive(Debug)]
+pub struct Routed {
+    /// Index into [Combined::members]
+    member: usize,
+    item: Box<dyn MediaItem>,
+}
+
+impl MediaItem for Routed {
+    fn to_dropdown(&self) -> DropdownOption {
+        self.item.to_dropdown()
+    }
+
+    fn year(&self) -> Option<i32> {
+        self.item.year()
+    }
+
+    fn is_adult(&self) -> bool {
+        self.item.is_adult()
+    }
+
+    fn kind(&self) -> Option<MediaKind> {
+        self.item.kind()
+    }
+
+    fn certification(&self) -> Option<String> {
+        self.item.certification()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+fn routed(media: &dyn MediaItem) -> &Routed {
+    media
+        .as_any()
+        .downcast_ref::<Routed>()
+        .expect("Combined backend given another backend's media")
+}
+
+/// Searches its members together, passing everything else on to the member
+/// that found the result
+pub struct Combined {
+    members: Vec<RegisteredBackend>,
+}
+
+impl Combined {
+    pub fn new(members: Vec<RegisteredBackend>) -> Self {
+        Self { members }
+    }
+
+    fn member<'a>(&'a self, media: &'a dyn MediaItem) -> (&'a dyn MediaBackend, &'a dyn MediaItem) {
+        let routed = routed(media);
+        (
+            self.members[routed.member].backend.as_ref(),
+            routed.item.as_ref(),
+        )
+    }
+}
+
+/// Merge each member's results, in member order. A title several members
+/// found is kept once, from the first member that already has it (so the
+/// requester hears it's there) or otherwise the first that found it.
+fn merge(members: &[RegisteredBackend], found: Vec<Vec<Box<dyn MediaItem>>>) -> Vec<Routed> {
+    let mut merged: Vec<(Option<MediaId>, Routed)> = Vec::new();
+    for (member, items) in found.into_iter().enumerate() {
+        let backend = &members[member].backend;
+        for item in items {
+            let id = backend.media_id(item.as_ref()).filter(|id| !id.is_empty());
+            let seen = id.as_ref().and_then(|id| {
+                merged
+                    .iter()
+                    .position(|(other, _)| other.as_ref().is_some_and(|o| o.matches(id)))
+            });
+            let routed = Routed { member, item };
+            match seen {
+                None => merged.push((id, routed)),
+                Some(i) => {
+                    let kept = &merged[i].1;
+                    let kept_present = members[kept.member].backend.early_stop(kept.item.as_ref());
+                    if !kept_present && backend.early_stop(routed.item.as_ref()) {
+                        merged[i].1 = routed;
+                    }
+                }
+            }
+        }
+    }
+    merged.into_iter().map(|(_, routed)| routed).collect()
+}
+
+#[async_trait]
+impl MediaBackend for Combined {
+    async fn search(&self, term: &str) -> Result<Vec<Box<dyn MediaItem>>> {
+        let mut searches = JoinSet::new();
+        for (i, member) in self.members.iter().enumerate() {
+            if !member.is_enabled() {
+                continue;
+            }
+            let (backend, term) = (member.backend.clone(), term.to_string());
+            searches.spawn(async move { (i, backend.search(&term).await) });
+        }
+        let mut found: Vec<Vec<Box<dyn MediaItem>>> =
+            self.members.iter().map(|_| Vec::new()).collect();
+        let mut failure = None;
+        let mut answered = false;
+        while let Some(joined) = searches.join_next().await {
+            let (i, result) = joined?;
+            match result {
+                Ok(items) => {
+                    found[i] = items;
+                    answered = true;
+                }
+                Err(e) => {
+                    warn!(backend = self.members[i].name, error = ?e, "Leaving backend out of the search");
+                    failure = Some(e);
+                }
+            }
+        }
+        match failure {
+            Some(e) if !answered => Err(e),
+            None if !answered => bail!(UserFacingError(
+                "Every backend for this is out of service right now.".into()
+            )),
+            _ => Ok(merge(&self.members, found)
+                .into_iter()
+                .map(|r| Box::new(r) as Box<dyn MediaItem>)
+                .collect()),
+        }
+    }
+
+    fn to_dropdown_options(&self, results: &[Box<dyn MediaItem>]) -> Vec<DropdownOption> {
+        results
+            .iter()
+            .map(|media| {
+                let routed = routed(media.as_ref());
+                let member = &self.members[routed.member];
+                let mut option = member
+                    .backend
+                    .to_dropdown_options(std::slice::from_ref(&routed.item))
+                    .pop()
+                    .unwrap_or_else(|| routed.item.to_dropdown());
+                // The instance goes first, so a long description can't cut it off
+                option.description = Some(match option.description {
+                    Some(description) => format!("→ {} · {description}", member.name),
+                    None => format!("→ {}", member.name),
+                });
+                option
+            })
+            .collect()
+    }
+
+    fn early_stop(&self, media: &dyn MediaItem) -> bool {
+        let (backend, item) = self.member(media);
+        backend.early_stop(item)
+    }
+
+    fn display_info(&self, media: &dyn MediaItem) -> MediaDisplayInfo {
+        let (backend, item) = self.member(media);
+        backend.display_info(item)
+    }
+
+    async fn additional_details(&self, media: &dyn MediaItem) -> Result<Vec<RequestDetails>> {
+        let (backend, item) = self.member(media);
+        backend.additional_details(item).await
+    }
+
+    async fn request(
+        &self,
+        details: Vec<RequestDetails>,
+        media: Box<dyn MediaItem>,
+        requester: &Requester,
+    ) -> Result<()> {
+        let routed = media
+            .into_any()
+            .downcast::<Routed>()
+            .map_err(|_| anyhow::anyhow!("Invalid media type for a combined backend"))?;
+        self.members[routed.member]
+            .backend
+            .request(details, routed.item, requester)
+            .await
+    }
+
+    fn success_message(&self, details: &[RequestDetails], media: &dyn MediaItem) -> SuccessMessage {
+        let (backend, item) = self.member(media);
+        backend.success_message(details, item)
+    }
+
+    fn media_id(&self, media: &dyn MediaItem) -> Option<MediaId> {
+        let (backend, item) = self.member(media);
+        backend.media_id(item)
+    }
+
+    async fn download_status(&self, id: &MediaId) -> Result<Option<DownloadStatus>> {
+        for member in &self.members {
+            if let Some(status) = member.backend.download_status(id).await? {
+                return Ok(Some(status));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Members are registered as backends of their own, which report their
+    /// queues themselves
+    async fn queue(&self) -> Result<Vec<QueueItem>> {
+        Ok(vec![])
+    }
+
+    /// Every member that has the title searches for it
+    async fn search_title(&self, id: &MediaId) -> Result<bool> {
+        let mut searched = false;
+        for member in &self.members {
+            searched |= member.backend.search_title(id).await?;
+        }
+        Ok(searched)
+    }
+
+    /// Members that don't have the title leave it be
+    async fn apply_tags(&self, id: &MediaId, tags: &[String]) -> Result<()> {
+        for member in &self.members {
+            member.backend.apply_tags(id, tags).await?;
+        }
+        Ok(())
+    }
+
+    async fn maintenance(&self, _task: Maintenance, _title: Option<&str>) -> Result<String> {
+        bail!(UserFacingError(
+            "Run maintenance on the instances this backend combines.".into()
+        ))
+    }
+
+    async fn upgrade(&self, _title: &str, _quality_profile: Option<&str>) -> Result<String> {
+        bail!(UserFacingError(
+            "Upgrade titles through the media commands of the instances this backend combines."
+                .into()
+        ))
+    }
+
+    /// Library IDs only mean something to the instance that gave them
+    async fn remove(&self, _id: &MediaId, _delete_files: bool) -> Result<()> {
+        bail!(UserFacingError(
+            "Remove titles through the media commands of the instances this backend combines."
+                .into()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::registry::BackendRegistry;
+    use std::sync::Arc;
+
+    #[derive(Debug)]
+    struct Found {
+        tmdb: i32,
+        present: bool,
+    }
+
+    impl MediaItem for Found {
+        fn to_dropdown(&self) -> DropdownOption {
+            DropdownOption {
+                title: format!("tmdb {}", self.tmdb),
+                description: None,
+                id: None,
+                present: self.present,
+                note: None,
+                warning: None,
+            }
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn into_any(self: Box<Self>) -> Box<dyn Any> {
+            self
+        }
+    }
+
+    fn found(media: &dyn MediaItem) -> &Found {
+        media.as_any().downcast_ref().unwrap()
+    }
+
+    /// Finds the same titles for every search, or fails when it has none
+    struct Instance(Vec<(i32, bool)>);
+
+    #[async_trait]
+    impl MediaBackend for Instance {
+        async fn search(&self, _term: &str) -> Result<Vec<Box<dyn MediaItem>>> {
+            if self.0.is_empty() {
+                bail!("down");
+            }
+            Ok(self
+                .0
+                .iter()
+                .map(|&(tmdb, present)| Box::new(Found { tmdb, present }) as Box<dyn MediaItem>)
+                .collect())
+        }
+        fn early_stop(&self, media: &dyn MediaItem) -> bool {
+            found(media).present
+        }
+        fn display_info(&self, _media: &dyn MediaItem) -> MediaDisplayInfo {
+            unimplemented!()
+        }
+        async fn additional_details(&self, _media: &dyn MediaItem) -> Result<Vec<RequestDetails>> {
+            unimplemented!()
+        }
+        async fn request(
+            &self,
+            _details: Vec<RequestDetails>,
+            _media: Box<dyn MediaItem>,
+            _requester: &Requester,
+        ) -> Result<()> {
+            unimplemented!()
+        }
+        fn success_message(
+            &self,
+            _details: &[RequestDetails],
+            _media: &dyn MediaItem,
+        ) -> SuccessMessage {
+            unimplemented!()
+        }
+        fn media_id(&self, media: &dyn MediaItem) -> Option<MediaId> {
+            Some(MediaId {
+                tmdb: Some(found(media).tmdb),
+                ..Default::default()
+            })
+        }
+    }
+
+    fn combine(instances: Vec<(&str, Instance)>) -> Combined {
+        let mut registry = BackendRegistry::default();
+        let names: Vec<_> = instances.iter().map(|(name, _)| name.to_string()).collect();
+        for (name, instance) in instances {
+            registry
+                .insert(name.into(), name.into(), None, Arc::new(instance))
+                .unwrap();
+        }
+        Combined::new(
+            names
+                .iter()
+                .map(|name| registry.named(name, None).unwrap().clone())
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn results_are_merged_and_labeled_with_their_instance() {
+        let combined = combine(vec![
+            ("radarr", Instance(vec![(1, false), (2, false)])),
+            ("radarr-4k", Instance(vec![(2, true), (3, false)])),
+            ("radarr-down", Instance(vec![])),
+        ]);
+        let results = combined.search("term").await.unwrap();
+        let picked: Vec<_> = results
+            .iter()
+            .map(|r| {
+                let routed = routed(r.as_ref());
+                (
+                    combined.members[routed.member].name.as_str(),
+                    found(routed.item.as_ref()).tmdb,
+                )
+            })
+            .collect();
+        // The title both found goes to the instance that already has it
+        assert_eq!(picked, [("radarr", 1), ("radarr-4k", 2), ("radarr-4k", 3)]);
+        assert!(combined.early_stop(results[1].as_ref()));
+
+        let options = combined.to_dropdown_options(&results);
+        assert_eq!(options[0].description.as_deref(), Some("→ radarr"));
+        assert_eq!(options[2].description.as_deref(), Some("→ radarr-4k"));
+
+        let down = combine(vec![("radarr-down", Instance(vec![]))]);
+        assert!(
+            down.search("term").await.is_err(),
+            "fails when every instance does"
+        );
+    }
+}
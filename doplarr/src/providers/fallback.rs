@@ -0,0 +1,577 @@
+//! TMDB search for Radarr instances whose title lookup is failing
+//!
+//! Radarr looks titles up through its metadata server, which can be down
+//! while Radarr itself is fine. With `[search_fallback]` set, the registry
+//! wraps Radarr instances in [Fallback]: when a search fails but the instance
+//! still answers its status check, the results come from TMDB instead.
+//! Requesting one of them queues it in the [Store], and [spawn] keeps trying
+//! the queue, adding each title once its backend can look it up again and
+//! telling the requester how it went.
+use super::{
+    CalendarEntry, DownloadStatus, DropdownOption, Maintenance, MediaBackend, MediaDisplayInfo,
+    MediaId, MediaItem, QueueItem, RequestDetails, Requester, SelectableId, SuccessMessage,
+    cache::CacheStats, registry::BackendRegistry,
+};
+use crate::{
+    announcer::Announcer,
+    config::{MediaKind, SearchFallback},
+    discord::{details_complete, escape_markdown},
+    store::{self, PendingAdd, RequestStatus, Selection, Store},
+};
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use reqwest::Url;
+use serde::Deserialize;
+use std::{any::Any, sync::Arc, time::Duration};
+use tokio::time::interval;
+use tracing::{Instrument, info, info_span, warn};
+use twilight_model::id::Id;
+
+const TMDB_API: &str = "https://api.themoviedb.org/3";
+
+const POSTER_BASE: &str = "https://image.tmdb.org/t/p/w500";
+
+/// How long a search waits on TMDB before giving up on the fallback too
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often queued requests are tried again (default)
+const DEFAULT_RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Queued requests their backend still can't add after this long are given up on
+const QUEUE_EXPIRY_SECS: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Deserialize)]
+struct SearchResults {
+    #[serde(default)]
+    results: Vec<TmdbMovie>,
+}
+
+/// A movie as TMDB's search has it, standing in for the backend's own result
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TmdbMovie {
+    id: i32,
+    #[serde(default)]
+    title: String,
+    release_date: Option<String>,
+    overview: Option<String>,
+    poster_path: Option<String>,
+    #[serde(default)]
+    adult: bool,
+}
+
+impl TmdbMovie {
+    /// e.g. "Dune (2021)", as the backend's success card would have it
+    fn label(&self) -> String {
+        match self.year() {
+            Some(year) => format!("{} ({year})", self.title),
+            None => self.title.clone(),
+        }
+    }
+
+    fn poster(&self) -> Option<String> {
+        self.poster_path
+            .as_deref()
+            .map(|path| format!("{POSTER_BASE}{path}"))
+    }
+}
+
+impl MediaItem for TmdbMovie {
+    fn to_dropdown(&self) -> DropdownOption {
+        DropdownOption {
+            title: self.title.clone(),
+            description: self.year().map(|y| y.to_string()),
+            id: Some(SelectableId::Integer(self.id)),
+            ..Default::default()
+        }
+    }
+
+    fn year(&self) -> Option<i32> {
+        self.release_date.as_deref()?.get(..4)?.parse().ok()
+    }
+
+    fn is_adult(&self) -> bool {
+        self.adult
+    }
+
+    fn kind(&self) -> Option<MediaKind> {
+        Some(MediaKind::Movie)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// The movies of a TMDB search response, best first
+fn movies(body: &[u8]) -> Result<Vec<TmdbMovie>> {
+    let results: SearchResults = serde_json::from_slice(body)?;
+    Ok(results
+        .results
+        .into_iter()
+        .filter(|m| !m.title.is_empty())
+        .collect())
+}
+
+/// Pick the options a queued request was made with on the backend's own
+/// form for the title. Fields that weren't on the form when it was queued
+/// keep their defaults.
+fn pick(details: &mut [RequestDetails], selections: &[Selection]) {
+    for detail in details {
+        let Some(selection) = selections.iter().find(|s| s.field == detail.title) else {
+            continue;
+        };
+        detail.selected_indices = detail
+            .options
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| selection.values.contains(&o.title))
+            .map(|(i, _)| i)
+            .collect();
+    }
+}
+
+/// Searches TMDB, and queues what's requested from its results
+pub struct TmdbSearch {
+    api_key: String,
+    client: reqwest::Client,
+    store: Arc<Store>,
+}
+
+impl TmdbSearch {
+    pub fn new(config: &SearchFallback, client: reqwest::Client, store: Arc<Store>) -> Self {
+        Self {
+            api_key: config.tmdb_api_key.clone(),
+            client,
+            store,
+        }
+    }
+
+    async fn search(&self, term: &str) -> Result<Vec<TmdbMovie>> {
+        let url = Url::parse_with_params(
+            &format!("{TMDB_API}/search/movie"),
+            [("api_key", self.api_key.as_str()), ("query", term)],
+        )?;
+        let body = self
+            .client
+            .get(url)
+            .timeout(LOOKUP_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        movies(&body)
+    }
+}
+
+/// Wraps a Radarr instance, searching TMDB when its lookup fails and queuing
+/// requests for the titles found there
+pub struct Fallback {
+    inner: Arc<dyn MediaBackend>,
+    /// The instance name, for the queue and the requester
+    name: String,
+    tmdb: Arc<TmdbSearch>,
+}
+
+impl Fallback {
+    pub fn new(inner: Arc<dyn MediaBackend>, name: &str, tmdb: Arc<TmdbSearch>) -> Self {
+        Self {
+            inner,
+            name: name.to_string(),
+            tmdb,
+        }
+    }
+}
+
+fn as_tmdb(media: &dyn MediaItem) -> Option<&TmdbMovie> {
+    media.as_any().downcast_ref::<TmdbMovie>()
+}
+
+#[async_trait]
+impl MediaBackend for Fallback {
+    async fn search(&self, term: &str) -> Result<Vec<Box<dyn MediaItem>>> {
+        let error = match self.inner.search(term).await {
+            Ok(results) => return Ok(results),
+            Err(e) => e,
+        };
+        // A backend that's down altogether couldn't add anything either
+        if let Err(e) = self.inner.version().await {
+            warn!(backend = self.name, error = ?e, "Backend is down, not searching TMDB instead");
+            return Err(error);
+        }
+        warn!(backend = self.name, error = ?error, "Title lookup failed, searching TMDB instead");
+        match self.tmdb.search(term).await {
+            Ok(movies) => Ok(movies
+                .into_iter()
+                .map(|m| Box::new(m) as Box<dyn MediaItem>)
+                .collect()),
+            Err(e) => {
+                warn!(error = ?e, "TMDB search failed too");
+                Err(error)
+            }
+        }
+    }
+
+    fn to_dropdown_options(&self, results: &[Box<dyn MediaItem>]) -> Vec<DropdownOption> {
+        if results.iter().any(|r| as_tmdb(&**r).is_some()) {
+            return results.iter().map(|r| r.to_dropdown()).collect();
+        }
+        self.inner.to_dropdown_options(results)
+    }
+
+    fn early_stop(&self, media: &dyn MediaItem) -> bool {
+        // Whether it's in the library is for the backend to say, once it can
+        as_tmdb(media).is_none() && self.inner.early_stop(media)
+    }
+
+    fn display_info(&self, media: &dyn MediaItem) -> MediaDisplayInfo {
+        let Some(movie) = as_tmdb(media) else {
+            return self.inner.display_info(media);
+        };
+        let note = format!("from TMDB while {} can't look titles up", self.name);
+        MediaDisplayInfo {
+            title: movie.title.clone(),
+            subtitle: Some(match movie.year() {
+                Some(year) => format!("{year} · {note}"),
+                None => note,
+            }),
+            description: movie.overview.clone().filter(|o| !o.is_empty()),
+            thumbnail_url: movie.poster(),
+        }
+    }
+
+    async fn additional_details(&self, media: &dyn MediaItem) -> Result<Vec<RequestDetails>> {
+        self.inner.additional_details(media).await
+    }
+
+    async fn request(
+        &self,
+        details: Vec<RequestDetails>,
+        media: Box<dyn MediaItem>,
+        requester: &Requester,
+    ) -> Result<()> {
+        let Some(movie) = as_tmdb(&*media) else {
+            return self.inner.request(details, media, requester).await;
+        };
+        info!(backend = self.name, title = %movie.label(), "Queuing the request until the backend can add it");
+        self.tmdb
+            .store
+            .queue_add(PendingAdd {
+                queued_at: store::now(),
+                backend: self.name.clone(),
+                user_id: requester.user_id,
+                guild_id: requester.guild_id,
+                media: requester.media.clone(),
+                tmdb: movie.id,
+                title: movie.label(),
+                selections: details.iter().map(Selection::picked).collect(),
+                tags: requester.tags.clone(),
+            })
+            .await
+    }
+
+    fn success_message(&self, details: &[RequestDetails], media: &dyn MediaItem) -> SuccessMessage {
+        let Some(movie) = as_tmdb(media) else {
+            return self.inner.success_message(details, media);
+        };
+        SuccessMessage {
+            summary: movie.label(),
+            description: format!(
+                "{} can't add titles right now, so this request is queued. It'll be sent \
+                 once {0} is back, and you'll be told here.",
+                self.name
+            ),
+            thumbnail_url: movie.poster(),
+            details: None,
+        }
+    }
+
+    async fn calendar(&self, start: &str, end: &str) -> Result<Vec<CalendarEntry>> {
+        self.inner.calendar(start, end).await
+    }
+
+    async fn version(&self) -> Result<Option<String>> {
+        self.inner.version().await
+    }
+
+    fn media_id(&self, media: &dyn MediaItem) -> Option<MediaId> {
+        match as_tmdb(media) {
+            Some(movie) => Some(MediaId {
+                tmdb: Some(movie.id),
+                ..Default::default()
+            }),
+            None => self.inner.media_id(media),
+        }
+    }
+
+    async fn download_status(&self, id: &MediaId) -> Result<Option<DownloadStatus>> {
+        self.inner.download_status(id).await
+    }
+
+    async fn queue(&self) -> Result<Vec<QueueItem>> {
+        self.inner.queue().await
+    }
+
+    async fn search_title(&self, id: &MediaId) -> Result<bool> {
+        self.inner.search_title(id).await
+    }
+
+    async fn apply_tags(&self, id: &MediaId, tags: &[String]) -> Result<()> {
+        // A queued title isn't in the backend yet; it's tagged once it's added
+        let queued =
+            self.tmdb.store.pending_adds().await.iter().any(|p| {
+                p.backend == self.name && id.tmdb == Some(p.tmdb) && id.backend_id.is_none()
+            });
+        if queued {
+            return Ok(());
+        }
+        self.inner.apply_tags(id, tags).await
+    }
+
+    async fn maintenance(&self, task: Maintenance, title: Option<&str>) -> Result<String> {
+        self.inner.maintenance(task, title).await
+    }
+
+    async fn upgrade(&self, title: &str, quality_profile: Option<&str>) -> Result<String> {
+        self.inner.upgrade(title, quality_profile).await
+    }
+
+    async fn remove(&self, id: &MediaId, delete_files: bool) -> Result<()> {
+        self.inner.remove(id, delete_files).await
+    }
+
+    fn cache_stats(&self) -> Vec<(&'static str, CacheStats)> {
+        self.inner.cache_stats()
+    }
+}
+
+/// Add a queued request to its backend, with the options and tags it was
+/// made with. `backend` is the instance's own connection, so a lookup that's
+/// still down isn't searched on TMDB again. Returns whether it was added;
+/// `false` while the backend still can't look the title up.
+async fn add(backend: &dyn MediaBackend, pending: &PendingAdd) -> Result<bool> {
+    let Ok(results) = backend.search(&format!("tmdb:{}", pending.tmdb)).await else {
+        return Ok(false);
+    };
+    let Some(media) = results.into_iter().find(|r| {
+        as_tmdb(&**r).is_none()
+            && backend
+                .media_id(&**r)
+                .is_some_and(|id| id.tmdb == Some(pending.tmdb))
+    }) else {
+        return Ok(false);
+    };
+    // Someone got there first
+    if backend.early_stop(&*media) {
+        return Ok(true);
+    }
+    let mut details = backend.additional_details(&*media).await?;
+    pick(&mut details, &pending.selections);
+    if !details_complete(&details) {
+        bail!("the options it was requested with aren't on offer any more");
+    }
+    let requester = Requester {
+        user_id: pending.user_id,
+        guild_id: pending.guild_id,
+        media: pending.media.clone(),
+        tags: pending.tags.clone(),
+    };
+    backend.request(details, media, &requester).await?;
+    // It's added either way; a missing tag is for an admin to fix
+    let id = MediaId {
+        tmdb: Some(pending.tmdb),
+        ..Default::default()
+    };
+    if !pending.tags.is_empty()
+        && let Err(e) = backend.apply_tags(&id, &pending.tags).await
+    {
+        warn!(error = ?e, tags = ?pending.tags, "Failed to tag a queued request");
+    }
+    Ok(true)
+}
+
+/// Try every queued request again, telling the requesters about the ones
+/// added or given up on
+async fn retry(backends: &BackendRegistry, store: &Store, announcer: &Announcer) {
+    for pending in store.pending_adds().await {
+        let Some(instance) = backends
+            .iter()
+            .map(|(_, b)| b)
+            .find(|b| b.name == pending.backend)
+        else {
+            warn!(
+                backend = pending.backend,
+                title = pending.title,
+                "Dropping a queued request for a backend that's gone"
+            );
+            if let Err(e) = store.finish_add(&pending).await {
+                warn!(error = ?e, "Failed to save the request queue");
+            }
+            continue;
+        };
+        let Some(connection) = instance.connection() else {
+            continue;
+        };
+        let outcome = match add(&*connection, &pending).await {
+            Ok(true) => Ok(()),
+            Ok(false) if store::now() < pending.queued_at + QUEUE_EXPIRY_SECS => continue,
+            Ok(false) => Err(format!("{} still can't look it up", pending.backend)),
+            Err(e) => Err(format!("{e:#}")),
+        };
+        match &outcome {
+            Ok(()) => info!(
+                backend = pending.backend,
+                title = pending.title,
+                "Added a queued request"
+            ),
+            Err(reason) => warn!(
+                backend = pending.backend,
+                title = pending.title,
+                reason,
+                "Gave up on a queued request"
+            ),
+        }
+        if let Err(e) = store.finish_add(&pending).await {
+            warn!(error = ?e, "Failed to save the request queue");
+        }
+        let ids = MediaId {
+            tmdb: Some(pending.tmdb),
+            ..Default::default()
+        };
+        let Some(record) = store
+            .requests(|r| {
+                r.user_id == pending.user_id
+                    && r.media == pending.media
+                    && r.guild_id == pending.guild_id
+                    && r.ids.matches(&ids)
+            })
+            .await
+            .pop()
+        else {
+            continue;
+        };
+        if outcome.is_err()
+            && let Err(e) = store
+                .update_status(
+                    &pending.media,
+                    pending.guild_id,
+                    &ids,
+                    RequestStatus::Failed,
+                )
+                .await
+        {
+            warn!(error = ?e, "Failed to save request status");
+        }
+        let Some(channel_id) = record.channel_id else {
+            continue;
+        };
+        let title = escape_markdown(&pending.title);
+        announcer.send(
+            Id::new(channel_id),
+            match outcome {
+                Ok(()) => format!(
+                    "<@{}> **{title}** has now been sent to {}.",
+                    pending.user_id, pending.backend
+                ),
+                Err(reason) => format!(
+                    "<@{}> **{title}** couldn't be sent to {}: {reason}. Please request it again.",
+                    pending.user_id, pending.backend
+                ),
+            },
+        );
+    }
+}
+
+/// Keep trying the queued requests in the background
+pub fn spawn(
+    config: &SearchFallback,
+    backends: BackendRegistry,
+    store: Arc<Store>,
+    announcer: Announcer,
+) {
+    let every = config.retry_minutes.map_or(DEFAULT_RETRY_INTERVAL, |m| {
+        Duration::from_secs(m.max(1) * 60)
+    });
+    info!(every = ?every, "Retrying requests queued by the search fallback");
+    tokio::spawn(
+        async move {
+            let mut ticks = interval(every);
+            loop {
+                ticks.tick().await;
+                retry(&backends, &store, &announcer).await;
+            }
+        }
+        .instrument(info_span!("search_fallback")),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::FieldType;
+
+    #[test]
+    fn tmdb_results_become_movies() {
+        let body = br#"{"results": [
+            {"id": 438631, "title": "Dune", "release_date": "2021-09-15",
+             "overview": "Paul...", "poster_path": "/d5NXSklXo0qyIYkgV94XAgMIckC.jpg"},
+            {"id": 1, "title": "", "release_date": "2000-01-01"},
+            {"id": 841, "title": "Dune", "release_date": "", "adult": false}
+        ]}"#;
+        let found = movies(body).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].label(), "Dune (2021)");
+        assert_eq!(
+            found[0].poster().as_deref(),
+            Some("https://image.tmdb.org/t/p/w500/d5NXSklXo0qyIYkgV94XAgMIckC.jpg")
+        );
+        assert_eq!(found[1].label(), "Dune");
+        assert!(movies(b"{}").unwrap().is_empty());
+    }
+
+    #[test]
+    fn queued_picks_are_made_again_by_option_title() {
+        let field = |title: &str, options: &[&str]| RequestDetails {
+            title: title.into(),
+            options: options
+                .iter()
+                .map(|o| DropdownOption {
+                    title: o.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            selected_indices: vec![],
+            metadata: None,
+            field_type: FieldType::Dropdown,
+            always_show: false,
+            status: None,
+        };
+        let mut details = vec![
+            field("Quality Profile", &["SD", "HD-1080p"]),
+            field("Root Folder", &["/movies"]),
+            field("Availability", &["Announced", "Released"]),
+        ];
+        pick(
+            &mut details,
+            &[
+                Selection {
+                    field: "Quality Profile".into(),
+                    values: vec!["HD-1080p".into()],
+                },
+                Selection {
+                    field: "Availability".into(),
+                    values: vec!["In Cinemas".into()],
+                },
+            ],
+        );
+        assert_eq!(details[0].selected_indices, [1]);
+        assert_eq!(details[1].selected_option().unwrap().title, "/movies");
+        // An option that's gone leaves the field unpicked
+        assert!(details[2].selected_option().is_none());
+        assert!(!details_complete(&details));
+    }
+}
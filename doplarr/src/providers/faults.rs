@@ -0,0 +1,215 @@
+//! Deliberately slow and failing backends, for testing how the bot copes
+//!
+//! With `[debug]` set, the registry wraps backends in [FaultInjecting], which
+//! delays their network calls and fails a share of them at random before the
+//! real backend is reached. The statistics wrapper sits outside it, so
+//! injected latency and failures show up in `/backend` like real ones.
+use super::{
+    CalendarEntry, DownloadStatus, Maintenance, MediaBackend, MediaDisplayInfo, MediaId, MediaItem,
+    QueueItem, RequestDetails, Requester, SuccessMessage, cache::CacheStats,
+};
+use crate::config::FaultInjection;
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use std::{
+    hash::{BuildHasher, RandomState},
+    sync::Arc,
+    time::Duration,
+};
+use tracing::debug;
+
+/// A random number. Every [RandomState] is keyed afresh, which is plenty for
+/// picking which calls to break.
+fn random() -> u64 {
+    RandomState::new().hash_one(())
+}
+
+/// Wraps a backend, delaying and failing its network calls as configured
+pub struct FaultInjecting {
+    inner: Arc<dyn MediaBackend>,
+    /// The instance name, for the injected errors
+    name: String,
+    latency: Duration,
+    jitter: Duration,
+    failure_percent: u64,
+}
+
+impl FaultInjecting {
+    pub fn new(inner: Arc<dyn MediaBackend>, name: &str, faults: &FaultInjection) -> Self {
+        Self {
+            inner,
+            name: name.to_string(),
+            latency: Duration::from_millis(faults.latency_ms.unwrap_or(0)),
+            jitter: Duration::from_millis(faults.jitter_ms.unwrap_or(0)),
+            failure_percent: faults.failure_percent.unwrap_or(0).min(100).into(),
+        }
+    }
+
+    async fn inject<T>(&self, call: &str, real: impl Future<Output = Result<T>>) -> Result<T> {
+        let jitter = self.jitter.as_millis() as u64;
+        let delay = self.latency + Duration::from_millis(random() % (jitter + 1));
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        if random() % 100 < self.failure_percent {
+            debug!(backend = self.name, call, "Injecting a backend failure");
+            bail!("Injected failure in {} {call} ([debug] config)", self.name);
+        }
+        real.await
+    }
+}
+
+#[async_trait]
+impl MediaBackend for FaultInjecting {
+    async fn search(&self, term: &str) -> Result<Vec<Box<dyn MediaItem>>> {
+        self.inject("search", self.inner.search(term)).await
+    }
+
+    fn to_dropdown_options(&self, results: &[Box<dyn MediaItem>]) -> Vec<super::DropdownOption> {
+        self.inner.to_dropdown_options(results)
+    }
+
+    fn early_stop(&self, media: &dyn MediaItem) -> bool {
+        self.inner.early_stop(media)
+    }
+
+    fn display_info(&self, media: &dyn MediaItem) -> MediaDisplayInfo {
+        self.inner.display_info(media)
+    }
+
+    async fn additional_details(&self, media: &dyn MediaItem) -> Result<Vec<RequestDetails>> {
+        self.inject("details", self.inner.additional_details(media))
+            .await
+    }
+
+    async fn request(
+        &self,
+        details: Vec<RequestDetails>,
+        media: Box<dyn MediaItem>,
+        requester: &Requester,
+    ) -> Result<()> {
+        self.inject("request", self.inner.request(details, media, requester))
+            .await
+    }
+
+    fn success_message(&self, details: &[RequestDetails], media: &dyn MediaItem) -> SuccessMessage {
+        self.inner.success_message(details, media)
+    }
+
+    async fn calendar(&self, start: &str, end: &str) -> Result<Vec<CalendarEntry>> {
+        self.inject("calendar", self.inner.calendar(start, end))
+            .await
+    }
+
+    async fn version(&self) -> Result<Option<String>> {
+        self.inject("version", self.inner.version()).await
+    }
+
+    fn media_id(&self, media: &dyn MediaItem) -> Option<MediaId> {
+        self.inner.media_id(media)
+    }
+
+    async fn download_status(&self, id: &MediaId) -> Result<Option<DownloadStatus>> {
+        self.inject("download status", self.inner.download_status(id))
+            .await
+    }
+
+    async fn queue(&self) -> Result<Vec<QueueItem>> {
+        self.inject("queue", self.inner.queue()).await
+    }
+
+    async fn search_title(&self, id: &MediaId) -> Result<bool> {
+        self.inject("title search", self.inner.search_title(id))
+            .await
+    }
+
+    async fn apply_tags(&self, id: &MediaId, tags: &[String]) -> Result<()> {
+        self.inject("tags", self.inner.apply_tags(id, tags)).await
+    }
+
+    async fn maintenance(&self, task: Maintenance, title: Option<&str>) -> Result<String> {
+        self.inject("maintenance", self.inner.maintenance(task, title))
+            .await
+    }
+
+    async fn upgrade(&self, title: &str, quality_profile: Option<&str>) -> Result<String> {
+        self.inject("upgrade", self.inner.upgrade(title, quality_profile))
+            .await
+    }
+
+    async fn remove(&self, id: &MediaId, delete_files: bool) -> Result<()> {
+        self.inject("remove", self.inner.remove(id, delete_files))
+            .await
+    }
+
+    fn cache_stats(&self) -> Vec<(&'static str, CacheStats)> {
+        self.inner.cache_stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// A backend whose calendar is always empty
+    struct Quiet;
+
+    #[async_trait]
+    impl MediaBackend for Quiet {
+        async fn search(&self, _term: &str) -> Result<Vec<Box<dyn MediaItem>>> {
+            unimplemented!()
+        }
+        fn early_stop(&self, _media: &dyn MediaItem) -> bool {
+            unimplemented!()
+        }
+        fn display_info(&self, _media: &dyn MediaItem) -> MediaDisplayInfo {
+            unimplemented!()
+        }
+        async fn additional_details(&self, _media: &dyn MediaItem) -> Result<Vec<RequestDetails>> {
+            unimplemented!()
+        }
+        async fn request(
+            &self,
+            _details: Vec<RequestDetails>,
+            _media: Box<dyn MediaItem>,
+            _requester: &Requester,
+        ) -> Result<()> {
+            unimplemented!()
+        }
+        fn success_message(
+            &self,
+            _details: &[RequestDetails],
+            _media: &dyn MediaItem,
+        ) -> SuccessMessage {
+            unimplemented!()
+        }
+    }
+
+    fn faulty(faults: FaultInjection) -> FaultInjecting {
+        FaultInjecting::new(Arc::new(Quiet), "radarr", &faults)
+    }
+
+    #[tokio::test]
+    async fn calls_are_delayed_and_failed_as_configured() {
+        let slow = faulty(FaultInjection {
+            latency_ms: Some(30),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        assert!(slow.calendar("2024-01-01", "2024-01-07").await.is_ok());
+        assert!(start.elapsed() >= Duration::from_millis(30));
+
+        let broken = faulty(FaultInjection {
+            failure_percent: Some(100),
+            ..Default::default()
+        });
+        let error = broken.calendar("2024-01-01", "2024-01-07").await;
+        assert!(format!("{:#}", error.unwrap_err()).contains("radarr calendar"));
+
+        let healthy = faulty(FaultInjection::default());
+        for _ in 0..20 {
+            assert!(healthy.calendar("2024-01-01", "2024-01-07").await.is_ok());
+        }
+    }
+}
@@ -5,9 +5,15 @@
 //! 2. Determines if a selected search result is already available or has been requested before
 //! 3. Provides a set of additional information needed to complete the request (quality profile, season, etc)
 //! 4. Perform the request using the payload and the set of additional information and respond with a success or failure
-use anyhow::Result;
+use crate::{
+    config::{MediaKind, ResultField},
+    discord::MAX_DROPDOWN_OPTIONS,
+};
+use anyhow::{Result, bail};
 use async_trait::async_trait;
-use std::{any::Any, fmt::Debug};
+use serde::{Deserialize, Serialize};
+use std::{any::Any, fmt::Debug, time::Duration};
+use tracing::debug;
 
 #[derive(Debug)]
 pub struct UserFacingError(pub String);
@@ -22,17 +28,35 @@ impl std::error::Error for UserFacingError {}
 
 // Shared utilities
 mod api_logging;
+mod base_url;
+pub mod cache;
 
 // Backend instances
+pub mod combined;
+pub mod fallback;
+pub mod faults;
+pub mod plugin;
 pub mod radarr;
+pub mod registry;
 pub mod seerr;
 pub mod sonarr;
+pub mod stats;
+pub mod swappable;
 
 /// Sentinel id for an "All Seasons" entry in a season multi-select. Real season
 /// numbers are >= 0, so -1 never collides. The Discord layer treats an option
 /// with this id as mutually exclusive with the rest of the multi-select.
 pub const ALL_SEASONS_ID: i32 = -1;
 
+/// Id of the "None" entry in a tags multi-select. Radarr and Sonarr number
+/// their tags from 1. Like [ALL_SEASONS_ID], it's mutually exclusive with the
+/// rest of the multi-select.
+pub const NO_TAG_ID: i32 = -2;
+
+/// How many downloads of a backend's queue are checked for problems, more than
+/// any healthy queue holds
+pub const QUEUE_PAGE_SIZE: i32 = 250;
+
 /// Represents the different ways we can capture a unique id for a menu selection
 /// Some objects in the backends have unique integer ids, while some are just string sentinel values
 #[derive(Debug, Clone)]
@@ -50,6 +74,16 @@ pub struct DropdownOption {
     pub description: Option<String>,
     /// Backend-specific id
     pub id: Option<SelectableId>,
+    /// Already on the backend (e.g. a downloaded season); highlighted where
+    /// the option is shown as a button
+    pub present: bool,
+    /// Shown under the menu while this option is picked, for detail that
+    /// doesn't fit the 100-character description
+    pub note: Option<String>,
+    /// Picking this has consequences the requester should know about (e.g.
+    /// monitoring nothing, so it never downloads); they're shown this and
+    /// asked to confirm before the request is made
+    pub warning: Option<String>,
 }
 
 /// Type of field for the request detail
@@ -80,6 +114,9 @@ pub struct RequestDetails {
     /// Show this field even when only a single option remains - single-option
     /// fields are otherwise hidden, as they represent admin-configured defaults
     pub always_show: bool,
+    /// What the backend already has for this field (e.g. which seasons are
+    /// downloaded), shown under its heading, one line each
+    pub status: Option<String>,
 }
 
 /// Represents the media selection box as presented by discord
@@ -90,13 +127,172 @@ pub struct MediaDisplayInfo {
     pub thumbnail_url: Option<String>,
 }
 
+/// An upcoming release, for the calendar digest
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CalendarEntry {
+    /// Release date, as YYYY-MM-DD
+    pub date: String,
+    /// When it airs, as a Unix timestamp, if the backend knows the time too
+    pub at: Option<u64>,
+    pub title: String,
+    /// What's being released, e.g. "S02E05 · Pilot" or "Digital release"
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Library maintenance an admin can trigger from Discord
+pub enum Maintenance {
+    /// Re-read metadata and rescan files, for one title or the whole library
+    Refresh,
+    /// Check the indexers' RSS feeds for new releases
+    RssSync,
+    /// Search the indexers for every monitored title that's still missing
+    SearchMissing,
+}
+
+/// Where a requested title stands in the download pipeline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadStatus {
+    /// Nothing grabbed yet, the indexers are still being searched
+    Searching,
+    /// A release was grabbed and is downloading, with the download client's
+    /// estimate of the time left when it has one
+    Grabbed { eta: Option<Duration> },
+    /// Already downloaded and imported
+    Downloaded,
+}
+
+/// One download in a backend's queue, to spot the ones an admin should look at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueItem {
+    /// The backend's ID for the queue entry
+    pub id: i32,
+    /// The release being downloaded
+    pub title: String,
+    /// Bytes left to download
+    pub size_left: u64,
+    /// Being downloaded right now, rather than waiting its turn or done, so
+    /// it should be making progress
+    pub downloading: bool,
+    /// What the backend says is wrong with it, when it's flagged
+    pub problem: Option<String>,
+}
+
+/// Who a title is, whichever backend found it: its IDs at the metadata
+/// providers the backends share, plus the backend's own ID when it has one.
+/// Backends know a title by different IDs (Radarr by TMDB, Sonarr by TVDB), so
+/// compare with [MediaId::matches] rather than `==`. TMDB numbers movies and
+/// series separately, so only compare IDs of the same kind of title.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MediaId {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tmdb: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tvdb: Option<i32>,
+    /// e.g. "tt0111161"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imdb: Option<String>,
+    /// The backend's internal ID (e.g. Radarr's movie ID), only meaningful to
+    /// the instance that gave it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend_id: Option<i32>,
+}
+
+impl MediaId {
+    /// Whether both name the same title by some shared metadata provider ID
+    pub fn matches(&self, other: &Self) -> bool {
+        fn same<T: PartialEq>(a: &Option<T>, b: &Option<T>) -> bool {
+            matches!((a, b), (Some(a), Some(b)) if a == b)
+        }
+        same(&self.tmdb, &other.tmdb)
+            || same(&self.tvdb, &other.tvdb)
+            || same(&self.imdb, &other.imdb)
+    }
+
+    /// No metadata provider ID, so nothing can be matched against it
+    pub fn is_empty(&self) -> bool {
+        self.tmdb.is_none() && self.tvdb.is_none() && self.imdb.is_none()
+    }
+}
+
+impl std::fmt::Display for MediaId {
+    /// The provider IDs, e.g. "tmdb:438631 imdb:tt1160419"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ids = [
+            self.tmdb.map(|id| format!("tmdb:{id}")),
+            self.tvdb.map(|id| format!("tvdb:{id}")),
+            self.imdb.as_ref().map(|id| format!("imdb:{id}")),
+        ];
+        f.write_str(&ids.into_iter().flatten().collect::<Vec<_>>().join(" "))
+    }
+}
+
+/// Parse the *arr queue's `timeleft` ("hh:mm:ss", with a "d." prefix past a day)
+pub fn parse_timeleft(timeleft: &str) -> Option<Duration> {
+    let (days, hms) = match timeleft.split_once('.') {
+        Some((days, hms)) => (days.parse::<u64>().ok()?, hms),
+        None => (0, timeleft),
+    };
+    let mut parts = hms.splitn(3, ':').map(str::parse::<u64>);
+    let (h, m, s) = (
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+    );
+    Some(Duration::from_secs(((days * 24 + h) * 60 + m) * 60 + s))
+}
+
+/// What's wrong with a queued download, from the *arr queue's error message
+/// or else its status messages, when the backend flagged it (`flagged`) or
+/// gave an error
+pub fn queue_problem(
+    flagged: bool,
+    error_message: Option<&str>,
+    status_messages: impl IntoIterator<Item = String>,
+) -> Option<String> {
+    let error_message = error_message.filter(|m| !m.trim().is_empty());
+    if !flagged && error_message.is_none() {
+        return None;
+    }
+    if let Some(message) = error_message {
+        return Some(message.trim().to_string());
+    }
+    let messages: Vec<_> = status_messages
+        .into_iter()
+        .filter(|m| !m.trim().is_empty())
+        .collect();
+    Some(if messages.is_empty() {
+        "flagged with no message".to_string()
+    } else {
+        messages.join("; ")
+    })
+}
+
+/// Who a request is made for, and where
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Requester {
+    /// Their Discord user ID
+    pub user_id: u64,
+    /// `None` for requests made outside a server
+    pub guild_id: Option<u64>,
+    /// The media command it was made with, e.g. "movie"
+    pub media: String,
+    /// Backend tags the title gets once it's added, e.g. its category's
+    pub tags: Vec<String>,
+}
+
 /// Represents the success block shown by discord
+#[derive(Clone)]
 pub struct SuccessMessage {
     /// Short one-liner identifying what was requested, e.g. "Title (Year) (Season 2)"
     /// Used as the heading and as OS notification content
     pub summary: String,
     pub description: String,
     pub thumbnail_url: Option<String>,
+    /// The options the request was made with, as one compact line (e.g.
+    /// "Quality Profile: HD-1080p · Root Folder: /movies"), so a misconfigured
+    /// request stands out. Filled in from the request form, not by backends.
+    pub details: Option<String>,
 }
 
 impl RequestDetails {
@@ -129,6 +325,27 @@ impl RequestDetails {
 pub trait MediaItem: Send + Sync + Debug {
     fn to_dropdown(&self) -> DropdownOption;
 
+    /// Release year (or first air year), used to rank results when the requester gives one
+    fn year(&self) -> Option<i32> {
+        None
+    }
+
+    /// Flagged as adult content by the metadata provider, so only shown
+    /// where NSFW content is allowed
+    fn is_adult(&self) -> bool {
+        false
+    }
+
+    /// Movie or series, when the backend says
+    fn kind(&self) -> Option<MediaKind> {
+        None
+    }
+
+    /// Parental rating, e.g. "PG-13" or "TV-MA"
+    fn certification(&self) -> Option<String> {
+        None
+    }
+
     fn as_any(&self) -> &dyn Any;
 
     fn into_any(self: Box<Self>) -> Box<dyn Any>;
@@ -162,17 +379,376 @@ pub trait MediaBackend: Send + Sync {
         &self,
         details: Vec<RequestDetails>,
         media: Box<dyn MediaItem>,
-        requester_discord_id: u64,
+        requester: &Requester,
     ) -> Result<()>;
 
     /// Build the success message including details about what was requested
     fn success_message(&self, details: &[RequestDetails], media: &dyn MediaItem) -> SuccessMessage;
+
+    /// Monitored releases from `start` to `end` (YYYY-MM-DD, inclusive).
+    /// Backends without a calendar have nothing to report.
+    async fn calendar(&self, _start: &str, _end: &str) -> Result<Vec<CalendarEntry>> {
+        Ok(vec![])
+    }
+
+    /// The backend's software version, from its status endpoint
+    async fn version(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Who a search result is, to recognize it again after it's been
+    /// requested (e.g. for [MediaBackend::download_status]). `None` when the
+    /// result carries no IDs.
+    fn media_id(&self, _media: &dyn MediaItem) -> Option<MediaId> {
+        None
+    }
+
+    /// Where the title with these IDs stands, `None` if it isn't in the
+    /// library or the backend can't track downloads
+    async fn download_status(&self, _id: &MediaId) -> Result<Option<DownloadStatus>> {
+        Ok(None)
+    }
+
+    /// Have the backend search its indexers again for the library title with
+    /// these IDs. Returns whether a search was queued: `false` when the title
+    /// isn't in the library or the backend can't search on demand.
+    async fn search_title(&self, _id: &MediaId) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// The downloads in the backend's queue. Backends without a queue have
+    /// nothing in it.
+    async fn queue(&self) -> Result<Vec<QueueItem>> {
+        Ok(vec![])
+    }
+
+    /// Tag the library title with these IDs, by tag label, creating the tags
+    /// the backend doesn't have yet. Backends without tags ignore them.
+    async fn apply_tags(&self, _id: &MediaId, _tags: &[String]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Queue a maintenance task, limited to the library titles matching
+    /// `title` where the task supports it. Returns what was queued.
+    async fn maintenance(&self, _task: Maintenance, _title: Option<&str>) -> Result<String> {
+        anyhow::bail!(UserFacingError(
+            "This backend has no maintenance commands.".into()
+        ))
+    }
+
+    /// Move the library titles matching `title` to the quality profile named
+    /// `quality_profile` (one on offer for requests), or keep theirs when
+    /// `None`, and search for releases better than the files they have.
+    /// Returns what was queued.
+    async fn upgrade(&self, _title: &str, _quality_profile: Option<&str>) -> Result<String> {
+        anyhow::bail!(UserFacingError("This backend can't upgrade titles.".into()))
+    }
+
+    /// Delete the library title with these IDs, and its files on disk when
+    /// `delete_files`
+    async fn remove(&self, _id: &MediaId, _delete_files: bool) -> Result<()> {
+        anyhow::bail!(UserFacingError("This backend can't remove titles.".into()))
+    }
+
+    /// How the backend's caches of its settings are doing, by what they hold
+    fn cache_stats(&self) -> Vec<(&'static str, cache::CacheStats)> {
+        vec![]
+    }
+}
+
+/// An item of a quality profile, a single quality or a group of them, as the
+/// Radarr and Sonarr APIs each describe it
+pub trait QualityItem: Sized {
+    fn allowed(&self) -> bool;
+    /// The item's own ID and name, which only groups have
+    fn group(&self) -> (Option<i32>, Option<String>);
+    /// The ID, name, and resolution of the quality it is, unless it's a group
+    fn quality(&self) -> Option<(Option<i32>, Option<String>, Option<i32>)>;
+    /// What's in the group, if it's one
+    fn items(&self) -> &[Self];
+}
+
+/// Walk a quality profile's items (and groups of items), collecting the
+/// resolutions it allows and the name of its cutoff quality or group
+pub fn collect_qualities<T: QualityItem>(
+    items: &[T],
+    group_allowed: bool,
+    cutoff: Option<i32>,
+    resolutions: &mut Vec<i32>,
+    cutoff_name: &mut Option<String>,
+) {
+    for item in items {
+        let allowed = group_allowed || item.allowed();
+        match item.quality() {
+            Some((id, name, resolution)) => {
+                if allowed {
+                    resolutions.extend(resolution);
+                }
+                if id.is_some() && id == cutoff {
+                    *cutoff_name = name;
+                }
+            }
+            None => {
+                let (id, name) = item.group();
+                if id.is_some() && id == cutoff {
+                    *cutoff_name = name;
+                }
+                collect_qualities(item.items(), allowed, cutoff, resolutions, cutoff_name);
+            }
+        }
+    }
+}
+
+/// A quality profile's allowed resolutions and upgrade cutoff, as a dropdown
+/// option description (e.g. "720p–1080p · upgrades until Bluray-1080p"), so
+/// requesters can tell profiles apart without knowing the *arr setup
+pub fn quality_profile_summary(
+    resolutions: &[i32],
+    cutoff: Option<&str>,
+    upgrade_allowed: bool,
+) -> Option<String> {
+    let known = resolutions.iter().copied().filter(|&r| r > 0);
+    let range = match (known.clone().min(), known.max()) {
+        (Some(lo), Some(hi)) if lo == hi => Some(format!("{lo}p")),
+        (Some(lo), Some(hi)) => Some(format!("{lo}p–{hi}p")),
+        _ => None,
+    };
+    let upgrades = match (upgrade_allowed, cutoff) {
+        (true, Some(cutoff)) => Some(format!("upgrades until {cutoff}")),
+        (true, None) => None,
+        (false, _) => Some("no upgrades".to_string()),
+    };
+    let parts: Vec<String> = range.into_iter().chain(upgrades).collect();
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+/// How many fields a search result's description can show before it's cut off
+pub const MAX_RESULT_FIELDS: usize = 2;
+
+/// The configured fields of search result descriptions, the year alone when
+/// none are configured
+pub fn result_fields(configured: Option<Vec<ResultField>>) -> Result<Vec<ResultField>> {
+    let fields = configured.unwrap_or_else(|| vec![ResultField::Year]);
+    if fields.len() > MAX_RESULT_FIELDS {
+        bail!(
+            "result_fields can only pick {MAX_RESULT_FIELDS} fields, not {}",
+            fields.len()
+        );
+    }
+    Ok(fields)
+}
+
+/// A search result's dropdown description: its `value` of each of `fields`
+/// that it has, in order (e.g. "2021 · Drama, Science Fiction")
+pub fn result_description(
+    fields: &[ResultField],
+    value: impl Fn(ResultField) -> Option<String>,
+) -> Option<String> {
+    let parts: Vec<String> = fields
+        .iter()
+        .filter_map(|&field| value(field))
+        .filter(|v| !v.is_empty())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+/// A runtime in minutes, e.g. "2h 35m"; `None` when the backend doesn't know
+/// it yet (0)
+pub fn runtime_label(minutes: i32) -> Option<String> {
+    match (minutes / 60, minutes % 60) {
+        _ if minutes <= 0 => None,
+        (0, m) => Some(format!("{m}m")),
+        (h, 0) => Some(format!("{h}h")),
+        (h, m) => Some(format!("{h}h {m}m")),
+    }
+}
+
+/// The first two genres, e.g. "Drama, Science Fiction"
+pub fn genres_label(genres: &[String]) -> Option<String> {
+    (!genres.is_empty()).then(|| {
+        genres
+            .iter()
+            .take(2)
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(", ")
+    })
+}
+
+/// The "Tags" choice for a backend's tags, by ID and label, starting on
+/// "None"; `None` when the backend has no tags. Requesters can pick several.
+/// A multi-select only holds so many options, so only the first tags are
+/// offered when the backend has more.
+pub fn tags_field(
+    metadata: &str,
+    tags: impl IntoIterator<Item = (i32, String)>,
+) -> Option<RequestDetails> {
+    let option = |id: i32, title: String| DropdownOption {
+        title,
+        description: None,
+        id: Some(SelectableId::Integer(id)),
+        present: false,
+        note: None,
+        warning: None,
+    };
+    let mut options: Vec<_> = tags
+        .into_iter()
+        .map(|(id, label)| option(id, label))
+        .collect();
+    if options.is_empty() {
+        return None;
+    }
+    if options.len() >= MAX_DROPDOWN_OPTIONS {
+        debug!(
+            field = metadata,
+            tags = options.len(),
+            offered = MAX_DROPDOWN_OPTIONS - 1,
+            "Too many tags for the request form, offering the first ones"
+        );
+        options.truncate(MAX_DROPDOWN_OPTIONS - 1);
+    }
+    options.insert(0, option(NO_TAG_ID, "None".to_string()));
+    Some(RequestDetails {
+        title: "Tags".to_string(),
+        options,
+        selected_indices: vec![0],
+        metadata: Some(metadata.to_string()),
+        field_type: FieldType::MultiSelect,
+        always_show: false,
+        status: None,
+    })
+}
+
+/// The tag IDs picked in a [tags_field], leaving out "None"
+pub fn picked_tags(field: &RequestDetails) -> Result<Vec<i32>> {
+    let mut picked = Vec::new();
+    for selection in field.selected_options() {
+        match &selection.id {
+            Some(SelectableId::Integer(NO_TAG_ID)) => {}
+            Some(SelectableId::Integer(id)) => picked.push(*id),
+            other => bail!("Tag must have an integer ID, got {other:?}"),
+        }
+    }
+    Ok(picked)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn tags_start_on_none() {
+        assert!(tags_field("radarr:tags", []).is_none());
+        let tags = [(3, "kids".to_string()), (5, "4k".to_string())];
+        let mut field = tags_field("radarr:tags", tags).unwrap();
+        assert_eq!(field.field_type, FieldType::MultiSelect);
+        assert!(picked_tags(&field).unwrap().is_empty());
+        field.selected_indices = vec![1, 2];
+        assert_eq!(picked_tags(&field).unwrap(), [3, 5]);
+
+        let many = (1..=40).map(|id| (id, format!("tag{id}")));
+        let field = tags_field("radarr:tags", many).unwrap();
+        assert_eq!(field.options.len(), MAX_DROPDOWN_OPTIONS);
+        assert_eq!(field.options[0].title, "None");
+    }
+
+    #[test]
+    fn timeleft_parses_with_and_without_days() {
+        assert_eq!(
+            parse_timeleft("00:35:12"),
+            Some(Duration::from_secs(35 * 60 + 12))
+        );
+        assert_eq!(
+            parse_timeleft("1.02:00:00"),
+            Some(Duration::from_secs(26 * 3600))
+        );
+        assert_eq!(parse_timeleft("soon"), None);
+    }
+
+    #[test]
+    fn queue_problems_prefer_the_error_message() {
+        let messages = || vec!["No files found are eligible for import".to_string()];
+        assert_eq!(queue_problem(false, None, messages()), None);
+        assert_eq!(queue_problem(false, Some(" "), messages()), None);
+        assert_eq!(
+            queue_problem(false, Some("Unpacking failed"), messages()).as_deref(),
+            Some("Unpacking failed")
+        );
+        assert_eq!(
+            queue_problem(true, None, messages()).as_deref(),
+            Some("No files found are eligible for import")
+        );
+        assert_eq!(
+            queue_problem(true, None, vec![]).as_deref(),
+            Some("flagged with no message")
+        );
+    }
+
+    #[test]
+    fn media_ids_match_on_any_shared_provider_id() {
+        let sonarr = MediaId {
+            tvdb: Some(81189),
+            tmdb: Some(1396),
+            imdb: Some("tt0903747".into()),
+            backend_id: Some(12),
+        };
+        let seerr = MediaId {
+            tmdb: Some(1396),
+            ..Default::default()
+        };
+        assert!(sonarr.matches(&seerr));
+        assert!(!seerr.matches(&MediaId::default()));
+        assert!(MediaId::default().is_empty());
+        assert_eq!(sonarr.to_string(), "tmdb:1396 tvdb:81189 imdb:tt0903747");
+    }
+
+    #[test]
+    fn result_descriptions_show_the_configured_fields() {
+        assert_eq!(result_fields(None).unwrap(), [ResultField::Year]);
+        assert!(
+            result_fields(Some(vec![
+                ResultField::Year,
+                ResultField::Status,
+                ResultField::Genres
+            ]))
+            .is_err()
+        );
+
+        let value = |field| match field {
+            ResultField::Year => Some("2021".to_string()),
+            ResultField::Runtime => runtime_label(155),
+            ResultField::Genres => genres_label(&["Drama".into(), "Sci-Fi".into(), "War".into()]),
+            ResultField::Status | ResultField::Network => None,
+        };
+        assert_eq!(
+            result_description(&[ResultField::Genres, ResultField::Runtime], value).as_deref(),
+            Some("Drama, Sci-Fi · 2h 35m")
+        );
+        assert_eq!(
+            result_description(&[ResultField::Network, ResultField::Year], value).as_deref(),
+            Some("2021")
+        );
+        assert_eq!(result_description(&[ResultField::Status], value), None);
+        assert_eq!(result_description(&[], value), None);
+
+        assert_eq!(runtime_label(0), None);
+        assert_eq!(runtime_label(45).as_deref(), Some("45m"));
+        assert_eq!(runtime_label(120).as_deref(), Some("2h"));
+    }
+
+    #[test]
+    fn quality_profile_summary_covers_range_and_cutoff() {
+        assert_eq!(
+            quality_profile_summary(&[720, 0, 1080, 1080], Some("Bluray-1080p"), true).as_deref(),
+            Some("720p–1080p · upgrades until Bluray-1080p")
+        );
+        assert_eq!(
+            quality_profile_summary(&[2160], Some("Remux-2160p"), false).as_deref(),
+            Some("2160p · no upgrades")
+        );
+        assert_eq!(quality_profile_summary(&[0], None, true), None);
+    }
+
     fn detail(field_type: FieldType, n_options: usize, selected: Vec<usize>) -> RequestDetails {
         RequestDetails {
             title: "Test".into(),
@@ -181,12 +757,16 @@ mod tests {
                     title: format!("opt{i}"),
                     description: None,
                     id: Some(SelectableId::Integer(i as i32)),
+                    present: false,
+                    note: None,
+                    warning: None,
                 })
                 .collect(),
             selected_indices: selected,
             metadata: None,
             field_type,
             always_show: false,
+            status: None,
         }
     }
 
@@ -0,0 +1,438 @@
+//! Backends provided by an external program, for services doplarr has no
+//! built-in support for (e.g. Mylar, Kavita)
+//!
+//! The plugin is started once and kept running. Doplarr writes one JSON
+//! request per line to its stdin, `{"method": ..., "params": ...}`, and reads
+//! one JSON reply per line from its stdout, `{"result": ...}` or
+//! `{"error": "message"}`. Requests are sent one at a time. stderr is passed
+//! through to doplarr's own, so plugins can log there. See
+//! README_DEVELOPER.md for the methods and their payloads.
+//!
+//! A plugin that exits or stops answering is started again on the next call.
+use super::{
+    DropdownOption, FieldType, MediaBackend, MediaDisplayInfo, MediaItem, RequestDetails,
+    Requester, SelectableId, SuccessMessage, UserFacingError,
+};
+use crate::config::BackendConfig;
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use std::{any::Any, collections::BTreeMap, process::Stdio, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    process::{Child, ChildStdin, ChildStdout, Command},
+    sync::Mutex,
+};
+use tracing::{info, warn};
+
+/// Version of the stdio protocol, sent in the `init` call so a plugin can
+/// refuse one it doesn't speak
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// How long a plugin gets to answer a call (default)
+const DEFAULT_CALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A search result, as the plugin describes it
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PluginItem {
+    /// The plugin's own ID for the item, passed back in later calls
+    pub id: String,
+    pub title: String,
+    pub year: Option<i32>,
+    /// Shown under the title in the results dropdown
+    pub description: Option<String>,
+    /// Plot or synopsis for the request card
+    pub overview: Option<String>,
+    pub thumbnail_url: Option<String>,
+    /// Already requested or available; ends the flow early
+    #[serde(default)]
+    pub requested: bool,
+}
+
+impl MediaItem for PluginItem {
+    fn to_dropdown(&self) -> DropdownOption {
+        DropdownOption {
+            title: self.title.clone(),
+            description: self
+                .description
+                .clone()
+                .or_else(|| self.year.map(|y| y.to_string())),
+            id: Some(SelectableId::String(self.id.clone())),
+            present: false,
+            note: None,
+            warning: None,
+        }
+    }
+
+    fn year(&self) -> Option<i32> {
+        self.year
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// A field of the request form, as the plugin describes it
+#[derive(Debug, Deserialize)]
+struct PluginField {
+    title: String,
+    #[serde(default)]
+    multiple: bool,
+    options: Vec<PluginOption>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PluginOption {
+    /// Sent back in `request` when picked
+    value: String,
+    /// Shown to the user; the value when absent
+    title: Option<String>,
+    description: Option<String>,
+}
+
+impl From<PluginField> for RequestDetails {
+    fn from(field: PluginField) -> Self {
+        RequestDetails {
+            metadata: Some(field.title.clone()),
+            title: field.title,
+            options: field
+                .options
+                .into_iter()
+                .map(|o| DropdownOption {
+                    title: o.title.unwrap_or_else(|| o.value.clone()),
+                    description: o.description,
+                    id: Some(SelectableId::String(o.value)),
+                    present: false,
+                    note: None,
+                    warning: None,
+                })
+                .collect(),
+            selected_indices: vec![],
+            field_type: if field.multiple {
+                FieldType::MultiSelect
+            } else {
+                FieldType::Dropdown
+            },
+            always_show: false,
+            status: None,
+        }
+    }
+}
+
+/// The values picked for each field, by field title
+fn selections(details: &[RequestDetails]) -> BTreeMap<String, Vec<String>> {
+    details
+        .iter()
+        .map(|detail| {
+            let picked: Vec<_> = match detail.field_type {
+                FieldType::MultiSelect => detail.selected_options().collect(),
+                _ => detail.selected_option().into_iter().collect(),
+            };
+            let values = picked
+                .into_iter()
+                .filter_map(|o| match &o.id {
+                    Some(SelectableId::String(v)) => Some(v.clone()),
+                    _ => None,
+                })
+                .collect();
+            (detail.title.clone(), values)
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct Reply {
+    result: Option<Value>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InitReply {
+    name: Option<String>,
+    version: Option<String>,
+}
+
+/// The running plugin process
+struct Process {
+    // Held so the process is killed when this is dropped
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+}
+
+pub struct Plugin {
+    command: String,
+    args: Vec<String>,
+    call_timeout: Duration,
+    process: Mutex<Option<Process>>,
+    version: Option<String>,
+}
+
+impl std::fmt::Debug for Plugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Plugin")
+            .field("command", &self.command)
+            .finish()
+    }
+}
+
+impl Plugin {
+    pub async fn connect(backend: BackendConfig, _client: reqwest::Client) -> Result<Self> {
+        let BackendConfig::Plugin {
+            command,
+            args,
+            timeout_secs,
+        } = backend
+        else {
+            bail!("Expected Plugin config");
+        };
+
+        let mut plugin = Self {
+            command,
+            args: args.unwrap_or_default(),
+            call_timeout: timeout_secs.map_or(DEFAULT_CALL_TIMEOUT, Duration::from_secs),
+            process: Mutex::new(None),
+            version: None,
+        };
+        let (process, init) = plugin.start().await?;
+        plugin.process = Mutex::new(Some(process));
+        info!(
+            command = plugin.command,
+            name = init.name.as_deref().unwrap_or("unnamed"),
+            "Connected to plugin"
+        );
+        plugin.version = init.version;
+        Ok(plugin)
+    }
+
+    fn spawn(&self) -> Result<Process> {
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start plugin \"{}\"", self.command))?;
+        let stdin = child.stdin.take().context("Plugin stdin unavailable")?;
+        let stdout = child.stdout.take().context("Plugin stdout unavailable")?;
+        Ok(Process {
+            _child: child,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+        })
+    }
+
+    /// Start the plugin and introduce ourselves
+    async fn start(&self) -> Result<(Process, InitReply)> {
+        let mut process = self.spawn()?;
+        let line = process
+            .exchange(
+                "init",
+                json!({"protocol": PROTOCOL_VERSION}),
+                self.call_timeout,
+            )
+            .await?;
+        let init = parse_reply(&line, "init")?;
+        Ok((process, init))
+    }
+
+    /// Make one call, starting the plugin first if it isn't running
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let mut guard = self.process.lock().await;
+        let process = match guard.as_mut() {
+            Some(process) => process,
+            None => guard.insert(self.start().await?.0),
+        };
+        match process.exchange(method, params, self.call_timeout).await {
+            Ok(line) => parse_reply(&line, method),
+            Err(e) => {
+                // Whatever state it's in, start it fresh next time
+                warn!(
+                    command = self.command,
+                    method, "Plugin call failed, restarting it"
+                );
+                *guard = None;
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Process {
+    /// Send one request and wait for its reply line
+    async fn exchange(&mut self, method: &str, params: Value, limit: Duration) -> Result<String> {
+        let exchange = async {
+            let mut line = serde_json::to_string(&json!({"method": method, "params": params}))?;
+            line.push('\n');
+            self.stdin.write_all(line.as_bytes()).await?;
+            self.stdin.flush().await?;
+            self.stdout
+                .next_line()
+                .await?
+                .context("Plugin exited without replying")
+        };
+        tokio::time::timeout(limit, exchange)
+            .await
+            .with_context(|| format!("Plugin didn't answer \"{method}\" in time"))?
+    }
+}
+
+fn parse_reply<T: DeserializeOwned>(line: &str, method: &str) -> Result<T> {
+    let reply: Reply = serde_json::from_str(line)
+        .with_context(|| format!("Plugin sent an invalid reply to \"{method}\""))?;
+    if let Some(error) = reply.error {
+        // The plugin's message is meant for the user
+        bail!(UserFacingError(error));
+    }
+    serde_json::from_value(reply.result.unwrap_or(Value::Null))
+        .with_context(|| format!("Plugin sent an unexpected result for \"{method}\""))
+}
+
+fn item(media: &dyn MediaItem) -> &PluginItem {
+    media
+        .as_any()
+        .downcast_ref::<PluginItem>()
+        .expect("Plugin given another backend's media")
+}
+
+#[async_trait]
+impl MediaBackend for Plugin {
+    async fn search(&self, term: &str) -> Result<Vec<Box<dyn MediaItem>>> {
+        let items: Vec<PluginItem> = self.call("search", json!({"term": term})).await?;
+        Ok(items
+            .into_iter()
+            .map(|i| Box::new(i) as Box<dyn MediaItem>)
+            .collect())
+    }
+
+    fn early_stop(&self, media: &dyn MediaItem) -> bool {
+        item(media).requested
+    }
+
+    fn display_info(&self, media: &dyn MediaItem) -> MediaDisplayInfo {
+        let item = item(media);
+        MediaDisplayInfo {
+            title: item.title.clone(),
+            subtitle: item.year.map(|y| y.to_string()),
+            description: item.overview.clone(),
+            thumbnail_url: item.thumbnail_url.clone(),
+        }
+    }
+
+    async fn additional_details(&self, media: &dyn MediaItem) -> Result<Vec<RequestDetails>> {
+        let fields: Option<Vec<PluginField>> =
+            self.call("details", json!({"id": item(media).id})).await?;
+        Ok(fields
+            .into_iter()
+            .flatten()
+            .map(RequestDetails::from)
+            .collect())
+    }
+
+    async fn request(
+        &self,
+        details: Vec<RequestDetails>,
+        media: Box<dyn MediaItem>,
+        requester: &Requester,
+    ) -> Result<()> {
+        let _: Value = self
+            .call(
+                "request",
+                json!({
+                    "id": item(&*media).id,
+                    "selections": selections(&details),
+                    "requester": requester.user_id.to_string(),
+                }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    fn success_message(
+        &self,
+        _details: &[RequestDetails],
+        media: &dyn MediaItem,
+    ) -> SuccessMessage {
+        let item = item(media);
+        let summary = match item.year {
+            Some(year) => format!("{} ({year})", item.title),
+            None => item.title.clone(),
+        };
+        SuccessMessage {
+            summary,
+            description: "Request sent.".to_string(),
+            thumbnail_url: item.thumbnail_url.clone(),
+            details: None,
+        }
+    }
+
+    async fn version(&self) -> Result<Option<String>> {
+        Ok(self.version.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn plugin_is_called_over_stdio_and_restarted() {
+        // Answers init, one search, one failing call, then exits
+        let script = r#"
+            read l; echo '{"result": {"name": "test", "version": "1.0"}}'
+            read l; echo '{"result": [{"id": "7", "title": "Saga", "year": 2012}]}'
+            read l; echo '{"error": "Nope"}'
+        "#;
+        let config = BackendConfig::Plugin {
+            command: "sh".into(),
+            args: Some(vec!["-c".into(), script.into()]),
+            timeout_secs: None,
+        };
+        let plugin = Plugin::connect(config, reqwest::Client::new())
+            .await
+            .unwrap();
+        assert_eq!(plugin.version().await.unwrap().as_deref(), Some("1.0"));
+
+        let results = plugin.search("saga").await.unwrap();
+        assert_eq!(item(&*results[0]).title, "Saga");
+        let e = plugin.search("saga").await.unwrap_err();
+        assert_eq!(e.downcast_ref::<UserFacingError>().unwrap().0, "Nope");
+
+        // It has exited by now; the call fails and the next one starts it again
+        assert!(plugin.search("saga").await.is_err());
+        assert_eq!(plugin.search("saga").await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn fields_round_trip_to_selections() {
+        let fields: Vec<PluginField> = serde_json::from_str(
+            r#"[
+                {"title": "Format", "options": [{"value": "cbz"}, {"value": "pdf", "title": "PDF"}]},
+                {"title": "Issues", "multiple": true, "options": [
+                    {"value": "1"}, {"value": "2"}, {"value": "3"}
+                ]}
+            ]"#,
+        )
+        .unwrap();
+        let mut details: Vec<RequestDetails> = fields.into_iter().map(Into::into).collect();
+        assert_eq!(details[0].options[1].title, "PDF");
+        assert_eq!(details[1].field_type, FieldType::MultiSelect);
+
+        details[0].selected_indices = vec![1];
+        details[1].selected_indices = vec![0, 2];
+        assert_eq!(
+            selections(&details),
+            BTreeMap::from([
+                ("Format".to_string(), vec!["pdf".to_string()]),
+                ("Issues".to_string(), vec!["1".to_string(), "3".to_string()]),
+            ])
+        );
+    }
+}
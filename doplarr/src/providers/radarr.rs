@@ -1,19 +1,35 @@
-use super::*;
-use crate::config::BackendConfig;
+use super::{
+    Requester, base_url,
+    cache::{Refreshing, SETTINGS_TTL},
+    *,
+};
+use crate::config::{BackendConfig, ResultField};
 use anyhow::{Context, Result, bail};
 use async_trait::async_trait;
 use radarr_api::{
     apis::{
         Error as RadarrApiError,
+        calendar_api::api_v3_calendar_get,
+        command_api::api_v3_command_post_custom,
         configuration::{ApiKey, Configuration},
-        movie_api::api_v3_movie_post,
+        metadata_config_api::api_v3_config_metadata_get,
+        movie_api::{
+            api_v3_movie_get, api_v3_movie_id_delete, api_v3_movie_id_put, api_v3_movie_post,
+        },
         movie_lookup_api::api_v3_movie_lookup_get,
         quality_profile_api::api_v3_qualityprofile_get,
+        queue_api::api_v3_queue_get,
         root_folder_api::api_v3_rootfolder_get,
+        system_api::api_v3_system_status_get,
+        tag_api::{api_v3_tag_get, api_v3_tag_post},
+    },
+    commands::{
+        MissingMoviesSearchCommand, MoviesSearchCommand, RefreshMovieCommand, RssSyncCommand,
     },
     models::{
-        AddMovieOptions, MonitorTypes, MovieResource, MovieStatusType, QualityProfileResource,
-        RootFolderResource,
+        AddMovieOptions, MonitorTypes, MovieResource, MovieStatusType,
+        QualityProfileQualityItemResource, QualityProfileResource, QueueStatus, RootFolderResource,
+        TagResource, TrackedDownloadStatus,
     },
 };
 use tracing::{debug, error, info, trace, warn};
@@ -39,6 +55,26 @@ fn log_api_error<T: std::fmt::Debug>(err: &RadarrApiError<T>, context: &str) {
     }
 }
 
+/// Check the configured URL reaches Radarr's API, so a wrong path prefix fails
+/// at connect time rather than as a 404 on every request
+async fn check_base_path(config: &Configuration) -> Result<()> {
+    match api_v3_system_status_get(config).await {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            let status = match &e {
+                RadarrApiError::ResponseError(r) => Some(r.status),
+                _ => None,
+            };
+            let not_json = matches!(e, RadarrApiError::Serde(_));
+            if base_url::is_wrong_prefix(status, not_json) {
+                return Err(base_url::wrong_prefix("Radarr", &config.base_path));
+            }
+            log_api_error(&e, "Failed to get Radarr status");
+            Err(e.into())
+        }
+    }
+}
+
 /// Treat a 2xx response whose body fails to parse as success - by the time we're
 /// reading the body, Radarr has already applied the change
 fn tolerate_response_parse_error<T, E>(
@@ -68,17 +104,31 @@ where
 pub struct Radarr {
     config: Configuration,
     details: Details,
+    /// Radarr's certification country, e.g. "US", which its release dates
+    /// are for
+    release_region: Option<String>,
+    /// What search results show under their titles
+    result_fields: Vec<ResultField>,
 }
 
 #[derive(Debug, Clone)]
 // All the details we want to collect
 pub struct Details {
-    rootfolders: Vec<RootFolderResource>,
-    quality_profiles: Vec<QualityProfileResource>,
+    choices: Refreshing<Choices>,
     monitor: Vec<MonitorTypes>,
     minimum_availability: Vec<MovieStatusType>,
 }
 
+#[derive(Debug)]
+/// The root folders and quality profiles Radarr has, which can change while
+/// we run
+struct Choices {
+    rootfolders: Vec<RootFolderResource>,
+    quality_profiles: Vec<QualityProfileResource>,
+    /// Empty unless requesters may tag what they request
+    tags: Vec<TagResource>,
+}
+
 #[derive(Debug)]
 // The final details needed to complete the request
 pub struct SelectedDetails {
@@ -86,46 +136,36 @@ pub struct SelectedDetails {
     pub quality_profile_id: i32,
     pub monitor: MonitorTypes,
     pub minimum_availability: MovieStatusType,
+    pub tag_ids: Vec<i32>,
 }
 
-impl Radarr {
-    /// Builds the Radarr connection and attempts to use it
-    pub async fn new(
-        base_path: String,
-        key: String,
-        monitor_type: Option<MonitorTypes>,
-        quality_profile: Option<String>,
+impl Choices {
+    /// Fetch what Radarr offers, narrowed down to what the config pins
+    async fn fetch(
+        config: &Configuration,
         rootfolder: Option<String>,
-        minimum_availability: Option<MovieStatusType>,
-        client: reqwest::Client,
+        quality_profile: Option<String>,
+        with_tags: bool,
     ) -> Result<Self> {
-        // Log connection before moving base_path
-        info!("Connecting to Radarr at {}", base_path);
-
-        // Build the API config
-        let config = Configuration {
-            base_path,
-            user_agent: None,
-            client,
-            basic_auth: None,
-            oauth_access_token: None,
-            bearer_access_token: None,
-            api_key: Some(ApiKey { prefix: None, key }),
-        };
-
-        // Grab the additional details and use the config data to filter
-
-        // First query the things we have to check (this will fail if we can't connect to the server anyway)
-        let mut rootfolders = api_v3_rootfolder_get(&config).await.inspect_err(|e| {
+        let mut rootfolders = api_v3_rootfolder_get(config).await.inspect_err(|e| {
             log_api_error(e, "Failed to get root folders from Radarr");
         })?;
         trace!("Retrieved {} root folders", rootfolders.len());
 
-        let mut quality_profiles = api_v3_qualityprofile_get(&config).await.inspect_err(|e| {
+        let mut quality_profiles = api_v3_qualityprofile_get(config).await.inspect_err(|e| {
             log_api_error(e, "Failed to get quality profiles from Radarr");
         })?;
         trace!("Retrieved {} quality profiles", quality_profiles.len());
 
+        let tags = if with_tags {
+            api_v3_tag_get(config)
+                .await
+                .inspect_err(|e| log_api_error(e, "Failed to get Radarr tags"))?
+        } else {
+            Vec::new()
+        };
+        trace!("Retrieved {} tags", tags.len());
+
         // Select rootfolder if given
         if let Some(rf) = rootfolder {
             // Get the index of the selection
@@ -168,6 +208,56 @@ impl Radarr {
             quality_profiles = vec![selected];
         }
 
+        Ok(Self {
+            rootfolders,
+            quality_profiles,
+            tags,
+        })
+    }
+}
+
+impl Radarr {
+    /// Builds the Radarr connection and attempts to use it
+    #[allow(clippy::too_many_arguments)]
+    pub async fn new(
+        base_path: String,
+        key: String,
+        monitor_type: Option<MonitorTypes>,
+        quality_profile: Option<String>,
+        rootfolder: Option<String>,
+        minimum_availability: Option<MovieStatusType>,
+        allow_unmonitored: bool,
+        allow_tags: bool,
+        result_fields: Option<Vec<ResultField>>,
+        client: reqwest::Client,
+    ) -> Result<Self> {
+        let result_fields = super::result_fields(result_fields)?;
+        let base_path = base_url::normalize(&base_path);
+        info!("Connecting to Radarr at {}", base_path);
+
+        // Build the API config
+        let config = Configuration {
+            base_path,
+            user_agent: None,
+            client,
+            basic_auth: None,
+            oauth_access_token: None,
+            bearer_access_token: None,
+            api_key: Some(ApiKey { prefix: None, key }),
+        };
+        check_base_path(&config).await?;
+
+        // Grab the additional details and use the config data to filter
+        let choices = Refreshing::new("root folders, quality profiles and tags", SETTINGS_TTL, {
+            let config = config.clone();
+            move || {
+                let (config, rootfolder, quality_profile) =
+                    (config.clone(), rootfolder.clone(), quality_profile.clone());
+                async move { Choices::fetch(&config, rootfolder, quality_profile, allow_tags).await }
+            }
+        })
+        .await?;
+
         let minimum_availability = if let Some(x) = minimum_availability {
             vec![x]
         } else {
@@ -180,25 +270,40 @@ impl Radarr {
             ]
         };
 
-        let monitor = if let Some(x) = monitor_type {
-            vec![x]
-        } else {
-            vec![
+        let monitor = match monitor_type {
+            Some(MonitorTypes::None) if !allow_unmonitored => {
+                bail!("monitor_type = \"none\" can't be used with allow_unmonitored = false")
+            }
+            Some(x) => vec![x],
+            None if allow_unmonitored => vec![
                 MonitorTypes::MovieAndCollection,
                 MonitorTypes::MovieOnly,
                 MonitorTypes::None,
-            ]
+            ],
+            None => vec![MonitorTypes::MovieAndCollection, MonitorTypes::MovieOnly],
         };
 
         // Build the details
         let details = Details {
-            rootfolders,
-            quality_profiles,
+            choices,
             monitor,
             minimum_availability,
         };
 
-        Ok(Self { config, details })
+        // Not worth failing over; the dates just go unlabeled
+        let release_region = api_v3_config_metadata_get(&config)
+            .await
+            .inspect_err(|e| log_api_error(e, "Failed to get Radarr's metadata settings"))
+            .ok()
+            .and_then(|m| m.certification_country)
+            .map(|c| c.to_string().to_uppercase());
+
+        Ok(Self {
+            config,
+            details,
+            release_region,
+            result_fields,
+        })
     }
 
     pub async fn connect(backend: BackendConfig, client: reqwest::Client) -> Result<Self> {
@@ -209,6 +314,9 @@ impl Radarr {
             quality_profile,
             rootfolder,
             minimum_availability,
+            allow_unmonitored,
+            allow_tags,
+            result_fields,
         } = backend
         {
             Self::new(
@@ -218,6 +326,9 @@ impl Radarr {
                 quality_profile,
                 rootfolder,
                 minimum_availability,
+                allow_unmonitored.unwrap_or(true),
+                allow_tags.unwrap_or(false),
+                result_fields,
                 client,
             )
             .await
@@ -225,6 +336,99 @@ impl Radarr {
             bail!("Configured backend not for Radarr");
         }
     }
+
+    /// Names of the quality profiles on offer, e.g. for `doplarr setup`
+    pub fn quality_profile_names(&self) -> Vec<String> {
+        self.details
+            .choices
+            .get()
+            .quality_profiles
+            .iter()
+            .filter_map(|p| p.name.clone().flatten())
+            .collect()
+    }
+
+    /// The ID of the quality profile on offer called `name`
+    fn quality_profile_id(&self, name: &str) -> Result<i32> {
+        self.details
+            .choices
+            .get()
+            .quality_profiles
+            .iter()
+            .find(|p| p.name.as_ref().and_then(|n| n.as_deref()) == Some(name))
+            .and_then(|p| p.id)
+            .ok_or_else(|| {
+                UserFacingError(format!(
+                    "There's no \"{name}\" quality profile. Pick one of: {}.",
+                    self.quality_profile_names().join(", ")
+                ))
+                .into()
+            })
+    }
+
+    /// The library's movies titled `title`, ignoring case
+    async fn movies_titled(&self, title: &str) -> Result<Vec<MovieResource>> {
+        let movies = api_v3_movie_get(&self.config, None, Some(true), None)
+            .await
+            .inspect_err(|e| log_api_error(e, "Failed to list Radarr movies"))?;
+        let movies: Vec<_> = movies
+            .into_iter()
+            .filter(|m| {
+                m.title
+                    .as_ref()
+                    .and_then(|t| t.as_deref())
+                    .is_some_and(|t| t.eq_ignore_ascii_case(title))
+            })
+            .collect();
+        if movies.is_empty() {
+            bail!(UserFacingError(format!(
+                "No movie titled \"{title}\" in Radarr."
+            )));
+        }
+        Ok(movies)
+    }
+
+    /// Paths of the root folders on offer
+    pub fn rootfolder_paths(&self) -> Vec<String> {
+        self.details
+            .choices
+            .get()
+            .rootfolders
+            .iter()
+            .filter_map(|f| f.path.clone().flatten())
+            .collect()
+    }
+
+    /// IDs of the tags with these labels, creating the missing ones. Radarr
+    /// matches labels without regard to case.
+    async fn tag_ids(&self, labels: &[String]) -> Result<Vec<i32>> {
+        let existing = api_v3_tag_get(&self.config)
+            .await
+            .inspect_err(|e| log_api_error(e, "Failed to get Radarr tags"))?;
+        let mut ids = Vec::with_capacity(labels.len());
+        for label in labels {
+            let found = existing.iter().find(|t| {
+                t.label
+                    .as_ref()
+                    .and_then(|l| l.as_deref())
+                    .is_some_and(|l| l.eq_ignore_ascii_case(label))
+            });
+            let tag = match found {
+                Some(tag) => tag.clone(),
+                None => api_v3_tag_post(
+                    &self.config,
+                    Some(TagResource {
+                        id: None,
+                        label: Some(Some(label.clone())),
+                    }),
+                )
+                .await
+                .inspect_err(|e| log_api_error(e, "Failed to create Radarr tag"))?,
+            };
+            ids.extend(tag.id);
+        }
+        Ok(ids)
+    }
 }
 
 /// Helper function to get to and from stringified references
@@ -238,11 +442,90 @@ mod field_keys {
     pub const MONITOR: &str = "radarr:monitor";
     pub const AVAILABILITY: &str = "radarr:availability";
     pub const QUALITY_PROFILE: &str = "radarr:quality_profile";
+    pub const TAGS: &str = "radarr:tags";
+}
+
+impl QualityItem for QualityProfileQualityItemResource {
+    fn allowed(&self) -> bool {
+        self.allowed.unwrap_or(false)
+    }
+
+    fn group(&self) -> (Option<i32>, Option<String>) {
+        (self.id, self.name.clone().flatten())
+    }
+
+    fn quality(&self) -> Option<(Option<i32>, Option<String>, Option<i32>)> {
+        let quality = self.quality.as_ref()?;
+        Some((
+            quality.id,
+            quality.name.clone().flatten(),
+            quality.resolution,
+        ))
+    }
+
+    fn items(&self) -> &[Self] {
+        self.items
+            .as_ref()
+            .and_then(|i| i.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+fn profile_summary(profile: &QualityProfileResource) -> Option<String> {
+    let mut resolutions = Vec::new();
+    let mut cutoff_name = None;
+    let items = profile.items.as_ref().and_then(|i| i.as_deref());
+    collect_qualities(
+        items.unwrap_or_default(),
+        false,
+        profile.cutoff,
+        &mut resolutions,
+        &mut cutoff_name,
+    );
+    quality_profile_summary(
+        &resolutions,
+        cutoff_name.as_deref(),
+        profile.upgrade_allowed.unwrap_or(false),
+    )
+}
+
+/// How many custom formats to name in a profile's note, by largest score
+const NOTABLE_CUSTOM_FORMATS: usize = 4;
+
+/// The custom formats a profile scores most strongly for and against, e.g.
+/// "Prefers DV (+1500), HDR10+ (+1000) · Avoids x265 (-10000)"
+fn custom_format_note(profile: &QualityProfileResource) -> Option<String> {
+    let mut scored: Vec<(String, i32)> = profile
+        .format_items
+        .iter()
+        .flatten()
+        .flatten()
+        .filter_map(|f| Some((f.name.clone().flatten()?, f.score.filter(|&s| s != 0)?)))
+        .collect();
+    scored.sort_by_key(|(_, score)| std::cmp::Reverse(score.abs()));
+    scored.truncate(NOTABLE_CUSTOM_FORMATS);
+
+    let list = |positive: bool| {
+        scored
+            .iter()
+            .filter(|(_, score)| (*score > 0) == positive)
+            .map(|(name, score)| format!("{name} ({score:+})"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let (prefers, avoids) = (list(true), list(false));
+    let parts: Vec<String> = [("Prefers", prefers), ("Avoids", avoids)]
+        .into_iter()
+        .filter(|(_, list)| !list.is_empty())
+        .map(|(verb, list)| format!("{verb} {list}"))
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(" · "))
 }
 
 impl From<Details> for Vec<RequestDetails> {
     fn from(details: Details) -> Vec<RequestDetails> {
-        let quality_profile_options = details
+        let choices = details.choices.get();
+        let quality_profile_options = choices
             .quality_profiles
             .iter()
             .filter_map(|x| {
@@ -252,8 +535,11 @@ impl From<Details> for Vec<RequestDetails> {
                 }
                 name.map(|n| DropdownOption {
                     title: n,
-                    description: None,
+                    description: profile_summary(x),
                     id: x.id.map(SelectableId::Integer),
+                    present: false,
+                    note: custom_format_note(x),
+                    warning: None,
                 })
             })
             .collect();
@@ -265,9 +551,10 @@ impl From<Details> for Vec<RequestDetails> {
             selected_indices: vec![],
             field_type: FieldType::Dropdown,
             always_show: false,
+            status: None,
         };
 
-        let rootfolder_options = details
+        let rootfolder_options = choices
             .rootfolders
             .iter()
             .filter_map(|x| {
@@ -279,6 +566,9 @@ impl From<Details> for Vec<RequestDetails> {
                     title: p,
                     description: None,
                     id: x.id.map(SelectableId::Integer),
+                    present: false,
+                    note: None,
+                    warning: None,
                 })
             })
             .collect();
@@ -290,6 +580,7 @@ impl From<Details> for Vec<RequestDetails> {
             selected_indices: vec![],
             field_type: FieldType::Dropdown,
             always_show: false,
+            status: None,
         };
 
         let monitor_options = details
@@ -306,6 +597,13 @@ impl From<Details> for Vec<RequestDetails> {
                     title: title.to_string(),
                     description: None,
                     id: Some(SelectableId::String(x.to_string())),
+                    present: false,
+                    note: None,
+                    warning: (*x == MonitorTypes::None).then(|| {
+                        "Radarr will add the movie without monitoring it, so it's never \
+                         searched for or downloaded."
+                            .to_string()
+                    }),
                 }
             })
             .collect();
@@ -317,24 +615,19 @@ impl From<Details> for Vec<RequestDetails> {
             selected_indices: vec![],
             field_type: FieldType::Dropdown,
             always_show: false,
+            status: None,
         };
 
         let availability_options = details
             .minimum_availability
             .iter()
-            .map(|x| {
-                let title = match x {
-                    MovieStatusType::Announced => "Announced",
-                    MovieStatusType::InCinemas => "In Cinemas",
-                    MovieStatusType::Released => "Released",
-                    MovieStatusType::Tba => "To Be Announced",
-                    MovieStatusType::Deleted => "Deleted",
-                };
-                DropdownOption {
-                    title: title.to_string(),
-                    description: None,
-                    id: Some(SelectableId::String(x.to_string())),
-                }
+            .map(|x| DropdownOption {
+                title: status_label(*x).to_string(),
+                description: None,
+                id: Some(SelectableId::String(x.to_string())),
+                present: false,
+                note: None,
+                warning: None,
             })
             .collect();
 
@@ -345,14 +638,97 @@ impl From<Details> for Vec<RequestDetails> {
             selected_indices: vec![],
             field_type: FieldType::Dropdown,
             always_show: false,
+            status: None,
         };
 
-        vec![
+        let tags_details = tags_field(
+            field_keys::TAGS,
+            choices
+                .tags
+                .iter()
+                .filter_map(|t| Some((t.id?, t.label.clone().flatten()?))),
+        );
+
+        let mut details = vec![
             rootfolder_details,
             monitor_details,
             availability_details,
             quality_profile_details,
-        ]
+        ];
+        details.extend(tags_details);
+        details
+    }
+}
+
+/// How a movie's release status reads, e.g. on the minimum availability options
+fn status_label(status: MovieStatusType) -> &'static str {
+    match status {
+        MovieStatusType::Announced => "Announced",
+        MovieStatusType::InCinemas => "In Cinemas",
+        MovieStatusType::Released => "Released",
+        MovieStatusType::Tba => "To Be Announced",
+        MovieStatusType::Deleted => "Deleted",
+    }
+}
+
+/// A release date from Radarr (an ISO timestamp) as e.g. "Apr 16, 2024"
+fn release_day(date: &str) -> Option<String> {
+    let date = crate::export::parse_calendar_date(date.get(..10)?)?;
+    Some(format!(
+        "{} {}, {}",
+        &date.month().to_string()[..3],
+        date.day(),
+        date.year()
+    ))
+}
+
+/// The movie's digital and physical release dates for the details card, e.g.
+/// "Digital Apr 16, 2024 · Physical TBA (US)"
+fn release_dates(movie: &MovieResource, region: Option<&str>) -> String {
+    let day = |date: &Option<Option<String>>| {
+        date.as_ref()
+            .and_then(|d| release_day(d.as_deref()?))
+            .unwrap_or_else(|| "TBA".to_string())
+    };
+    let dates = format!(
+        "Digital {} · Physical {}",
+        day(&movie.digital_release),
+        day(&movie.physical_release)
+    );
+    match region {
+        Some(region) => format!("{dates} ({region})"),
+        None => dates,
+    }
+}
+
+/// What a minimum availability means for this movie: from when Radarr grabs
+/// releases of it
+fn availability_description(
+    availability: MovieStatusType,
+    movie: &MovieResource,
+) -> Option<String> {
+    let released = [&movie.digital_release, &movie.physical_release]
+        .into_iter()
+        .filter_map(|d| d.as_ref()?.as_deref())
+        .min()
+        .and_then(release_day);
+    let in_cinemas = movie
+        .in_cinemas
+        .as_ref()
+        .and_then(|d| release_day(d.as_deref()?));
+    match availability {
+        MovieStatusType::Tba | MovieStatusType::Announced => {
+            Some("Grab releases right away, even before it's out".to_string())
+        }
+        MovieStatusType::InCinemas => Some(match in_cinemas {
+            Some(day) => format!("Grab releases once it's in cinemas ({day})"),
+            None => "Grab releases once it's in cinemas".to_string(),
+        }),
+        MovieStatusType::Released => Some(match released {
+            Some(day) => format!("Grab releases once it's out digitally or on disc ({day})"),
+            None => "Grab releases once it's out digitally or on disc".to_string(),
+        }),
+        MovieStatusType::Deleted => None,
     }
 }
 
@@ -364,8 +740,15 @@ impl TryFrom<Vec<RequestDetails>> for SelectedDetails {
         let mut quality_profile_id = None;
         let mut monitor = None;
         let mut minimum_availability = None;
+        let mut tag_ids = Vec::new();
 
         for detail in &details {
+            // Tags are multi-select, and picking none is fine
+            if detail.metadata.as_deref() == Some(field_keys::TAGS) {
+                tag_ids = picked_tags(detail)?;
+                continue;
+            }
+
             let Some(selection) = detail.selected_option() else {
                 bail!("No option was selected for '{}'", detail.title);
             };
@@ -402,6 +785,7 @@ impl TryFrom<Vec<RequestDetails>> for SelectedDetails {
             monitor: monitor.context("No monitor type was selected")?,
             minimum_availability: minimum_availability
                 .context("No minimum availability was selected")?,
+            tag_ids,
         })
     }
 }
@@ -412,9 +796,27 @@ impl MediaItem for MovieResource {
             title: self.title.clone().flatten().unwrap_or_default(),
             description: self.year.map(|y| y.to_string()),
             id: self.id.map(SelectableId::Integer),
+            present: false,
+            note: None,
+            warning: None,
         }
     }
 
+    fn year(&self) -> Option<i32> {
+        self.year
+    }
+
+    fn kind(&self) -> Option<MediaKind> {
+        Some(MediaKind::Movie)
+    }
+
+    fn certification(&self) -> Option<String> {
+        self.certification
+            .clone()
+            .flatten()
+            .filter(|c| !c.is_empty())
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -426,6 +828,11 @@ impl MediaItem for MovieResource {
 
 #[async_trait]
 impl MediaBackend for Radarr {
+    fn cache_stats(&self) -> Vec<(&'static str, cache::CacheStats)> {
+        let choices = &self.details.choices;
+        vec![(choices.name(), choices.stats())]
+    }
+
     async fn search(&self, term: &str) -> Result<Vec<Box<dyn MediaItem>>> {
         info!("Searching Radarr for movie: {}", term);
         let results = api_v3_movie_lookup_get(&self.config, Some(term))
@@ -440,6 +847,36 @@ impl MediaBackend for Radarr {
             .collect())
     }
 
+    fn to_dropdown_options(&self, results: &[Box<dyn MediaItem>]) -> Vec<DropdownOption> {
+        results
+            .iter()
+            .map(|result| {
+                let mut option = result.to_dropdown();
+                if let Some(movie) = result.as_any().downcast_ref::<MovieResource>() {
+                    option.description =
+                        result_description(&self.result_fields, |field| match field {
+                            ResultField::Year => {
+                                movie.year.filter(|&y| y > 0).map(|y| y.to_string())
+                            }
+                            ResultField::Status => {
+                                movie.status.map(|s| status_label(s).to_string())
+                            }
+                            ResultField::Network => movie.studio.clone().flatten(),
+                            ResultField::Runtime => movie.runtime.and_then(runtime_label),
+                            ResultField::Genres => genres_label(
+                                movie
+                                    .genres
+                                    .as_ref()
+                                    .and_then(|g| g.as_deref())
+                                    .unwrap_or_default(),
+                            ),
+                        });
+                }
+                option
+            })
+            .collect()
+    }
+
     fn early_stop(&self, media: &dyn MediaItem) -> bool {
         media
             .as_any()
@@ -459,23 +896,42 @@ impl MediaBackend for Radarr {
             };
         };
 
+        let dates = release_dates(media, self.release_region.as_deref());
         MediaDisplayInfo {
             title: media.title.clone().flatten().unwrap_or_default(),
-            subtitle: media.year.map(|y| y.to_string()),
+            subtitle: Some(match media.year {
+                Some(year) => format!("{year} · {dates}"),
+                None => dates,
+            }),
             description: media.overview.clone().flatten(),
             thumbnail_url: media.remote_poster.clone().flatten(),
         }
     }
 
-    async fn additional_details(&self, _media: &dyn MediaItem) -> Result<Vec<RequestDetails>> {
-        Ok(self.details.clone().into())
+    async fn additional_details(&self, media: &dyn MediaItem) -> Result<Vec<RequestDetails>> {
+        let mut details: Vec<RequestDetails> = self.details.clone().into();
+        // Spell out what each availability means in terms of this movie's dates
+        if let Some(movie) = media.as_any().downcast_ref::<MovieResource>()
+            && let Some(field) = details
+                .iter_mut()
+                .find(|d| d.metadata.as_deref() == Some(field_keys::AVAILABILITY))
+        {
+            for (option, availability) in field
+                .options
+                .iter_mut()
+                .zip(&self.details.minimum_availability)
+            {
+                option.description = availability_description(*availability, movie);
+            }
+        }
+        Ok(details)
     }
 
     async fn request(
         &self,
         details: Vec<RequestDetails>,
         media: Box<dyn MediaItem>,
-        _requester_discord_id: u64,
+        _requester: &Requester,
     ) -> Result<()> {
         let selected = SelectedDetails::try_from(details)?;
 
@@ -494,6 +950,13 @@ impl MediaBackend for Radarr {
         media.quality_profile_id = Some(selected.quality_profile_id);
         media.minimum_availability = Some(selected.minimum_availability);
         media.root_folder_path = Some(Some(selected.rootfolder_path.clone()));
+        if !selected.tag_ids.is_empty() {
+            media
+                .tags
+                .get_or_insert_default()
+                .get_or_insert_default()
+                .extend(&selected.tag_ids);
+        }
 
         if selected.monitor != MonitorTypes::None {
             media.monitored = Some(true);
@@ -533,6 +996,7 @@ impl MediaBackend for Radarr {
                 summary: "Request submitted".into(),
                 description: "Will be downloaded when available.".into(),
                 thumbnail_url: None,
+                details: None,
             };
         };
 
@@ -542,13 +1006,320 @@ impl MediaBackend for Radarr {
             summary: format!("{title} ({year})"),
             description: "Will be downloaded when available.".to_string(),
             thumbnail_url: media.remote_poster.clone().flatten(),
+            details: None,
+        }
+    }
+
+    async fn calendar(&self, start: &str, end: &str) -> Result<Vec<CalendarEntry>> {
+        let movies = api_v3_calendar_get(
+            &self.config,
+            Some(start.to_string()),
+            Some(end.to_string()),
+            Some(false),
+            None,
+        )
+        .await
+        .inspect_err(|e| {
+            log_api_error(e, "Failed to get the Radarr calendar");
+        })?;
+        debug!("Found {} movies on the calendar", movies.len());
+
+        // A movie is on the calendar for any of its releases in the window;
+        // report each one that is
+        let mut entries = Vec::new();
+        for movie in movies {
+            let title = match movie.year {
+                Some(year) => format!(
+                    "{} ({year})",
+                    movie.title.clone().flatten().unwrap_or_default()
+                ),
+                None => movie.title.clone().flatten().unwrap_or_default(),
+            };
+            for (date, kind) in [
+                (&movie.in_cinemas, "In cinemas"),
+                (&movie.digital_release, "Digital release"),
+                (&movie.physical_release, "Physical release"),
+            ] {
+                let Some(date) = date.as_ref().and_then(|d| d.as_deref()?.get(..10)) else {
+                    continue;
+                };
+                if (start..=end).contains(&date) {
+                    entries.push(CalendarEntry {
+                        date: date.to_string(),
+                        at: None,
+                        title: title.clone(),
+                        detail: kind.to_string(),
+                    });
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    async fn version(&self) -> Result<Option<String>> {
+        let status = api_v3_system_status_get(&self.config)
+            .await
+            .inspect_err(|e| log_api_error(e, "Failed to get Radarr status"))?;
+        Ok(status.version.flatten())
+    }
+
+    fn media_id(&self, media: &dyn MediaItem) -> Option<MediaId> {
+        let movie = media.as_any().downcast_ref::<MovieResource>()?;
+        Some(MediaId {
+            tmdb: movie.tmdb_id,
+            imdb: movie.imdb_id.clone().flatten(),
+            backend_id: movie.id,
+            ..Default::default()
+        })
+    }
+
+    async fn search_title(&self, id: &MediaId) -> Result<bool> {
+        let Some(tmdb_id) = id.tmdb else {
+            return Ok(false);
+        };
+        let movies = api_v3_movie_get(&self.config, Some(tmdb_id), Some(true), None)
+            .await
+            .inspect_err(|e| log_api_error(e, "Failed to look up Radarr movie"))?;
+        let Some(movie_id) = movies.into_iter().next().and_then(|m| m.id) else {
+            return Ok(false);
+        };
+        tolerate_response_parse_error(
+            api_v3_command_post_custom(&self.config, &MoviesSearchCommand::new(vec![movie_id]))
+                .await,
+            "Failed to trigger Radarr movie search",
+        )?;
+        info!(movie_id, "Queued movie search");
+        Ok(true)
+    }
+
+    async fn apply_tags(&self, id: &MediaId, tags: &[String]) -> Result<()> {
+        let Some(tmdb_id) = id.tmdb else {
+            return Ok(());
+        };
+        let movies = api_v3_movie_get(&self.config, Some(tmdb_id), Some(true), None)
+            .await
+            .inspect_err(|e| log_api_error(e, "Failed to look up Radarr movie"))?;
+        let Some(mut movie) = movies.into_iter().next() else {
+            return Ok(());
+        };
+        let movie_id = movie.id.context("Radarr movie has no ID")?;
+        let mut tag_ids = movie.tags.clone().flatten().unwrap_or_default();
+        let missing: Vec<_> = self
+            .tag_ids(tags)
+            .await?
+            .into_iter()
+            .filter(|id| !tag_ids.contains(id))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        tag_ids.extend(missing);
+        movie.tags = Some(Some(tag_ids));
+        tolerate_response_parse_error(
+            api_v3_movie_id_put(
+                &self.config,
+                &movie_id.to_string(),
+                Some(false),
+                Some(movie),
+            )
+            .await,
+            "Failed to tag movie in Radarr",
+        )?;
+        info!(movie_id, ?tags, "Tagged movie");
+        Ok(())
+    }
+
+    async fn download_status(&self, id: &MediaId) -> Result<Option<DownloadStatus>> {
+        let Some(tmdb_id) = id.tmdb else {
+            return Ok(None);
+        };
+        let movies = api_v3_movie_get(&self.config, Some(tmdb_id), Some(true), None)
+            .await
+            .inspect_err(|e| log_api_error(e, "Failed to look up Radarr movie"))?;
+        let Some(movie) = movies.into_iter().next() else {
+            return Ok(None);
+        };
+        if movie.has_file.flatten() == Some(true) {
+            return Ok(Some(DownloadStatus::Downloaded));
+        }
+        let Some(movie_id) = movie.id else {
+            return Ok(None);
+        };
+
+        let queue = api_v3_queue_get(
+            &self.config,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(vec![movie_id]),
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .inspect_err(|e| log_api_error(e, "Failed to get the Radarr queue"))?;
+        let records = queue.records.flatten().unwrap_or_default();
+        if records.is_empty() {
+            return Ok(Some(DownloadStatus::Searching));
         }
+        // Several releases can be downloading at once; report the soonest
+        let eta = records
+            .iter()
+            .filter_map(|r| parse_timeleft(r.timeleft.as_ref()?.as_deref()?))
+            .min();
+        Ok(Some(DownloadStatus::Grabbed { eta }))
+    }
+
+    async fn queue(&self) -> Result<Vec<QueueItem>> {
+        let queue = api_v3_queue_get(
+            &self.config,
+            None,
+            Some(QUEUE_PAGE_SIZE),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .inspect_err(|e| log_api_error(e, "Failed to get the Radarr queue"))?;
+        Ok(queue
+            .records
+            .flatten()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|r| {
+                let flagged = matches!(
+                    r.tracked_download_status,
+                    Some(TrackedDownloadStatus::Warning | TrackedDownloadStatus::Error)
+                ) || r.status == Some(QueueStatus::Failed);
+                let messages = r
+                    .status_messages
+                    .flatten()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .flat_map(|m| m.messages.flatten().unwrap_or_default());
+                Some(QueueItem {
+                    id: r.id?,
+                    title: r.title.flatten().unwrap_or_default(),
+                    size_left: r.sizeleft.unwrap_or(0.0) as u64,
+                    downloading: r.status == Some(QueueStatus::Downloading),
+                    problem: queue_problem(flagged, r.error_message.flatten().as_deref(), messages),
+                })
+            })
+            .collect())
+    }
+
+    async fn maintenance(&self, task: Maintenance, title: Option<&str>) -> Result<String> {
+        let (movie_ids, queued) = match (task, title) {
+            (Maintenance::RssSync, _) => {
+                tolerate_response_parse_error(
+                    api_v3_command_post_custom(&self.config, &RssSyncCommand::new()).await,
+                    "Failed to trigger Radarr RSS sync",
+                )?;
+                return Ok("RSS sync queued.".to_string());
+            }
+            (Maintenance::SearchMissing, _) => {
+                tolerate_response_parse_error(
+                    api_v3_command_post_custom(&self.config, &MissingMoviesSearchCommand::new())
+                        .await,
+                    "Failed to trigger Radarr missing movies search",
+                )?;
+                return Ok("Search for missing movies queued.".to_string());
+            }
+            (Maintenance::Refresh, None) => (vec![], "Library refresh queued.".to_string()),
+            (Maintenance::Refresh, Some(title)) => {
+                let ids: Vec<i32> = self
+                    .movies_titled(title)
+                    .await?
+                    .iter()
+                    .filter_map(|m| m.id)
+                    .collect();
+                let queued = match ids.len() {
+                    1 => format!("Refresh queued for \"{title}\"."),
+                    n => format!("Refresh queued for {n} movies titled \"{title}\"."),
+                };
+                (ids, queued)
+            }
+        };
+        tolerate_response_parse_error(
+            api_v3_command_post_custom(&self.config, &RefreshMovieCommand::new(movie_ids)).await,
+            "Failed to trigger Radarr refresh",
+        )?;
+        Ok(queued)
+    }
+
+    async fn upgrade(&self, title: &str, quality_profile: Option<&str>) -> Result<String> {
+        let profile_id = quality_profile
+            .map(|name| self.quality_profile_id(name))
+            .transpose()?;
+        let mut movie_ids = Vec::new();
+        for mut movie in self.movies_titled(title).await? {
+            let Some(movie_id) = movie.id else {
+                continue;
+            };
+            if let Some(profile_id) = profile_id
+                && movie.quality_profile_id != Some(profile_id)
+            {
+                movie.quality_profile_id = Some(profile_id);
+                tolerate_response_parse_error(
+                    api_v3_movie_id_put(
+                        &self.config,
+                        &movie_id.to_string(),
+                        Some(false),
+                        Some(movie),
+                    )
+                    .await,
+                    "Failed to change the movie's quality profile in Radarr",
+                )?;
+                info!(movie_id, profile_id, "Changed movie's quality profile");
+            }
+            movie_ids.push(movie_id);
+        }
+        let count = movie_ids.len();
+        tolerate_response_parse_error(
+            api_v3_command_post_custom(&self.config, &MoviesSearchCommand::new(movie_ids)).await,
+            "Failed to trigger Radarr movie search",
+        )?;
+        let movies = match count {
+            1 => format!("\"{title}\""),
+            n => format!("{n} movies titled \"{title}\""),
+        };
+        Ok(match quality_profile {
+            Some(profile) => {
+                format!("Moved {movies} to {profile} and queued a search for an upgrade.")
+            }
+            None => format!("Queued a search for an upgrade of {movies}."),
+        })
+    }
+
+    async fn remove(&self, id: &MediaId, delete_files: bool) -> Result<()> {
+        let Some(movie_id) = id.backend_id else {
+            bail!(UserFacingError("That movie isn't in Radarr.".into()));
+        };
+        // Not excluded from import lists: an admin removing it by hand can
+        // add the exclusion in Radarr if it keeps coming back
+        api_v3_movie_id_delete(&self.config, movie_id, Some(delete_files), Some(false))
+            .await
+            .inspect_err(|e| log_api_error(e, "Failed to delete the Radarr movie"))?;
+        info!(movie_id, delete_files, "Removed movie");
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::discord::{FormEvent, apply_form_event, pick_event};
+    use proptest::{prelude::*, sample::Index};
 
     /// Build a single-select detail with one option, optionally pre-selected.
     fn detail(metadata: &str, title: &str, id: SelectableId, selected: bool) -> RequestDetails {
@@ -558,11 +1329,15 @@ mod tests {
                 title: title.to_string(),
                 description: None,
                 id: Some(id),
+                present: false,
+                note: None,
+                warning: None,
             }],
             selected_indices: if selected { vec![0] } else { vec![] },
             metadata: Some(metadata.to_string()),
             field_type: FieldType::Dropdown,
             always_show: false,
+            status: None,
         }
     }
 
@@ -623,8 +1398,182 @@ mod tests {
             title: "4K".into(),
             description: None,
             id: Some(SelectableId::Integer(8)),
+            present: false,
+            note: None,
+            warning: None,
         });
         details[1].selected_indices = vec![];
         assert!(SelectedDetails::try_from(details).is_err());
     }
+
+    #[test]
+    fn profile_summary_walks_quality_groups() {
+        let quality = |id: i32, name: &str, resolution: i32, allowed: bool| {
+            QualityProfileQualityItemResource {
+                quality: Some(Box::new(radarr_api::models::Quality {
+                    id: Some(id),
+                    name: Some(Some(name.into())),
+                    resolution: Some(resolution),
+                    ..Default::default()
+                })),
+                allowed: Some(allowed),
+                ..Default::default()
+            }
+        };
+        let profile = QualityProfileResource {
+            upgrade_allowed: Some(true),
+            cutoff: Some(1001),
+            items: Some(Some(vec![
+                quality(1, "SDTV", 480, false),
+                QualityProfileQualityItemResource {
+                    id: Some(1001),
+                    name: Some(Some("WEB 1080p".into())),
+                    items: Some(Some(vec![
+                        quality(3, "WEBDL-1080p", 1080, false),
+                        quality(15, "WEBRip-1080p", 1080, false),
+                    ])),
+                    allowed: Some(true),
+                    ..Default::default()
+                },
+                quality(4, "HDTV-720p", 720, true),
+            ])),
+            ..Default::default()
+        };
+        assert_eq!(
+            profile_summary(&profile).as_deref(),
+            Some("720p–1080p · upgrades until WEB 1080p")
+        );
+    }
+
+    #[test]
+    fn custom_format_note_names_the_strongest_scores() {
+        let format = |name: &str, score: i32| radarr_api::models::ProfileFormatItemResource {
+            name: Some(Some(name.into())),
+            score: Some(score),
+            ..Default::default()
+        };
+        let mut profile = QualityProfileResource {
+            format_items: Some(Some(vec![
+                format("HDR10+", 1000),
+                format("Unused", 0),
+                format("x265", -10000),
+                format("DV", 1500),
+                format("Repack", 5),
+                format("Remux", 20),
+            ])),
+            ..Default::default()
+        };
+        assert_eq!(
+            custom_format_note(&profile).as_deref(),
+            Some("Prefers DV (+1500), HDR10+ (+1000), Remux (+20) · Avoids x265 (-10000)")
+        );
+
+        profile.format_items = Some(Some(vec![format("Unused", 0)]));
+        assert_eq!(custom_format_note(&profile), None);
+    }
+
+    /// Radarr's root folders and quality profiles, with distinct IDs
+    fn choices() -> impl Strategy<Value = Choices> {
+        (
+            prop::collection::btree_map(1..1000, "/[a-z]{1,8}", 1..5),
+            prop::collection::btree_map(1..1000, "[A-Za-z0-9 -]{1,12}", 1..5),
+        )
+            .prop_map(|(folders, profiles)| Choices {
+                rootfolders: folders
+                    .into_iter()
+                    .map(|(id, path)| RootFolderResource {
+                        id: Some(id),
+                        path: Some(Some(path)),
+                        ..Default::default()
+                    })
+                    .collect(),
+                quality_profiles: profiles
+                    .into_iter()
+                    .map(|(id, name)| QualityProfileResource {
+                        id: Some(id),
+                        name: Some(Some(name)),
+                        ..Default::default()
+                    })
+                    .collect(),
+                tags: vec![],
+            })
+    }
+
+    #[test]
+    fn release_dates_show_both_kinds_for_the_region() {
+        let movie = MovieResource {
+            in_cinemas: Some(Some("2024-03-01T00:00:00Z".into())),
+            digital_release: Some(Some("2024-04-16T00:00:00Z".into())),
+            physical_release: Some(None),
+            ..Default::default()
+        };
+        assert_eq!(
+            release_dates(&movie, Some("GB")),
+            "Digital Apr 16, 2024 · Physical TBA (GB)"
+        );
+        assert_eq!(
+            availability_description(MovieStatusType::Released, &movie).as_deref(),
+            Some("Grab releases once it's out digitally or on disc (Apr 16, 2024)")
+        );
+        assert_eq!(
+            availability_description(MovieStatusType::InCinemas, &movie).as_deref(),
+            Some("Grab releases once it's in cinemas (Mar 1, 2024)")
+        );
+        assert_eq!(
+            availability_description(MovieStatusType::InCinemas, &MovieResource::default())
+                .as_deref(),
+            Some("Grab releases once it's in cinemas")
+        );
+    }
+
+    proptest! {
+        /// Whatever Radarr offers and the requester picks through the form
+        /// comes back out as the matching selected details
+        #[test]
+        fn form_picks_round_trip_to_selected_details(
+            choices in choices(),
+            monitor in prop::sample::subsequence(
+                vec![MonitorTypes::MovieAndCollection, MonitorTypes::MovieOnly, MonitorTypes::None],
+                1..=3,
+            ),
+            availability in prop::sample::subsequence(
+                vec![
+                    MovieStatusType::Tba,
+                    MovieStatusType::Announced,
+                    MovieStatusType::InCinemas,
+                    MovieStatusType::Released,
+                    MovieStatusType::Deleted,
+                ],
+                1..=5,
+            ),
+            picks in prop::collection::vec(any::<Index>(), 4),
+        ) {
+            let expected_folder = picks[0].get(&choices.rootfolders).path.clone().flatten();
+            let expected_monitor = *picks[1].get(&monitor);
+            let expected_availability = *picks[2].get(&availability);
+            let expected_profile = picks[3].get(&choices.quality_profiles).id;
+
+            let mut details: Vec<RequestDetails> = Details {
+                choices: Refreshing::fixed(choices),
+                monitor,
+                minimum_availability: availability,
+            }
+            .into();
+            prop_assert_eq!(details.len(), picks.len());
+            for (i, pick) in picks.iter().enumerate() {
+                // A lone option is shown as text, so nothing is ever picked
+                let count = details[i].options.len();
+                if count > 1 {
+                    let event = pick_event(&details[i].title, &[pick.index(count)]);
+                    prop_assert_eq!(apply_form_event(&mut details, &event), FormEvent::Updated);
+                }
+            }
+
+            let selected = SelectedDetails::try_from(details).unwrap();
+            prop_assert_eq!(Some(selected.rootfolder_path), expected_folder);
+            prop_assert_eq!(selected.monitor, expected_monitor);
+            prop_assert_eq!(selected.minimum_availability, expected_availability);
+            prop_assert_eq!(Some(selected.quality_profile_id), expected_profile);
+        }
+    }
 }
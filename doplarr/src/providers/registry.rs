@@ -0,0 +1,683 @@
+//! The set of connected backends, keyed by the media command they serve
+use super::{
+    MediaBackend, UserFacingError,
+    combined::Combined,
+    fallback::{Fallback, TmdbSearch},
+    faults::FaultInjecting,
+    plugin::Plugin,
+    radarr::Radarr,
+    seerr::Seerr,
+    sonarr::Sonarr,
+    stats::{BackendStats, Instrumented},
+    swappable::Swappable,
+};
+use crate::{
+    config::{Backend, BackendConfig, FaultInjection},
+    discord::{MAX_COMMAND_CHOICES, MAX_SUBCOMMANDS, REMOVE_SUBCOMMAND_NAME},
+    maintenance::Schedule,
+};
+use anyhow::{Context, Result, bail};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+#[derive(Clone)]
+/// A connected backend instance
+pub struct RegisteredBackend {
+    /// Instance name from the config, used in logs and admin commands
+    pub name: String,
+    pub backend: Arc<dyn MediaBackend>,
+    /// Cleared by an admin to take the backend out of service (e.g. during
+    /// maintenance) without restarting. Shared with in-flight flows.
+    enabled: Arc<AtomicBool>,
+    /// Latency and error rates of recent calls, recorded by the wrapper around `backend`
+    pub stats: Arc<BackendStats>,
+    /// The only guilds this instance serves. When `None`, it serves every
+    /// guild that has no instance of its own for the media command.
+    guilds: Option<Vec<u64>>,
+    /// The connection `backend` calls through, to reconnect it with new
+    /// credentials. `None` for combined backends, which have none of their own.
+    connection: Option<Arc<Swappable>>,
+    /// When it's down for scheduled maintenance
+    maintenance: Arc<Schedule>,
+}
+
+impl RegisteredBackend {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// The instance's own connection, without the wrappers around `backend`
+    /// (stats, fault injection, the search fallback). `None` for combined
+    /// backends.
+    pub fn connection(&self) -> Option<Arc<dyn MediaBackend>> {
+        self.connection.clone().map(|c| c as Arc<dyn MediaBackend>)
+    }
+
+    /// When scheduled maintenance ends, if it's under way at `now`
+    pub fn maintenance_until(&self, now: OffsetDateTime) -> Option<OffsetDateTime> {
+        self.maintenance.until(now)
+    }
+
+    fn is_dedicated_to(&self, guild_id: Option<u64>) -> bool {
+        guild_id.is_some_and(|g| self.guilds.as_ref().is_some_and(|gs| gs.contains(&g)))
+    }
+}
+
+/// Connected backends, keyed by media command name (e.g. "movie"). A command
+/// can have several instances, each serving different guilds.
+/// Nothing outside this module needs to know which concrete backends exist.
+#[derive(Default, Clone)]
+pub struct BackendRegistry {
+    backends: BTreeMap<String, Vec<RegisteredBackend>>,
+}
+
+/// The instance that handles a command in a guild: the one dedicated to the
+/// guild if any, otherwise the command's default instance
+fn resolve(instances: &[RegisteredBackend], guild_id: Option<u64>) -> Option<&RegisteredBackend> {
+    instances
+        .iter()
+        .find(|b| b.is_dedicated_to(guild_id))
+        .or_else(|| instances.iter().find(|b| b.guilds.is_none()))
+}
+
+impl BackendRegistry {
+    /// Connect to every configured backend, wrapping the ones `faults` names
+    /// in [FaultInjecting], and Radarr instances in a [Fallback] to `tmdb`
+    /// when there is one. Combined backends are put together once the
+    /// instances they search are connected.
+    pub async fn connect(
+        configs: &[Backend],
+        faults: Option<&FaultInjection>,
+        tmdb: Option<&Arc<TmdbSearch>>,
+        client: reqwest::Client,
+    ) -> Result<Self> {
+        validate(configs)?;
+        let mut registry = Self::default();
+        let (combined, configs): (Vec<_>, Vec<_>) = configs
+            .iter()
+            .partition(|b| matches!(b.config, BackendConfig::Combined { .. }));
+        for backend in configs {
+            info!(
+                name = backend.name(),
+                media = backend.media,
+                "Connecting backend instance"
+            );
+            let maintenance = maintenance_schedule(backend)?;
+            let connected = connect_backend(backend.config.clone(), client.clone())
+                .await
+                .with_context(|| format!("Failed to connect backend \"{}\"", backend.name()))?;
+            let connection = Arc::new(Swappable::new(
+                backend.config.clone(),
+                client.clone(),
+                connected,
+            ));
+            let connected: Arc<dyn MediaBackend> = match faults {
+                Some(faults)
+                    if faults
+                        .backends
+                        .as_ref()
+                        .is_none_or(|names| names.iter().any(|n| n == backend.name())) =>
+                {
+                    warn!(
+                        name = backend.name(),
+                        "Injecting latency and failures into backend calls"
+                    );
+                    Arc::new(FaultInjecting::new(
+                        connection.clone(),
+                        backend.name(),
+                        faults,
+                    ))
+                }
+                _ => connection.clone(),
+            };
+            let connected = match tmdb {
+                Some(tmdb) if matches!(backend.config, BackendConfig::Radarr { .. }) => {
+                    Arc::new(Fallback::new(connected, backend.name(), Arc::clone(tmdb)))
+                }
+                _ => connected,
+            };
+            registry.insert_instance(
+                backend.media.clone(),
+                backend.name().to_string(),
+                backend.guilds.clone(),
+                connected,
+                Some(connection),
+                maintenance,
+            )?;
+        }
+        for backend in combined {
+            let BackendConfig::Combined { backends } = &backend.config else {
+                unreachable!("partitioned above");
+            };
+            info!(
+                name = backend.name(),
+                media = backend.media,
+                members = ?backends,
+                "Combining backend instances"
+            );
+            let members = registry
+                .members(backends)
+                .with_context(|| format!("Failed to combine backend \"{}\"", backend.name()))?;
+            registry.insert_instance(
+                backend.media.clone(),
+                backend.name().to_string(),
+                backend.guilds.clone(),
+                Arc::new(Combined::new(members)),
+                None,
+                maintenance_schedule(backend)?,
+            )?;
+        }
+        Ok(registry)
+    }
+
+    /// The instances a combined backend searches, by name
+    fn members(&self, names: &[String]) -> Result<Vec<RegisteredBackend>> {
+        if names.is_empty() {
+            bail!("A combined backend needs at least one backend to search");
+        }
+        names
+            .iter()
+            .map(|name| {
+                self.iter()
+                    .map(|(_, b)| b)
+                    .find(|b| &b.name == name)
+                    .cloned()
+                    .with_context(|| {
+                        format!("No backend named \"{name}\" (combined backends can't be combined)")
+                    })
+            })
+            .collect()
+    }
+
+    /// Register a backend instance for a media command, serving `guilds` or,
+    /// when `None`, every other guild. Each guild can only be served by one
+    /// instance per command, and instance names must be unique.
+    pub fn insert(
+        &mut self,
+        media: String,
+        name: String,
+        guilds: Option<Vec<u64>>,
+        backend: Arc<dyn MediaBackend>,
+    ) -> Result<()> {
+        self.insert_instance(media, name, guilds, backend, None, Schedule::default())
+    }
+
+    fn insert_instance(
+        &mut self,
+        media: String,
+        name: String,
+        guilds: Option<Vec<u64>>,
+        backend: Arc<dyn MediaBackend>,
+        connection: Option<Arc<Swappable>>,
+        maintenance: Schedule,
+    ) -> Result<()> {
+        let existing: Vec<_> = self
+            .iter()
+            .map(|(media, b)| (media, b.name.as_str(), b.guilds.as_deref()))
+            .collect();
+        check_instance(&existing, &media, &name, guilds.as_deref())?;
+        let instances = self.backends.entry(media.clone()).or_default();
+        let stats = Arc::new(BackendStats::default());
+        instances.push(RegisteredBackend {
+            name,
+            backend: Arc::new(Instrumented::new(backend, stats.clone())),
+            enabled: Arc::new(AtomicBool::new(true)),
+            stats,
+            guilds,
+            connection,
+            maintenance: Arc::new(maintenance),
+        });
+        Ok(())
+    }
+
+    /// Reconnect the instance called `name`, if it serves the guild, with a
+    /// new API key. The old connection stays in use unless the new one works.
+    pub async fn rotate_key(&self, name: &str, guild_id: Option<u64>, api_key: &str) -> Result<()> {
+        let instance = self.named(name, guild_id)?;
+        let Some(connection) = &instance.connection else {
+            bail!(UserFacingError(format!(
+                "`{name}` has no connection of its own; rotate the keys of the backends it combines."
+            )));
+        };
+        let config = with_api_key(connection.config(), api_key)
+            .ok_or_else(|| UserFacingError(format!("`{name}` doesn't use an API key.")))?;
+        let backend = connect_backend(config.clone(), connection.client())
+            .await
+            .context("The new key didn't work, so the old one is still in use")?;
+        connection.replace(config, backend);
+        Ok(())
+    }
+
+    /// The instance that handles a media command in a guild
+    pub fn get(&self, media: &str, guild_id: Option<u64>) -> Option<RegisteredBackend> {
+        self.backends
+            .get(media)
+            .and_then(|instances| resolve(instances, guild_id))
+            .cloned()
+    }
+
+    /// The registered media command names, in sorted order
+    pub fn media_kinds(&self) -> impl Iterator<Item = &str> {
+        self.backends.keys().map(String::as_str)
+    }
+
+    /// The media command names a guild can use right now, in sorted order
+    pub fn enabled_media_kinds(&self, guild_id: Option<u64>) -> impl Iterator<Item = &str> {
+        self.for_guild(guild_id)
+            .filter(|(_, b)| b.is_enabled())
+            .map(|(media, _)| media)
+    }
+
+    /// Enable or disable the instance called `name`, if it serves the guild.
+    /// Returns whether the state changed.
+    pub fn set_enabled(&self, name: &str, guild_id: Option<u64>, enabled: bool) -> Result<bool> {
+        let backend = self.named(name, guild_id)?;
+        Ok(backend.enabled.swap(enabled, Ordering::Relaxed) != enabled)
+    }
+
+    /// The instance with the given name, if it serves the guild
+    pub fn named(&self, name: &str, guild_id: Option<u64>) -> Result<&RegisteredBackend> {
+        self.for_guild(guild_id)
+            .map(|(_, b)| b)
+            .find(|b| b.name == name)
+            .with_context(|| format!("No backend named \"{name}\""))
+    }
+
+    /// Every registered instance with the media command it serves, sorted by command
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &RegisteredBackend)> {
+        self.backends
+            .iter()
+            .flat_map(|(media, instances)| instances.iter().map(move |b| (media.as_str(), b)))
+    }
+
+    /// The instance handling each media command in a guild, sorted by command
+    pub fn for_guild(
+        &self,
+        guild_id: Option<u64>,
+    ) -> impl Iterator<Item = (&str, &RegisteredBackend)> {
+        self.backends.iter().filter_map(move |(media, instances)| {
+            resolve(instances, guild_id).map(|b| (media.as_str(), b))
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.backends.is_empty()
+    }
+}
+
+/// Whether Discord accepts `media` as the name of a subcommand
+fn is_subcommand_name(media: &str) -> bool {
+    (1..=32).contains(&media.chars().count())
+        && media
+            .chars()
+            .all(|c| (c.is_alphanumeric() && !c.is_uppercase()) || c == '-' || c == '_')
+}
+
+/// Check that an instance can join the `existing` ones (media command, name,
+/// guilds): its media command must be a valid subcommand name, its name
+/// unique, and each guild served by one instance per command
+fn check_instance(
+    existing: &[(&str, &str, Option<&[u64]>)],
+    media: &str,
+    name: &str,
+    guilds: Option<&[u64]>,
+) -> Result<()> {
+    if !is_subcommand_name(media) {
+        bail!(
+            "\"{media}\" can't be a /request subcommand: media names must be 1-32 \
+             lowercase letters, digits, dashes, or underscores (e.g. \"movie-4k\")"
+        );
+    }
+    if media == REMOVE_SUBCOMMAND_NAME {
+        bail!(
+            "Backend \"{name}\" can't use media = \"{media}\" any more: /request {media} is \
+             now how admins take titles out of the library. Pick another media name."
+        );
+    }
+    if existing.iter().any(|(_, n, _)| *n == name) {
+        bail!("Backend names must be unique (\"{name}\" is repeated)");
+    }
+    let mut instances = existing.iter().filter(|(m, _, _)| *m == media);
+    match guilds {
+        None if instances.any(|(_, _, gs)| gs.is_none()) => bail!(
+            "There must only be one of each media type (\"{media}\" is repeated); \
+             give one of them a `guilds` list to route by server"
+        ),
+        Some(gs) => {
+            let taken: Vec<u64> = instances
+                .flat_map(|(_, _, gs)| gs.unwrap_or_default().iter().copied())
+                .collect();
+            if let Some(g) = gs.iter().find(|g| taken.contains(g)) {
+                bail!("Guild {g} has more than one \"{media}\" backend");
+            }
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+/// Check the whole `[[backends]]` list the way the registry will take it, so
+/// a mistake in the config shows up before any slow connection attempt
+fn validate(configs: &[Backend]) -> Result<()> {
+    let mut seen: Vec<(&str, &str, Option<&[u64]>)> = Vec::new();
+    for backend in configs {
+        check_instance(
+            &seen,
+            &backend.media,
+            backend.name(),
+            backend.guilds.as_deref(),
+        )?;
+        maintenance_schedule(backend)?;
+        seen.push((&backend.media, backend.name(), backend.guilds.as_deref()));
+    }
+    // `/request` has a subcommand for each command a guild has, besides
+    // `remove`, and the admin commands offer each as a choice
+    let max_media = MAX_COMMAND_CHOICES.min(MAX_SUBCOMMANDS - 1);
+    let scopes = configs
+        .iter()
+        .flat_map(|b| b.guilds.iter().flatten().copied().map(Some))
+        .chain([None]);
+    for scope in scopes {
+        let media: BTreeSet<&str> = configs
+            .iter()
+            .filter(|b| match &b.guilds {
+                None => true,
+                Some(gs) => scope.is_some_and(|g| gs.contains(&g)),
+            })
+            .map(|b| b.media.as_str())
+            .collect();
+        if media.len() > max_media {
+            let whom = match scope {
+                Some(g) => format!("Guild {g}"),
+                None => "Guilds without backends of their own".to_string(),
+            };
+            bail!(
+                "{whom} would have {} media commands, more than the {max_media} Discord's \
+                 limits leave room for; give some of them a `guilds` list",
+                media.len()
+            );
+        }
+    }
+    for backend in configs {
+        let BackendConfig::Combined { backends } = &backend.config else {
+            continue;
+        };
+        if backends.is_empty() {
+            bail!("A combined backend needs at least one backend to search");
+        }
+        for name in backends {
+            if !configs
+                .iter()
+                .any(|b| b.name() == name && !matches!(b.config, BackendConfig::Combined { .. }))
+            {
+                bail!(
+                    "Failed to combine backend \"{}\": no backend named \"{name}\" \
+                     (combined backends can't be combined)",
+                    backend.name()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A backend's parsed maintenance windows
+fn maintenance_schedule(backend: &Backend) -> Result<Schedule> {
+    Schedule::parse(backend.maintenance.as_deref().unwrap_or_default()).with_context(|| {
+        format!(
+            "Invalid maintenance window for backend \"{}\"",
+            backend.name()
+        )
+    })
+}
+
+/// The config with its API key replaced, for backends that have one
+fn with_api_key(mut config: BackendConfig, new_key: &str) -> Option<BackendConfig> {
+    match &mut config {
+        BackendConfig::Radarr { api_key, .. }
+        | BackendConfig::Sonarr { api_key, .. }
+        | BackendConfig::Seerr { api_key, .. } => *api_key = new_key.to_string(),
+        BackendConfig::Plugin { .. } | BackendConfig::Combined { .. } => return None,
+    }
+    Some(config)
+}
+
+/// Build the backend for a single config entry, cast into a trait object
+async fn connect_backend(
+    config: BackendConfig,
+    client: reqwest::Client,
+) -> Result<Arc<dyn MediaBackend>> {
+    Ok(match config {
+        BackendConfig::Radarr { .. } => Arc::new(Radarr::connect(config, client).await?),
+        BackendConfig::Sonarr { .. } => Arc::new(Sonarr::connect(config, client).await?),
+        BackendConfig::Seerr { .. } => Arc::new(Seerr::connect(config, client).await?),
+        BackendConfig::Plugin { .. } => Arc::new(Plugin::connect(config, client).await?),
+        BackendConfig::Combined { .. } => bail!("Combined backends have nothing to connect to"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{
+        MediaDisplayInfo, MediaItem, RequestDetails, Requester, SuccessMessage,
+    };
+    use async_trait::async_trait;
+
+    /// A backend that is never called, for exercising the registry itself
+    struct Stub;
+
+    #[async_trait]
+    impl MediaBackend for Stub {
+        async fn search(&self, _term: &str) -> Result<Vec<Box<dyn MediaItem>>> {
+            unimplemented!()
+        }
+        fn early_stop(&self, _media: &dyn MediaItem) -> bool {
+            unimplemented!()
+        }
+        fn display_info(&self, _media: &dyn MediaItem) -> MediaDisplayInfo {
+            unimplemented!()
+        }
+        async fn additional_details(&self, _media: &dyn MediaItem) -> Result<Vec<RequestDetails>> {
+            unimplemented!()
+        }
+        async fn request(
+            &self,
+            _details: Vec<RequestDetails>,
+            _media: Box<dyn MediaItem>,
+            _requester: &Requester,
+        ) -> Result<()> {
+            unimplemented!()
+        }
+        fn success_message(
+            &self,
+            _details: &[RequestDetails],
+            _media: &dyn MediaItem,
+        ) -> SuccessMessage {
+            unimplemented!()
+        }
+    }
+
+    fn registry() -> BackendRegistry {
+        let mut registry = BackendRegistry::default();
+        registry
+            .insert("series".into(), "sonarr".into(), None, Arc::new(Stub))
+            .unwrap();
+        registry
+            .insert("movie".into(), "radarr".into(), None, Arc::new(Stub))
+            .unwrap();
+        registry
+    }
+
+    #[test]
+    fn insert_rejects_duplicate_media_and_names() {
+        let mut registry = registry();
+        assert!(
+            registry
+                .insert("movie".into(), "radarr-4k".into(), None, Arc::new(Stub))
+                .is_err()
+        );
+        assert!(
+            registry
+                .insert("movie_4k".into(), "radarr".into(), None, Arc::new(Stub))
+                .is_err()
+        );
+        assert_eq!(
+            registry.media_kinds().collect::<Vec<_>>(),
+            ["movie", "series"]
+        );
+    }
+
+    #[test]
+    fn each_instance_gets_a_subcommand_named_by_its_media() {
+        let mut registry = registry();
+        registry
+            .insert("movie-4k".into(), "radarr-4k".into(), None, Arc::new(Stub))
+            .unwrap();
+        assert_eq!(
+            registry.media_kinds().collect::<Vec<_>>(),
+            ["movie", "movie-4k", "series"]
+        );
+        assert_eq!(registry.get("movie-4k", None).unwrap().name, "radarr-4k");
+        for bad in ["Movie", "movie 4k", "", &"m".repeat(33), "remove"] {
+            let insert = registry.insert(bad.into(), bad.into(), None, Arc::new(Stub));
+            assert!(insert.is_err(), "{bad:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn the_backend_list_is_checked_before_connecting() {
+        let backend = |media: &str, name: &str, members: &[&str]| Backend {
+            media: media.into(),
+            name: Some(name.into()),
+            guilds: None,
+            config: BackendConfig::Combined {
+                backends: members.iter().map(|m| m.to_string()).collect(),
+            },
+            maintenance: None,
+        };
+        let repeated = validate(&[
+            backend("movie", "a", &["b"]),
+            backend("series", "a", &["b"]),
+        ]);
+        assert!(format!("{:#}", repeated.unwrap_err()).contains("\"a\" is repeated"));
+        let missing = validate(&[backend("movie", "a", &["b"])]);
+        assert!(format!("{:#}", missing.unwrap_err()).contains("no backend named \"b\""));
+
+        let many: Vec<_> = (0..MAX_SUBCOMMANDS)
+            .map(|i| backend(&format!("m{i}"), &format!("b{i}"), &["b0"]))
+            .collect();
+        let too_many = validate(&many).unwrap_err();
+        assert!(format!("{too_many:#}").contains("25 media commands"));
+    }
+
+    #[test]
+    fn disabling_hides_media_kind_and_is_shared() {
+        let registry = registry();
+        let in_flight = registry.get("movie", None).unwrap();
+
+        assert!(registry.set_enabled("radarr", None, false).unwrap());
+        assert!(!registry.set_enabled("radarr", None, false).unwrap());
+        assert_eq!(
+            registry.enabled_media_kinds(None).collect::<Vec<_>>(),
+            ["series"]
+        );
+        // Flows holding the backend see the change
+        assert!(!in_flight.is_enabled());
+
+        assert!(registry.set_enabled("radarr", None, true).unwrap());
+        assert!(in_flight.is_enabled());
+        assert!(registry.set_enabled("nope", None, true).is_err());
+    }
+
+    #[test]
+    fn guilds_route_to_their_own_instance() {
+        let mut registry = registry();
+        registry
+            .insert(
+                "movie".into(),
+                "radarr-family".into(),
+                Some(vec![1, 2]),
+                Arc::new(Stub),
+            )
+            .unwrap();
+        // A guild can't have two instances for one command
+        assert!(
+            registry
+                .insert(
+                    "movie".into(),
+                    "radarr-x".into(),
+                    Some(vec![2]),
+                    Arc::new(Stub)
+                )
+                .is_err()
+        );
+
+        let name = |guild| registry.get("movie", guild).unwrap().name;
+        assert_eq!(name(Some(1)), "radarr-family");
+        assert_eq!(name(Some(3)), "radarr");
+        assert_eq!(name(None), "radarr");
+
+        // Other guilds can't see or toggle a guild's dedicated instance
+        assert!(
+            registry
+                .set_enabled("radarr-family", Some(3), false)
+                .is_err()
+        );
+        assert!(
+            registry
+                .set_enabled("radarr-family", Some(1), false)
+                .unwrap()
+        );
+        assert_eq!(
+            registry.enabled_media_kinds(Some(1)).collect::<Vec<_>>(),
+            ["series"]
+        );
+        assert_eq!(
+            registry.enabled_media_kinds(Some(3)).collect::<Vec<_>>(),
+            ["movie", "series"]
+        );
+        assert_eq!(registry.iter().count(), 3);
+    }
+
+    #[tokio::test]
+    async fn only_backends_with_a_key_of_their_own_rotate_it() {
+        let radarr = BackendConfig::Radarr {
+            url: "http://radarr".into(),
+            api_key: "old".into(),
+            monitor_type: None,
+            quality_profile: None,
+            rootfolder: None,
+            minimum_availability: None,
+            allow_unmonitored: None,
+            allow_tags: None,
+            result_fields: None,
+        };
+        let Some(BackendConfig::Radarr { api_key, url, .. }) = with_api_key(radarr, "new") else {
+            panic!("Radarr has an API key");
+        };
+        assert_eq!((api_key.as_str(), url.as_str()), ("new", "http://radarr"));
+        let plugin = BackendConfig::Plugin {
+            command: "plugin".into(),
+            args: None,
+            timeout_secs: None,
+        };
+        assert_eq!(with_api_key(plugin, "new"), None);
+
+        // Registered without a connection, like combined backends
+        let registry = registry();
+        let err = registry
+            .rotate_key("radarr", None, "new")
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<UserFacingError>().is_some());
+        assert!(registry.rotate_key("nope", None, "new").await.is_err());
+    }
+}
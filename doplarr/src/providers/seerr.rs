@@ -10,7 +10,9 @@ use seerr_api::{
         Error as SeerrApiError,
         auth_api::auth_me_get,
         configuration::{ApiKey, Configuration},
-        request_api::request_post,
+        movies_api::movie_movie_id_get,
+        public_api::status_get,
+        request_api::{request_get, request_post},
         search_api::search_get,
         tv_api::tv_tv_id_get,
         users_api::{user_get, user_user_id_settings_notifications_get},
@@ -72,6 +74,192 @@ where
     }
 }
 
+/// Every Discord user ID linked to a Seerr account (in its Discord
+/// notification settings) -> that account's Seerr user ID
+async fn linked_users(config: &Configuration) -> Result<HashMap<u64, i32>> {
+    let mut map: HashMap<u64, i32> = HashMap::new();
+    let mut skip = 0.0f64;
+    loop {
+        let page = require(
+            user_get(config, Some(100.0), Some(skip), None, None, None, None).await,
+            "Fetching Seerr users",
+        )?;
+
+        let users = page.results.unwrap_or_default();
+        if users.is_empty() {
+            break;
+        }
+
+        for user in &users {
+            let notif = require(
+                user_user_id_settings_notifications_get(config, user.id as f64).await,
+                "Fetching user notification settings",
+            )?;
+
+            if let Some(Some(ids)) = notif.discord_ids {
+                for id_str in ids {
+                    if let Ok(did) = id_str.parse::<u64>() {
+                        map.insert(did, user.id);
+                    }
+                }
+            }
+        }
+
+        let total = page.page_info.and_then(|p| p.results).unwrap_or(0.0) as usize;
+
+        skip += users.len() as f64;
+        if skip as usize >= total {
+            break;
+        }
+    }
+    Ok(map)
+}
+
+fn configuration(url: &str, api_key: String, client: reqwest::Client) -> Configuration {
+    Configuration {
+        base_path: format!("{}/api/v1", url.trim_end_matches('/')),
+        client,
+        api_key: Some(ApiKey {
+            prefix: None,
+            key: api_key,
+        }),
+        ..Default::default()
+    }
+}
+
+/// A request from a Seerr instance's history
+#[derive(Debug)]
+pub struct PastRequest {
+    /// As Seerr reports it, e.g. "2024-03-04T18:30:00.000Z"
+    pub created_at: Option<String>,
+    pub discord_id: u64,
+    pub kind: MediaKind,
+    /// "Title (Year)"
+    pub title: String,
+    pub media_id: MediaId,
+}
+
+/// Every request made on a Seerr instance by a user with a linked Discord
+/// account, oldest first, and how many were skipped for having none
+pub async fn request_history(
+    url: &str,
+    api_key: String,
+    client: reqwest::Client,
+) -> Result<(Vec<PastRequest>, usize)> {
+    const PAGE_SIZE: f64 = 100.0;
+    let config = configuration(url, api_key, client);
+    require(auth_me_get(&config).await, "Seerr auth validation")?;
+    let discord_ids: HashMap<i32, u64> = linked_users(&config)
+        .await?
+        .into_iter()
+        .map(|(discord_id, user_id)| (user_id, discord_id))
+        .collect();
+
+    let mut history = Vec::new();
+    let mut unlinked = 0;
+    // The request list doesn't say which kind each one is, so list each kind
+    for (kind, media_type) in [(MediaKind::Movie, "movie"), (MediaKind::Tv, "tv")] {
+        let mut titles: HashMap<i32, String> = HashMap::new();
+        let mut skip = 0.0;
+        loop {
+            let page = require(
+                request_get(
+                    &config,
+                    Some(PAGE_SIZE),
+                    Some(skip),
+                    Some("all"),
+                    None,
+                    None,
+                    None,
+                    Some(media_type),
+                )
+                .await,
+                "Fetching Seerr requests",
+            )?;
+            let requests = page.results.unwrap_or_default();
+            if requests.is_empty() {
+                break;
+            }
+            skip += requests.len() as f64;
+
+            for request in requests {
+                let Some(discord_id) = request
+                    .requested_by
+                    .as_ref()
+                    .and_then(|u| discord_ids.get(&u.id))
+                else {
+                    unlinked += 1;
+                    continue;
+                };
+                let Some(tmdb_id) = request.media.as_ref().and_then(|m| m.tmdb_id) else {
+                    continue;
+                };
+                let tmdb_id = tmdb_id as i32;
+                let title = match titles.get(&tmdb_id) {
+                    Some(title) => title.clone(),
+                    None => {
+                        let title = match kind {
+                            MediaKind::Movie => {
+                                let movie = require(
+                                    movie_movie_id_get(&config, tmdb_id.into(), None).await,
+                                    "Fetching Seerr movie",
+                                )?;
+                                with_year(movie.title, movie.release_date)
+                            }
+                            MediaKind::Tv => {
+                                let show = require(
+                                    tv_tv_id_get(&config, tmdb_id.into(), None).await,
+                                    "Fetching Seerr series",
+                                )?;
+                                with_year(show.name, show.first_air_date)
+                            }
+                        };
+                        titles.insert(tmdb_id, title.clone());
+                        title
+                    }
+                };
+                let media_id = MediaId {
+                    tmdb: Some(tmdb_id),
+                    tvdb: request
+                        .media
+                        .as_ref()
+                        .and_then(|m| m.tvdb_id.flatten())
+                        .map(|id| id as i32),
+                    backend_id: request
+                        .media
+                        .as_ref()
+                        .and_then(|m| m.id)
+                        .map(|id| id as i32),
+                    ..Default::default()
+                };
+                history.push(PastRequest {
+                    created_at: request.created_at,
+                    discord_id: *discord_id,
+                    kind,
+                    title,
+                    media_id,
+                });
+            }
+
+            let total = page.page_info.and_then(|p| p.results).unwrap_or(0.0);
+            if skip >= total {
+                break;
+            }
+        }
+    }
+    history.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    Ok((history, unlinked))
+}
+
+/// "Title (Year)", from a title and a YYYY-MM-DD date
+fn with_year(title: Option<String>, date: Option<String>) -> String {
+    let title = title.unwrap_or_else(|| "Unknown".into());
+    match date.as_deref().and_then(|d| d.get(..4)) {
+        Some(year) => format!("{title} ({year})"),
+        None => title,
+    }
+}
+
 struct UserMapCache {
     map: HashMap<u64, i32>,
     fetched_at: Instant,
@@ -108,16 +296,7 @@ impl Seerr {
             bail!("Expected Seerr config");
         };
 
-        let base_path = format!("{}/api/v1", url.trim_end_matches('/'));
-        let config = Configuration {
-            base_path,
-            client,
-            api_key: Some(ApiKey {
-                prefix: None,
-                key: api_key,
-            }),
-            ..Default::default()
-        };
+        let config = configuration(&url, api_key, client);
 
         require(auth_me_get(&config).await, "Seerr auth validation")?;
         info!("Connected to Seerr at {}", config.base_path);
@@ -152,51 +331,7 @@ impl Seerr {
             return Ok(c.map.get(&discord_id).copied());
         }
 
-        let mut map: HashMap<u64, i32> = HashMap::new();
-        let mut skip = 0.0f64;
-        loop {
-            let page = require(
-                user_get(
-                    &self.config,
-                    Some(100.0),
-                    Some(skip),
-                    None,
-                    None,
-                    None,
-                    None,
-                )
-                .await,
-                "Fetching Seerr users",
-            )?;
-
-            let users = page.results.unwrap_or_default();
-            if users.is_empty() {
-                break;
-            }
-
-            for user in &users {
-                let notif = require(
-                    user_user_id_settings_notifications_get(&self.config, user.id as f64).await,
-                    "Fetching user notification settings",
-                )?;
-
-                if let Some(Some(ids)) = notif.discord_ids {
-                    for id_str in ids {
-                        if let Ok(did) = id_str.parse::<u64>() {
-                            map.insert(did, user.id);
-                        }
-                    }
-                }
-            }
-
-            let total = page.page_info.and_then(|p| p.results).unwrap_or(0.0) as usize;
-
-            skip += users.len() as f64;
-            if skip as usize >= total {
-                break;
-            }
-        }
-
+        let map = linked_users(&self.config).await?;
         info!(
             "Seerr user cache refreshed: {} linked Discord user(s)",
             map.len()
@@ -213,16 +348,33 @@ impl Seerr {
 // The search result type is our MediaItem for Seerr
 use seerr_api::models::SearchGet200ResponseResultsInner as SeerrResult;
 
+/// The year part of a result's release (movie) or first air (TV) date
+fn year_str(result: &SeerrResult) -> Option<&str> {
+    match result.media_type.as_str() {
+        "tv" => result.first_air_date.as_deref().and_then(|d| d.get(..4)),
+        _ => result.release_date.as_deref().and_then(|d| d.get(..4)),
+    }
+}
+
 impl MediaItem for SeerrResult {
+    fn is_adult(&self) -> bool {
+        self.adult.unwrap_or(false)
+    }
+
+    fn kind(&self) -> Option<MediaKind> {
+        match self.media_type.as_str() {
+            "movie" => Some(MediaKind::Movie),
+            "tv" => Some(MediaKind::Tv),
+            _ => None,
+        }
+    }
+
     fn to_dropdown(&self) -> DropdownOption {
         let display_name = match self.media_type.as_str() {
             "tv" => self.name.as_deref().unwrap_or("Unknown"),
             _ => self.title.as_deref().unwrap_or("Unknown"),
         };
-        let year = match self.media_type.as_str() {
-            "tv" => self.first_air_date.as_deref().and_then(|d| d.get(..4)),
-            _ => self.release_date.as_deref().and_then(|d| d.get(..4)),
-        };
+        let year = year_str(self);
         let type_tag = match self.media_type.as_str() {
             "movie" => "Movie",
             "tv" => "Series",
@@ -236,9 +388,16 @@ impl MediaItem for SeerrResult {
             title: display_name.to_string(),
             description: Some(description),
             id: Some(SelectableId::Integer(self.id as i32)),
+            present: false,
+            note: None,
+            warning: None,
         }
     }
 
+    fn year(&self) -> Option<i32> {
+        year_str(self).and_then(|y| y.parse().ok())
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -280,6 +439,9 @@ impl MediaBackend for Seerr {
                     title: display_name.to_string(),
                     description,
                     id: Some(SelectableId::Integer(result.id as i32)),
+                    present: false,
+                    note: None,
+                    warning: None,
                 }
             })
             .collect()
@@ -372,17 +534,24 @@ impl MediaBackend for Seerr {
                     title: "Standard".into(),
                     description: None,
                     id: Some(SelectableId::Boolean(false)),
+                    present: false,
+                    note: None,
+                    warning: None,
                 },
                 DropdownOption {
                     title: "4K".into(),
                     description: None,
                     id: Some(SelectableId::Boolean(true)),
+                    present: false,
+                    note: None,
+                    warning: None,
                 },
             ],
             selected_indices: vec![],
             metadata: Some("seerr:is_4k".into()),
             field_type: FieldType::Dropdown,
             always_show: true,
+            status: None,
         });
 
         if result.media_type != "tv" {
@@ -406,6 +575,9 @@ impl MediaBackend for Seerr {
                     title: n.to_string(),
                     description: None,
                     id: Some(SelectableId::Integer(n)),
+                    present: false,
+                    note: None,
+                    warning: None,
                 }
             })
             .collect();
@@ -422,6 +594,9 @@ impl MediaBackend for Seerr {
                 title: "All Seasons".into(),
                 description: Some("Includes future seasons".into()),
                 id: Some(SelectableId::Integer(ALL_SEASONS_ID)),
+                present: false,
+                note: None,
+                warning: None,
             });
         }
 
@@ -444,6 +619,7 @@ impl MediaBackend for Seerr {
             metadata: Some("seerr:season".into()),
             field_type: FieldType::MultiSelect,
             always_show: true,
+            status: None,
         };
 
         Ok(quality_step
@@ -456,20 +632,21 @@ impl MediaBackend for Seerr {
         &self,
         details: Vec<RequestDetails>,
         media: Box<dyn MediaItem>,
-        requester_discord_id: u64,
+        requester: &Requester,
     ) -> Result<()> {
         let result = media
             .into_any()
             .downcast::<SeerrResult>()
             .map_err(|_| anyhow::anyhow!("Unexpected media type for Seerr backend"))?;
 
-        let seerr_user_id = match self.resolve_seerr_user(requester_discord_id).await? {
+        let seerr_user_id = match self.resolve_seerr_user(requester.user_id).await? {
             Some(id) => id,
             None => match self.fallback_user_id {
                 Some(id) => id,
                 None => bail!(UserFacingError(format!(
-                    "Your Discord account (ID: {requester_discord_id}) is not linked to a Seerr account. \
-                     To link it, go to your Seerr profile → Settings → Notifications → Discord and enter your Discord User ID."
+                    "Your Discord account (ID: {}) is not linked to a Seerr account. \
+                     To link it, go to your Seerr profile → Settings → Notifications → Discord and enter your Discord User ID.",
+                    requester.user_id
                 ))),
             },
         };
@@ -535,6 +712,7 @@ impl MediaBackend for Seerr {
                 summary: "Request submitted".into(),
                 description: "Your request has been submitted.".into(),
                 thumbnail_url: None,
+                details: None,
             };
         };
 
@@ -601,6 +779,27 @@ impl MediaBackend for Seerr {
             summary,
             description: "Your request has been submitted to Seerr.".into(),
             thumbnail_url,
+            details: None,
         }
     }
+
+    fn media_id(&self, media: &dyn MediaItem) -> Option<MediaId> {
+        let result = media.as_any().downcast_ref::<SeerrResult>()?;
+        // Search results can be people too
+        if !matches!(result.media_type.as_str(), "movie" | "tv") {
+            return None;
+        }
+        let info = result.media_info.as_deref();
+        Some(MediaId {
+            tmdb: Some(result.id as i32),
+            tvdb: info.and_then(|m| m.tvdb_id.flatten()).map(|id| id as i32),
+            backend_id: info.and_then(|m| m.id).map(|id| id as i32),
+            ..Default::default()
+        })
+    }
+
+    async fn version(&self) -> Result<Option<String>> {
+        let status = require(status_get(&self.config).await, "Seerr status")?;
+        Ok(status.version)
+    }
 }
@@ -1,21 +1,43 @@
-use super::*;
-use crate::{config::BackendConfig, discord::MAX_DROPDOWN_OPTIONS};
+use super::{
+    Requester, base_url,
+    cache::{Refreshing, SETTINGS_TTL},
+    *,
+};
+use crate::{
+    config::{BackendConfig, ResultField},
+    discord::MAX_DROPDOWN_OPTIONS,
+    export::parse_timestamp,
+};
 use anyhow::{Context, Result, bail};
 use async_trait::async_trait;
+#[allow(deprecated)] // Sonarr v4 dropped language profiles; v3 still has them
+use sonarr_api::apis::language_profile_api::api_v3_languageprofile_get;
 use sonarr_api::{
     apis::{
         Error as SonarrApiError,
+        calendar_api::api_v3_calendar_get,
         command_api::api_v3_command_post_custom,
         configuration::{ApiKey, Configuration},
         quality_profile_api::api_v3_qualityprofile_get,
+        queue_api::api_v3_queue_get,
         root_folder_api::api_v3_rootfolder_get,
-        series_api::{api_v3_series_id_get, api_v3_series_id_put, api_v3_series_post},
+        series_api::{
+            api_v3_series_get, api_v3_series_id_delete, api_v3_series_id_get, api_v3_series_id_put,
+            api_v3_series_post,
+        },
         series_lookup_api::api_v3_series_lookup_get,
+        system_api::api_v3_system_status_get,
+        tag_api::{api_v3_tag_get, api_v3_tag_post},
+    },
+    commands::{
+        MissingEpisodeSearchCommand, RefreshSeriesCommand, RssSyncCommand, SeasonSearchCommand,
+        SeriesSearchCommand,
     },
-    commands::SeasonSearchCommand,
     models::{
-        AddSeriesOptions, NewItemMonitorTypes, QualityProfileResource, RootFolderResource,
-        SeasonResource, SeriesResource, SeriesTypes,
+        AddSeriesOptions, LanguageProfileResource, NewItemMonitorTypes,
+        QualityProfileQualityItemResource, QualityProfileResource, QueueStatus, RootFolderResource,
+        SeasonResource, SeriesResource, SeriesStatusType, SeriesTypes, SystemResource, TagResource,
+        TrackedDownloadStatus,
     },
 };
 use tracing::{debug, error, info, trace, warn};
@@ -41,6 +63,26 @@ fn log_api_error<T: std::fmt::Debug>(err: &SonarrApiError<T>, context: &str) {
     }
 }
 
+/// Check the configured URL reaches Sonarr's API, so a wrong path prefix fails
+/// at connect time rather than as a 404 on every request
+async fn check_base_path(config: &Configuration) -> Result<SystemResource> {
+    match api_v3_system_status_get(config).await {
+        Ok(status) => Ok(status),
+        Err(e) => {
+            let status = match &e {
+                SonarrApiError::ResponseError(r) => Some(r.status),
+                _ => None,
+            };
+            let not_json = matches!(e, SonarrApiError::Serde(_));
+            if base_url::is_wrong_prefix(status, not_json) {
+                return Err(base_url::wrong_prefix("Sonarr", &config.base_path));
+            }
+            log_api_error(&e, "Failed to get Sonarr status");
+            Err(e.into())
+        }
+    }
+}
+
 /// Treat a 2xx response whose body fails to parse as success - by the time we're
 /// reading the body, Sonarr has already applied the change
 fn tolerate_response_parse_error<T, E>(
@@ -70,76 +112,98 @@ where
 pub struct Sonarr {
     config: Configuration,
     details: Details,
-    /// Whether Season 0 (specials) can be requested for existing series
+    /// Whether Season 0 (specials) can be requested, with its own yes/no field
     allow_specials: bool,
     /// Whether to offer an "All Seasons" option (all current + future seasons)
     allow_all_seasons: bool,
+    /// What search results show under their titles
+    result_fields: Vec<ResultField>,
 }
 
 #[derive(Debug, Clone)]
 // All the details we want to collect
 pub struct Details {
-    rootfolders: Vec<RootFolderResource>,
-    quality_profiles: Vec<QualityProfileResource>,
+    choices: Refreshing<Choices>,
     /// Config-pinned series type; when unset, it's auto-detected per series
     series_type: Option<SeriesTypes>,
     season_folder: Option<bool>,
 }
 
+#[derive(Debug)]
+/// The root folders and quality profiles Sonarr has, which can change while
+/// we run
+struct Choices {
+    rootfolders: Vec<RootFolderResource>,
+    quality_profiles: Vec<QualityProfileResource>,
+    /// Empty on Sonarr v4, which replaced language profiles with custom formats
+    language_profiles: Vec<LanguageProfileResource>,
+    /// Empty unless requesters may tag what they request
+    tags: Vec<TagResource>,
+}
+
 #[derive(Debug)]
 // The final details needed to complete the request
 pub struct SelectedDetails {
     pub rootfolder_path: Option<String>, // Only for new series - existing series inherit
     pub quality_profile_id: Option<i32>, // Only for new series
+    pub language_profile_id: Option<i32>, // Only for new series, on Sonarr v3
+    pub tag_ids: Vec<i32>,               // Only for new series
     pub series_type: Option<SeriesTypes>, // Only for new series
     pub season_folder: Option<bool>,     // Only for new series - existing series inherit
     /// Season numbers the user chose to monitor (both new and existing series)
     pub season_numbers: Vec<i32>,
     /// User chose "All Seasons" - monitor all current + future seasons
     pub all_seasons: bool,
+    /// User chose to include Season 0 (specials)
+    pub specials: bool,
+    /// User chose to have Sonarr monitor seasons that air later
+    pub new_seasons: bool,
 }
 
-impl Sonarr {
-    #[allow(clippy::too_many_arguments)]
-    /// Builds the Sonarr connection and attempts to use it
-    pub async fn new(
-        base_path: String,
-        key: String,
-        quality_profile: Option<String>,
+impl Choices {
+    /// Fetch what Sonarr offers, narrowed down to what the config pins
+    async fn fetch(
+        config: &Configuration,
         rootfolder: Option<String>,
-        series_type: Option<SeriesTypes>,
-        season_folder: Option<bool>,
-        allow_specials: bool,
-        allow_all_seasons: bool,
-        client: reqwest::Client,
+        quality_profile: Option<String>,
+        language_profile: Option<String>,
+        has_language_profiles: bool,
+        with_tags: bool,
     ) -> Result<Self> {
-        // Log connection before moving base_path
-        info!("Connecting to Sonarr at {}", base_path);
-
-        // Build the API config
-        let config = Configuration {
-            base_path,
-            user_agent: None,
-            client,
-            basic_auth: None,
-            oauth_access_token: None,
-            bearer_access_token: None,
-            api_key: Some(ApiKey { prefix: None, key }),
-        };
-
-        // Grab the additional details and use the config data to filter
-
-        // First query the things we have to check (this will fail if we can't connect to the server anyway)
-        let mut rootfolders = api_v3_rootfolder_get(&config).await.inspect_err(|e| {
+        let mut rootfolders = api_v3_rootfolder_get(config).await.inspect_err(|e| {
             log_api_error(e, "Failed to get root folders from Sonarr");
         })?;
         trace!("Retrieved {} root folders", rootfolders.len());
 
-        let mut quality_profiles = api_v3_qualityprofile_get(&config).await.inspect_err(|e| {
+        let mut quality_profiles = api_v3_qualityprofile_get(config).await.inspect_err(|e| {
             log_api_error(e, "Failed to get quality profiles from Sonarr");
         })?;
         trace!("Retrieved {} quality profiles", quality_profiles.len());
 
+        #[allow(deprecated)]
+        let mut language_profiles = if has_language_profiles {
+            api_v3_languageprofile_get(config).await.inspect_err(|e| {
+                log_api_error(e, "Failed to get language profiles from Sonarr");
+            })?
+        } else if language_profile.is_some() {
+            bail!(
+                "language_profile is set, but Sonarr v4 has no language profiles \
+                 (languages are chosen with custom formats in the quality profile)"
+            );
+        } else {
+            Vec::new()
+        };
+        trace!("Retrieved {} language profiles", language_profiles.len());
+
+        let tags = if with_tags {
+            api_v3_tag_get(config)
+                .await
+                .inspect_err(|e| log_api_error(e, "Failed to get Sonarr tags"))?
+        } else {
+            Vec::new()
+        };
+        trace!("Retrieved {} tags", tags.len());
+
         // Select rootfolder if given
         if let Some(rf) = rootfolder {
             // Get the index of the selection
@@ -182,10 +246,102 @@ impl Sonarr {
             quality_profiles = vec![selected];
         }
 
-        // Build the details
-        let details = Details {
+        // Select language profile if given
+        if let Some(lp) = language_profile {
+            let lp_idx = language_profiles
+                .iter()
+                .position(|x| matches!(&x.name, Some(Some(name)) if name == &lp))
+                .with_context(|| {
+                    let available = language_profiles
+                        .iter()
+                        .filter_map(|x| x.name.as_ref().and_then(|inner| inner.as_deref()))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    format!(
+                        "Language profile '{}' not found. Available options: [{}]",
+                        lp, available
+                    )
+                })?;
+            let selected = language_profiles.swap_remove(lp_idx);
+            language_profiles = vec![selected];
+        }
+
+        Ok(Self {
             rootfolders,
             quality_profiles,
+            language_profiles,
+            tags,
+        })
+    }
+}
+
+impl Sonarr {
+    #[allow(clippy::too_many_arguments)]
+    /// Builds the Sonarr connection and attempts to use it
+    pub async fn new(
+        base_path: String,
+        key: String,
+        quality_profile: Option<String>,
+        language_profile: Option<String>,
+        rootfolder: Option<String>,
+        series_type: Option<SeriesTypes>,
+        season_folder: Option<bool>,
+        allow_specials: bool,
+        allow_all_seasons: bool,
+        allow_tags: bool,
+        result_fields: Option<Vec<ResultField>>,
+        client: reqwest::Client,
+    ) -> Result<Self> {
+        let result_fields = super::result_fields(result_fields)?;
+        let base_path = base_url::normalize(&base_path);
+        info!("Connecting to Sonarr at {}", base_path);
+
+        // Build the API config
+        let config = Configuration {
+            base_path,
+            user_agent: None,
+            client,
+            basic_auth: None,
+            oauth_access_token: None,
+            bearer_access_token: None,
+            api_key: Some(ApiKey { prefix: None, key }),
+        };
+        let status = check_base_path(&config).await?;
+        // Language profiles are gone from v4 (an unknown version gets asked)
+        let has_language_profiles = status
+            .version
+            .flatten()
+            .and_then(|v| v.split('.').next()?.parse::<u32>().ok())
+            .is_none_or(|major| major < 4);
+
+        // Grab the additional details and use the config data to filter
+        let choices = Refreshing::new("root folders, profiles and tags", SETTINGS_TTL, {
+            let config = config.clone();
+            move || {
+                let (config, rootfolder, quality_profile, language_profile) = (
+                    config.clone(),
+                    rootfolder.clone(),
+                    quality_profile.clone(),
+                    language_profile.clone(),
+                );
+                async move {
+                    Choices::fetch(
+                        &config,
+                        rootfolder,
+                        quality_profile,
+                        language_profile,
+                        has_language_profiles,
+                        allow_tags,
+                    )
+                    .await
+                }
+            }
+        })
+        .await?;
+
+        // Build the details
+        let details = Details {
+            choices,
             series_type,
             season_folder,
         };
@@ -195,6 +351,7 @@ impl Sonarr {
             details,
             allow_specials,
             allow_all_seasons,
+            result_fields,
         })
     }
 
@@ -204,22 +361,28 @@ impl Sonarr {
             url,
             api_key,
             quality_profile,
+            language_profile,
             rootfolder,
             series_type,
             season_folders,
             allow_specials,
             allow_all_seasons,
+            allow_tags,
+            result_fields,
         } = backend
         {
             Self::new(
                 url,
                 api_key,
                 quality_profile,
+                language_profile,
                 rootfolder,
                 series_type,
                 season_folders,
                 allow_specials.unwrap_or(false),
                 allow_all_seasons.unwrap_or(true),
+                allow_tags.unwrap_or(false),
+                result_fields,
                 client,
             )
             .await
@@ -228,9 +391,73 @@ impl Sonarr {
         }
     }
 
+    /// Names of the quality profiles on offer, e.g. for `doplarr setup`
+    pub fn quality_profile_names(&self) -> Vec<String> {
+        self.details
+            .choices
+            .get()
+            .quality_profiles
+            .iter()
+            .filter_map(|p| p.name.clone().flatten())
+            .collect()
+    }
+
+    /// The ID of the quality profile on offer called `name`
+    fn quality_profile_id(&self, name: &str) -> Result<i32> {
+        self.details
+            .choices
+            .get()
+            .quality_profiles
+            .iter()
+            .find(|p| p.name.as_ref().and_then(|n| n.as_deref()) == Some(name))
+            .and_then(|p| p.id)
+            .ok_or_else(|| {
+                UserFacingError(format!(
+                    "There's no \"{name}\" quality profile. Pick one of: {}.",
+                    self.quality_profile_names().join(", ")
+                ))
+                .into()
+            })
+    }
+
+    /// The library's series titled `title`, ignoring case
+    async fn series_titled(&self, title: &str) -> Result<Vec<SeriesResource>> {
+        let series = api_v3_series_get(&self.config, None, Some(false))
+            .await
+            .inspect_err(|e| log_api_error(e, "Failed to list Sonarr series"))?;
+        let series: Vec<_> = series
+            .into_iter()
+            .filter(|s| {
+                s.title
+                    .as_ref()
+                    .and_then(|t| t.as_deref())
+                    .is_some_and(|t| t.eq_ignore_ascii_case(title))
+            })
+            .collect();
+        if series.is_empty() {
+            bail!(UserFacingError(format!(
+                "No series titled \"{title}\" in Sonarr."
+            )));
+        }
+        Ok(series)
+    }
+
+    /// Paths of the root folders on offer
+    pub fn rootfolder_paths(&self) -> Vec<String> {
+        self.details
+            .choices
+            .get()
+            .rootfolders
+            .iter()
+            .filter_map(|f| f.path.clone().flatten())
+            .collect()
+    }
+
     /// Builds the multi-select season picker, or `None` when the series exposes
-    /// no requestable seasons (after applying the specials filter). Already-
-    /// monitored seasons are shown but tagged, so users see the full list.
+    /// no requestable seasons. Specials have a field of their own, so Season 0
+    /// is never listed. Already-monitored seasons are shown but tagged, so
+    /// users see the full list. Series with more seasons than fit in a
+    /// dropdown offer their latest ones, which are what's usually wanted.
     fn build_season_picker(&self, media: &SeriesResource) -> Option<RequestDetails> {
         let Some(Some(seasons)) = &media.seasons else {
             return None;
@@ -244,7 +471,7 @@ impl Sonarr {
 
         let mut seasons: Vec<&SeasonResource> = seasons
             .iter()
-            .filter(|s| self.allow_specials || s.season_number.unwrap_or(0) != 0)
+            .filter(|s| s.season_number.unwrap_or(0) != 0)
             .collect();
 
         if seasons.is_empty() {
@@ -262,35 +489,55 @@ impl Sonarr {
                 title: "All Seasons".to_string(),
                 description: Some("Includes future seasons".to_string()),
                 id: Some(SelectableId::Integer(ALL_SEASONS_ID)),
+                present: false,
+                note: None,
+                warning: None,
             });
         }
 
         // Reserve a slot for the "All Seasons" entry against Discord's option cap
         let season_capacity = MAX_DROPDOWN_OPTIONS - options.len();
-        if seasons.len() > season_capacity {
+        let hidden = seasons.len().saturating_sub(season_capacity);
+        let status = (hidden > 0).then(|| {
             debug!(
                 total = seasons.len(),
                 showing = season_capacity,
                 "Truncating season list to fit Discord dropdown limit"
             );
-        }
+            let first = seasons[0].season_number.unwrap_or(0);
+            let last = seasons[hidden - 1].season_number.unwrap_or(0);
+            if first == last {
+                format!("Season {first} doesn't fit in the list")
+            } else {
+                format!("Seasons {first}–{last} don't fit in the list")
+            }
+        });
 
-        options.extend(seasons.into_iter().take(season_capacity).map(|s| {
+        options.extend(seasons.into_iter().skip(hidden).map(|s| {
             let n = s.season_number.unwrap_or(0);
             // Bare number as the label (matching the Seerr picker), with any
             // status tags in the subtitle.
             let mut tags = Vec::new();
-            if n == 0 {
-                tags.push("Specials");
-            }
             if series_exists && s.monitored.unwrap_or(false) {
                 tags.push("Already monitored");
             }
+            // Every aired episode has a file
+            let downloaded = series_exists
+                && s.statistics.as_ref().is_some_and(|st| {
+                    let files = st.episode_file_count.unwrap_or(0);
+                    files > 0 && files >= st.episode_count.unwrap_or(0)
+                });
+            if downloaded {
+                tags.push("Downloaded");
+            }
             let description = (!tags.is_empty()).then(|| tags.join(" · "));
             DropdownOption {
                 title: n.to_string(),
                 description,
                 id: Some(SelectableId::Integer(n)),
+                present: downloaded,
+                note: None,
+                warning: None,
             }
         }));
 
@@ -301,8 +548,163 @@ impl Sonarr {
             selected_indices: vec![],
             field_type: FieldType::MultiSelect,
             always_show: true,
+            status,
         })
     }
+
+    /// The "Include Specials" choice (default: no), or `None` when specials
+    /// aren't allowed, the series has none, or an existing series already
+    /// monitors them
+    fn build_specials_field(&self, media: &SeriesResource) -> Option<RequestDetails> {
+        if !self.allow_specials {
+            return None;
+        }
+        let Some(Some(seasons)) = &media.seasons else {
+            return None;
+        };
+        let specials = seasons.iter().find(|s| s.season_number == Some(0))?;
+        if media.id.is_some() && specials.monitored.unwrap_or(false) {
+            return None;
+        }
+        let option = |value: bool| DropdownOption {
+            title: if value { "Yes" } else { "No" }.to_string(),
+            description: None,
+            id: Some(SelectableId::Boolean(value)),
+            present: false,
+            note: None,
+            warning: None,
+        };
+        Some(RequestDetails {
+            title: "Include Specials".to_string(),
+            options: vec![option(true), option(false)],
+            selected_indices: vec![1],
+            metadata: Some(field_keys::SPECIALS.to_string()),
+            field_type: FieldType::Boolean,
+            always_show: false,
+            status: None,
+        })
+    }
+
+    /// The "Monitor New Seasons" choice (default: no), spelling out what each
+    /// answer means, or `None` when an existing series already monitors them
+    fn build_new_seasons_field(&self, media: &SeriesResource) -> Option<RequestDetails> {
+        if media.id.is_some() && media.monitor_new_items == Some(NewItemMonitorTypes::All) {
+            return None;
+        }
+        let option = |value: bool| DropdownOption {
+            title: if value { "Yes" } else { "No" }.to_string(),
+            description: Some(
+                if value {
+                    "Seasons that air later are grabbed automatically"
+                } else {
+                    "Only the seasons picked here are grabbed"
+                }
+                .to_string(),
+            ),
+            id: Some(SelectableId::Boolean(value)),
+            present: false,
+            note: None,
+            warning: None,
+        };
+        Some(RequestDetails {
+            title: "Monitor New Seasons".to_string(),
+            options: vec![option(true), option(false)],
+            selected_indices: vec![1],
+            metadata: Some(field_keys::NEW_SEASONS.to_string()),
+            field_type: FieldType::Boolean,
+            always_show: false,
+            status: None,
+        })
+    }
+
+    /// IDs of the tags with these labels, creating the missing ones. Sonarr
+    /// matches labels without regard to case.
+    async fn tag_ids(&self, labels: &[String]) -> Result<Vec<i32>> {
+        let existing = api_v3_tag_get(&self.config)
+            .await
+            .inspect_err(|e| log_api_error(e, "Failed to get Sonarr tags"))?;
+        let mut ids = Vec::with_capacity(labels.len());
+        for label in labels {
+            let found = existing.iter().find(|t| {
+                t.label
+                    .as_ref()
+                    .and_then(|l| l.as_deref())
+                    .is_some_and(|l| l.eq_ignore_ascii_case(label))
+            });
+            let tag = match found {
+                Some(tag) => tag.clone(),
+                None => api_v3_tag_post(
+                    &self.config,
+                    Some(TagResource {
+                        id: None,
+                        label: Some(Some(label.clone())),
+                    }),
+                )
+                .await
+                .inspect_err(|e| log_api_error(e, "Failed to create Sonarr tag"))?,
+            };
+            ids.extend(tag.id);
+        }
+        Ok(ids)
+    }
+}
+
+/// Where an existing series' seasons stand, one line per group, e.g.
+/// "Downloaded: 1, 2" / "Monitored, missing episodes: 3 (4 of 10)" /
+/// "Not monitored: 4, 5". `None` when there are no seasons to describe.
+fn season_breakdown<'a>(seasons: impl IntoIterator<Item = &'a SeasonResource>) -> Option<String> {
+    let mut downloaded = Vec::new();
+    let mut missing = Vec::new();
+    let mut unmonitored = Vec::new();
+    let mut seasons: Vec<_> = seasons.into_iter().collect();
+    seasons.sort_by_key(|s| s.season_number.unwrap_or(0));
+    for season in seasons {
+        let n = season.season_number.unwrap_or(0);
+        let (files, episodes) = season.statistics.as_ref().map_or((0, 0), |st| {
+            (
+                st.episode_file_count.unwrap_or(0),
+                st.episode_count.unwrap_or(0),
+            )
+        });
+        let name = if n == 0 {
+            "Specials".to_string()
+        } else {
+            n.to_string()
+        };
+        let label = if files > 0 && files < episodes {
+            format!("{name} ({files} of {episodes})")
+        } else {
+            name
+        };
+        if files > 0 && files >= episodes {
+            downloaded.push(label);
+        } else if season.monitored.unwrap_or(false) {
+            missing.push(label);
+        } else {
+            unmonitored.push(label);
+        }
+    }
+    let lines: Vec<_> = [
+        ("Downloaded", downloaded),
+        ("Monitored, missing episodes", missing),
+        ("Not monitored", unmonitored),
+    ]
+    .into_iter()
+    .filter(|(_, seasons)| !seasons.is_empty())
+    .map(|(group, seasons)| format!("{group}: {}", seasons.join(", ")))
+    .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// How a series' status reads in its search result, e.g. "Continuing"
+fn status_label(status: SeriesStatusType) -> String {
+    match status {
+        SeriesStatusType::Continuing => "Continuing",
+        SeriesStatusType::Ended => "Ended",
+        SeriesStatusType::Upcoming => "Upcoming",
+        SeriesStatusType::Deleted => "Deleted",
+    }
+    .to_string()
 }
 
 /// Helper function to get to and from stringified references
@@ -343,13 +745,73 @@ mod field_keys {
     pub const ROOT_FOLDER: &str = "sonarr:root_folder";
     pub const SERIES_TYPE: &str = "sonarr:series_type";
     pub const QUALITY_PROFILE: &str = "sonarr:quality_profile";
+    pub const LANGUAGE_PROFILE: &str = "sonarr:language_profile";
     pub const SEASON_FOLDER: &str = "sonarr:season_folder";
     pub const SEASON: &str = "sonarr:season";
+    pub const SPECIALS: &str = "sonarr:specials";
+    pub const NEW_SEASONS: &str = "sonarr:new_seasons";
+    pub const TAGS: &str = "sonarr:tags";
+}
+
+impl QualityItem for QualityProfileQualityItemResource {
+    fn allowed(&self) -> bool {
+        self.allowed.unwrap_or(false)
+    }
+
+    fn group(&self) -> (Option<i32>, Option<String>) {
+        (self.id, self.name.clone().flatten())
+    }
+
+    fn quality(&self) -> Option<(Option<i32>, Option<String>, Option<i32>)> {
+        let quality = self.quality.as_ref()?;
+        Some((
+            quality.id,
+            quality.name.clone().flatten(),
+            quality.resolution,
+        ))
+    }
+
+    fn items(&self) -> &[Self] {
+        self.items
+            .as_ref()
+            .and_then(|i| i.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+fn profile_summary(profile: &QualityProfileResource) -> Option<String> {
+    let mut resolutions = Vec::new();
+    let mut cutoff_name = None;
+    let items = profile.items.as_ref().and_then(|i| i.as_deref());
+    collect_qualities(
+        items.unwrap_or_default(),
+        false,
+        profile.cutoff,
+        &mut resolutions,
+        &mut cutoff_name,
+    );
+    quality_profile_summary(
+        &resolutions,
+        cutoff_name.as_deref(),
+        profile.upgrade_allowed.unwrap_or(false),
+    )
+}
+
+/// The languages a language profile allows, e.g. "English, Japanese"
+fn language_summary(profile: &LanguageProfileResource) -> Option<String> {
+    let items = profile.languages.as_ref().and_then(|l| l.as_deref())?;
+    let names: Vec<_> = items
+        .iter()
+        .filter(|item| item.allowed.unwrap_or(false))
+        .filter_map(|item| item.language.as_ref()?.name.clone().flatten())
+        .collect();
+    (!names.is_empty()).then(|| names.join(", "))
 }
 
 impl From<Details> for Vec<RequestDetails> {
     fn from(details: Details) -> Vec<RequestDetails> {
-        let quality_profile_options = details
+        let choices = details.choices.get();
+        let quality_profile_options = choices
             .quality_profiles
             .iter()
             .filter_map(|x| {
@@ -359,8 +821,11 @@ impl From<Details> for Vec<RequestDetails> {
                 }
                 name.map(|n| DropdownOption {
                     title: n,
-                    description: None,
+                    description: profile_summary(x),
                     id: x.id.map(SelectableId::Integer),
+                    present: false,
+                    note: None,
+                    warning: None,
                 })
             })
             .collect();
@@ -372,9 +837,41 @@ impl From<Details> for Vec<RequestDetails> {
             selected_indices: vec![],
             field_type: FieldType::Dropdown,
             always_show: false,
+            status: None,
         };
 
-        let rootfolder_options = details
+        // Only Sonarr v3 has language profiles to pick from
+        let language_profile_details = (!choices.language_profiles.is_empty()).then(|| {
+            let options = choices
+                .language_profiles
+                .iter()
+                .filter_map(|x| {
+                    let name = x.name.clone().flatten();
+                    if name.is_none() {
+                        warn!("Skipping language profile with no name (id: {:?})", x.id);
+                    }
+                    name.map(|n| DropdownOption {
+                        title: n,
+                        description: language_summary(x),
+                        id: x.id.map(SelectableId::Integer),
+                        present: false,
+                        note: None,
+                        warning: None,
+                    })
+                })
+                .collect();
+            RequestDetails {
+                title: "Language Profile".to_string(),
+                options,
+                metadata: Some(field_keys::LANGUAGE_PROFILE.to_string()),
+                selected_indices: vec![],
+                field_type: FieldType::Dropdown,
+                always_show: false,
+                status: None,
+            }
+        });
+
+        let rootfolder_options = choices
             .rootfolders
             .iter()
             .filter_map(|x| {
@@ -386,6 +883,9 @@ impl From<Details> for Vec<RequestDetails> {
                     title: p,
                     description: None,
                     id: x.id.map(SelectableId::Integer),
+                    present: false,
+                    note: None,
+                    warning: None,
                 })
             })
             .collect();
@@ -397,6 +897,7 @@ impl From<Details> for Vec<RequestDetails> {
             selected_indices: vec![],
             field_type: FieldType::Dropdown,
             always_show: false,
+            status: None,
         };
 
         // Season folder boolean option - show both if None, or just the config value if Some
@@ -407,6 +908,9 @@ impl From<Details> for Vec<RequestDetails> {
                     title: if value { "Yes" } else { "No" }.to_string(),
                     description: None,
                     id: Some(SelectableId::Boolean(value)),
+                    present: false,
+                    note: None,
+                    warning: None,
                 }]
             }
             None => {
@@ -416,11 +920,17 @@ impl From<Details> for Vec<RequestDetails> {
                         title: "Yes".to_string(),
                         description: None,
                         id: Some(SelectableId::Boolean(true)),
+                        present: false,
+                        note: None,
+                        warning: None,
                     },
                     DropdownOption {
                         title: "No".to_string(),
                         description: None,
                         id: Some(SelectableId::Boolean(false)),
+                        present: false,
+                        note: None,
+                        warning: None,
                     },
                 ]
             }
@@ -433,13 +943,22 @@ impl From<Details> for Vec<RequestDetails> {
             selected_indices: vec![],
             field_type: FieldType::Boolean,
             always_show: false,
+            status: None,
         };
 
-        vec![
-            rootfolder_details,
-            quality_profile_details,
-            season_folder_details,
-        ]
+        let tags_details = tags_field(
+            field_keys::TAGS,
+            choices
+                .tags
+                .iter()
+                .filter_map(|t| Some((t.id?, t.label.clone().flatten()?))),
+        );
+
+        let mut details = vec![rootfolder_details, quality_profile_details];
+        details.extend(language_profile_details);
+        details.push(season_folder_details);
+        details.extend(tags_details);
+        details
     }
 }
 
@@ -449,10 +968,14 @@ impl TryFrom<Vec<RequestDetails>> for SelectedDetails {
     fn try_from(details: Vec<RequestDetails>) -> Result<Self> {
         let mut root_folder_path = None;
         let mut quality_profile_id = None;
+        let mut language_profile_id = None;
+        let mut tag_ids = Vec::new();
         let mut series_type = None;
         let mut season_folder = None;
         let mut season_numbers = Vec::new();
         let mut all_seasons = false;
+        let mut specials = false;
+        let mut new_seasons = false;
 
         for detail in &details {
             // The season picker is multi-select; collect every chosen season.
@@ -466,6 +989,11 @@ impl TryFrom<Vec<RequestDetails>> for SelectedDetails {
                 }
                 continue;
             }
+            // Tags are multi-select too, and picking none is fine
+            if detail.metadata.as_deref() == Some(field_keys::TAGS) {
+                tag_ids = picked_tags(detail)?;
+                continue;
+            }
 
             let Some(selection) = detail.selected_option() else {
                 bail!("No option was selected for '{}'", detail.title);
@@ -481,6 +1009,12 @@ impl TryFrom<Vec<RequestDetails>> for SelectedDetails {
                         other => bail!("Quality profile must have an integer ID, got {other:?}"),
                     };
                 }
+                Some(field_keys::LANGUAGE_PROFILE) => {
+                    language_profile_id = match &selection.id {
+                        Some(SelectableId::Integer(i)) => Some(*i),
+                        other => bail!("Language profile must have an integer ID, got {other:?}"),
+                    };
+                }
                 Some(field_keys::SERIES_TYPE) => {
                     series_type = match &selection.id {
                         Some(SelectableId::String(s)) => Some(deserialize_from_string(s)?),
@@ -493,6 +1027,18 @@ impl TryFrom<Vec<RequestDetails>> for SelectedDetails {
                         other => bail!("Season folder must have a boolean ID, got {other:?}"),
                     };
                 }
+                Some(field_keys::SPECIALS) => {
+                    specials = match &selection.id {
+                        Some(SelectableId::Boolean(b)) => *b,
+                        other => bail!("Specials must have a boolean ID, got {other:?}"),
+                    };
+                }
+                Some(field_keys::NEW_SEASONS) => {
+                    new_seasons = match &selection.id {
+                        Some(SelectableId::Boolean(b)) => *b,
+                        other => bail!("New seasons must have a boolean ID, got {other:?}"),
+                    };
+                }
                 other => bail!("Unknown metadata key: {other:?}"),
             }
         }
@@ -500,10 +1046,14 @@ impl TryFrom<Vec<RequestDetails>> for SelectedDetails {
         Ok(Self {
             rootfolder_path: root_folder_path, // Optional - only for new series
             quality_profile_id,                // Optional - only for new series
+            language_profile_id,               // Optional - only on Sonarr v3
+            tag_ids,                           // Optional - only for new series
             series_type,                       // Optional - only for new series
             season_folder,                     // Optional - only for new series
             season_numbers,
             all_seasons,
+            specials,
+            new_seasons,
         })
     }
 }
@@ -514,9 +1064,27 @@ impl MediaItem for SeriesResource {
             title: self.title.clone().flatten().unwrap_or_default(),
             description: self.year.map(|y| y.to_string()),
             id: self.id.map(SelectableId::Integer),
+            present: false,
+            note: None,
+            warning: None,
         }
     }
 
+    fn year(&self) -> Option<i32> {
+        self.year
+    }
+
+    fn kind(&self) -> Option<MediaKind> {
+        Some(MediaKind::Tv)
+    }
+
+    fn certification(&self) -> Option<String> {
+        self.certification
+            .clone()
+            .flatten()
+            .filter(|c| !c.is_empty())
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -528,6 +1096,11 @@ impl MediaItem for SeriesResource {
 
 #[async_trait]
 impl MediaBackend for Sonarr {
+    fn cache_stats(&self) -> Vec<(&'static str, cache::CacheStats)> {
+        let choices = &self.details.choices;
+        vec![(choices.name(), choices.stats())]
+    }
+
     async fn search(&self, term: &str) -> Result<Vec<Box<dyn MediaItem>>> {
         info!("Searching Sonarr for series: {}", term);
         let results = api_v3_series_lookup_get(&self.config, Some(term))
@@ -542,6 +1115,34 @@ impl MediaBackend for Sonarr {
             .collect())
     }
 
+    fn to_dropdown_options(&self, results: &[Box<dyn MediaItem>]) -> Vec<DropdownOption> {
+        results
+            .iter()
+            .map(|result| {
+                let mut option = result.to_dropdown();
+                if let Some(series) = result.as_any().downcast_ref::<SeriesResource>() {
+                    option.description =
+                        result_description(&self.result_fields, |field| match field {
+                            ResultField::Year => {
+                                series.year.filter(|&y| y > 0).map(|y| y.to_string())
+                            }
+                            ResultField::Status => series.status.map(status_label),
+                            ResultField::Network => series.network.clone().flatten(),
+                            ResultField::Runtime => series.runtime.and_then(runtime_label),
+                            ResultField::Genres => genres_label(
+                                series
+                                    .genres
+                                    .as_ref()
+                                    .and_then(|g| g.as_deref())
+                                    .unwrap_or_default(),
+                            ),
+                        });
+                }
+                option
+            })
+            .collect()
+    }
+
     fn early_stop(&self, media: &dyn MediaItem) -> bool {
         let Some(media) = media.as_any().downcast_ref::<SeriesResource>() else {
             error!("early_stop called with wrong media type for Sonarr backend");
@@ -596,16 +1197,28 @@ impl MediaBackend for Sonarr {
 
         let mut details: Vec<RequestDetails> = self.details.clone().into();
 
-        if media.id.is_some() {
+        // The library's own view of an existing series, so the season
+        // breakdown is current rather than whatever the lookup had cached
+        let mut library = None;
+        if let Some(id) = media.id {
             // Existing series: every add-time setting is inherited, so the only
             // thing to collect is which seasons to monitor.
             debug!("Series already exists, showing only the season picker");
             details.clear();
+            library = api_v3_series_id_get(&self.config, id, Some(false))
+                .await
+                .inspect_err(
+                    |e| warn!(error = ?e, "Couldn't get series statistics, using the lookup's"),
+                )
+                .ok();
         } else {
             // New series: series type is Sonarr arcana most requesters won't
             // understand, so don't ask - use the config pin if present,
-            // otherwise auto-detect anime from the lookup's genres
-            let series_type = self.details.series_type.unwrap_or_else(|| {
+            // otherwise auto-detect anime from the lookup's genres. An
+            // auto-detected type is only preselected, so a defaults profile
+            // can still override it.
+            let pinned = self.details.series_type;
+            let series_type = pinned.unwrap_or_else(|| {
                 let is_anime = matches!(&media.genres, Some(Some(genres))
                     if genres.iter().any(|g| g.eq_ignore_ascii_case("anime")));
                 if is_anime {
@@ -616,22 +1229,39 @@ impl MediaBackend for Sonarr {
             });
             debug!(series_type = %series_type, "Resolved series type");
 
-            let title = match series_type {
-                SeriesTypes::Standard => "Standard",
-                SeriesTypes::Daily => "Daily",
-                SeriesTypes::Anime => "Anime",
+            let types = match pinned {
+                Some(t) => vec![t],
+                None => vec![
+                    SeriesTypes::Standard,
+                    SeriesTypes::Daily,
+                    SeriesTypes::Anime,
+                ],
             };
+            let options = types
+                .iter()
+                .map(|t| DropdownOption {
+                    title: match t {
+                        SeriesTypes::Standard => "Standard",
+                        SeriesTypes::Daily => "Daily",
+                        SeriesTypes::Anime => "Anime",
+                    }
+                    .to_string(),
+                    description: None,
+                    id: Some(SelectableId::String(t.to_string())),
+                    present: false,
+                    note: None,
+                    warning: None,
+                })
+                .collect();
+            let selected = types.iter().position(|t| *t == series_type);
             details.push(RequestDetails {
                 title: "Series Type".to_string(),
-                options: vec![DropdownOption {
-                    title: title.to_string(),
-                    description: None,
-                    id: Some(SelectableId::String(series_type.to_string())),
-                }],
+                options,
                 metadata: Some(field_keys::SERIES_TYPE.to_string()),
-                selected_indices: vec![],
+                selected_indices: selected.into_iter().collect(),
                 field_type: FieldType::Dropdown,
                 always_show: false,
+                status: None,
             });
         }
 
@@ -639,10 +1269,30 @@ impl MediaBackend for Sonarr {
         // show every requestable season - including ones already monitored on
         // an existing series - and reject already-monitored picks at request
         // time rather than hiding them from the list.
-        let Some(season_picker) = self.build_season_picker(media) else {
+        let media = library.as_ref().unwrap_or(media);
+        let season_picker = self.build_season_picker(media);
+        let specials = self.build_specials_field(media);
+        if season_picker.is_none() && specials.is_none() {
             bail!(UserFacingError("No requestable seasons found.".into()));
-        };
-        details.push(season_picker);
+        }
+        if let Some(mut season_picker) = season_picker {
+            if media.id.is_some()
+                && let Some(Some(seasons)) = &media.seasons
+            {
+                let breakdown = season_breakdown(
+                    seasons
+                        .iter()
+                        .filter(|s| self.allow_specials || s.season_number.unwrap_or(0) != 0),
+                );
+                season_picker.status = match (breakdown, season_picker.status.take()) {
+                    (Some(breakdown), Some(hidden)) => Some(format!("{breakdown}\n{hidden}")),
+                    (breakdown, hidden) => breakdown.or(hidden),
+                };
+            }
+            details.push(season_picker);
+        }
+        details.extend(specials);
+        details.extend(self.build_new_seasons_field(media));
 
         Ok(details)
     }
@@ -651,7 +1301,7 @@ impl MediaBackend for Sonarr {
         &self,
         details: Vec<RequestDetails>,
         media: Box<dyn MediaItem>,
-        _requester_discord_id: u64,
+        _requester: &Requester,
     ) -> Result<()> {
         let selected = SelectedDetails::try_from(details)?;
 
@@ -667,7 +1317,11 @@ impl MediaBackend for Sonarr {
             media.tvdb_id
         );
 
-        if selected.season_numbers.is_empty() && !selected.all_seasons {
+        if selected.season_numbers.is_empty()
+            && !selected.all_seasons
+            && !selected.specials
+            && !selected.new_seasons
+        {
             bail!(UserFacingError("No seasons were selected.".into()));
         }
 
@@ -683,9 +1337,10 @@ impl MediaBackend for Sonarr {
                 })?;
 
             // Determine which seasons still need monitoring (additive only).
-            // For "All Seasons" that's every currently-unmonitored season
-            // (specials gated); otherwise the picks minus what's already on.
-            let to_monitor: Vec<i32> = if selected.all_seasons {
+            // For "All Seasons" that's every currently-unmonitored season;
+            // otherwise the picks minus what's already on. Specials only when
+            // asked for.
+            let mut to_monitor: Vec<i32> = if selected.all_seasons {
                 existing_series
                     .seasons
                     .as_ref()
@@ -693,7 +1348,7 @@ impl MediaBackend for Sonarr {
                     .map(|seasons| {
                         seasons
                             .iter()
-                            .filter(|s| self.allow_specials || s.season_number.unwrap_or(0) != 0)
+                            .filter(|s| s.season_number.unwrap_or(0) != 0)
                             .filter(|s| !s.monitored.unwrap_or(false))
                             .filter_map(|s| s.season_number)
                             .collect()
@@ -714,10 +1369,15 @@ impl MediaBackend for Sonarr {
                     .unwrap_or_default();
                 seasons_to_monitor(&selected.season_numbers, &already_monitored)
             };
+            if selected.specials {
+                to_monitor.push(0);
+            }
 
-            // "All Seasons" also keeps future seasons monitored, so it's still a
-            // meaningful change even when every current season is already on.
-            if to_monitor.is_empty() && !selected.all_seasons {
+            // "All Seasons" and "Monitor New Seasons" also keep future seasons
+            // monitored, so they're still a meaningful change even when every
+            // current season is already on.
+            let monitor_new = selected.all_seasons || selected.new_seasons;
+            if to_monitor.is_empty() && !monitor_new {
                 bail!(UserFacingError(format!(
                     "{} already monitored.",
                     format_seasons(&selected.season_numbers)
@@ -740,7 +1400,7 @@ impl MediaBackend for Sonarr {
                 }
             }
             existing_series.monitored = Some(true);
-            if selected.all_seasons {
+            if monitor_new {
                 existing_series.monitor_new_items = Some(NewItemMonitorTypes::All);
             }
 
@@ -786,14 +1446,15 @@ impl MediaBackend for Sonarr {
                 selected.season_numbers
             );
 
-            // Monitor exactly the requested seasons (or all of them, specials
-            // gated, for "All Seasons"); everything else off. Like Seerr, the
-            // explicit season list drives monitoring rather than the monitor
-            // enum.
+            // Monitor exactly the requested seasons (or all of them for "All
+            // Seasons"), plus specials when asked for; everything else off.
+            // Like Seerr, the explicit season list drives monitoring rather
+            // than the monitor enum.
             if let Some(Some(seasons)) = media.seasons.as_mut() {
                 for season in seasons.iter_mut() {
                     let monitored = match season.season_number {
-                        Some(n) if selected.all_seasons => self.allow_specials || n != 0,
+                        Some(0) => selected.specials,
+                        Some(_) if selected.all_seasons => true,
                         Some(n) => selected.season_numbers.contains(&n),
                         None => false,
                     };
@@ -812,9 +1473,20 @@ impl MediaBackend for Sonarr {
             media.season_folder = Some(season_folder);
             media.monitored = Some(true);
             media.quality_profile_id = Some(quality_profile_id);
+            if selected.language_profile_id.is_some() {
+                media.language_profile_id = selected.language_profile_id;
+            }
+            if !selected.tag_ids.is_empty() {
+                media
+                    .tags
+                    .get_or_insert_default()
+                    .get_or_insert_default()
+                    .extend(&selected.tag_ids);
+            }
             media.series_type = Some(series_type);
-            // Keep grabbing future seasons too when "All Seasons" was chosen
-            if selected.all_seasons {
+            // Keep grabbing future seasons too when "All Seasons" or "Monitor
+            // New Seasons" was chosen
+            if selected.all_seasons || selected.new_seasons {
                 media.monitor_new_items = Some(NewItemMonitorTypes::All);
             }
 
@@ -836,6 +1508,7 @@ impl MediaBackend for Sonarr {
                 summary: "Request submitted".into(),
                 description: "Will be downloaded when available.".into(),
                 thumbnail_url: None,
+                details: None,
             };
         };
 
@@ -856,26 +1529,366 @@ impl MediaBackend for Sonarr {
             })
             .unwrap_or_default();
 
-        let detail_text = if season_nums.contains(&ALL_SEASONS_ID) {
-            " (All Seasons)".to_string()
+        let chose = |key: &str| {
+            details.iter().any(|d| {
+                d.metadata.as_deref() == Some(key)
+                    && d.selected_option()
+                        .is_some_and(|o| matches!(o.id, Some(SelectableId::Boolean(true))))
+            })
+        };
+        let all_seasons = season_nums.contains(&ALL_SEASONS_ID);
+        let seasons = if all_seasons {
+            "All Seasons".to_string()
         } else {
-            match format_seasons(&season_nums) {
-                s if s.is_empty() => String::new(),
-                s => format!(" ({s})"),
-            }
+            format_seasons(&season_nums)
+        };
+        let mut parts: Vec<&str> = Vec::new();
+        if !seasons.is_empty() {
+            parts.push(&seasons);
+        }
+        if chose(field_keys::SPECIALS) {
+            parts.push("Specials");
+        }
+        // "All Seasons" already says future seasons are coming
+        if !all_seasons && chose(field_keys::NEW_SEASONS) {
+            parts.push("New Seasons");
+        }
+        let detail_text = if parts.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", parts.join(" + "))
         };
 
         SuccessMessage {
             summary: format!("{title} ({year}){detail_text}"),
             description: "Will be downloaded when available.".to_string(),
             thumbnail_url: media.remote_poster.clone().flatten(),
+            details: None,
         }
     }
+
+    async fn calendar(&self, start: &str, end: &str) -> Result<Vec<CalendarEntry>> {
+        let episodes = api_v3_calendar_get(
+            &self.config,
+            Some(start.to_string()),
+            Some(end.to_string()),
+            Some(false),
+            Some(true),
+            None,
+            None,
+            None,
+        )
+        .await
+        .inspect_err(|e| {
+            log_api_error(e, "Failed to get the Sonarr calendar");
+        })?;
+        debug!("Found {} episodes on the calendar", episodes.len());
+
+        Ok(episodes
+            .into_iter()
+            .filter_map(|ep| {
+                let date = ep.air_date.clone().flatten()?;
+                let series = ep
+                    .series
+                    .as_ref()
+                    .and_then(|s| s.title.clone().flatten())
+                    .unwrap_or_default();
+                let number = format!(
+                    "S{:02}E{:02}",
+                    ep.season_number.unwrap_or(0),
+                    ep.episode_number.unwrap_or(0)
+                );
+                let detail = match ep.title.clone().flatten() {
+                    Some(title) => format!("{number} · {title}"),
+                    None => number,
+                };
+                Some(CalendarEntry {
+                    date,
+                    at: ep
+                        .air_date_utc
+                        .as_ref()
+                        .and_then(|t| parse_timestamp(t.as_deref()?)),
+                    title: series,
+                    detail,
+                })
+            })
+            .collect())
+    }
+
+    async fn version(&self) -> Result<Option<String>> {
+        let status = api_v3_system_status_get(&self.config)
+            .await
+            .inspect_err(|e| log_api_error(e, "Failed to get Sonarr status"))?;
+        Ok(status.version.flatten())
+    }
+
+    fn media_id(&self, media: &dyn MediaItem) -> Option<MediaId> {
+        let series = media.as_any().downcast_ref::<SeriesResource>()?;
+        Some(MediaId {
+            tmdb: series.tmdb_id,
+            tvdb: series.tvdb_id,
+            imdb: series.imdb_id.clone().flatten(),
+            backend_id: series.id,
+        })
+    }
+
+    async fn apply_tags(&self, id: &MediaId, tags: &[String]) -> Result<()> {
+        let Some(tvdb_id) = id.tvdb else {
+            return Ok(());
+        };
+        let series = api_v3_series_get(&self.config, Some(tvdb_id), Some(false))
+            .await
+            .inspect_err(|e| log_api_error(e, "Failed to look up Sonarr series"))?;
+        let Some(mut series) = series.into_iter().next() else {
+            return Ok(());
+        };
+        let series_id = series.id.context("Sonarr series has no ID")?;
+        let mut tag_ids = series.tags.clone().flatten().unwrap_or_default();
+        let missing: Vec<_> = self
+            .tag_ids(tags)
+            .await?
+            .into_iter()
+            .filter(|id| !tag_ids.contains(id))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        tag_ids.extend(missing);
+        series.tags = Some(Some(tag_ids));
+        tolerate_response_parse_error(
+            api_v3_series_id_put(&self.config, &series_id.to_string(), None, Some(series)).await,
+            "Failed to tag series in Sonarr",
+        )?;
+        info!(series_id, ?tags, "Tagged series");
+        Ok(())
+    }
+
+    async fn search_title(&self, id: &MediaId) -> Result<bool> {
+        let Some(tvdb_id) = id.tvdb else {
+            return Ok(false);
+        };
+        let series = api_v3_series_get(&self.config, Some(tvdb_id), Some(false))
+            .await
+            .inspect_err(|e| log_api_error(e, "Failed to look up Sonarr series"))?;
+        let Some(series_id) = series.into_iter().next().and_then(|s| s.id) else {
+            return Ok(false);
+        };
+        tolerate_response_parse_error(
+            api_v3_command_post_custom(&self.config, &SeriesSearchCommand::new(series_id)).await,
+            "Failed to trigger Sonarr series search",
+        )?;
+        info!(series_id, "Queued series search");
+        Ok(true)
+    }
+
+    async fn download_status(&self, id: &MediaId) -> Result<Option<DownloadStatus>> {
+        let Some(tvdb_id) = id.tvdb else {
+            return Ok(None);
+        };
+        let series = api_v3_series_get(&self.config, Some(tvdb_id), Some(false))
+            .await
+            .inspect_err(|e| log_api_error(e, "Failed to look up Sonarr series"))?;
+        let Some(series) = series.into_iter().next() else {
+            return Ok(None);
+        };
+        // Every monitored, aired episode has a file
+        if series.statistics.as_ref().is_some_and(|st| {
+            let files = st.episode_file_count.unwrap_or(0);
+            files > 0 && files >= st.episode_count.unwrap_or(0)
+        }) {
+            return Ok(Some(DownloadStatus::Downloaded));
+        }
+        let Some(series_id) = series.id else {
+            return Ok(None);
+        };
+
+        let queue = api_v3_queue_get(
+            &self.config,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(vec![series_id]),
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .inspect_err(|e| log_api_error(e, "Failed to get the Sonarr queue"))?;
+        let records = queue.records.flatten().unwrap_or_default();
+        // Season searches only queue what's missing, so an empty queue means
+        // the search is still running (or found nothing yet)
+        if records.is_empty() {
+            return Ok(Some(DownloadStatus::Searching));
+        }
+        // A season is done when its last episode is, so report the latest
+        let eta = records
+            .iter()
+            .filter_map(|r| parse_timeleft(r.timeleft.as_ref()?.as_deref()?))
+            .max();
+        Ok(Some(DownloadStatus::Grabbed { eta }))
+    }
+
+    async fn queue(&self) -> Result<Vec<QueueItem>> {
+        let queue = api_v3_queue_get(
+            &self.config,
+            None,
+            Some(QUEUE_PAGE_SIZE),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .inspect_err(|e| log_api_error(e, "Failed to get the Sonarr queue"))?;
+        Ok(queue
+            .records
+            .flatten()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|r| {
+                let flagged = matches!(
+                    r.tracked_download_status,
+                    Some(TrackedDownloadStatus::Warning | TrackedDownloadStatus::Error)
+                ) || r.status == Some(QueueStatus::Failed);
+                let messages = r
+                    .status_messages
+                    .flatten()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .flat_map(|m| m.messages.flatten().unwrap_or_default());
+                Some(QueueItem {
+                    id: r.id?,
+                    title: r.title.flatten().unwrap_or_default(),
+                    size_left: r.sizeleft.unwrap_or(0.0) as u64,
+                    downloading: r.status == Some(QueueStatus::Downloading),
+                    problem: queue_problem(flagged, r.error_message.flatten().as_deref(), messages),
+                })
+            })
+            .collect())
+    }
+
+    async fn maintenance(&self, task: Maintenance, title: Option<&str>) -> Result<String> {
+        match (task, title) {
+            (Maintenance::RssSync, _) => {
+                tolerate_response_parse_error(
+                    api_v3_command_post_custom(&self.config, &RssSyncCommand::new()).await,
+                    "Failed to trigger Sonarr RSS sync",
+                )?;
+                Ok("RSS sync queued.".to_string())
+            }
+            (Maintenance::SearchMissing, _) => {
+                tolerate_response_parse_error(
+                    api_v3_command_post_custom(&self.config, &MissingEpisodeSearchCommand::new())
+                        .await,
+                    "Failed to trigger Sonarr missing episode search",
+                )?;
+                Ok("Search for missing episodes queued.".to_string())
+            }
+            (Maintenance::Refresh, None) => {
+                tolerate_response_parse_error(
+                    api_v3_command_post_custom(&self.config, &RefreshSeriesCommand::new(None))
+                        .await,
+                    "Failed to trigger Sonarr refresh",
+                )?;
+                Ok("Library refresh queued.".to_string())
+            }
+            (Maintenance::Refresh, Some(title)) => {
+                let ids: Vec<i32> = self
+                    .series_titled(title)
+                    .await?
+                    .iter()
+                    .filter_map(|s| s.id)
+                    .collect();
+                // RefreshSeries takes one series at a time
+                for id in &ids {
+                    tolerate_response_parse_error(
+                        api_v3_command_post_custom(
+                            &self.config,
+                            &RefreshSeriesCommand::new(Some(*id)),
+                        )
+                        .await,
+                        "Failed to trigger Sonarr refresh",
+                    )?;
+                }
+                Ok(match ids.len() {
+                    1 => format!("Refresh queued for \"{title}\"."),
+                    n => format!("Refresh queued for {n} series titled \"{title}\"."),
+                })
+            }
+        }
+    }
+
+    async fn upgrade(&self, title: &str, quality_profile: Option<&str>) -> Result<String> {
+        let profile_id = quality_profile
+            .map(|name| self.quality_profile_id(name))
+            .transpose()?;
+        let mut series_ids = Vec::new();
+        for mut series in self.series_titled(title).await? {
+            let Some(series_id) = series.id else {
+                continue;
+            };
+            if let Some(profile_id) = profile_id
+                && series.quality_profile_id != Some(profile_id)
+            {
+                series.quality_profile_id = Some(profile_id);
+                tolerate_response_parse_error(
+                    api_v3_series_id_put(&self.config, &series_id.to_string(), None, Some(series))
+                        .await,
+                    "Failed to change the series' quality profile in Sonarr",
+                )?;
+                info!(series_id, profile_id, "Changed series' quality profile");
+            }
+            series_ids.push(series_id);
+        }
+        // SeriesSearch takes one series at a time
+        for series_id in &series_ids {
+            tolerate_response_parse_error(
+                api_v3_command_post_custom(&self.config, &SeriesSearchCommand::new(*series_id))
+                    .await,
+                "Failed to trigger Sonarr series search",
+            )?;
+        }
+        let series = match series_ids.len() {
+            1 => format!("\"{title}\""),
+            n => format!("{n} series titled \"{title}\""),
+        };
+        Ok(match quality_profile {
+            Some(profile) => {
+                format!("Moved {series} to {profile} and queued a search for an upgrade.")
+            }
+            None => format!("Queued a search for an upgrade of {series}."),
+        })
+    }
+
+    async fn remove(&self, id: &MediaId, delete_files: bool) -> Result<()> {
+        let Some(series_id) = id.backend_id else {
+            bail!(UserFacingError("That series isn't in Sonarr.".into()));
+        };
+        api_v3_series_id_delete(&self.config, series_id, Some(delete_files), Some(false))
+            .await
+            .inspect_err(|e| log_api_error(e, "Failed to delete the Sonarr series"))?;
+        info!(series_id, delete_files, "Removed series");
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::discord::{FormEvent, apply_form_event, pick_event};
+    use proptest::{prelude::*, sample::Index};
+    use sonarr_api::models::{Language, LanguageProfileItemResource, SeasonStatisticsResource};
 
     fn detail(
         metadata: &str,
@@ -890,11 +1903,15 @@ mod tests {
                 title: title.to_string(),
                 description: None,
                 id: Some(id),
+                present: false,
+                note: None,
+                warning: None,
             }],
             selected_indices: if selected { vec![0] } else { vec![] },
             metadata: Some(metadata.to_string()),
             field_type,
             always_show: false,
+            status: None,
         }
     }
 
@@ -909,12 +1926,16 @@ mod tests {
                     title: format!("Season {n}"),
                     description: None,
                     id: Some(SelectableId::Integer(*n)),
+                    present: false,
+                    note: None,
+                    warning: None,
                 })
                 .collect(),
             selected_indices: selected.to_vec(),
             metadata: Some(field_keys::SEASON.to_string()),
             field_type: FieldType::MultiSelect,
             always_show: true,
+            status: None,
         }
     }
 
@@ -1004,6 +2025,9 @@ mod tests {
             title: "4K".into(),
             description: None,
             id: Some(SelectableId::Integer(4)),
+            present: false,
+            note: None,
+            warning: None,
         });
         details[1].selected_indices = vec![];
         assert!(SelectedDetails::try_from(details).is_err());
@@ -1045,13 +2069,18 @@ mod tests {
                 api_key: None,
             },
             details: Details {
-                rootfolders: vec![],
-                quality_profiles: vec![],
+                choices: Refreshing::fixed(Choices {
+                    rootfolders: vec![],
+                    quality_profiles: vec![],
+                    language_profiles: vec![],
+                    tags: vec![],
+                }),
                 series_type: None,
                 season_folder: None,
             },
             allow_specials,
             allow_all_seasons,
+            result_fields: vec![ResultField::Year],
         }
     }
 
@@ -1073,6 +2102,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn result_descriptions_follow_the_configured_fields() {
+        let mut sonarr = test_sonarr(false, true);
+        let series = SeriesResource {
+            title: Some(Some("Severance".into())),
+            year: Some(2022),
+            network: Some(Some("Apple TV+".into())),
+            status: Some(SeriesStatusType::Continuing),
+            ..Default::default()
+        };
+        let results: Vec<Box<dyn MediaItem>> = vec![Box::new(series)];
+        let description =
+            |sonarr: &Sonarr| sonarr.to_dropdown_options(&results)[0].description.clone();
+        assert_eq!(description(&sonarr).as_deref(), Some("2022"));
+
+        sonarr.result_fields = vec![ResultField::Network, ResultField::Status];
+        assert_eq!(
+            description(&sonarr).as_deref(),
+            Some("Apple TV+ · Continuing")
+        );
+        // Fields the series doesn't have are left out
+        sonarr.result_fields = vec![ResultField::Genres];
+        assert_eq!(description(&sonarr), None);
+    }
+
     fn season_descriptions(picker: &RequestDetails) -> Vec<Option<String>> {
         picker
             .options
@@ -1111,4 +2165,313 @@ mod tests {
         assert_eq!(descs[0].as_deref(), Some("Already monitored"));
         assert_eq!(descs[1], None);
     }
+
+    #[test]
+    fn picker_keeps_the_latest_seasons_of_long_series() {
+        let sonarr = test_sonarr(false, true);
+        let seasons: Vec<_> = (1..=30).map(|n| (n, false)).collect();
+        let picker = sonarr
+            .build_season_picker(&series_with_seasons(None, &seasons))
+            .expect("picker");
+
+        assert_eq!(picker.options.len(), MAX_DROPDOWN_OPTIONS);
+        assert_eq!(picker.options[0].title, "All Seasons");
+        assert_eq!(picker.options[1].title, "7");
+        assert_eq!(picker.options.last().unwrap().title, "30");
+        assert_eq!(
+            picker.status.as_deref(),
+            Some("Seasons 1–6 don't fit in the list")
+        );
+
+        let short = sonarr
+            .build_season_picker(&series_with_seasons(None, &[(1, false)]))
+            .expect("picker");
+        assert_eq!(short.status, None);
+    }
+
+    #[test]
+    fn picker_marks_fully_downloaded_seasons() {
+        let sonarr = test_sonarr(false, false);
+        let mut media = series_with_seasons(Some(42), &[(1, false), (2, false)]);
+        let seasons = media.seasons.as_mut().unwrap().as_mut().unwrap();
+        for (season, files) in seasons.iter_mut().zip([10, 4]) {
+            season.statistics = Some(Box::new(SeasonStatisticsResource {
+                episode_file_count: Some(files),
+                episode_count: Some(10),
+                ..Default::default()
+            }));
+        }
+        let picker = sonarr.build_season_picker(&media).expect("picker");
+
+        let present: Vec<bool> = picker.options.iter().map(|o| o.present).collect();
+        assert_eq!(present, vec![true, false]);
+        assert_eq!(
+            season_descriptions(&picker)[0].as_deref(),
+            Some("Downloaded")
+        );
+    }
+
+    #[test]
+    fn specials_are_their_own_choice() {
+        let media = series_with_seasons(None, &[(0, false), (1, false)]);
+        let picker = test_sonarr(true, false)
+            .build_season_picker(&media)
+            .expect("picker");
+        let seasons: Vec<_> = picker.options.iter().map(|o| o.title.as_str()).collect();
+        assert_eq!(seasons, ["1"], "Season 0 isn't in the picker");
+
+        assert!(
+            test_sonarr(false, false)
+                .build_specials_field(&media)
+                .is_none()
+        );
+        let mut specials = test_sonarr(true, false)
+            .build_specials_field(&media)
+            .expect("specials");
+        assert_eq!(
+            specials.selected_option().map(|o| o.title.as_str()),
+            Some("No")
+        );
+        specials.selected_indices = vec![0];
+        let selected = SelectedDetails::try_from(vec![specials]).unwrap();
+        assert!(selected.specials);
+
+        // Nothing to add once an existing series monitors them
+        let existing = series_with_seasons(Some(42), &[(0, true), (1, false)]);
+        assert!(
+            test_sonarr(true, false)
+                .build_specials_field(&existing)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn new_seasons_are_asked_about_until_sonarr_monitors_them() {
+        let sonarr = test_sonarr(false, false);
+        let media = series_with_seasons(None, &[(1, false)]);
+        let mut field = sonarr.build_new_seasons_field(&media).expect("field");
+        assert_eq!(
+            field.selected_option().map(|o| o.title.as_str()),
+            Some("No")
+        );
+        assert!(field.options.iter().all(|o| o.description.is_some()));
+        field.selected_indices = vec![0];
+        assert!(SelectedDetails::try_from(vec![field]).unwrap().new_seasons);
+
+        let mut existing = series_with_seasons(Some(42), &[(1, true)]);
+        assert!(sonarr.build_new_seasons_field(&existing).is_some());
+        existing.monitor_new_items = Some(NewItemMonitorTypes::All);
+        assert!(sonarr.build_new_seasons_field(&existing).is_none());
+    }
+
+    #[test]
+    fn language_profiles_list_their_allowed_languages() {
+        let item = |name: &str, allowed: bool| LanguageProfileItemResource {
+            language: Some(Box::new(Language {
+                name: Some(Some(name.into())),
+                ..Default::default()
+            })),
+            allowed: Some(allowed),
+            ..Default::default()
+        };
+        let profile = LanguageProfileResource {
+            languages: Some(Some(vec![
+                item("English", true),
+                item("French", false),
+                item("Japanese", true),
+            ])),
+            ..Default::default()
+        };
+        assert_eq!(
+            language_summary(&profile).as_deref(),
+            Some("English, Japanese")
+        );
+        assert_eq!(language_summary(&LanguageProfileResource::default()), None);
+    }
+
+    #[test]
+    fn breakdown_groups_seasons_by_what_is_missing() {
+        let media = series_with_seasons(Some(42), &[(3, false), (1, true), (2, true), (4, false)]);
+        let mut seasons = media.seasons.unwrap().unwrap();
+        for season in &mut seasons {
+            let files = match season.season_number {
+                Some(1) => 10,
+                Some(2) => 4,
+                Some(3) => 2,
+                _ => 0,
+            };
+            season.statistics = Some(Box::new(SeasonStatisticsResource {
+                episode_file_count: Some(files),
+                episode_count: Some(10),
+                ..Default::default()
+            }));
+        }
+        assert_eq!(
+            season_breakdown(&seasons).as_deref(),
+            Some(
+                "Downloaded: 1\n\
+                 Monitored, missing episodes: 2 (4 of 10)\n\
+                 Not monitored: 3 (2 of 10), 4"
+            )
+        );
+        assert_eq!(season_breakdown(&[]), None);
+    }
+
+    /// Sonarr's root folders and quality and language profiles, with
+    /// distinct IDs (no language profiles being Sonarr v4)
+    fn choices() -> impl Strategy<Value = Choices> {
+        (
+            prop::collection::btree_map(1..1000, "/[a-z]{1,8}", 1..5),
+            prop::collection::btree_map(1..1000, "[A-Za-z0-9 -]{1,12}", 1..5),
+            prop::collection::btree_map(1..1000, "[A-Za-z0-9 -]{1,12}", 0..4),
+        )
+            .prop_map(|(folders, profiles, languages)| Choices {
+                rootfolders: folders
+                    .into_iter()
+                    .map(|(id, path)| RootFolderResource {
+                        id: Some(id),
+                        path: Some(Some(path)),
+                        ..Default::default()
+                    })
+                    .collect(),
+                quality_profiles: profiles
+                    .into_iter()
+                    .map(|(id, name)| QualityProfileResource {
+                        id: Some(id),
+                        name: Some(Some(name)),
+                        ..Default::default()
+                    })
+                    .collect(),
+                language_profiles: languages
+                    .into_iter()
+                    .map(|(id, name)| LanguageProfileResource {
+                        id: Some(id),
+                        name: Some(Some(name)),
+                        ..Default::default()
+                    })
+                    .collect(),
+                tags: vec![],
+            })
+    }
+
+    proptest! {
+        /// Whatever Sonarr offers for a new series and the requester picks
+        /// through the form comes back out as the matching selected details
+        #[test]
+        fn form_picks_round_trip_to_selected_details(
+            choices in choices(),
+            season_folder in any::<Option<bool>>(),
+            (allow_specials, allow_all_seasons) in any::<(bool, bool)>(),
+            seasons in prop::collection::btree_set(0..20, 1..8),
+            picks in prop::collection::vec(any::<Index>(), 8),
+            season_picks in prop::collection::vec(any::<Index>(), 1..4),
+        ) {
+            prop_assume!(seasons.iter().any(|&n| n != 0));
+            let mut sonarr = test_sonarr(allow_specials, allow_all_seasons);
+            sonarr.details = Details {
+                choices: Refreshing::fixed(choices),
+                series_type: None,
+                season_folder,
+            };
+            let seasons: Vec<i32> = seasons.into_iter().collect();
+            let media = series_with_seasons(
+                None,
+                &seasons.iter().map(|&n| (n, false)).collect::<Vec<_>>(),
+            );
+            let mut details = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap()
+                .block_on(sonarr.additional_details(&media))
+                .unwrap();
+
+            // What each pick should come back as, by field
+            let choices = sonarr.details.choices.get();
+            let (mut folder, mut profile, mut series_type) = (None, None, None);
+            let mut language = None;
+            let (mut folders_by_season, mut specials) = (season_folder, false);
+            let (mut season_numbers, mut all_seasons) = (Vec::new(), false);
+            let mut new_seasons = false;
+            let fields: Vec<_> = details
+                .iter()
+                .map(|d| (d.title.clone(), d.metadata.clone(), d.options.len()))
+                .collect();
+            for ((title, metadata, count), pick) in fields.into_iter().zip(&picks) {
+                let index = pick.index(count);
+                let picked = match metadata.as_deref() {
+                    Some(field_keys::SEASON) => {
+                        let mut indices: Vec<_> =
+                            season_picks.iter().map(|p| p.index(count)).collect();
+                        indices.sort_unstable();
+                        indices.dedup();
+                        let offset = usize::from(allow_all_seasons);
+                        if allow_all_seasons && indices.contains(&0) {
+                            // "All Seasons" crowds out the rest
+                            all_seasons = true;
+                        } else {
+                            let numbered: Vec<_> = seasons.iter().filter(|&&n| n != 0).collect();
+                            season_numbers = indices.iter().map(|&i| *numbered[i - offset]).collect();
+                        }
+                        indices
+                    }
+                    // A lone option is shown as text, so nothing is ever picked
+                    _ if count == 1 => Vec::new(),
+                    Some(field_keys::ROOT_FOLDER) => {
+                        folder = choices.rootfolders[index].path.clone().flatten();
+                        vec![index]
+                    }
+                    Some(field_keys::QUALITY_PROFILE) => {
+                        profile = choices.quality_profiles[index].id;
+                        vec![index]
+                    }
+                    Some(field_keys::LANGUAGE_PROFILE) => {
+                        language = choices.language_profiles[index].id;
+                        vec![index]
+                    }
+                    Some(field_keys::SEASON_FOLDER) => {
+                        folders_by_season = Some(index == 0);
+                        vec![index]
+                    }
+                    Some(field_keys::SERIES_TYPE) => {
+                        series_type = Some(
+                            [SeriesTypes::Standard, SeriesTypes::Daily, SeriesTypes::Anime][index],
+                        );
+                        vec![index]
+                    }
+                    Some(field_keys::SPECIALS) => {
+                        specials = index == 0;
+                        vec![index]
+                    }
+                    Some(field_keys::NEW_SEASONS) => {
+                        new_seasons = index == 0;
+                        vec![index]
+                    }
+                    other => panic!("Unexpected field {other:?}"),
+                };
+                if !picked.is_empty() {
+                    let event = pick_event(&title, &picked);
+                    prop_assert_eq!(apply_form_event(&mut details, &event), FormEvent::Updated);
+                }
+            }
+            if choices.rootfolders.len() == 1 {
+                folder = choices.rootfolders[0].path.clone().flatten();
+            }
+            if choices.quality_profiles.len() == 1 {
+                profile = choices.quality_profiles[0].id;
+            }
+            if choices.language_profiles.len() == 1 {
+                language = choices.language_profiles[0].id;
+            }
+
+            let selected = SelectedDetails::try_from(details).unwrap();
+            prop_assert_eq!(selected.rootfolder_path, folder);
+            prop_assert_eq!(selected.quality_profile_id, profile);
+            prop_assert_eq!(selected.language_profile_id, language);
+            prop_assert_eq!(selected.season_folder, folders_by_season);
+            prop_assert_eq!(selected.series_type, series_type);
+            prop_assert_eq!(selected.specials, specials);
+            prop_assert_eq!(selected.new_seasons, new_seasons);
+            prop_assert_eq!(selected.all_seasons, all_seasons);
+            prop_assert_eq!(selected.season_numbers, season_numbers);
+        }
+    }
 }
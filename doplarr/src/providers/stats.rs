@@ -0,0 +1,257 @@
+//! Rolling latency and error-rate statistics for backend calls
+//!
+//! The registry wraps every backend in [Instrumented], which times the calls
+//! that go over the network and records them here, so admins can spot a slow
+//! reverse proxy or an overloaded instance from Discord.
+use super::{
+    CalendarEntry, DownloadStatus, Maintenance, MediaBackend, MediaDisplayInfo, MediaId, MediaItem,
+    QueueItem, RequestDetails, Requester, SuccessMessage, cache::CacheStats,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How many of the most recent calls per operation the statistics cover
+const WINDOW: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// The backend calls we time
+pub enum Operation {
+    Search,
+    Details,
+    Request,
+    Calendar,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Operation::Search => "search",
+            Operation::Details => "details",
+            Operation::Request => "request",
+            Operation::Calendar => "calendar",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    latency: Duration,
+    ok: bool,
+}
+
+#[derive(Debug, Default)]
+/// The most recent calls to one backend, per operation
+pub struct BackendStats {
+    samples: Mutex<BTreeMap<Operation, VecDeque<Sample>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Statistics over the recorded window of one operation
+pub struct Summary {
+    pub calls: usize,
+    pub errors: usize,
+    pub p50: Duration,
+    pub p95: Duration,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} calls, {} errors ({}%), p50 {} ms, p95 {} ms",
+            self.calls,
+            self.errors,
+            self.errors * 100 / self.calls.max(1),
+            self.p50.as_millis(),
+            self.p95.as_millis()
+        )
+    }
+}
+
+impl BackendStats {
+    pub fn record(&self, op: Operation, latency: Duration, ok: bool) {
+        let mut samples = self.samples.lock().expect("stats lock poisoned");
+        let window = samples.entry(op).or_default();
+        if window.len() == WINDOW {
+            window.pop_front();
+        }
+        window.push_back(Sample { latency, ok });
+    }
+
+    /// Summaries for every operation that has been called, in a stable order
+    pub fn summaries(&self) -> Vec<(Operation, Summary)> {
+        let samples = self.samples.lock().expect("stats lock poisoned");
+        samples
+            .iter()
+            .filter_map(|(op, window)| summarize(window).map(|s| (*op, s)))
+            .collect()
+    }
+}
+
+fn summarize(window: &VecDeque<Sample>) -> Option<Summary> {
+    if window.is_empty() {
+        return None;
+    }
+    let mut latencies: Vec<Duration> = window.iter().map(|s| s.latency).collect();
+    latencies.sort_unstable();
+    // Nearest-rank percentile
+    let percentile = |p: usize| latencies[(latencies.len() * p).div_ceil(100).max(1) - 1];
+    Some(Summary {
+        calls: window.len(),
+        errors: window.iter().filter(|s| !s.ok).count(),
+        p50: percentile(50),
+        p95: percentile(95),
+    })
+}
+
+/// Wraps a backend, recording the latency and outcome of its network calls
+pub struct Instrumented {
+    inner: Arc<dyn MediaBackend>,
+    stats: Arc<BackendStats>,
+}
+
+impl Instrumented {
+    pub fn new(inner: Arc<dyn MediaBackend>, stats: Arc<BackendStats>) -> Self {
+        Self { inner, stats }
+    }
+
+    async fn timed<T>(&self, op: Operation, call: impl Future<Output = Result<T>>) -> Result<T> {
+        let start = Instant::now();
+        let result = call.await;
+        self.stats.record(op, start.elapsed(), result.is_ok());
+        result
+    }
+}
+
+#[async_trait]
+impl MediaBackend for Instrumented {
+    async fn search(&self, term: &str) -> Result<Vec<Box<dyn MediaItem>>> {
+        self.timed(Operation::Search, self.inner.search(term)).await
+    }
+
+    fn to_dropdown_options(&self, results: &[Box<dyn MediaItem>]) -> Vec<super::DropdownOption> {
+        self.inner.to_dropdown_options(results)
+    }
+
+    fn early_stop(&self, media: &dyn MediaItem) -> bool {
+        self.inner.early_stop(media)
+    }
+
+    fn display_info(&self, media: &dyn MediaItem) -> MediaDisplayInfo {
+        self.inner.display_info(media)
+    }
+
+    async fn additional_details(&self, media: &dyn MediaItem) -> Result<Vec<RequestDetails>> {
+        self.timed(Operation::Details, self.inner.additional_details(media))
+            .await
+    }
+
+    async fn request(
+        &self,
+        details: Vec<RequestDetails>,
+        media: Box<dyn MediaItem>,
+        requester: &Requester,
+    ) -> Result<()> {
+        self.timed(
+            Operation::Request,
+            self.inner.request(details, media, requester),
+        )
+        .await
+    }
+
+    fn success_message(&self, details: &[RequestDetails], media: &dyn MediaItem) -> SuccessMessage {
+        self.inner.success_message(details, media)
+    }
+
+    async fn calendar(&self, start: &str, end: &str) -> Result<Vec<CalendarEntry>> {
+        self.timed(Operation::Calendar, self.inner.calendar(start, end))
+            .await
+    }
+
+    async fn version(&self) -> Result<Option<String>> {
+        self.inner.version().await
+    }
+
+    fn media_id(&self, media: &dyn MediaItem) -> Option<MediaId> {
+        self.inner.media_id(media)
+    }
+
+    async fn download_status(&self, id: &MediaId) -> Result<Option<DownloadStatus>> {
+        self.inner.download_status(id).await
+    }
+
+    async fn queue(&self) -> Result<Vec<QueueItem>> {
+        self.inner.queue().await
+    }
+
+    async fn search_title(&self, id: &MediaId) -> Result<bool> {
+        self.inner.search_title(id).await
+    }
+
+    async fn apply_tags(&self, id: &MediaId, tags: &[String]) -> Result<()> {
+        self.inner.apply_tags(id, tags).await
+    }
+
+    async fn maintenance(&self, task: Maintenance, title: Option<&str>) -> Result<String> {
+        self.inner.maintenance(task, title).await
+    }
+
+    async fn upgrade(&self, title: &str, quality_profile: Option<&str>) -> Result<String> {
+        self.inner.upgrade(title, quality_profile).await
+    }
+
+    async fn remove(&self, id: &MediaId, delete_files: bool) -> Result<()> {
+        self.inner.remove(id, delete_files).await
+    }
+
+    fn cache_stats(&self) -> Vec<(&'static str, CacheStats)> {
+        self.inner.cache_stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_percentiles_and_errors() {
+        let stats = BackendStats::default();
+        for ms in 1..=20 {
+            stats.record(Operation::Search, Duration::from_millis(ms), ms % 10 != 0);
+        }
+        let summaries = stats.summaries();
+        assert_eq!(summaries.len(), 1);
+        let (op, summary) = summaries[0];
+        assert_eq!(op, Operation::Search);
+        assert_eq!(
+            summary,
+            Summary {
+                calls: 20,
+                errors: 2,
+                p50: Duration::from_millis(10),
+                p95: Duration::from_millis(19),
+            }
+        );
+    }
+
+    #[test]
+    fn window_keeps_only_recent_calls() {
+        let stats = BackendStats::default();
+        for _ in 0..WINDOW {
+            stats.record(Operation::Request, Duration::from_secs(10), false);
+        }
+        for _ in 0..WINDOW {
+            stats.record(Operation::Request, Duration::from_millis(5), true);
+        }
+        let (_, summary) = stats.summaries()[0];
+        assert_eq!(summary.calls, WINDOW);
+        assert_eq!(summary.errors, 0);
+        assert_eq!(summary.p95, Duration::from_millis(5));
+    }
+}
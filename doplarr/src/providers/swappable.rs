@@ -0,0 +1,138 @@
+//! Backends that can be reconnected while in use
+//!
+//! The registry puts every configured backend behind a [Swappable], so an
+//! admin can reconnect it with new credentials (e.g. after rotating the API
+//! key in Radarr) from `/backend rotate-key`, without restarting the bot.
+//! Flows in progress carry on with the new connection from their next call.
+use super::{
+    CalendarEntry, DownloadStatus, Maintenance, MediaBackend, MediaDisplayInfo, MediaId, MediaItem,
+    QueueItem, RequestDetails, Requester, SuccessMessage, cache::CacheStats,
+};
+use crate::config::BackendConfig;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::{Arc, RwLock};
+
+/// Wraps a backend, passing every call to its current connection
+pub struct Swappable {
+    /// What the current connection was made with
+    config: RwLock<BackendConfig>,
+    /// Where new connections are made from
+    client: reqwest::Client,
+    current: RwLock<Arc<dyn MediaBackend>>,
+}
+
+impl Swappable {
+    pub fn new(
+        config: BackendConfig,
+        client: reqwest::Client,
+        backend: Arc<dyn MediaBackend>,
+    ) -> Self {
+        Self {
+            config: RwLock::new(config),
+            client,
+            current: RwLock::new(backend),
+        }
+    }
+
+    pub fn config(&self) -> BackendConfig {
+        self.config
+            .read()
+            .expect("backend config lock poisoned")
+            .clone()
+    }
+
+    pub fn client(&self) -> reqwest::Client {
+        self.client.clone()
+    }
+
+    /// Use `backend`, connected with `config`, from now on
+    pub fn replace(&self, config: BackendConfig, backend: Arc<dyn MediaBackend>) {
+        *self.current.write().expect("backend lock poisoned") = backend;
+        *self.config.write().expect("backend config lock poisoned") = config;
+    }
+
+    fn current(&self) -> Arc<dyn MediaBackend> {
+        Arc::clone(&self.current.read().expect("backend lock poisoned"))
+    }
+}
+
+#[async_trait]
+impl MediaBackend for Swappable {
+    async fn search(&self, term: &str) -> Result<Vec<Box<dyn MediaItem>>> {
+        self.current().search(term).await
+    }
+
+    fn to_dropdown_options(&self, results: &[Box<dyn MediaItem>]) -> Vec<super::DropdownOption> {
+        self.current().to_dropdown_options(results)
+    }
+
+    fn early_stop(&self, media: &dyn MediaItem) -> bool {
+        self.current().early_stop(media)
+    }
+
+    fn display_info(&self, media: &dyn MediaItem) -> MediaDisplayInfo {
+        self.current().display_info(media)
+    }
+
+    async fn additional_details(&self, media: &dyn MediaItem) -> Result<Vec<RequestDetails>> {
+        self.current().additional_details(media).await
+    }
+
+    async fn request(
+        &self,
+        details: Vec<RequestDetails>,
+        media: Box<dyn MediaItem>,
+        requester: &Requester,
+    ) -> Result<()> {
+        self.current().request(details, media, requester).await
+    }
+
+    fn success_message(&self, details: &[RequestDetails], media: &dyn MediaItem) -> SuccessMessage {
+        self.current().success_message(details, media)
+    }
+
+    async fn calendar(&self, start: &str, end: &str) -> Result<Vec<CalendarEntry>> {
+        self.current().calendar(start, end).await
+    }
+
+    async fn version(&self) -> Result<Option<String>> {
+        self.current().version().await
+    }
+
+    fn media_id(&self, media: &dyn MediaItem) -> Option<MediaId> {
+        self.current().media_id(media)
+    }
+
+    async fn download_status(&self, id: &MediaId) -> Result<Option<DownloadStatus>> {
+        self.current().download_status(id).await
+    }
+
+    async fn queue(&self) -> Result<Vec<QueueItem>> {
+        self.current().queue().await
+    }
+
+    async fn search_title(&self, id: &MediaId) -> Result<bool> {
+        self.current().search_title(id).await
+    }
+
+    async fn apply_tags(&self, id: &MediaId, tags: &[String]) -> Result<()> {
+        self.current().apply_tags(id, tags).await
+    }
+
+    async fn maintenance(&self, task: Maintenance, title: Option<&str>) -> Result<String> {
+        self.current().maintenance(task, title).await
+    }
+
+    async fn upgrade(&self, title: &str, quality_profile: Option<&str>) -> Result<String> {
+        self.current().upgrade(title, quality_profile).await
+    }
+
+    async fn remove(&self, id: &MediaId, delete_files: bool) -> Result<()> {
+        self.current().remove(id, delete_files).await
+    }
+
+    fn cache_stats(&self) -> Vec<(&'static str, CacheStats)> {
+        self.current().cache_stats()
+    }
+}
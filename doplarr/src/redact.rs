@@ -0,0 +1,186 @@
+//! Keeping credentials out of the logs
+//!
+//! Debug output of API clients and payloads can carry credentials (a
+//! backend's generated `Configuration` holds its API key, for one), and
+//! trace-level logs print plenty of it. Every log line is written through
+//! [Redacting], which replaces each known secret with `[redacted]` first.
+use crate::config::{BackendConfig, Config};
+use std::{
+    io::{self, Write},
+    sync::{Arc, RwLock},
+};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// What a secret is replaced with
+const REDACTED: &str = "[redacted]";
+
+/// Shorter values aren't treated as secrets, as scrubbing them would mangle
+/// unrelated text (e.g. a placeholder key like "abc")
+const MIN_SECRET_LENGTH: usize = 8;
+
+/// The values to keep out of the logs. Clones share the list, so a secret
+/// added at runtime (e.g. a rotated API key) is scrubbed everywhere.
+#[derive(Debug, Clone, Default)]
+pub struct Secrets(Arc<RwLock<Vec<String>>>);
+
+impl Secrets {
+    /// The Discord token and every API key and token in the config
+    pub fn from_config(config: &Config) -> Self {
+        let secrets = Self::default();
+        secrets.add(&config.discord_token);
+        for backend in &config.backends {
+            match &backend.config {
+                BackendConfig::Radarr { api_key, .. }
+                | BackendConfig::Sonarr { api_key, .. }
+                | BackendConfig::Seerr { api_key, .. } => secrets.add(api_key),
+                BackendConfig::Plugin { .. } | BackendConfig::Combined { .. } => {}
+            }
+        }
+        if let Some(enrichment) = &config.enrichment {
+            if let Some(key) = &enrichment.tmdb_api_key {
+                secrets.add(key);
+            }
+            if let Some(plex) = &enrichment.plex {
+                secrets.add(&plex.token);
+            }
+        }
+        if let Some(suggestions) = &config.suggestions {
+            secrets.add(&suggestions.tmdb_api_key);
+        }
+        if let Some(password) = config
+            .tracking
+            .as_ref()
+            .and_then(|t| t.webhook_password.as_ref())
+        {
+            secrets.add(password);
+        }
+        secrets
+    }
+
+    /// Scrub `secret` from logs from now on
+    pub fn add(&self, secret: &str) {
+        let secret = secret.trim();
+        if secret.len() < MIN_SECRET_LENGTH {
+            return;
+        }
+        let mut secrets = self.0.write().expect("secrets lock poisoned");
+        if !secrets.iter().any(|s| s == secret) {
+            secrets.push(secret.to_string());
+            // Longest first, so a secret containing another is scrubbed whole
+            secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        }
+    }
+
+    fn scrub(&self, text: &str) -> String {
+        let secrets = self.0.read().expect("secrets lock poisoned");
+        secrets.iter().fold(text.to_string(), |text, secret| {
+            text.replace(secret, REDACTED)
+        })
+    }
+}
+
+/// Makes log writers that scrub [Secrets] from what's written through them
+pub struct Redacting<M> {
+    secrets: Secrets,
+    inner: M,
+}
+
+impl<M> Redacting<M> {
+    pub fn new(secrets: Secrets, inner: M) -> Self {
+        Self { secrets, inner }
+    }
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter::new(self.secrets.clone(), self.inner.make_writer())
+    }
+}
+
+/// Holds one log event until it's complete, so a secret split across writes
+/// is still caught, then writes it scrubbed
+pub struct RedactingWriter<W: Write> {
+    secrets: Secrets,
+    inner: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> RedactingWriter<W> {
+    fn new(secrets: Secrets, inner: W) -> Self {
+        Self {
+            secrets,
+            inner,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let scrubbed = self.secrets.scrub(&String::from_utf8_lossy(&self.buffer));
+            self.buffer.clear();
+            self.inner.write_all(scrubbed.as_bytes())?;
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for RedactingWriter<W> {
+    fn drop(&mut self) {
+        // Nowhere left to report a failed log write
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_are_scrubbed_even_when_split_across_writes() {
+        let config = Config {
+            discord_token: "MTIz.discord.token".into(),
+            backends: vec![crate::config::Backend {
+                media: "movie".into(),
+                name: None,
+                guilds: None,
+                config: BackendConfig::Radarr {
+                    url: "http://radarr".into(),
+                    api_key: "0123456789abcdef".into(),
+                    monitor_type: None,
+                    quality_profile: None,
+                    rootfolder: None,
+                    minimum_availability: None,
+                    allow_unmonitored: None,
+                    allow_tags: None,
+                    result_fields: None,
+                },
+                maintenance: None,
+            }],
+            ..Default::default()
+        };
+        let secrets = Secrets::from_config(&config);
+        secrets.add("short");
+        secrets.add("rotated-key-1234");
+
+        let mut log = Vec::new();
+        {
+            let mut writer = RedactingWriter::new(secrets.clone(), &mut log);
+            write!(writer, "ApiKey {{ key: \"01234567").unwrap();
+            writeln!(writer, "89abcdef\" }} token=MTIz.discord.token short").unwrap();
+            writeln!(writer, "rotated-key-1234").unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(log).unwrap(),
+            "ApiKey { key: \"[redacted]\" } token=[redacted] short\n[redacted]\n"
+        );
+    }
+}
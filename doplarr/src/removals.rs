@@ -0,0 +1,88 @@
+//! `/request remove`, which takes titles out of a backend's library
+//!
+//! The subcommand searches the backend and lists the matches it already has,
+//! each with a button. Nothing is deleted until one is clicked, and only by
+//! the admin who ran the command.
+use crate::providers::MediaId;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use twilight_model::id::{Id, marker::UserMarker};
+use uuid::Uuid;
+
+/// How long a listed title's button keeps working
+const OFFER_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// A library title offered for removal, and who it was offered to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Removal {
+    /// The media command it was found with, e.g. "movie"
+    pub media: String,
+    /// e.g. "Dune (2021)"
+    pub label: String,
+    pub id: MediaId,
+    /// Delete its files from disk as well
+    pub delete_files: bool,
+    pub admin: Id<UserMarker>,
+}
+
+/// Titles listed by `/request remove`, until their buttons are clicked
+#[derive(Default)]
+pub struct PendingRemovals {
+    offered: Mutex<HashMap<Uuid, (Removal, Instant)>>,
+}
+
+impl PendingRemovals {
+    /// Each title under the ID its button is sent back with
+    pub fn offer(&self, removals: Vec<Removal>) -> Vec<(Uuid, Removal)> {
+        let offers: Vec<_> = removals
+            .into_iter()
+            .map(|removal| (Uuid::new_v4(), removal))
+            .collect();
+        let mut offered = self.offered.lock().expect("removals lock poisoned");
+        offered.retain(|_, (_, at)| at.elapsed() < OFFER_WINDOW);
+        let now = Instant::now();
+        offered.extend(offers.iter().map(|(id, r)| (*id, (r.clone(), now))));
+        offers
+    }
+
+    /// The title behind a clicked button, if it was listed for `admin`
+    /// recently. Each button works once.
+    pub fn take(&self, id: Uuid, admin: Id<UserMarker>) -> Option<Removal> {
+        let mut offered = self.offered.lock().expect("removals lock poisoned");
+        match offered.get(&id) {
+            Some((removal, at)) if removal.admin == admin && at.elapsed() < OFFER_WINDOW => {
+                offered.remove(&id).map(|(removal, _)| removal)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removals_are_taken_once_by_their_admin() {
+        let pending = PendingRemovals::default();
+        let removal = Removal {
+            media: "movie".into(),
+            label: "Dune (2021)".into(),
+            id: MediaId {
+                tmdb: Some(438631),
+                backend_id: Some(7),
+                ..Default::default()
+            },
+            delete_files: true,
+            admin: Id::new(1),
+        };
+        let offers = pending.offer(vec![removal.clone()]);
+        let id = offers[0].0;
+        assert_eq!(pending.take(id, Id::new(2)), None);
+        assert_eq!(pending.take(id, Id::new(1)), Some(removal));
+        assert_eq!(pending.take(id, Id::new(1)), None);
+    }
+}
@@ -0,0 +1,369 @@
+//! Keeping the request log from growing without bound
+//!
+//! With `[retention]` set, requests older than `months` leave the request log
+//! once a day while the bot runs, or whenever `doplarr db prune` is run. By
+//! default they're archived: appended to a JSON lines file next to the state
+//! file, out of the way of `/export-requests` but still there to look back on.
+//! With `mode = "purge"` they're deleted. Audit records from before the cutoff
+//! are dropped either way.
+//!
+//! Anyone can ask to be forgotten with `/forget-me`, and admins can forget
+//! someone with `/forget-user`; either takes them out of the log and the
+//! archive once its button confirms it. `doplarr db forget` does the same with
+//! the bot stopped.
+use crate::{
+    config::{Config, Retention, RetentionMode},
+    store::{self, Pruned, RequestRecord, Store},
+};
+use anyhow::{Context, Result, bail};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::time::interval;
+use tracing::{info, warn};
+use twilight_model::id::{Id, marker::UserMarker};
+use uuid::Uuid;
+
+/// A month, as far as retention is concerned
+const MONTH_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// How often the running bot prunes the log
+const PRUNE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The oldest request time kept, for requests kept `months` as of `now`
+fn cutoff(months: u32, now: u64) -> u64 {
+    now.saturating_sub(u64::from(months) * MONTH_SECS)
+}
+
+/// Where archived requests go: the configured file, or the state file's name
+/// ending in .archive.jsonl
+fn archive_path(retention: &Retention, state_file: &str) -> PathBuf {
+    match &retention.archive_file {
+        Some(file) => file.into(),
+        None => Path::new(state_file).with_extension("archive.jsonl"),
+    }
+}
+
+/// Prune requests older than `months` from the log, archiving them unless
+/// `mode` says to purge
+async fn prune(
+    store: &Store,
+    retention: &Retention,
+    months: u32,
+    mode: RetentionMode,
+    state_file: &str,
+) -> Result<Pruned> {
+    let archive = match mode {
+        RetentionMode::Archive => Some(archive_path(retention, state_file)),
+        RetentionMode::Purge => None,
+    };
+    store
+        .prune(cutoff(months, store::now()), archive.as_deref())
+        .await
+}
+
+/// Prune the log now and then once a day, in the background
+pub async fn spawn(config: &Config, store: Arc<Store>) -> Result<()> {
+    let Some(retention) = config.retention.clone() else {
+        return Ok(());
+    };
+    if retention.months == 0 {
+        bail!("retention.months must be at least 1");
+    }
+    let state_file = config
+        .state_file
+        .clone()
+        .unwrap_or_else(|| store::DEFAULT_STATE_FILE.to_string());
+    let mode = retention.mode.unwrap_or_default();
+    tokio::spawn(async move {
+        let mut ticker = interval(PRUNE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            match prune(&store, &retention, retention.months, mode, &state_file).await {
+                Ok(pruned) if pruned == Pruned::default() => {}
+                Ok(pruned) => info!(
+                    requests = pruned.requests,
+                    audits = pruned.audits,
+                    ?mode,
+                    "Pruned old requests from the request log"
+                ),
+                Err(e) => warn!(error = ?e, "Couldn't prune the request log"),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// `doplarr db prune`: prune the log once, then exit
+pub async fn prune_command(config_file: &Path, months: Option<u32>, purge: bool) -> Result<()> {
+    let config = Config::from_file(config_file)?;
+    let retention = config.retention.clone().unwrap_or(Retention {
+        months: 0,
+        mode: None,
+        archive_file: None,
+    });
+    let months = months.unwrap_or(retention.months);
+    if months == 0 {
+        bail!(
+            "Set [retention] months in {}, or pass --months",
+            config_file.display()
+        );
+    }
+    let mode = if purge {
+        RetentionMode::Purge
+    } else {
+        retention.mode.unwrap_or_default()
+    };
+    let state_file = config
+        .state_file
+        .as_deref()
+        .unwrap_or(store::DEFAULT_STATE_FILE);
+    if !Path::new(state_file).exists() {
+        bail!("No state file at {state_file}, so there's nothing to prune");
+    }
+    let store = Store::load(state_file, |media| config.media_kind(media)).await?;
+    let pruned = prune(&store, &retention, months, mode, state_file)
+        .await
+        .context("Failed to prune the request log")?;
+    let what = match mode {
+        RetentionMode::Archive => format!(
+            "Archived {} request(s) to {}",
+            pruned.requests,
+            archive_path(&retention, state_file).display()
+        ),
+        RetentionMode::Purge => format!("Deleted {} request(s)", pruned.requests),
+    };
+    println!(
+        "{what} older than {months} month(s), and dropped {} audit record(s).",
+        pruned.audits
+    );
+    Ok(())
+}
+
+/// Take a user's requests out of an archive, and them off the Also notify
+/// lists of the rest. Returns how many requests were taken out.
+async fn forget_archived(archive: &Path, user_id: u64) -> Result<usize> {
+    let content = match tokio::fs::read_to_string(archive).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read archive {}", archive.display()));
+        }
+    };
+    let mut kept = String::new();
+    let mut forgotten = 0;
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        let mut record: RequestRecord = serde_json::from_str(line)
+            .with_context(|| format!("Failed to read archive {}", archive.display()))?;
+        if record.user_id == user_id {
+            forgotten += 1;
+            continue;
+        }
+        record.subscribers.retain(|&id| id != user_id);
+        kept.push_str(&serde_json::to_string(&record)?);
+        kept.push('\n');
+    }
+    tokio::fs::write(archive, kept)
+        .await
+        .with_context(|| format!("Failed to write archive {}", archive.display()))?;
+    Ok(forgotten)
+}
+
+/// Take a user out of the log and its archive. Returns what came out of the
+/// log, and how many archived requests.
+pub async fn forget_user(store: &Store, config: &Config, user_id: u64) -> Result<(Pruned, usize)> {
+    let forgotten = store
+        .forget_user(user_id)
+        .await
+        .context("Failed to update the request log")?;
+    let retention = config.retention.clone().unwrap_or(Retention {
+        months: 0,
+        mode: None,
+        archive_file: None,
+    });
+    let state_file = config
+        .state_file
+        .as_deref()
+        .unwrap_or(store::DEFAULT_STATE_FILE);
+    let archive = archive_path(&retention, state_file);
+    let archived = forget_archived(&archive, user_id).await?;
+    Ok((forgotten, archived))
+}
+
+/// `doplarr db forget`: take a user out of the log and its archive, then exit.
+/// Only while the bot is stopped, or it saves over the change.
+pub async fn forget_command(config_file: &Path, user_id: u64) -> Result<()> {
+    let config = Config::from_file(config_file)?;
+    let state_file = config
+        .state_file
+        .as_deref()
+        .unwrap_or(store::DEFAULT_STATE_FILE);
+    if !Path::new(state_file).exists() {
+        bail!("No state file at {state_file}, so there's nothing to forget");
+    }
+    let store = Store::load(state_file, |media| config.media_kind(media)).await?;
+    let (forgotten, archived) = forget_user(&store, &config, user_id).await?;
+    println!(
+        "Forgot user {user_id}: {} request(s) and {} audit record(s) in the log, and {archived} \
+         archived request(s).",
+        forgotten.requests, forgotten.audits
+    );
+    Ok(())
+}
+
+/// How long a `/forget-me` or `/forget-user` confirm button keeps working
+const CONFIRM_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// Who's to be forgotten, who asked, and when
+#[derive(Debug, Clone, Copy)]
+struct Forget {
+    user_id: Id<UserMarker>,
+    asked_by: Id<UserMarker>,
+    at: Instant,
+}
+
+/// Users asked to be forgotten with `/forget-me` or `/forget-user`, by the
+/// ID their confirm button is sent back with, until it's clicked
+#[derive(Default)]
+pub struct PendingForgets {
+    asked: Mutex<HashMap<Uuid, Forget>>,
+}
+
+impl PendingForgets {
+    /// Note that `asked_by` wants `user_id` forgotten, returning the ID for
+    /// the confirm button
+    pub fn ask(&self, user_id: Id<UserMarker>, asked_by: Id<UserMarker>) -> Uuid {
+        let id = Uuid::new_v4();
+        let mut asked = self.asked.lock().expect("forgets lock poisoned");
+        asked.retain(|_, forget| forget.at.elapsed() < CONFIRM_WINDOW);
+        asked.insert(
+            id,
+            Forget {
+                user_id,
+                asked_by,
+                at: Instant::now(),
+            },
+        );
+        id
+    }
+
+    /// The user to forget behind a clicked confirm button, if `asked_by`
+    /// asked recently. Each button works once.
+    pub fn take(&self, id: Uuid, asked_by: Id<UserMarker>) -> Option<Id<UserMarker>> {
+        let mut asked = self.asked.lock().expect("forgets lock poisoned");
+        match asked.get(&id) {
+            Some(forget) if forget.asked_by == asked_by && forget.at.elapsed() < CONFIRM_WINDOW => {
+                asked.remove(&id).map(|forget| forget.user_id)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::MediaId;
+
+    #[tokio::test]
+    async fn old_requests_are_archived_then_gone_from_the_log() {
+        let state_file = std::env::temp_dir()
+            .join(format!("doplarr-{}.json", uuid::Uuid::new_v4()))
+            .display()
+            .to_string();
+        let store = Store::load(&state_file, |_| None).await.unwrap();
+        let record = |title: &str, months_ago: u32| {
+            let mut record =
+                RequestRecord::new(1, Some(1), "movie".into(), title.into(), MediaId::default());
+            record.requested_at = cutoff(months_ago, store::now());
+            record
+        };
+        store.log_request(record("Heat (1995)", 13)).await.unwrap();
+        store.log_request(record("Dune (2021)", 1)).await.unwrap();
+
+        let retention = Retention {
+            months: 12,
+            mode: None,
+            archive_file: None,
+        };
+        let pruned = prune(&store, &retention, 12, RetentionMode::Archive, &state_file)
+            .await
+            .unwrap();
+        assert_eq!(pruned.requests, 1);
+        let titles: Vec<_> = Store::load(&state_file, |_| None)
+            .await
+            .unwrap()
+            .requests(|_| true)
+            .await
+            .into_iter()
+            .map(|r| r.title)
+            .collect();
+        assert_eq!(titles, ["Dune (2021)"]);
+
+        let archive = archive_path(&retention, &state_file);
+        assert!(archive.display().to_string().ends_with(".archive.jsonl"));
+        let archived: RequestRecord =
+            serde_json::from_str(std::fs::read_to_string(&archive).unwrap().trim()).unwrap();
+        assert_eq!(archived.title, "Heat (1995)");
+
+        let purged = prune(&store, &retention, 0, RetentionMode::Purge, &state_file)
+            .await
+            .unwrap();
+        assert_eq!(purged.requests, 1);
+        assert_eq!(
+            std::fs::read_to_string(&archive).unwrap().lines().count(),
+            1,
+            "purged requests aren't archived"
+        );
+
+        std::fs::remove_file(&state_file).unwrap();
+        std::fs::remove_file(&archive).unwrap();
+    }
+
+    #[tokio::test]
+    async fn forgotten_users_are_taken_out_of_the_archive() {
+        let archive =
+            std::env::temp_dir().join(format!("doplarr-{}.archive.jsonl", uuid::Uuid::new_v4()));
+        let record = |user_id, subscribers| RequestRecord {
+            subscribers,
+            ..RequestRecord::new(
+                user_id,
+                Some(1),
+                "movie".into(),
+                "T".into(),
+                MediaId::default(),
+            )
+        };
+        let lines: Vec<_> = [record(1, vec![]), record(2, vec![1])]
+            .iter()
+            .map(|r| serde_json::to_string(r).unwrap())
+            .collect();
+        std::fs::write(&archive, lines.join("\n")).unwrap();
+
+        assert_eq!(forget_archived(&archive, 1).await.unwrap(), 1);
+        let kept: RequestRecord =
+            serde_json::from_str(std::fs::read_to_string(&archive).unwrap().trim()).unwrap();
+        assert_eq!((kept.user_id, kept.subscribers), (2, vec![]));
+
+        std::fs::remove_file(&archive).unwrap();
+        let missing = std::env::temp_dir().join(format!("doplarr-{}.jsonl", uuid::Uuid::new_v4()));
+        assert_eq!(forget_archived(&missing, 1).await.unwrap(), 0);
+    }
+
+    #[test]
+    fn forgets_are_confirmed_once_by_who_asked() {
+        let pending = PendingForgets::default();
+        let (user, admin) = (Id::new(1), Id::new(2));
+        let id = pending.ask(user, admin);
+        // Only whoever ran the command can confirm it
+        assert_eq!(pending.take(id, user), None);
+        assert_eq!(pending.take(id, admin), Some(user));
+        assert_eq!(pending.take(id, admin), None);
+        // Asking to forget yourself works the same
+        let id = pending.ask(user, user);
+        assert_eq!(pending.take(id, user), Some(user));
+    }
+}
@@ -0,0 +1,394 @@
+//! `doplarr setup`: an interactive first-run walkthrough that writes a config
+//!
+//! Every answer is checked live before moving on: the Discord token by logging
+//! in, each backend by connecting to it, and the quality profiles and root
+//! folders are picked from what the backend actually has. The finished config
+//! is parsed again before it's written, so the bot will start with it.
+use crate::{
+    config::{Backend, BackendConfig, Config, MediaKind},
+    providers::{radarr::Radarr, seerr::Seerr, sonarr::Sonarr},
+};
+use anyhow::{Context, Result, bail};
+use std::{
+    io::{BufRead, Write},
+    path::Path,
+    time::Duration,
+};
+use twilight_http::Client as HttpClient;
+
+/// Reads answers from `input`, asking on `output`
+struct Prompter<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Prompter<R, W> {
+    fn say(&mut self, line: &str) -> Result<()> {
+        writeln!(self.output, "{line}")?;
+        Ok(())
+    }
+
+    /// One line of input, or `default` when it's left empty
+    fn ask(&mut self, question: &str, default: Option<&str>) -> Result<String> {
+        loop {
+            match default {
+                Some(default) => write!(self.output, "{question} [{default}]: ")?,
+                None => write!(self.output, "{question}: ")?,
+            }
+            self.output.flush()?;
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                bail!("Setup was cancelled");
+            }
+            match (line.trim(), default) {
+                ("", Some(default)) => return Ok(default.to_string()),
+                ("", None) => continue,
+                (answer, _) => return Ok(answer.to_string()),
+            }
+        }
+    }
+
+    fn confirm(&mut self, question: &str, default: bool) -> Result<bool> {
+        let hint = if default { "Y/n" } else { "y/N" };
+        loop {
+            let answer = self.ask(question, Some(hint))?;
+            match answer.to_lowercase().as_str() {
+                _ if answer == hint => return Ok(default),
+                "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                _ => self.say("Please answer y or n.")?,
+            }
+        }
+    }
+
+    /// Pick one of `options` by number. With `none`, 0 picks nothing, which
+    /// is described by `none` (e.g. "let requesters choose").
+    fn choose(
+        &mut self,
+        question: &str,
+        options: &[String],
+        none: Option<&str>,
+    ) -> Result<Option<usize>> {
+        self.say(question)?;
+        for (i, option) in options.iter().enumerate() {
+            self.say(&format!("  {}) {option}", i + 1))?;
+        }
+        if let Some(none) = none {
+            self.say(&format!("  0) {none}"))?;
+        }
+        loop {
+            let default = if none.is_some() { "0" } else { "1" };
+            let answer = self.ask("Choice", Some(default))?;
+            match answer.parse::<usize>() {
+                Ok(0) if none.is_some() => return Ok(None),
+                Ok(n) if (1..=options.len()).contains(&n) => return Ok(Some(n - 1)),
+                _ => self.say("That's not one of the choices.")?,
+            }
+        }
+    }
+}
+
+/// Log in with the token, returning the bot's name
+async fn check_token(token: &str) -> Result<String> {
+    let http = HttpClient::new(token.to_string());
+    let user = http
+        .current_user()
+        .await
+        .context("Discord didn't accept the token")?
+        .model()
+        .await?;
+    Ok(user.name)
+}
+
+/// The kinds of backend the wizard can set up
+const BACKEND_KINDS: [&str; 3] = [
+    "Radarr (movies)",
+    "Sonarr (series)",
+    "Overseerr/Jellyseerr (movies and series)",
+];
+
+/// Ask for one backend until it connects, or the user gives up on it
+async fn ask_backend<R: BufRead, W: Write>(
+    prompt: &mut Prompter<R, W>,
+    kind: usize,
+    taken: &[Backend],
+    client: &reqwest::Client,
+) -> Result<Option<Backend>> {
+    let (default_url, default_media) = match kind {
+        0 => ("http://localhost:7878", "movie"),
+        1 => ("http://localhost:8989", "series"),
+        _ => ("http://localhost:5055", "request"),
+    };
+    let config = loop {
+        let url = prompt.ask("URL", Some(default_url))?;
+        let api_key = prompt.ask("API key (Settings → General)", None)?;
+        let connected = match kind {
+            0 => {
+                let config = BackendConfig::Radarr {
+                    url: url.clone(),
+                    api_key: api_key.clone(),
+                    monitor_type: None,
+                    quality_profile: None,
+                    rootfolder: None,
+                    minimum_availability: None,
+                    allow_unmonitored: None,
+                    allow_tags: None,
+                    result_fields: None,
+                };
+                Radarr::connect(config, client.clone())
+                    .await
+                    .map(|radarr| (radarr.quality_profile_names(), radarr.rootfolder_paths()))
+            }
+            1 => {
+                let config = BackendConfig::Sonarr {
+                    url: url.clone(),
+                    api_key: api_key.clone(),
+                    quality_profile: None,
+                    language_profile: None,
+                    rootfolder: None,
+                    series_type: None,
+                    season_folders: None,
+                    allow_specials: None,
+                    allow_all_seasons: None,
+                    allow_tags: None,
+                    result_fields: None,
+                };
+                Sonarr::connect(config, client.clone())
+                    .await
+                    .map(|sonarr| (sonarr.quality_profile_names(), sonarr.rootfolder_paths()))
+            }
+            _ => Seerr::connect(
+                seerr_config(url.clone(), api_key.clone(), None),
+                client.clone(),
+            )
+            .await
+            .map(|_| (vec![], vec![])),
+        };
+        let (profiles, folders) = match connected {
+            Ok(choices) => choices,
+            Err(e) => {
+                prompt.say(&format!("  ✗ Couldn't connect: {e:#}"))?;
+                if prompt.confirm("Try again?", true)? {
+                    continue;
+                }
+                return Ok(None);
+            }
+        };
+        prompt.say("  ✓ Connected")?;
+
+        let pick = |prompt: &mut Prompter<R, W>, what: &str, options: &[String]| {
+            if options.len() < 2 {
+                return anyhow::Ok(None);
+            }
+            let picked = prompt.choose(
+                &format!("Which {what} should requests use?"),
+                options,
+                Some("Let requesters pick"),
+            )?;
+            Ok(picked.map(|i| options[i].clone()))
+        };
+        break match kind {
+            0 => BackendConfig::Radarr {
+                url,
+                api_key,
+                monitor_type: None,
+                quality_profile: pick(prompt, "quality profile", &profiles)?,
+                rootfolder: pick(prompt, "root folder", &folders)?,
+                minimum_availability: None,
+                allow_unmonitored: None,
+                allow_tags: None,
+                result_fields: None,
+            },
+            1 => BackendConfig::Sonarr {
+                url,
+                api_key,
+                quality_profile: pick(prompt, "quality profile", &profiles)?,
+                language_profile: None,
+                rootfolder: pick(prompt, "root folder", &folders)?,
+                series_type: None,
+                season_folders: None,
+                allow_specials: None,
+                allow_all_seasons: None,
+                allow_tags: None,
+                result_fields: None,
+            },
+            _ => {
+                let filters = ["Movies only".to_string(), "Series only".to_string()];
+                let filter = prompt
+                    .choose("What can be requested?", &filters, Some("Both"))?
+                    .map(|i| [MediaKind::Movie, MediaKind::Tv][i]);
+                seerr_config(url, api_key, filter)
+            }
+        };
+    };
+
+    let media = loop {
+        let media = prompt.ask("Command name, as in /request <name>", Some(default_media))?;
+        if taken.iter().any(|b| b.media == media) {
+            prompt.say("Another backend already uses that name.")?;
+        } else {
+            break media;
+        }
+    };
+    Ok(Some(Backend {
+        media,
+        name: None,
+        guilds: None,
+        config,
+        maintenance: None,
+    }))
+}
+
+fn seerr_config(url: String, api_key: String, media_filter: Option<MediaKind>) -> BackendConfig {
+    BackendConfig::Seerr {
+        url,
+        api_key,
+        fallback_user_id: None,
+        allow_4k: None,
+        media_filter,
+        allow_all_seasons: None,
+    }
+}
+
+/// The config file's contents, checked to load the same way the bot will
+fn render(config: &Config) -> Result<String> {
+    let body = toml::to_string_pretty(config).context("Failed to write the config as TOML")?;
+    let content = format!(
+        "# Written by `doplarr setup`. See config.example.toml for everything\n\
+         # else you can set (followup channels, approval, profiles, ...).\n\n{body}"
+    );
+    let reloaded = Config::from_toml_str(&content, "the new config")?;
+    if reloaded != *config {
+        bail!("The written config doesn't load the same");
+    }
+    Ok(content)
+}
+
+async fn walk_through<R: BufRead, W: Write>(
+    prompt: &mut Prompter<R, W>,
+    path: &Path,
+) -> Result<()> {
+    prompt.say(&format!("Doplarr setup: this writes {}.\n", path.display()))?;
+    if path.exists()
+        && !prompt.confirm(
+            &format!("{} already exists. Replace it?", path.display()),
+            false,
+        )?
+    {
+        return Ok(());
+    }
+
+    let discord_token = loop {
+        let token = prompt.ask(
+            "Discord bot token (Developer Portal → your application → Bot)",
+            None,
+        )?;
+        match check_token(&token).await {
+            Ok(name) => {
+                prompt.say(&format!("  ✓ Logged in as {name}\n"))?;
+                break token;
+            }
+            Err(e) => prompt.say(&format!("  ✗ {e:#}"))?,
+        }
+    };
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let kinds = BACKEND_KINDS.map(String::from);
+    let mut backends = Vec::new();
+    loop {
+        let question = if backends.is_empty() {
+            "Where should requests go?"
+        } else {
+            "Add another backend?"
+        };
+        let none = (!backends.is_empty()).then_some("No, I'm done");
+        let Some(kind) = prompt.choose(question, &kinds, none)? else {
+            break;
+        };
+        if let Some(backend) = ask_backend(prompt, kind, &backends, &client).await? {
+            prompt.say(&format!("  Added /request {}\n", backend.media))?;
+            backends.push(backend);
+        }
+    }
+
+    let content = render(&Config {
+        discord_token,
+        backends,
+        ..Default::default()
+    })?;
+    std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    prompt.say(&format!(
+        "\nWrote {}. Start Doplarr with it, then invite the bot to your server.",
+        path.display()
+    ))
+}
+
+/// Run the wizard on the terminal
+pub async fn run(path: &Path) -> Result<()> {
+    let mut prompt = Prompter {
+        input: std::io::stdin().lock(),
+        output: std::io::stdout(),
+    };
+    walk_through(&mut prompt, path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompter(input: &str) -> Prompter<&[u8], Vec<u8>> {
+        Prompter {
+            input: input.as_bytes(),
+            output: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn choices_are_picked_by_number_until_valid() {
+        let options = ["HD-1080p".to_string(), "Ultra-HD".to_string()];
+        let mut prompt = prompter("7\n2\n");
+        assert_eq!(prompt.choose("Profile?", &options, None).unwrap(), Some(1));
+        let shown = String::from_utf8(prompt.output).unwrap();
+        assert!(shown.contains("That's not one of the choices."));
+
+        let mut prompt = prompter("\n");
+        assert_eq!(
+            prompt
+                .choose("Profile?", &options, Some("Let requesters pick"))
+                .unwrap(),
+            None,
+            "0 is the default when there's a way out"
+        );
+        assert!(prompter("").ask("Token", None).is_err(), "end of input");
+    }
+
+    #[test]
+    fn rendered_config_loads_back() {
+        let config = Config {
+            discord_token: "token".into(),
+            backends: vec![Backend {
+                media: "movie".into(),
+                name: None,
+                guilds: None,
+                config: BackendConfig::Radarr {
+                    url: "http://localhost:7878".into(),
+                    api_key: "key".into(),
+                    monitor_type: None,
+                    quality_profile: Some("HD-1080p".into()),
+                    rootfolder: None,
+                    minimum_availability: None,
+                    allow_unmonitored: None,
+                    allow_tags: None,
+                    result_fields: None,
+                },
+                maintenance: None,
+            }],
+            ..Default::default()
+        };
+        let content = render(&config).unwrap();
+        assert!(content.contains("[backends.config.Radarr]"));
+        assert_eq!(Config::from_toml_str(&content, "test").unwrap(), config);
+    }
+}
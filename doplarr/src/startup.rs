@@ -0,0 +1,280 @@
+//! A health report posted to admins each time the bot starts
+//!
+//! With `[startup_report]` set, the bot posts one message once it has
+//! connected: each backend's version (or why it couldn't be reached), how
+//! registering commands went in the servers Discord announced, and the
+//! warnings about the config. A deploy that went fine says so, instead of
+//! leaving operators to go through the logs to be sure.
+use crate::{config::StartupReport, discord::MAX_MESSAGE_LENGTH, handlers::BotState, text};
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+use tokio::{sync::Notify, task::JoinSet, time::timeout};
+use tracing::{info, warn};
+use twilight_model::id::{
+    Id,
+    marker::{ChannelMarker, GuildMarker},
+};
+
+/// How long to wait for commands to be registered in every server before
+/// reporting what's known by then
+const REGISTRATION_WAIT: Duration = Duration::from_secs(30);
+
+/// How long each backend gets to answer with its version
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How a backend answered when asked for its version
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BackendCheck {
+    media: String,
+    name: String,
+    version: Result<Option<String>, String>,
+}
+
+#[derive(Debug, Default)]
+struct Progress {
+    /// The servers READY listed, each of which a GUILD_CREATE follows
+    expected: HashSet<Id<GuildMarker>>,
+    /// How many commands were registered to each server, or why they weren't
+    registered: BTreeMap<Id<GuildMarker>, Result<usize, String>>,
+    /// Configured channels the bot can't post in
+    channel_warnings: Vec<String>,
+}
+
+impl Progress {
+    fn waiting_on(&self) -> usize {
+        self.expected
+            .iter()
+            .filter(|id| !self.registered.contains_key(id))
+            .count()
+    }
+}
+
+/// Collects what happens while the bot connects, and posts it once every
+/// server has been seen to
+pub struct Reporter {
+    channel_id: Id<ChannelMarker>,
+    started: AtomicBool,
+    progress: Mutex<Progress>,
+    changed: Notify,
+}
+
+impl Reporter {
+    pub fn new(config: &StartupReport) -> Option<Self> {
+        let Some(channel_id) = Id::new_checked(config.channel_id) else {
+            warn!("startup_report.channel_id can't be 0, so no startup report will be posted");
+            return None;
+        };
+        Some(Self {
+            channel_id,
+            started: AtomicBool::new(false),
+            progress: Mutex::default(),
+            changed: Notify::new(),
+        })
+    }
+
+    /// Start the report on the first READY, to be posted once the servers it
+    /// lists are registered. Later READYs (reconnects) aren't reported.
+    pub fn ready(
+        self: &Arc<Self>,
+        state: &Arc<BotState>,
+        guilds: impl IntoIterator<Item = Id<GuildMarker>>,
+    ) {
+        if self.started.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        self.progress
+            .lock()
+            .expect("startup report lock poisoned")
+            .expected
+            .extend(guilds);
+        let (reporter, state) = (Arc::clone(self), Arc::clone(state));
+        tokio::spawn(async move {
+            let (backends, ()) = tokio::join!(check_backends(&state), reporter.registrations());
+            let report = render(
+                &backends,
+                &reporter
+                    .progress
+                    .lock()
+                    .expect("startup report lock poisoned"),
+                &state.config.warnings,
+            );
+            info!(channel_id = %reporter.channel_id, "Posting the startup report");
+            state.announcer.send(reporter.channel_id, report);
+        });
+    }
+
+    /// Record how registering commands to a server went
+    pub fn registered(&self, guild_id: Id<GuildMarker>, result: Result<usize, String>) {
+        self.progress
+            .lock()
+            .expect("startup report lock poisoned")
+            .registered
+            .insert(guild_id, result);
+        self.changed.notify_one();
+    }
+
+    /// Record a configured channel the bot can't post in
+    pub fn channel_warning(&self, warning: String) {
+        self.progress
+            .lock()
+            .expect("startup report lock poisoned")
+            .channel_warnings
+            .push(warning);
+    }
+
+    /// Wait until every server READY listed is registered, or
+    /// [REGISTRATION_WAIT] has passed
+    async fn registrations(&self) {
+        let all_registered = async {
+            loop {
+                let waiting_on = self
+                    .progress
+                    .lock()
+                    .expect("startup report lock poisoned")
+                    .waiting_on();
+                if waiting_on == 0 {
+                    return;
+                }
+                self.changed.notified().await;
+            }
+        };
+        let _ = timeout(REGISTRATION_WAIT, all_registered).await;
+    }
+}
+
+/// Ask every backend for its version, all at once
+async fn check_backends(state: &BotState) -> Vec<BackendCheck> {
+    let mut checks = JoinSet::new();
+    for (i, (media, backend)) in state.backends.iter().enumerate() {
+        let (media, backend) = (media.to_string(), backend.clone());
+        checks.spawn(async move {
+            let version = match timeout(VERSION_TIMEOUT, backend.backend.version()).await {
+                Ok(Ok(version)) => Ok(version),
+                Ok(Err(e)) => Err(e.to_string()),
+                Err(_) => Err(format!("no answer in {} s", VERSION_TIMEOUT.as_secs())),
+            };
+            let check = BackendCheck {
+                media,
+                name: backend.name,
+                version,
+            };
+            (i, check)
+        });
+    }
+    let mut checked: Vec<_> = checks.join_all().await;
+    checked.sort_by_key(|(i, _)| *i);
+    checked.into_iter().map(|(_, check)| check).collect()
+}
+
+/// The report's message, headed by whether anything needs looking at
+fn render(backends: &[BackendCheck], progress: &Progress, config_warnings: &[String]) -> String {
+    let mut problems = 0;
+
+    let mut lines = vec!["**Backends**".to_string()];
+    for check in backends {
+        let backend = format!("`{}` → /request {}", check.name, check.media);
+        lines.push(match &check.version {
+            Ok(Some(version)) => format!("- ✅ {backend} · v{version}"),
+            Ok(None) => format!("- ✅ {backend}"),
+            Err(e) => {
+                problems += 1;
+                format!("- ❌ {backend} · **unreachable**: {e}")
+            }
+        });
+    }
+
+    lines.push("**Servers**".to_string());
+    let registered = progress.registered.values().filter(|r| r.is_ok()).count();
+    lines.push(format!(
+        "- ✅ Commands registered in {registered} server(s)"
+    ));
+    for (guild_id, result) in &progress.registered {
+        if let Err(e) = result {
+            problems += 1;
+            lines.push(format!(
+                "- ❌ Registering commands in server `{guild_id}` failed: {e}"
+            ));
+        }
+    }
+    let waiting_on = progress.waiting_on();
+    if waiting_on > 0 {
+        problems += 1;
+        lines.push(format!(
+            "- ⚠️ {waiting_on} server(s) weren't available to register commands in yet"
+        ));
+    }
+
+    let warnings: Vec<_> = config_warnings
+        .iter()
+        .chain(&progress.channel_warnings)
+        .collect();
+    if !warnings.is_empty() {
+        problems += warnings.len();
+        lines.push("**Warnings**".to_string());
+        lines.extend(warnings.iter().map(|w| format!("- ⚠️ {w}")));
+    }
+
+    let version = env!("CARGO_PKG_VERSION");
+    let heading = match problems {
+        0 => format!("### ✅ Doplarr v{version} is up"),
+        n => format!("### ⚠️ Doplarr v{version} is up, with {n} problem(s)"),
+    };
+    lines.insert(0, heading);
+    text::truncate(&lines.join("\n"), MAX_MESSAGE_LENGTH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lists_backends_servers_and_warnings() {
+        let check = |name: &str, version| BackendCheck {
+            media: "movie".into(),
+            name: name.into(),
+            version,
+        };
+        let backends = [
+            check("radarr", Ok(Some("5.2.6".into()))),
+            check("radarr-4k", Err("connection refused".into())),
+        ];
+        let progress = Progress {
+            expected: HashSet::from([Id::new(1), Id::new(2), Id::new(3)]),
+            registered: BTreeMap::from([
+                (Id::new(1), Ok(9)),
+                (Id::new(2), Err("Missing Access".into())),
+            ]),
+            channel_warnings: vec!["Can't post in <#5>: missing SEND_MESSAGES".into()],
+        };
+        let report = render(&backends, &progress, &["unknown key `log_levle`".into()]);
+        let version = env!("CARGO_PKG_VERSION");
+        assert_eq!(
+            report,
+            format!(
+                "### ⚠️ Doplarr v{version} is up, with 5 problem(s)\n\
+                 **Backends**\n\
+                 - ✅ `radarr` → /request movie · v5.2.6\n\
+                 - ❌ `radarr-4k` → /request movie · **unreachable**: connection refused\n\
+                 **Servers**\n\
+                 - ✅ Commands registered in 1 server(s)\n\
+                 - ❌ Registering commands in server `2` failed: Missing Access\n\
+                 - ⚠️ 1 server(s) weren't available to register commands in yet\n\
+                 **Warnings**\n\
+                 - ⚠️ unknown key `log_levle`\n\
+                 - ⚠️ Can't post in <#5>: missing SEND_MESSAGES"
+            )
+        );
+
+        let healthy = Progress {
+            registered: BTreeMap::from([(Id::new(1), Ok(9))]),
+            ..Default::default()
+        };
+        assert!(render(&backends[..1], &healthy, &[]).starts_with("### ✅"));
+    }
+}
@@ -0,0 +1,1396 @@
+//! Runtime state that must survive restarts, saved as a JSON file
+//!
+//! The config file is for what admins set up ahead of time; this is for what
+//! changes while the bot runs (request bans, per-server request totals, the
+//! request log, audit records). Every change
+//! is written out immediately, through a temporary file so a crash mid-write
+//! can't leave a truncated file behind.
+//!
+//! The file carries a schema version. Files saved by older releases are
+//! brought up to date on load by the [MIGRATIONS] they haven't had yet, after
+//! a copy of the original is kept next to them.
+use crate::{
+    config::MediaKind,
+    providers::{FieldType, MediaId, RequestDetails},
+};
+use anyhow::{Context, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{io::AsyncWriteExt, sync::Mutex};
+use tracing::{info, warn};
+
+pub const DEFAULT_STATE_FILE: &str = "doplarr-state.json";
+
+/// How many audit records to keep, dropping the oldest first
+const AUDIT_CAPACITY: usize = 500;
+
+/// A change to the state file's layout, applied to files saved before it
+struct Migration {
+    /// What it changes, for the logs
+    description: &'static str,
+    apply: fn(&mut Map<String, Value>, &MigrationContext) -> anyhow::Result<()>,
+}
+
+/// What migrations can know beyond the file itself
+struct MigrationContext<'a> {
+    /// What a media command serves, `None` when the config can't tell
+    kind_of: &'a dyn Fn(&str) -> Option<MediaKind>,
+}
+
+/// Every migration, oldest first. A file at version N has had the first N
+/// applied. New ones go at the end, and released ones are never changed.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        description: "start versioning the state file",
+        apply: |_, _| Ok(()),
+    },
+    Migration {
+        description: "key request bans by server",
+        apply: ban_per_guild,
+    },
+    Migration {
+        description: "sort the IDs of logged requests by the kind of title",
+        apply: sort_media_ids,
+    },
+];
+
+/// Bans used to apply in every server. Keep them in force in each server the
+/// file knows of, leaving it to each server's admins to lift them.
+fn ban_per_guild(doc: &mut Map<String, Value>, _: &MigrationContext) -> anyhow::Result<()> {
+    let Some(Value::Object(bans)) = doc.remove("bans") else {
+        return Ok(());
+    };
+    let mut guilds: Vec<String> = doc
+        .get("guild_requests")
+        .and_then(Value::as_object)
+        .map(|counts| counts.keys().cloned().collect())
+        .unwrap_or_default();
+    guilds.extend(
+        doc.get("requests")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|r| r.get("guild_id")?.as_u64())
+            .map(|g| g.to_string()),
+    );
+    guilds.sort_unstable();
+    guilds.dedup();
+    let per_guild: Map<String, Value> = guilds
+        .into_iter()
+        .map(|guild| (guild, Value::Object(bans.clone())))
+        .collect();
+    doc.insert("bans".into(), Value::Object(per_guild));
+    Ok(())
+}
+
+/// Requests used to be logged with a single ID, whose meaning depends on the
+/// kind of title. Requests made with a media command the config no longer
+/// serves keep it as it was, since there's no telling which ID it is.
+fn sort_media_ids(doc: &mut Map<String, Value>, cx: &MigrationContext) -> anyhow::Result<()> {
+    let mut unknown = 0;
+    let records = doc
+        .get_mut("requests")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut);
+    for record in records {
+        if record.get("media_id").is_none_or(Value::is_null) {
+            continue;
+        }
+        let kind = record
+            .get("media")
+            .and_then(Value::as_str)
+            .and_then(cx.kind_of);
+        let key = match kind {
+            Some(MediaKind::Movie) => "tmdb",
+            Some(MediaKind::Tv) => "tvdb",
+            None => {
+                unknown += 1;
+                continue;
+            }
+        };
+        let id = record.remove("media_id").unwrap_or_default();
+        let ids = record
+            .entry("ids")
+            .or_insert_with(|| Value::Object(Map::new()));
+        let Value::Object(ids) = ids else {
+            bail!("A logged request's ids aren't an object: {ids}");
+        };
+        ids.insert(key.into(), id);
+    }
+    if unknown > 0 {
+        warn!(
+            count = unknown,
+            "Left the IDs of requests made with media commands no backend serves as they were"
+        );
+    }
+    Ok(())
+}
+
+/// The schema version of the state files this build writes
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// A user blocked from making requests
+pub struct Ban {
+    pub reason: Option<String>,
+    /// The admin who issued the ban
+    pub banned_by: u64,
+    /// Unix timestamp, in seconds
+    pub banned_at: u64,
+}
+
+impl Ban {
+    pub fn new(reason: Option<String>, banned_by: u64) -> Self {
+        Self {
+            reason,
+            banned_by,
+            banned_at: now(),
+        }
+    }
+}
+
+/// The current Unix timestamp, in seconds
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// A request a backend accepted
+pub struct RequestRecord {
+    /// Unix timestamp, in seconds
+    pub requested_at: u64,
+    pub user_id: u64,
+    /// `None` for requests made outside a server
+    pub guild_id: Option<u64>,
+    /// The media command it was made with, e.g. "movie"
+    pub media: String,
+    /// What was requested, as on the success card, e.g. "Dune (2021)"
+    pub title: String,
+    /// Who the title is, as far as the backend could tell
+    #[serde(default)]
+    pub ids: MediaId,
+    /// The one ID older versions logged (TMDB for movies, TVDB for series),
+    /// for requests whose kind of title wasn't known when migrating them
+    #[serde(default, rename = "media_id", skip_serializing_if = "Option::is_none")]
+    pub legacy_media_id: Option<i32>,
+    /// Where it stands, kept current by [crate::tracking]
+    #[serde(default)]
+    pub status: RequestStatus,
+    /// The channel it was requested in, to tell the requester once it's available
+    #[serde(default)]
+    pub channel_id: Option<u64>,
+    /// Who else the requester asked to have mentioned once it's available
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subscribers: Vec<u64>,
+    /// The admin-defined category it was filed under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Unix timestamp the requester needs it by, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub need_by: Option<u64>,
+    /// Whether it was searched for again, and admins told, for missing `need_by`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub escalated: bool,
+    /// How many times the backend was asked to search for it again, when
+    /// nothing had been grabbed for a while
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub searches: u32,
+    /// Unix timestamp of the latest of those searches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub searched_at: Option<u64>,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// Where a logged request stands, from the backend accepting it to the title
+/// being in the library
+pub enum RequestStatus {
+    /// Sent to the backend, which is searching for a release
+    #[default]
+    Requested,
+    /// Sent to the backend once an admin approved it
+    Approved,
+    /// A release was grabbed and is waiting in the download client
+    Grabbed,
+    /// The download client is working on it
+    Downloading,
+    /// Imported into the library
+    Available,
+    /// The backend needs someone to sort out the download
+    Failed,
+    /// Taken out of the library by an admin, with `/request remove`
+    Removed,
+}
+
+impl RequestStatus {
+    /// Whether `next` is news rather than a step back, e.g. a poll seeing an
+    /// older state than a webhook just reported. Nothing follows availability
+    /// but removal, and nothing follows removal; a failure can be retried
+    /// into any other state.
+    pub fn advances_to(self, next: Self) -> bool {
+        let rank = |status| match status {
+            Self::Requested | Self::Approved | Self::Failed => 0,
+            Self::Grabbed => 1,
+            Self::Downloading => 2,
+            Self::Available | Self::Removed => 3,
+        };
+        match (self, next) {
+            (Self::Removed, _) => false,
+            (_, Self::Removed) => true,
+            (Self::Available, _) => false,
+            (_, Self::Failed) => self != next,
+            _ => rank(next) > rank(self),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Requested => "requested",
+            Self::Approved => "approved",
+            Self::Grabbed => "grabbed",
+            Self::Downloading => "downloading",
+            Self::Available => "available",
+            Self::Failed => "failed",
+            Self::Removed => "removed",
+        }
+    }
+
+    /// Nothing more will happen to it, so it isn't tracked any more
+    pub fn is_settled(self) -> bool {
+        matches!(self, Self::Available | Self::Removed)
+    }
+}
+
+impl RequestRecord {
+    pub fn new(
+        user_id: u64,
+        guild_id: Option<u64>,
+        media: String,
+        title: String,
+        ids: MediaId,
+    ) -> Self {
+        Self {
+            requested_at: now(),
+            user_id,
+            guild_id,
+            media,
+            title,
+            ids,
+            legacy_media_id: None,
+            status: RequestStatus::default(),
+            channel_id: None,
+            subscribers: Vec::new(),
+            category: None,
+            need_by: None,
+            escalated: false,
+            searches: 0,
+            searched_at: None,
+        }
+    }
+
+    /// Whether two records are the same request, whatever has happened to it since
+    fn same_request(&self, other: &Self) -> bool {
+        (
+            self.requested_at,
+            self.user_id,
+            self.guild_id,
+            &self.media,
+            &self.title,
+        ) == (
+            other.requested_at,
+            other.user_id,
+            other.guild_id,
+            &other.media,
+            &other.title,
+        ) && (self.ids.matches(&other.ids) || (self.ids.is_empty() && other.ids.is_empty()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// What was picked for one of a request's detail fields
+pub struct Selection {
+    pub field: String,
+    pub values: Vec<String>,
+}
+
+impl Selection {
+    /// The option titles picked for a request form field
+    pub fn picked(detail: &RequestDetails) -> Self {
+        Self {
+            field: detail.title.clone(),
+            values: match detail.field_type {
+                FieldType::MultiSelect => {
+                    detail.selected_options().map(|o| o.title.clone()).collect()
+                }
+                _ => detail
+                    .selected_option()
+                    .map(|o| o.title.clone())
+                    .into_iter()
+                    .collect(),
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// A request found through the TMDB search fallback, waiting for its backend
+/// to be able to add it
+pub struct PendingAdd {
+    /// Unix timestamp, in seconds
+    pub queued_at: u64,
+    /// The backend instance to add it to, by name
+    pub backend: String,
+    pub user_id: u64,
+    /// `None` for requests made outside a server
+    #[serde(default)]
+    pub guild_id: Option<u64>,
+    /// The media command it was requested with, e.g. "movie", to find its
+    /// [RequestRecord] by
+    #[serde(default)]
+    pub media: String,
+    pub tmdb: i32,
+    /// e.g. "Dune (2021)"
+    pub title: String,
+    /// What was picked on the request form, to pick again once the backend
+    /// has the title
+    pub selections: Vec<Selection>,
+    /// Backend tags to put on the title once it's added
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// How long one stage of a request flow took
+pub struct StageTiming {
+    /// e.g. "search", "details", "request"
+    pub stage: String,
+    pub millis: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// Everything about one submitted request, for debugging it after the fact
+pub struct AuditRecord {
+    /// The request flow's interaction UUID
+    pub id: String,
+    /// Unix timestamp, in seconds
+    pub at: u64,
+    pub user_id: u64,
+    pub guild_id: Option<u64>,
+    pub media: String,
+    /// The backend instance's name
+    pub backend: String,
+    pub query: String,
+    /// What was requested, as on the success card
+    pub title: String,
+    pub selections: Vec<Selection>,
+    /// What the backend answered: `None` when it accepted the request (or
+    /// never saw it, when denied), otherwise its full error
+    pub error: Option<String>,
+    pub stages: Vec<StageTiming>,
+    /// Set when an admin denied the request, so it never reached the backend
+    #[serde(default)]
+    pub denied: Option<Denial>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// An admin's denial of a request awaiting approval
+pub struct Denial {
+    pub admin_id: u64,
+    /// Why, as told to the requester
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+/// How many requests reached a backend
+pub struct RequestCounts {
+    pub submitted: u64,
+    /// Requests the backend rejected or errored on
+    pub failed: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+/// How long request flows spent in one stage, summed over every flow
+pub struct StageTotals {
+    /// Flows that got through the stage
+    pub completed: u64,
+    /// Time those flows spent in it, in milliseconds
+    pub millis: u64,
+    /// Flows the requester gave up on in this stage
+    pub abandoned: u64,
+}
+
+impl StageTotals {
+    /// The average time a flow spent in the stage
+    pub fn average(&self) -> Option<Duration> {
+        (self.completed > 0).then(|| Duration::from_millis(self.millis / self.completed))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// What [Store::prune] or [Store::forget_user] took out of the state
+pub struct Pruned {
+    pub requests: usize,
+    pub audits: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+/// Everything in the state file
+struct State {
+    /// How many [MIGRATIONS] the file has had
+    version: u32,
+    /// Banned users, by guild ID and Discord user ID
+    bans: BTreeMap<u64, BTreeMap<u64, Ban>>,
+    /// Request totals, by guild ID
+    guild_requests: BTreeMap<u64, RequestCounts>,
+    /// Request flow stage totals, by guild ID and stage
+    flow_stages: BTreeMap<u64, BTreeMap<String, StageTotals>>,
+    /// Every accepted request, oldest first
+    requests: Vec<RequestRecord>,
+    /// Latest known display name, by Discord user ID. Refreshed whenever the
+    /// user makes a request, so the log stays readable after renames.
+    user_names: BTreeMap<u64, String>,
+    /// The most recent submitted requests' audit records, oldest first
+    audit: VecDeque<AuditRecord>,
+    /// Request log threads, by the channel they're in
+    request_threads: BTreeMap<u64, u64>,
+    /// Requests waiting on their backend, oldest first
+    pending_adds: Vec<PendingAdd>,
+}
+
+/// The persistent state, loaded at startup
+pub struct Store {
+    path: PathBuf,
+    state: Mutex<State>,
+}
+
+impl Store {
+    /// Load the state file, starting empty if it doesn't exist yet. `kind_of`
+    /// says what a media command serves, for migrating older files.
+    pub async fn load(
+        path: impl Into<PathBuf>,
+        kind_of: impl Fn(&str) -> Option<MediaKind>,
+    ) -> anyhow::Result<Self> {
+        let path = path.into();
+        let (state, from) = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => {
+                let cx = MigrationContext { kind_of: &kind_of };
+                let (state, from) = migrate(&content, &cx)
+                    .with_context(|| format!("Failed to load state file {}", path.display()))?;
+                if from < SCHEMA_VERSION {
+                    let backup = path.with_extension(format!("json.v{from}.bak"));
+                    tokio::fs::write(&backup, &content).await.with_context(|| {
+                        format!("Failed to back up state file to {}", backup.display())
+                    })?;
+                    info!(backup = %backup.display(), "Kept a copy of the state file before migrating it");
+                }
+                (state, from)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                info!(path = %path.display(), "No state file yet, starting fresh");
+                let state = State {
+                    version: SCHEMA_VERSION,
+                    ..Default::default()
+                };
+                (state, SCHEMA_VERSION)
+            }
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read state file {}", path.display()));
+            }
+        };
+        let store = Self {
+            path,
+            state: Mutex::new(state),
+        };
+        if from < SCHEMA_VERSION {
+            store.save(&*store.state.lock().await).await?;
+        }
+        info!(path = %store.path.display(), version = SCHEMA_VERSION, "Loaded state file");
+        Ok(store)
+    }
+
+    /// The schema version of the state file
+    pub async fn schema_version(&self) -> u32 {
+        self.state.lock().await.version
+    }
+
+    async fn save(&self, state: &State) -> anyhow::Result<()> {
+        let content = serde_json::to_string_pretty(state)?;
+        let tmp = self.path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, content)
+            .await
+            .with_context(|| format!("Failed to write state file {}", tmp.display()))?;
+        tokio::fs::rename(&tmp, &self.path)
+            .await
+            .with_context(|| format!("Failed to replace state file {}", self.path.display()))
+    }
+
+    /// A user's ban in a guild
+    pub async fn ban(&self, guild_id: u64, user_id: u64) -> Option<Ban> {
+        let state = self.state.lock().await;
+        state.bans.get(&guild_id)?.get(&user_id).cloned()
+    }
+
+    /// Ban a user in a guild, replacing any existing ban there. Returns
+    /// whether they were already banned.
+    pub async fn add_ban(&self, guild_id: u64, user_id: u64, ban: Ban) -> anyhow::Result<bool> {
+        let mut state = self.state.lock().await;
+        let existed = state
+            .bans
+            .entry(guild_id)
+            .or_default()
+            .insert(user_id, ban)
+            .is_some();
+        self.save(&state).await?;
+        Ok(existed)
+    }
+
+    /// Lift a user's ban in a guild. Returns whether they were banned there.
+    pub async fn remove_ban(&self, guild_id: u64, user_id: u64) -> anyhow::Result<bool> {
+        let mut state = self.state.lock().await;
+        let Some(bans) = state.bans.get_mut(&guild_id) else {
+            return Ok(false);
+        };
+        if bans.remove(&user_id).is_none() {
+            return Ok(false);
+        }
+        if bans.is_empty() {
+            state.bans.remove(&guild_id);
+        }
+        self.save(&state).await?;
+        Ok(true)
+    }
+
+    /// Count a request submitted to a backend from a guild
+    pub async fn record_request(&self, guild_id: u64, ok: bool) -> anyhow::Result<()> {
+        let mut state = self.state.lock().await;
+        let counts = state.guild_requests.entry(guild_id).or_default();
+        counts.submitted += 1;
+        if !ok {
+            counts.failed += 1;
+        }
+        self.save(&state).await
+    }
+
+    /// Add an accepted request to the log
+    pub async fn log_request(&self, record: RequestRecord) -> anyhow::Result<()> {
+        let mut state = self.state.lock().await;
+        state.requests.push(record);
+        self.save(&state).await
+    }
+
+    /// Queue a request until its backend can add it
+    pub async fn queue_add(&self, pending: PendingAdd) -> anyhow::Result<()> {
+        let mut state = self.state.lock().await;
+        state.pending_adds.push(pending);
+        self.save(&state).await
+    }
+
+    pub async fn pending_adds(&self) -> Vec<PendingAdd> {
+        self.state.lock().await.pending_adds.clone()
+    }
+
+    /// Take a queued request off the queue, once it's been added or given up on
+    pub async fn finish_add(&self, pending: &PendingAdd) -> anyhow::Result<()> {
+        let mut state = self.state.lock().await;
+        let before = state.pending_adds.len();
+        state.pending_adds.retain(|p| p != pending);
+        if state.pending_adds.len() == before {
+            return Ok(());
+        }
+        self.save(&state).await
+    }
+
+    /// Keep a submitted request's audit record, dropping the oldest past capacity
+    pub async fn record_audit(&self, record: AuditRecord) -> anyhow::Result<()> {
+        let mut state = self.state.lock().await;
+        state.audit.push_back(record);
+        while state.audit.len() > AUDIT_CAPACITY {
+            state.audit.pop_front();
+        }
+        self.save(&state).await
+    }
+
+    /// The audit record of the request flow with this UUID
+    pub async fn audit(&self, id: &str) -> Option<AuditRecord> {
+        let state = self.state.lock().await;
+        state.audit.iter().rev().find(|r| r.id == id).cloned()
+    }
+
+    /// The latest audit records for a guild, newest first
+    pub async fn recent_audits(&self, guild_id: Option<u64>, count: usize) -> Vec<AuditRecord> {
+        let state = self.state.lock().await;
+        state
+            .audit
+            .iter()
+            .rev()
+            .filter(|r| r.guild_id == guild_id)
+            .take(count)
+            .cloned()
+            .collect()
+    }
+
+    /// Remember a user's current display name, saving only when it changed
+    pub async fn remember_name(&self, user_id: u64, name: &str) -> anyhow::Result<()> {
+        let mut state = self.state.lock().await;
+        if state.user_names.get(&user_id).map(String::as_str) == Some(name) {
+            return Ok(());
+        }
+        state.user_names.insert(user_id, name.to_string());
+        self.save(&state).await
+    }
+
+    pub async fn user_names(&self) -> BTreeMap<u64, String> {
+        self.state.lock().await.user_names.clone()
+    }
+
+    /// The request log thread in a channel, if one was started
+    pub async fn request_thread(&self, channel_id: u64) -> Option<u64> {
+        self.state
+            .lock()
+            .await
+            .request_threads
+            .get(&channel_id)
+            .copied()
+    }
+
+    /// Remember the channel's request log thread, or forget it with `None`
+    pub async fn set_request_thread(
+        &self,
+        channel_id: u64,
+        thread_id: Option<u64>,
+    ) -> anyhow::Result<()> {
+        let mut state = self.state.lock().await;
+        match thread_id {
+            Some(thread_id) => state.request_threads.insert(channel_id, thread_id),
+            None => state.request_threads.remove(&channel_id),
+        };
+        self.save(&state).await
+    }
+
+    /// Add requests from another tool's history, skipping any already
+    /// logged. Returns how many were added.
+    pub async fn import_requests(&self, records: Vec<RequestRecord>) -> anyhow::Result<usize> {
+        let mut state = self.state.lock().await;
+        let before = state.requests.len();
+        for record in records {
+            if !state.requests.iter().any(|r| r.same_request(&record)) {
+                state.requests.push(record);
+            }
+        }
+        let added = state.requests.len() - before;
+        if added > 0 {
+            state.requests.sort_by_key(|r| r.requested_at);
+            self.save(&state).await?;
+        }
+        Ok(added)
+    }
+
+    /// The logged requests matching `filter`, oldest first
+    pub async fn requests(&self, filter: impl Fn(&RequestRecord) -> bool) -> Vec<RequestRecord> {
+        self.state
+            .lock()
+            .await
+            .requests
+            .iter()
+            .filter(|r| filter(r))
+            .cloned()
+            .collect()
+    }
+
+    /// Move the logged requests for a title along to `status`, where that's
+    /// news. Returns the records that changed, as they are now.
+    pub async fn update_status(
+        &self,
+        media: &str,
+        guild_id: Option<u64>,
+        ids: &MediaId,
+        status: RequestStatus,
+    ) -> anyhow::Result<Vec<RequestRecord>> {
+        let mut state = self.state.lock().await;
+        let mut changed = Vec::new();
+        for record in state
+            .requests
+            .iter_mut()
+            .filter(|r| r.media == media && r.guild_id == guild_id && r.ids.matches(ids))
+        {
+            if record.status.advances_to(status) {
+                record.status = status;
+                changed.push(record.clone());
+            }
+        }
+        if !changed.is_empty() {
+            self.save(&state).await?;
+        }
+        Ok(changed)
+    }
+
+    /// Take the requests still not available past their need-by date, `now`,
+    /// marking them escalated so each is only returned once
+    pub async fn escalate_overdue(&self, now: u64) -> anyhow::Result<Vec<RequestRecord>> {
+        let mut state = self.state.lock().await;
+        let mut overdue = Vec::new();
+        for record in state.requests.iter_mut().filter(|r| {
+            !r.escalated && !r.status.is_settled() && r.need_by.is_some_and(|t| t <= now)
+        }) {
+            record.escalated = true;
+            overdue.push(record.clone());
+        }
+        if !overdue.is_empty() {
+            self.save(&state).await?;
+        }
+        Ok(overdue)
+    }
+
+    /// Count a new search for the title's requests that have waited `after`
+    /// seconds since they were made or last searched for, and been searched
+    /// for fewer than `limit` times. Returns whether any of them was due.
+    pub async fn claim_research(
+        &self,
+        media: &str,
+        guild_id: Option<u64>,
+        ids: &MediaId,
+        now: u64,
+        after: u64,
+        limit: u32,
+    ) -> anyhow::Result<bool> {
+        let mut state = self.state.lock().await;
+        let mut due = false;
+        for record in state.requests.iter_mut().filter(|r| {
+            r.media == media
+                && r.guild_id == guild_id
+                && r.ids.matches(ids)
+                && !r.status.is_settled()
+                && r.searches < limit
+                && r.searched_at
+                    .unwrap_or(r.requested_at)
+                    .saturating_add(after)
+                    <= now
+        }) {
+            record.searches += 1;
+            record.searched_at = Some(now);
+            due = true;
+        }
+        if due {
+            self.save(&state).await?;
+        }
+        Ok(due)
+    }
+
+    /// Add one request flow's stage timings to its guild's totals, and the
+    /// stage it was abandoned in, if it was
+    pub async fn record_flow(
+        &self,
+        guild_id: u64,
+        stages: &[StageTiming],
+        abandoned_at: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let mut state = self.state.lock().await;
+        let totals = state.flow_stages.entry(guild_id).or_default();
+        for timing in stages {
+            let stage = totals.entry(timing.stage.clone()).or_default();
+            stage.completed += 1;
+            stage.millis += timing.millis;
+        }
+        if let Some(stage) = abandoned_at {
+            totals.entry(stage.to_string()).or_default().abandoned += 1;
+        }
+        self.save(&state).await
+    }
+
+    pub async fn flow_stages(&self, guild_id: u64) -> BTreeMap<String, StageTotals> {
+        self.state
+            .lock()
+            .await
+            .flow_stages
+            .get(&guild_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Take requests logged before `cutoff` (a Unix timestamp), and audit
+    /// records from then, out of the state. With an `archive` file, the
+    /// requests are appended to it as JSON lines first, so nothing is lost if
+    /// saving fails halfway.
+    pub async fn prune(&self, cutoff: u64, archive: Option<&Path>) -> anyhow::Result<Pruned> {
+        let mut state = self.state.lock().await;
+        let is_old = |r: &RequestRecord| r.requested_at < cutoff;
+        let pruned = Pruned {
+            requests: state.requests.iter().filter(|r| is_old(r)).count(),
+            audits: state.audit.iter().filter(|r| r.at < cutoff).count(),
+        };
+        if pruned == Pruned::default() {
+            return Ok(pruned);
+        }
+        if let Some(archive) = archive
+            && pruned.requests > 0
+        {
+            let mut lines = String::new();
+            for record in state.requests.iter().filter(|r| is_old(r)) {
+                lines.push_str(&serde_json::to_string(record)?);
+                lines.push('\n');
+            }
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(archive)
+                .await
+                .with_context(|| format!("Failed to open archive {}", archive.display()))?;
+            file.write_all(lines.as_bytes())
+                .await
+                .with_context(|| format!("Failed to write archive {}", archive.display()))?;
+        }
+        state.requests.retain(|r| !is_old(r));
+        state.audit.retain(|r| r.at >= cutoff);
+        self.save(&state).await?;
+        Ok(pruned)
+    }
+
+    /// Take a user out of the state: their requests, audit records, saved
+    /// name, and queued requests, and their place on other requests' Also
+    /// notify lists. Bans stay, as they're for a server's admins to lift.
+    pub async fn forget_user(&self, user_id: u64) -> anyhow::Result<Pruned> {
+        let mut state = self.state.lock().await;
+        let (requests, audits) = (state.requests.len(), state.audit.len());
+        state.requests.retain(|r| r.user_id != user_id);
+        state.audit.retain(|r| r.user_id != user_id);
+        let forgotten = Pruned {
+            requests: requests - state.requests.len(),
+            audits: audits - state.audit.len(),
+        };
+        for record in &mut state.requests {
+            record.subscribers.retain(|&id| id != user_id);
+        }
+        let mention = format!("<@{user_id}>");
+        for record in &mut state.audit {
+            for selection in &mut record.selections {
+                selection.values.retain(|v| *v != mention);
+            }
+        }
+        state.user_names.remove(&user_id);
+        state.pending_adds.retain(|p| p.user_id != user_id);
+        self.save(&state).await?;
+        Ok(forgotten)
+    }
+
+    pub async fn request_counts(&self, guild_id: u64) -> RequestCounts {
+        self.state
+            .lock()
+            .await
+            .guild_requests
+            .get(&guild_id)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Parse a state file, applying the migrations it hasn't had. Returns the
+/// state, and the version the file was at.
+fn migrate(content: &str, cx: &MigrationContext) -> anyhow::Result<(State, u32)> {
+    let mut doc: Map<String, Value> = serde_json::from_str(content)?;
+    let from = match doc.get("version").and_then(Value::as_u64) {
+        Some(version) => u32::try_from(version).unwrap_or(u32::MAX),
+        // Saved before the file was versioned
+        None => 0,
+    };
+    if from > SCHEMA_VERSION {
+        bail!(
+            "It's at schema version {from}, from a newer Doplarr than this one (which \
+             knows up to {SCHEMA_VERSION}). Upgrade Doplarr, or restore a backup."
+        );
+    }
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(from as usize) {
+        (migration.apply)(&mut doc, cx)
+            .with_context(|| format!("Migration {} failed: {}", i + 1, migration.description))?;
+        info!(
+            version = i + 1,
+            "Migrated state file: {}", migration.description
+        );
+    }
+    doc.insert("version".into(), SCHEMA_VERSION.into());
+    Ok((serde_json::from_value(Value::Object(doc))?, from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn old_state_files_are_migrated_and_backed_up() {
+        let path = std::env::temp_dir().join(format!("doplarr-{}.json", uuid::Uuid::new_v4()));
+        let unversioned = r#"{"bans": {"1": {"reason": null, "banned_by": 2, "banned_at": 3}},
+            "guild_requests": {"10": {"submitted": 1}},
+            "requests": [{"requested_at": 1, "user_id": 1, "guild_id": 20, "media": "movie", "title": "T"}]}"#;
+        std::fs::write(&path, unversioned).unwrap();
+
+        let store = Store::load(&path, |_| None).await.unwrap();
+        assert_eq!(store.schema_version().await, SCHEMA_VERSION);
+        // Bans from before they were per server stay in force in each one known
+        assert!(store.ban(10, 1).await.is_some());
+        assert!(store.ban(20, 1).await.is_some());
+        assert!(store.ban(30, 1).await.is_none());
+        let backup = path.with_extension("json.v0.bak");
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), unversioned);
+        let saved: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["version"], SCHEMA_VERSION);
+
+        std::fs::write(&path, format!(r#"{{"version": {}}}"#, SCHEMA_VERSION + 1)).unwrap();
+        let newer = Store::load(&path, |_| None).await.err().unwrap();
+        assert!(format!("{newer:#}").contains("from a newer Doplarr"));
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&backup).unwrap();
+    }
+
+    #[tokio::test]
+    async fn bans_persist_across_loads() {
+        let path = std::env::temp_dir().join(format!("doplarr-{}.json", uuid::Uuid::new_v4()));
+        let store = Store::load(&path, |_| None).await.unwrap();
+        assert_eq!(store.ban(10, 1).await, None);
+
+        let ban = Ban::new(Some("spam".into()), 99);
+        assert!(!store.add_ban(10, 1, ban.clone()).await.unwrap());
+        assert!(store.add_ban(10, 1, ban.clone()).await.unwrap());
+        assert!(!store.remove_ban(10, 2).await.unwrap());
+        // Another server's admins can neither see nor lift it
+        assert_eq!(store.ban(20, 1).await, None);
+        assert!(!store.remove_ban(20, 1).await.unwrap());
+
+        let reloaded = Store::load(&path, |_| None).await.unwrap();
+        assert_eq!(reloaded.ban(10, 1).await, Some(ban));
+        assert!(reloaded.remove_ban(10, 1).await.unwrap());
+        assert_eq!(
+            Store::load(&path, |_| None).await.unwrap().ban(10, 1).await,
+            None
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn request_threads_are_kept_until_forgotten() {
+        let path = std::env::temp_dir().join(format!("doplarr-{}.json", uuid::Uuid::new_v4()));
+        let store = Store::load(&path, |_| None).await.unwrap();
+        store.set_request_thread(1, Some(10)).await.unwrap();
+
+        let reloaded = Store::load(&path, |_| None).await.unwrap();
+        assert_eq!(reloaded.request_thread(1).await, Some(10));
+        reloaded.set_request_thread(1, None).await.unwrap();
+        assert_eq!(
+            Store::load(&path, |_| None)
+                .await
+                .unwrap()
+                .request_thread(1)
+                .await,
+            None
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn request_counts_are_per_guild() {
+        let path = std::env::temp_dir().join(format!("doplarr-{}.json", uuid::Uuid::new_v4()));
+        let store = Store::load(&path, |_| None).await.unwrap();
+        store.record_request(1, true).await.unwrap();
+        store.record_request(1, false).await.unwrap();
+        store.record_request(2, true).await.unwrap();
+
+        let reloaded = Store::load(&path, |_| None).await.unwrap();
+        assert_eq!(
+            reloaded.request_counts(1).await,
+            RequestCounts {
+                submitted: 2,
+                failed: 1
+            }
+        );
+        assert_eq!(reloaded.request_counts(2).await.submitted, 1);
+        assert_eq!(reloaded.request_counts(3).await, RequestCounts::default());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn flow_stages_add_up_per_guild() {
+        let path = std::env::temp_dir().join(format!("doplarr-{}.json", uuid::Uuid::new_v4()));
+        let store = Store::load(&path, |_| None).await.unwrap();
+        let timing = |stage: &str, millis| StageTiming {
+            stage: stage.into(),
+            millis,
+        };
+        store
+            .record_flow(1, &[timing("search", 400), timing("select", 3000)], None)
+            .await
+            .unwrap();
+        store
+            .record_flow(1, &[timing("search", 600)], Some("select"))
+            .await
+            .unwrap();
+
+        let stages = Store::load(&path, |_| None)
+            .await
+            .unwrap()
+            .flow_stages(1)
+            .await;
+        assert_eq!(
+            stages["search"],
+            StageTotals {
+                completed: 2,
+                millis: 1000,
+                abandoned: 0
+            }
+        );
+        assert_eq!(stages["search"].average(), Some(Duration::from_millis(500)));
+        assert_eq!(stages["select"].abandoned, 1);
+        assert!(store.flow_stages(2).await.is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn audit_keeps_the_latest_records() {
+        let path = std::env::temp_dir().join(format!("doplarr-{}.json", uuid::Uuid::new_v4()));
+        let store = Store::load(&path, |_| None).await.unwrap();
+        let record = |i: usize| AuditRecord {
+            id: i.to_string(),
+            at: 0,
+            user_id: 1,
+            guild_id: Some(1),
+            media: "movie".into(),
+            backend: "radarr".into(),
+            query: "dune".into(),
+            title: "Dune (2021)".into(),
+            selections: vec![],
+            error: None,
+            stages: vec![],
+            denied: None,
+        };
+        for i in 0..=AUDIT_CAPACITY {
+            store.record_audit(record(i)).await.unwrap();
+        }
+
+        let reloaded = Store::load(&path, |_| None).await.unwrap();
+        assert_eq!(reloaded.audit("0").await, None, "oldest dropped");
+        assert_eq!(reloaded.audit("1").await, Some(record(1)));
+        let recent: Vec<_> = reloaded
+            .recent_audits(Some(1), 2)
+            .await
+            .into_iter()
+            .map(|r| r.id)
+            .collect();
+        assert_eq!(
+            recent,
+            [AUDIT_CAPACITY.to_string(), (AUDIT_CAPACITY - 1).to_string()]
+        );
+        assert!(reloaded.recent_audits(Some(2), 2).await.is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn request_status_only_moves_forward() {
+        let path = std::env::temp_dir().join(format!("doplarr-{}.json", uuid::Uuid::new_v4()));
+        let store = Store::load(&path, |_| None).await.unwrap();
+        let dune = MediaId {
+            tmdb: Some(438631),
+            ..Default::default()
+        };
+        let record = RequestRecord::new(10, Some(1), "movie".into(), "Dune (2021)".into(), dune);
+        store.log_request(record.clone()).await.unwrap();
+        store.log_request(record).await.unwrap();
+
+        // e.g. a webhook, which knows the title by more IDs
+        let webhook = MediaId {
+            tmdb: Some(438631),
+            imdb: Some("tt1160419".into()),
+            ..Default::default()
+        };
+        let update = |status| store.update_status("movie", Some(1), &webhook, status);
+        assert_eq!(update(RequestStatus::Downloading).await.unwrap().len(), 2);
+        // A poll that's behind the webhook doesn't undo it
+        assert!(update(RequestStatus::Grabbed).await.unwrap().is_empty());
+        assert!(
+            store
+                .update_status("series", Some(1), &webhook, RequestStatus::Available)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let available = update(RequestStatus::Available).await.unwrap();
+        assert_eq!(available[0].status, RequestStatus::Available);
+        assert!(update(RequestStatus::Failed).await.unwrap().is_empty());
+
+        let reloaded = Store::load(&path, |_| None).await.unwrap();
+        assert!(
+            reloaded
+                .requests(|_| true)
+                .await
+                .iter()
+                .all(|r| r.status == RequestStatus::Available)
+        );
+        // Removing it from the library settles it for good
+        assert_eq!(update(RequestStatus::Removed).await.unwrap().len(), 2);
+        assert!(update(RequestStatus::Available).await.unwrap().is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn overdue_requests_escalate_once() {
+        let path = std::env::temp_dir().join(format!("doplarr-{}.json", uuid::Uuid::new_v4()));
+        let store = Store::load(&path, |_| None).await.unwrap();
+        let logged = |title: &str, need_by| RequestRecord {
+            need_by,
+            ..RequestRecord::new(
+                10,
+                Some(1),
+                "movie".into(),
+                title.into(),
+                MediaId::default(),
+            )
+        };
+        store.log_request(logged("Dune", Some(100))).await.unwrap();
+        store.log_request(logged("Heat", Some(200))).await.unwrap();
+        store.log_request(logged("Alien", None)).await.unwrap();
+        store
+            .log_request(RequestRecord {
+                status: RequestStatus::Available,
+                ..logged("Up", Some(100))
+            })
+            .await
+            .unwrap();
+
+        let overdue = store.escalate_overdue(150).await.unwrap();
+        assert_eq!(overdue.len(), 1);
+        assert_eq!(overdue[0].title, "Dune");
+        assert!(store.escalate_overdue(150).await.unwrap().is_empty());
+
+        let reloaded = Store::load(&path, |_| None).await.unwrap();
+        let overdue = reloaded.escalate_overdue(250).await.unwrap();
+        assert_eq!(overdue.len(), 1);
+        assert_eq!(overdue[0].title, "Heat");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn stalled_requests_are_searched_up_to_the_limit() {
+        let path = std::env::temp_dir().join(format!("doplarr-{}.json", uuid::Uuid::new_v4()));
+        let store = Store::load(&path, |_| None).await.unwrap();
+        let dune = MediaId {
+            tmdb: Some(438631),
+            ..Default::default()
+        };
+        store
+            .log_request(RequestRecord {
+                requested_at: 1000,
+                ..RequestRecord::new(10, Some(1), "movie".into(), "Dune".into(), dune.clone())
+            })
+            .await
+            .unwrap();
+        let claim = |now| store.claim_research("movie", Some(1), &dune, now, 100, 2);
+        assert!(!claim(1050).await.unwrap(), "hasn't waited long enough");
+        assert!(claim(1100).await.unwrap());
+        assert!(!claim(1150).await.unwrap(), "just searched");
+        assert!(claim(1200).await.unwrap());
+        assert!(!claim(5000).await.unwrap(), "out of searches");
+
+        let reloaded = Store::load(&path, |_| None).await.unwrap();
+        let requests = reloaded.requests(|_| true).await;
+        assert_eq!(requests[0].searches, 2);
+        assert_eq!(requests[0].searched_at, Some(1200));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn legacy_media_ids_are_upgraded() {
+        let path = std::env::temp_dir().join(format!("doplarr-{}.json", uuid::Uuid::new_v4()));
+        let logged = |at, media, id| {
+            format!(
+                r#"{{"requested_at": {at}, "user_id": 10, "guild_id": 1, "media": "{media}", "title": "T", "media_id": {id}}}"#
+            )
+        };
+        let requests = [
+            logged(1, "movie", 438631),
+            logged(2, "series", 81189),
+            logged(3, "plugin", 5),
+        ];
+        std::fs::write(
+            &path,
+            format!(r#"{{"requests": [{}]}}"#, requests.join(",")),
+        )
+        .unwrap();
+
+        Store::load(&path, |media| match media {
+            "movie" => Some(MediaKind::Movie),
+            "series" => Some(MediaKind::Tv),
+            _ => None,
+        })
+        .await
+        .unwrap();
+
+        let requests = Store::load(&path, |_| None)
+            .await
+            .unwrap()
+            .requests(|_| true)
+            .await;
+        assert_eq!(requests[0].ids.tmdb, Some(438631));
+        assert_eq!(requests[0].legacy_media_id, None);
+        assert_eq!(requests[1].ids.tvdb, Some(81189));
+        assert_eq!(
+            requests[2].legacy_media_id,
+            Some(5),
+            "kept as it was, with no telling which ID it is"
+        );
+        assert_eq!(requests[2].ids, MediaId::default());
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("json.v0.bak")).unwrap();
+    }
+
+    #[tokio::test]
+    async fn forgotten_users_leave_nothing_behind() {
+        let path = std::env::temp_dir().join(format!("doplarr-{}.json", uuid::Uuid::new_v4()));
+        let store = Store::load(&path, |_| None).await.unwrap();
+        let record = |user_id, subscribers| RequestRecord {
+            subscribers,
+            ..RequestRecord::new(
+                user_id,
+                Some(1),
+                "movie".into(),
+                "T".into(),
+                MediaId::default(),
+            )
+        };
+        store.log_request(record(1, vec![])).await.unwrap();
+        store.log_request(record(2, vec![1, 3])).await.unwrap();
+        store.remember_name(1, "someone").await.unwrap();
+
+        let forgotten = store.forget_user(1).await.unwrap();
+        assert_eq!(forgotten.requests, 1);
+        let reloaded = Store::load(&path, |_| None).await.unwrap();
+        let requests = reloaded.requests(|_| true).await;
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].subscribers, [3]);
+        assert!(!reloaded.user_names().await.contains_key(&1));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn names_follow_renames() {
+        let path = std::env::temp_dir().join(format!("doplarr-{}.json", uuid::Uuid::new_v4()));
+        let store = Store::load(&path, |_| None).await.unwrap();
+        store.remember_name(1, "old").await.unwrap();
+        store.remember_name(1, "new").await.unwrap();
+
+        let names = Store::load(&path, |_| None)
+            .await
+            .unwrap()
+            .user_names()
+            .await;
+        assert_eq!(names.get(&1).map(String::as_str), Some("new"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn request_log_persists_in_order() {
+        let path = std::env::temp_dir().join(format!("doplarr-{}.json", uuid::Uuid::new_v4()));
+        let store = Store::load(&path, |_| None).await.unwrap();
+        let record = |user_id, title: &str| {
+            RequestRecord::new(
+                user_id,
+                Some(1),
+                "movie".into(),
+                title.into(),
+                MediaId::default(),
+            )
+        };
+        store.log_request(record(10, "Dune (2021)")).await.unwrap();
+        store
+            .log_request(record(20, "Arrival (2016)"))
+            .await
+            .unwrap();
+
+        let reloaded = Store::load(&path, |_| None).await.unwrap();
+        let titles = |records: Vec<RequestRecord>| -> Vec<String> {
+            records.into_iter().map(|r| r.title).collect()
+        };
+        assert_eq!(
+            titles(reloaded.requests(|_| true).await),
+            ["Dune (2021)", "Arrival (2016)"]
+        );
+        assert_eq!(
+            titles(reloaded.requests(|r| r.user_id == 20).await),
+            ["Arrival (2016)"]
+        );
+
+        let mut older = record(30, "Heat (1995)");
+        older.requested_at = 0;
+        let added = reloaded
+            .import_requests(vec![older.clone(), older])
+            .await
+            .unwrap();
+        assert_eq!(added, 1, "duplicates skipped");
+        assert_eq!(
+            titles(reloaded.requests(|_| true).await)[0],
+            "Heat (1995)",
+            "imports slot in by date"
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn queued_adds_persist_until_finished() {
+        let path = std::env::temp_dir().join(format!("doplarr-{}.json", uuid::Uuid::new_v4()));
+        let store = Store::load(&path, |_| None).await.unwrap();
+        let pending = PendingAdd {
+            queued_at: 1,
+            backend: "radarr".into(),
+            user_id: 10,
+            guild_id: Some(1),
+            media: "movie".into(),
+            tmdb: 438631,
+            title: "Dune (2021)".into(),
+            selections: vec![Selection {
+                field: "Quality Profile".into(),
+                values: vec!["HD-1080p".into()],
+            }],
+            tags: vec!["requested-by-10".into()],
+        };
+        store.queue_add(pending.clone()).await.unwrap();
+
+        let reloaded = Store::load(&path, |_| None).await.unwrap();
+        assert_eq!(reloaded.pending_adds().await, vec![pending.clone()]);
+        reloaded.finish_add(&pending).await.unwrap();
+        assert!(
+            Store::load(&path, |_| None)
+                .await
+                .unwrap()
+                .pending_adds()
+                .await
+                .is_empty()
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
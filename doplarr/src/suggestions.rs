@@ -0,0 +1,230 @@
+//! "You might also like" titles under a successful request
+//!
+//! With `[suggestions]` set, a request's success card gets a row of buttons
+//! for titles TMDB recommends based on the one just requested. Each button
+//! starts a quick request for its title on the same media command, as if the
+//! requester had typed `/request <media> <title> quick:true year:<year>`.
+use crate::config::{MediaKind, Suggestions};
+use anyhow::Result;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::{debug, warn};
+use twilight_model::id::{Id, marker::UserMarker};
+use uuid::Uuid;
+
+const TMDB_API: &str = "https://api.themoviedb.org/3";
+
+/// How many titles are suggested, when not configured
+const DEFAULT_COUNT: usize = 3;
+
+/// How long a suggestion's button keeps working
+const OFFER_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// How long the success card waits on TMDB before it's shown without suggestions
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A title offered on a success card, and who it was offered to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Offer {
+    /// The media command the original request went through
+    pub media: String,
+    pub title: String,
+    pub year: Option<i32>,
+    pub user_id: Id<UserMarker>,
+}
+
+impl Offer {
+    /// The button's label, e.g. "Inception (2010)"
+    pub fn label(&self) -> String {
+        match self.year {
+            Some(year) => format!("{} ({year})", self.title),
+            None => self.title.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Recommendations {
+    #[serde(default)]
+    results: Vec<Recommendation>,
+}
+
+/// A movie (`title`, `release_date`) or series (`name`, `first_air_date`)
+#[derive(Deserialize)]
+struct Recommendation {
+    #[serde(alias = "name")]
+    title: Option<String>,
+    #[serde(alias = "first_air_date")]
+    release_date: Option<String>,
+    #[serde(default)]
+    adult: bool,
+}
+
+/// The titles and years of a TMDB recommendations response, best first.
+/// Adult titles are left out, as suggestions aren't checked against the
+/// channel like search results are.
+fn recommended(body: &[u8], count: usize) -> Result<Vec<(String, Option<i32>)>> {
+    let recommendations: Recommendations = serde_json::from_slice(body)?;
+    Ok(recommendations
+        .results
+        .into_iter()
+        .filter(|r| !r.adult)
+        .filter_map(|r| {
+            let year = r
+                .release_date
+                .as_deref()
+                .and_then(|d| d.get(..4)?.parse().ok());
+            Some((r.title.filter(|t| !t.is_empty())?, year))
+        })
+        .take(count)
+        .collect())
+}
+
+/// Finds similar titles, and remembers the ones offered until they're clicked
+pub struct Suggester {
+    api_key: String,
+    client: reqwest::Client,
+    count: usize,
+    offered: Mutex<HashMap<Uuid, (Offer, Instant)>>,
+}
+
+impl Suggester {
+    pub fn new(config: &Suggestions, client: reqwest::Client) -> Self {
+        Self {
+            api_key: config.tmdb_api_key.clone(),
+            client,
+            count: config.count.unwrap_or(DEFAULT_COUNT).clamp(3, 5),
+            offered: Mutex::default(),
+        }
+    }
+
+    async fn similar(&self, tmdb: i32, kind: MediaKind) -> Result<Vec<(String, Option<i32>)>> {
+        let list = match kind {
+            MediaKind::Movie => "movie",
+            MediaKind::Tv => "tv",
+        };
+        let body = self
+            .client
+            .get(format!(
+                "{TMDB_API}/{list}/{tmdb}/recommendations?api_key={}",
+                self.api_key
+            ))
+            .timeout(LOOKUP_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        recommended(&body, self.count)
+    }
+
+    /// Titles like the one `user_id` just requested, each under the ID its
+    /// button is sent back with. Nothing when TMDB has none or can't be reached.
+    pub async fn offer(
+        &self,
+        media: &str,
+        tmdb: i32,
+        kind: MediaKind,
+        user_id: Id<UserMarker>,
+    ) -> Vec<(Uuid, Offer)> {
+        let similar = match self.similar(tmdb, kind).await {
+            Ok(similar) => similar,
+            Err(e) => {
+                warn!(error = ?e, "Couldn't look up similar titles");
+                return vec![];
+            }
+        };
+        debug!(count = similar.len(), "Suggesting similar titles");
+        let offers: Vec<_> = similar
+            .into_iter()
+            .map(|(title, year)| {
+                let offer = Offer {
+                    media: media.to_string(),
+                    title,
+                    year,
+                    user_id,
+                };
+                (Uuid::new_v4(), offer)
+            })
+            .collect();
+        let mut offered = self.offered.lock().expect("suggestions lock poisoned");
+        offered.retain(|_, (_, at)| at.elapsed() < OFFER_WINDOW);
+        let now = Instant::now();
+        offered.extend(offers.iter().map(|(id, o)| (*id, (o.clone(), now))));
+        offers
+    }
+
+    /// The title behind a clicked button, if it was offered to `user_id`
+    /// recently. Each button works once.
+    pub fn take(&self, id: Uuid, user_id: Id<UserMarker>) -> Option<Offer> {
+        let mut offered = self.offered.lock().expect("suggestions lock poisoned");
+        match offered.get(&id) {
+            Some((offer, at)) if offer.user_id == user_id && at.elapsed() < OFFER_WINDOW => {
+                offered.remove(&id).map(|(offer, _)| offer)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recommendations_give_titles_and_years() {
+        let movies = br#"{"page": 1, "results": [
+            {"title": "Interstellar", "release_date": "2014-11-05", "adult": false},
+            {"title": "Adult", "release_date": "2001-01-01", "adult": true},
+            {"title": "Undated", "release_date": ""},
+            {"title": "Tenet", "release_date": "2020-08-22"},
+            {"title": "Too many", "release_date": "2021-01-01"}
+        ]}"#;
+        assert_eq!(
+            recommended(movies, 3).unwrap(),
+            [
+                ("Interstellar".to_string(), Some(2014)),
+                ("Undated".to_string(), None),
+                ("Tenet".to_string(), Some(2020)),
+            ]
+        );
+        let series =
+            br#"{"results": [{"name": "Better Call Saul", "first_air_date": "2015-02-08"}]}"#;
+        assert_eq!(
+            recommended(series, 3).unwrap(),
+            [("Better Call Saul".to_string(), Some(2015))]
+        );
+    }
+
+    #[test]
+    fn offers_are_taken_once_by_their_requester() {
+        let suggester = Suggester::new(
+            &Suggestions {
+                tmdb_api_key: "key".into(),
+                count: Some(9),
+            },
+            reqwest::Client::new(),
+        );
+        assert_eq!(suggester.count, 5);
+        let offer = Offer {
+            media: "movie".into(),
+            title: "Tenet".into(),
+            year: Some(2020),
+            user_id: Id::new(1),
+        };
+        assert_eq!(offer.label(), "Tenet (2020)");
+        let id = Uuid::new_v4();
+        suggester
+            .offered
+            .lock()
+            .unwrap()
+            .insert(id, (offer.clone(), Instant::now()));
+        assert_eq!(suggester.take(id, Id::new(2)), None);
+        assert_eq!(suggester.take(id, Id::new(1)), Some(offer));
+        assert_eq!(suggester.take(id, Id::new(1)), None);
+    }
+}
@@ -0,0 +1,145 @@
+//! Fitting text into Discord's length limits
+//!
+//! Discord counts limits in characters, and rejects a message outright when
+//! any part runs over. Cutting at an arbitrary point can also break the
+//! markdown around the cut: an unclosed `||` spoils the rest of the card, and
+//! half a mention renders as garbage. [truncate] cuts between words, drops a
+//! mention or link it would split, and closes any formatting left open.
+
+const ELLIPSIS: char = '…';
+
+/// Paired markdown markers, longest first so `**` isn't read as two `*`
+const MARKERS: [&str; 5] = ["```", "||", "**", "~~", "`"];
+
+/// Only back up to a word boundary if it keeps at least this much of the text
+const MIN_WORD_CUT: f32 = 0.75;
+
+/// Shorten `text` to at most `max` characters, ellipsis included
+pub fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    let mut budget = max - 1;
+    loop {
+        let cut = drop_partial_entity(cut_at_word(text, budget));
+        let closers = unclosed_markers(cut);
+        let len = cut.chars().count() + 1 + closers.chars().count();
+        if len <= max || budget == 0 {
+            return format!("{cut}{ELLIPSIS}{closers}");
+        }
+        // Make room for the closing markers and try again
+        budget = budget.saturating_sub(len - max);
+    }
+}
+
+/// The longest prefix of at most `budget` characters, ending between words
+/// when that doesn't lose too much
+fn cut_at_word(text: &str, budget: usize) -> &str {
+    let end = text
+        .char_indices()
+        .nth(budget)
+        .map_or(text.len(), |(i, _)| i);
+    let prefix = &text[..end];
+    if text[end..].starts_with(char::is_whitespace) {
+        return prefix.trim_end();
+    }
+    match prefix.rfind(char::is_whitespace) {
+        Some(space) if space as f32 >= prefix.len() as f32 * MIN_WORD_CUT => {
+            prefix[..space].trim_end()
+        }
+        _ => prefix,
+    }
+}
+
+/// Cut before a mention/timestamp (`<...>`) or link (`[...](...)`) the
+/// prefix ends inside of
+fn drop_partial_entity(prefix: &str) -> &str {
+    let mut end = prefix.len();
+    if let Some(open) = prefix.rfind('<')
+        && !prefix[open..].contains('>')
+    {
+        end = end.min(open);
+    }
+    if let Some(open) = prefix.rfind('[') {
+        let rest = &prefix[open..];
+        let closed = match rest.find("](") {
+            Some(url) => rest[url..].contains(')'),
+            None => rest.contains(']'),
+        };
+        if !closed {
+            end = end.min(open);
+        }
+    }
+    prefix[..end].trim_end()
+}
+
+/// The markers needed to close the formatting `text` leaves open, innermost first
+fn unclosed_markers(text: &str) -> String {
+    let mut open: Vec<&str> = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '\\' {
+            // Escaped: skip it and the character it escapes
+            let mut chars = rest.chars();
+            chars.next();
+            chars.next();
+            rest = chars.as_str();
+            continue;
+        }
+        let in_code = open.last().is_some_and(|m| m.starts_with('`'));
+        match MARKERS.iter().find(|m| rest.starts_with(**m)) {
+            // Nothing is formatting inside code, except the end of it
+            Some(marker) if in_code && open.last() != Some(marker) => {
+                rest = &rest[c.len_utf8()..];
+            }
+            Some(marker) => {
+                if open.last() == Some(marker) {
+                    open.pop();
+                } else {
+                    open.push(marker);
+                }
+                rest = &rest[marker.len()..];
+            }
+            None => rest = &rest[c.len_utf8()..],
+        }
+    }
+    open.iter().rev().copied().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_is_untouched() {
+        assert_eq!(truncate("Dune", 4), "Dune");
+        assert_eq!(truncate("Dune", 0), "");
+    }
+
+    #[test]
+    fn cuts_between_words_by_characters() {
+        assert_eq!(truncate("The quick brown fox", 16), "The quick brown…");
+        // A long word isn't worth losing most of the text over
+        assert_eq!(truncate("Supercalifragilistic", 8), "Superca…");
+        // Counted in characters, not bytes
+        assert_eq!(truncate("ééééé", 4), "ééé…");
+    }
+
+    #[test]
+    fn formatting_is_closed_and_entities_kept_whole() {
+        assert_eq!(truncate("||a big twist ending||", 16), "||a big twist…||");
+        assert_eq!(
+            truncate("**bold** and `some code here`", 22),
+            "**bold** and `some…`"
+        );
+        assert_eq!(truncate("by <@123456789012345678>", 12), "by…");
+        assert_eq!(
+            truncate("see [the trailer](https://example.com)", 20),
+            "see…"
+        );
+        assert_eq!(truncate("a \\*not bold and long", 13), "a \\*not bold…");
+    }
+}
@@ -0,0 +1,96 @@
+//! Request log threads for busy channels
+//!
+//! With `[[request_threads]]` set for a channel, what the bot posts there on
+//! its own (request confirmations, availability mentions, approval outcomes)
+//! goes into one long-lived thread in that channel instead, keeping the
+//! channel itself for conversation. The bot starts the thread the first time
+//! it's needed and keeps using it across restarts, starting a new one if it's
+//! deleted.
+use crate::{config::Config, store::Store};
+use anyhow::{Context, Result};
+use std::{collections::HashMap, sync::Arc};
+use tracing::{info, warn};
+use twilight_http::Client as HttpClient;
+use twilight_model::{
+    channel::{ChannelType, thread::AutoArchiveDuration},
+    id::{Id, marker::ChannelMarker},
+};
+
+/// What the thread is called unless configured otherwise
+const DEFAULT_THREAD_NAME: &str = "Request log";
+
+/// The channels with a request log thread, and where their threads are
+pub struct RequestThreads {
+    /// Thread names, by the channel they're in
+    names: HashMap<Id<ChannelMarker>, String>,
+    store: Arc<Store>,
+    client: Arc<HttpClient>,
+}
+
+impl RequestThreads {
+    /// `None` when no channel is configured to have a thread
+    pub fn new(config: &Config, store: Arc<Store>, client: Arc<HttpClient>) -> Option<Self> {
+        let names: HashMap<_, _> = config
+            .request_threads
+            .iter()
+            .flatten()
+            .filter_map(|t| {
+                let name = t.name.as_deref().unwrap_or(DEFAULT_THREAD_NAME);
+                Some((Id::new_checked(t.channel_id)?, name.to_string()))
+            })
+            .collect();
+        (!names.is_empty()).then_some(Self {
+            names,
+            store,
+            client,
+        })
+    }
+
+    /// Where a message for `channel_id` goes: the channel's thread, started
+    /// now if it has none yet, or the channel itself when it has no thread
+    /// configured (or the thread can't be started)
+    pub async fn target(&self, channel_id: Id<ChannelMarker>) -> Id<ChannelMarker> {
+        let Some(name) = self.names.get(&channel_id) else {
+            return channel_id;
+        };
+        if let Some(thread_id) = self.store.request_thread(channel_id.get()).await {
+            return Id::new(thread_id);
+        }
+        match self.start(channel_id, name).await {
+            Ok(thread_id) => thread_id,
+            Err(e) => {
+                warn!(
+                    channel_id = %channel_id,
+                    error = ?e,
+                    "Couldn't start the request log thread, posting in the channel. \
+                     The bot needs \"Create Public Threads\" and \"Send Messages in Threads\"."
+                );
+                channel_id
+            }
+        }
+    }
+
+    /// Forget the channel's thread, e.g. because it was deleted, so the next
+    /// message starts a new one
+    pub async fn forget(&self, channel_id: Id<ChannelMarker>) {
+        if let Err(e) = self.store.set_request_thread(channel_id.get(), None).await {
+            warn!(channel_id = %channel_id, error = ?e, "Failed to forget the request log thread");
+        }
+    }
+
+    async fn start(&self, channel_id: Id<ChannelMarker>, name: &str) -> Result<Id<ChannelMarker>> {
+        let thread = self
+            .client
+            .create_thread(channel_id, name, ChannelType::PublicThread)
+            .auto_archive_duration(AutoArchiveDuration::Week)
+            .await
+            .context("Failed to create the thread")?
+            .model()
+            .await?;
+        info!(channel_id = %channel_id, thread_id = %thread.id, "Started a request log thread");
+        self.store
+            .set_request_thread(channel_id.get(), Some(thread.id.get()))
+            .await?;
+        Ok(thread.id)
+    }
+}
@@ -0,0 +1,237 @@
+//! Optional poster proxy
+//!
+//! Poster URLs from metadata providers are sometimes slow, huge, or blocked
+//! for Discord's media proxy, leaving broken thumbnails in the request cards.
+//! When `[thumbnails]` is configured, cards point at this bot's own HTTP
+//! server instead, which fetches each poster once, shrinks it to thumbnail
+//! size, and serves the cached copy. Only posters the bot has put in a card
+//! are served, so it can't be used as an open proxy.
+use crate::config::Thumbnails;
+use anyhow::Context;
+use axum::{
+    Router,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::IntoResponse,
+    routing::get,
+};
+use image::{ImageFormat, imageops::FilterType};
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::Cursor,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tracing::{debug, info, warn};
+
+/// How many posters to remember, evicting the oldest first
+const CACHE_ENTRIES: usize = 256;
+
+/// Posters are scaled down to fit in this box, keeping their aspect ratio
+const MAX_WIDTH: u32 = 300;
+const MAX_HEIGHT: u32 = 450;
+
+/// Refuse posters larger than this, before decoding
+const MAX_SOURCE_BYTES: usize = 20 * 1024 * 1024;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+struct Cache {
+    /// Key -> original poster URL, for every poster handed out
+    sources: HashMap<String, String>,
+    /// Key -> resized JPEG, once fetched
+    images: HashMap<String, Arc<Vec<u8>>>,
+    /// Keys in the order they were handed out
+    order: VecDeque<String>,
+}
+
+/// Hands out proxied poster URLs and serves the resized images behind them
+pub struct ThumbnailProxy {
+    public_url: String,
+    client: reqwest::Client,
+    cache: Mutex<Cache>,
+}
+
+/// A stable key for a poster URL, so re-rendering a card reuses its entry
+fn key(source: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Decode a poster and re-encode it as a JPEG no bigger than the thumbnail box
+fn shrink(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let image = image::load_from_memory(bytes).context("Failed to decode poster")?;
+    let image = if image.width() > MAX_WIDTH || image.height() > MAX_HEIGHT {
+        image.resize(MAX_WIDTH, MAX_HEIGHT, FilterType::Triangle)
+    } else {
+        image
+    };
+    let mut out = Cursor::new(Vec::new());
+    image
+        .to_rgb8()
+        .write_to(&mut out, ImageFormat::Jpeg)
+        .context("Failed to encode poster")?;
+    Ok(out.into_inner())
+}
+
+impl ThumbnailProxy {
+    pub fn new(config: &Thumbnails) -> anyhow::Result<Self> {
+        Ok(Self {
+            public_url: config.public_url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?,
+            cache: Mutex::default(),
+        })
+    }
+
+    /// The proxied URL for a poster, remembering it so it can be served
+    pub fn url_for(&self, source: &str) -> String {
+        let key = key(source);
+        let mut cache = self.cache.lock().expect("thumbnail cache lock poisoned");
+        if cache
+            .sources
+            .insert(key.clone(), source.to_string())
+            .is_none()
+        {
+            cache.order.push_back(key.clone());
+            while cache.order.len() > CACHE_ENTRIES {
+                if let Some(old) = cache.order.pop_front() {
+                    cache.sources.remove(&old);
+                    cache.images.remove(&old);
+                }
+            }
+        }
+        format!("{}/thumbnails/{key}.jpg", self.public_url)
+    }
+
+    /// The resized poster for a key, fetching it on first use.
+    /// `None` for keys the bot never handed out.
+    async fn image(&self, key: &str) -> Option<anyhow::Result<Arc<Vec<u8>>>> {
+        let source = {
+            let cache = self.cache.lock().expect("thumbnail cache lock poisoned");
+            if let Some(image) = cache.images.get(key) {
+                return Some(Ok(Arc::clone(image)));
+            }
+            cache.sources.get(key)?.clone()
+        };
+        debug!(source = %source, "Fetching poster");
+        let result = async {
+            let response = self.client.get(&source).send().await?.error_for_status()?;
+            if response
+                .content_length()
+                .is_some_and(|len| len > MAX_SOURCE_BYTES as u64)
+            {
+                anyhow::bail!("Poster is over {MAX_SOURCE_BYTES} bytes");
+            }
+            let bytes = response.bytes().await?;
+            if bytes.len() > MAX_SOURCE_BYTES {
+                anyhow::bail!("Poster is over {MAX_SOURCE_BYTES} bytes");
+            }
+            let image = tokio::task::spawn_blocking(move || shrink(&bytes)).await??;
+            Ok(Arc::new(image))
+        }
+        .await
+        .with_context(|| format!("Failed to proxy poster {source}"));
+
+        if let Ok(image) = &result {
+            let mut cache = self.cache.lock().expect("thumbnail cache lock poisoned");
+            // Only keep it if it wasn't evicted while we were fetching
+            if cache.sources.contains_key(key) {
+                cache.images.insert(key.to_string(), Arc::clone(image));
+            }
+        }
+        Some(result)
+    }
+}
+
+async fn serve_thumbnail(
+    State(proxy): State<Arc<ThumbnailProxy>>,
+    Path(file): Path<String>,
+) -> impl IntoResponse {
+    let Some(key) = file.strip_suffix(".jpg") else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    match proxy.image(key).await {
+        None => StatusCode::NOT_FOUND.into_response(),
+        Some(Err(e)) => {
+            warn!(error = ?e, "Could not serve thumbnail");
+            StatusCode::BAD_GATEWAY.into_response()
+        }
+        Some(Ok(image)) => (
+            [
+                (header::CONTENT_TYPE, "image/jpeg"),
+                (header::CACHE_CONTROL, "public, max-age=86400"),
+            ],
+            image.as_ref().clone(),
+        )
+            .into_response(),
+    }
+}
+
+/// Start the thumbnail server in the background
+pub async fn spawn(config: &Thumbnails) -> anyhow::Result<Arc<ThumbnailProxy>> {
+    let proxy = Arc::new(ThumbnailProxy::new(config)?);
+    let listener = tokio::net::TcpListener::bind(&config.listen)
+        .await
+        .with_context(|| format!("Failed to listen for thumbnails on {}", config.listen))?;
+    info!(listen = %config.listen, public_url = %proxy.public_url, "Serving thumbnails");
+
+    let app = Router::new()
+        .route("/thumbnails/{file}", get(serve_thumbnail))
+        .with_state(Arc::clone(&proxy));
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            warn!(error = ?e, "Thumbnail server stopped");
+        }
+    });
+    Ok(proxy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, RgbImage};
+
+    fn proxy() -> ThumbnailProxy {
+        ThumbnailProxy::new(&Thumbnails {
+            listen: "127.0.0.1:0".into(),
+            public_url: "https://doplarr.example.com/".into(),
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn only_handed_out_posters_are_served() {
+        let proxy = proxy();
+        let url = proxy.url_for("https://image.tmdb.org/poster.jpg");
+        assert_eq!(url, proxy.url_for("https://image.tmdb.org/poster.jpg"));
+        assert!(url.starts_with("https://doplarr.example.com/thumbnails/"));
+
+        assert!(proxy.image("0000000000000000").await.is_none());
+
+        for i in 0..CACHE_ENTRIES {
+            proxy.url_for(&format!("https://image.tmdb.org/{i}.jpg"));
+        }
+        let first = key("https://image.tmdb.org/poster.jpg");
+        assert!(proxy.image(&first).await.is_none(), "oldest entry evicted");
+    }
+
+    #[test]
+    fn posters_shrink_to_the_thumbnail_box() {
+        let mut png = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(RgbImage::new(1000, 1500))
+            .write_to(&mut png, ImageFormat::Png)
+            .unwrap();
+
+        let jpeg = shrink(png.get_ref()).unwrap();
+        let resized = image::load_from_memory(&jpeg).unwrap();
+        assert_eq!((resized.width(), resized.height()), (MAX_WIDTH, MAX_HEIGHT));
+        assert_eq!(
+            image::guess_format(&jpeg).unwrap(),
+            ImageFormat::Jpeg,
+            "re-encoded as JPEG"
+        );
+    }
+}
@@ -0,0 +1,731 @@
+//! Following requests after they're made
+//!
+//! Every logged request the backend can report on carries a [RequestStatus].
+//! A background task checks the download queue for the ones still on their
+//! way, and Radarr/Sonarr webhooks (when `webhook_listen` is set) prompt an
+//! immediate check of the title they're about, so changes show up within
+//! seconds rather than at the next poll. A slower sweep reconciles every
+//! request that still isn't available, however old, against the library (e.g.
+//! a file imported by hand), so nothing depends on webhooks being set up. Once
+//! a title is available, its requesters are mentioned in the channel they
+//! requested it from. Requests made with a need-by date that still aren't
+//! available once it's over are searched for again, and admins told. With
+//! `research_hours` set, so are requests that have had nothing grabbed for
+//! that long, a few times at most. With `stalled_minutes` set, each poll also
+//! reads the backends' download queues, to tell admins about downloads that
+//! stopped moving or that the backend flagged.
+use crate::{
+    announcer::Announcer,
+    config::Tracking,
+    discord::escape_markdown,
+    export::parse_calendar_date,
+    maintenance::parse_day,
+    providers::{DownloadStatus, MediaBackend, MediaId, QueueItem, registry::BackendRegistry},
+    store::{self, RequestStatus, Store},
+};
+use anyhow::Context;
+use axum::{
+    Router,
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header::AUTHORIZATION},
+    routing::post,
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use time::Date;
+use tokio::time::interval;
+use tracing::{Instrument, debug, info, info_span, warn};
+use twilight_model::id::{Id, marker::ChannelMarker};
+
+/// How often to check on requests that aren't available yet (default)
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Requests still not available this long after they were made are left to
+/// the reconciliation sweep, so the backends aren't asked about them as often
+const TRACKING_WINDOW_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// How often every request that isn't available yet is checked, however old
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// How many times each stalled request is searched for again (default)
+const DEFAULT_RESEARCH_LIMIT: u32 = 3;
+
+/// The part of a Radarr/Sonarr webhook we use: what happened, and to which title
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ArrWebhook {
+    event_type: String,
+    movie: Option<WebhookTitle>,
+    series: Option<WebhookTitle>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct WebhookTitle {
+    tmdb_id: Option<i32>,
+    tvdb_id: Option<i32>,
+    imdb_id: Option<String>,
+}
+
+impl ArrWebhook {
+    /// Who the title is, when the event is about one
+    fn media_id(&self) -> Option<MediaId> {
+        let title = self.movie.as_ref().or(self.series.as_ref())?;
+        let id = MediaId {
+            tmdb: title.tmdb_id,
+            tvdb: title.tvdb_id,
+            imdb: title.imdb_id.clone().filter(|id| !id.is_empty()),
+            backend_id: None,
+        };
+        (!id.is_empty()).then_some(id)
+    }
+
+    /// The download needs someone to step in (e.g. it can't be imported)
+    fn is_failure(&self) -> bool {
+        self.event_type == "ManualInteractionRequired"
+    }
+}
+
+/// Where a download queue check puts a request, when it says anything new
+fn status_of(download: &DownloadStatus) -> Option<RequestStatus> {
+    match download {
+        DownloadStatus::Searching => None,
+        DownloadStatus::Grabbed { eta: None } => Some(RequestStatus::Grabbed),
+        DownloadStatus::Grabbed { eta: Some(_) } => Some(RequestStatus::Downloading),
+        DownloadStatus::Downloaded => Some(RequestStatus::Available),
+    }
+}
+
+/// When a request is needed by, from the requester's `need_by` option: a
+/// YYYY-MM-DD date, "today", "tomorrow", or a day of the week (its next
+/// occurrence, today included). The deadline is the end of that day, UTC, as
+/// a Unix timestamp. `None` for anything else, or a day that's already over.
+pub fn parse_need_by(input: &str, today: Date) -> Option<u64> {
+    let input = input.trim();
+    let day = match input.to_lowercase().as_str() {
+        "today" => today,
+        "tomorrow" => today.next_day()?,
+        _ => match parse_day(input) {
+            Ok(weekday) => {
+                let mut day = today;
+                while day.weekday() != weekday {
+                    day = day.next_day()?;
+                }
+                day
+            }
+            Err(_) => parse_calendar_date(input)?,
+        },
+    };
+    if day < today {
+        return None;
+    }
+    u64::try_from(day.next_day()?.midnight().assume_utc().unix_timestamp()).ok()
+}
+
+/// What was last seen of a queued download, to tell when it stops moving
+struct Watched {
+    size_left: u64,
+    /// When `size_left` was first seen at its current value, as a Unix timestamp
+    since: u64,
+    /// Admins were told about the problem the backend flagged
+    told_problem: bool,
+    /// Admins were told it stopped moving, since it last moved
+    told_stalled: bool,
+}
+
+/// The backends' queued downloads, by backend name and queue ID, kept between
+/// polls
+#[derive(Default)]
+struct StallWatch {
+    downloads: HashMap<(String, i32), Watched>,
+}
+
+impl StallWatch {
+    /// Take in a backend's queue as of `now`, returning the downloads to tell
+    /// admins about with what's wrong: the ones the backend flagged, and the
+    /// ones downloading with no progress for `after` seconds. Each problem is
+    /// only returned once.
+    fn observe(
+        &mut self,
+        backend: &str,
+        queue: Vec<QueueItem>,
+        now: u64,
+        after: u64,
+    ) -> Vec<(QueueItem, String)> {
+        self.downloads
+            .retain(|(name, id), _| name != backend || queue.iter().any(|q| q.id == *id));
+        let mut alerts = Vec::new();
+        for item in queue {
+            let watched = self
+                .downloads
+                .entry((backend.to_string(), item.id))
+                .or_insert(Watched {
+                    size_left: item.size_left,
+                    since: now,
+                    told_problem: false,
+                    told_stalled: false,
+                });
+            if watched.size_left != item.size_left {
+                watched.size_left = item.size_left;
+                watched.since = now;
+                watched.told_stalled = false;
+            }
+            let problem = if let Some(problem) = &item.problem {
+                if watched.told_problem {
+                    continue;
+                }
+                watched.told_problem = true;
+                problem.clone()
+            } else if item.downloading
+                && !watched.told_stalled
+                && now.saturating_sub(watched.since) >= after
+            {
+                watched.told_stalled = true;
+                format!("no progress for {} minutes", after / 60)
+            } else {
+                continue;
+            };
+            alerts.push((item, problem));
+        }
+        alerts
+    }
+}
+
+/// A title some logged requests are waiting on: media command, guild, and IDs
+type Title = (String, Option<u64>, MediaId);
+
+struct Tracker {
+    backends: BackendRegistry,
+    store: Arc<Store>,
+    announcer: Announcer,
+    /// Where to tell admins about requests that missed their need-by date
+    admin_channel: Option<Id<ChannelMarker>>,
+    /// How long a request can go with nothing grabbed before it's searched
+    /// for again, when that's on
+    research_after: Option<Duration>,
+    research_limit: u32,
+    /// How long a download can go without progress before admins are told,
+    /// when that's on
+    stalled_after: Option<Duration>,
+    stalls: Mutex<StallWatch>,
+    /// What webhooks must carry as their basic auth password
+    webhook_password: Option<String>,
+}
+
+impl Tracker {
+    /// The titles with requests made since `since` that are still on their way
+    async fn titles(&self, since: u64) -> Vec<Title> {
+        let mut titles: Vec<_> = self
+            .store
+            .requests(|r| r.requested_at >= since && !r.status.is_settled())
+            .await
+            .into_iter()
+            .filter(|r| !r.ids.is_empty())
+            .map(|r| (r.media, r.guild_id, r.ids))
+            .collect();
+        titles.sort_unstable();
+        titles.dedup();
+        titles
+    }
+
+    /// Check on every title with requests made since `since` that are still
+    /// on their way
+    async fn poll(&self, since: u64) {
+        let titles = self.titles(since).await;
+        if !titles.is_empty() {
+            debug!(count = titles.len(), since, "Checking on requested titles");
+        }
+        for (media, guild_id, media_id) in titles {
+            self.check(&media, guild_id, &media_id).await;
+        }
+    }
+
+    /// Ask the title's backend where it stands
+    async fn check(&self, media: &str, guild_id: Option<u64>, media_id: &MediaId) {
+        let Some(instance) = self.backends.get(media, guild_id) else {
+            return;
+        };
+        match instance.backend.download_status(media_id).await {
+            Ok(Some(DownloadStatus::Searching)) => {
+                self.research(media, guild_id, media_id, &*instance.backend)
+                    .await
+            }
+            Ok(Some(download)) => {
+                if let Some(status) = status_of(&download) {
+                    self.record(media, guild_id, media_id, status).await;
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                debug!(error = ?e, media, %media_id, "Couldn't check on a requested title")
+            }
+        }
+    }
+
+    /// Have the backend search again for a title nothing's been grabbed for,
+    /// when its requests have waited long enough and have searches left
+    async fn research(
+        &self,
+        media: &str,
+        guild_id: Option<u64>,
+        media_id: &MediaId,
+        backend: &dyn MediaBackend,
+    ) {
+        let Some(after) = self.research_after else {
+            return;
+        };
+        match self
+            .store
+            .claim_research(
+                media,
+                guild_id,
+                media_id,
+                store::now(),
+                after.as_secs(),
+                self.research_limit,
+            )
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(e) => {
+                warn!(error = ?e, "Failed to save a stalled request's search");
+                return;
+            }
+        }
+        match backend.search_title(media_id).await {
+            Ok(searched) => {
+                info!(media, %media_id, searched, "Searched again for a stalled request")
+            }
+            Err(e) => warn!(error = ?e, media, %media_id, "Couldn't search for a stalled request"),
+        }
+    }
+
+    /// Save a title's new status, telling its requesters once it's available
+    async fn record(
+        &self,
+        media: &str,
+        guild_id: Option<u64>,
+        media_id: &MediaId,
+        status: RequestStatus,
+    ) {
+        let changed = match self
+            .store
+            .update_status(media, guild_id, media_id, status)
+            .await
+        {
+            Ok(changed) => changed,
+            Err(e) => {
+                warn!(error = ?e, "Failed to save request status");
+                return;
+            }
+        };
+        for record in changed {
+            info!(title = %record.title, status = status.label(), "Request status changed");
+            if status == RequestStatus::Available
+                && let Some(channel_id) = record.channel_id
+            {
+                let mentions: Vec<_> = std::iter::once(record.user_id)
+                    .chain(record.subscribers.iter().copied())
+                    .map(|id| format!("<@{id}>"))
+                    .collect();
+                self.announcer.send(
+                    Id::new(channel_id),
+                    format!(
+                        "{} **{}** is available now.",
+                        mentions.join(" "),
+                        escape_markdown(&record.title)
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Search again for the requests that just missed their need-by date,
+    /// telling the admins about each
+    async fn escalate(&self) {
+        let overdue = match self.store.escalate_overdue(store::now()).await {
+            Ok(overdue) => overdue,
+            Err(e) => {
+                warn!(error = ?e, "Failed to save overdue requests");
+                return;
+            }
+        };
+        for record in overdue {
+            let searched = match self.backends.get(&record.media, record.guild_id) {
+                Some(instance) if !record.ids.is_empty() => instance
+                    .backend
+                    .search_title(&record.ids)
+                    .await
+                    .inspect_err(|e| {
+                        warn!(error = ?e, title = %record.title, "Couldn't search for an overdue title")
+                    })
+                    .unwrap_or(false),
+                _ => false,
+            };
+            info!(title = %record.title, searched, "Request missed its need-by date");
+            if let Some(channel_id) = self.admin_channel {
+                let need_by = record.need_by.unwrap_or_default();
+                self.announcer.send(
+                    channel_id,
+                    format!(
+                        "**{}**, requested by <@{}>, was needed by <t:{need_by}:R> but is still {}. {}",
+                        escape_markdown(&record.title),
+                        record.user_id,
+                        record.status.label(),
+                        if searched {
+                            "Searched for it again."
+                        } else {
+                            "It couldn't be searched for again."
+                        }
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Tell the admins about downloads that stopped moving or that the
+    /// backend flagged. Instances behind several media commands are only read
+    /// once.
+    async fn check_queues(&self) {
+        let (Some(after), Some(channel_id)) = (self.stalled_after, self.admin_channel) else {
+            return;
+        };
+        let mut read = Vec::new();
+        for (_, instance) in self.backends.iter() {
+            if !instance.is_enabled() || read.contains(&instance.name) {
+                continue;
+            }
+            read.push(instance.name.clone());
+            let queue = match instance.backend.queue().await {
+                Ok(queue) => queue,
+                Err(e) => {
+                    debug!(error = ?e, backend = instance.name, "Couldn't read the download queue");
+                    continue;
+                }
+            };
+            let alerts = self
+                .stalls
+                .lock()
+                .expect("stall watch lock poisoned")
+                .observe(&instance.name, queue, store::now(), after.as_secs());
+            for (item, problem) in alerts {
+                info!(
+                    backend = instance.name,
+                    title = item.title,
+                    problem,
+                    "Download needs an admin"
+                );
+                self.announcer.send(
+                    channel_id,
+                    format!(
+                        "A download in `{}` needs a look: **{}**: {}",
+                        instance.name,
+                        escape_markdown(&item.title),
+                        escape_markdown(&problem)
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Act on a webhook from the backend instance called `name`
+    async fn webhook(&self, name: &str, event: ArrWebhook) {
+        let Some(media_id) = event.media_id() else {
+            // e.g. the "Test" button in the backend's settings
+            debug!(event = event.event_type, "Webhook isn't about a title");
+            return;
+        };
+        let titles = self
+            .titles(0)
+            .await
+            .into_iter()
+            .filter(|(media, guild_id, id)| {
+                id.matches(&media_id)
+                    && self
+                        .backends
+                        .get(media, *guild_id)
+                        .is_some_and(|b| b.name == name)
+            });
+        for (media, guild_id, media_id) in titles {
+            if event.is_failure() {
+                self.record(&media, guild_id, &media_id, RequestStatus::Failed)
+                    .await;
+            } else {
+                self.check(&media, guild_id, &media_id).await;
+            }
+        }
+    }
+}
+
+/// Whether `headers` carry basic auth with `password`, under any username
+fn authorized(headers: &HeaderMap, password: &str) -> bool {
+    let Some(credentials) = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Basic "))
+        .and_then(|v| STANDARD.decode(v.trim()).ok())
+    else {
+        return false;
+    };
+    let Some(given) = credentials
+        .iter()
+        .position(|&b| b == b':')
+        .map(|colon| &credentials[colon + 1..])
+    else {
+        return false;
+    };
+    // Compare every byte, so the time taken doesn't tell how much matched
+    given.len() == password.len()
+        && given
+            .iter()
+            .zip(password.as_bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn receive_webhook(
+    State(tracker): State<Arc<Tracker>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    if !tracker
+        .webhook_password
+        .as_deref()
+        .is_some_and(|password| authorized(&headers, password))
+    {
+        debug!(
+            backend = name,
+            "Turning away a webhook without the password"
+        );
+        return StatusCode::UNAUTHORIZED;
+    }
+    if !tracker.backends.iter().any(|(_, b)| b.name == name) {
+        return StatusCode::NOT_FOUND;
+    }
+    let event: ArrWebhook = match serde_json::from_slice(&body) {
+        Ok(event) => event,
+        Err(e) => {
+            debug!(error = %e, backend = name, "Ignoring malformed webhook");
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+    debug!(backend = name, event = event.event_type, "Got a webhook");
+    // Answer right away; the backend doesn't need to wait on our checks
+    tokio::spawn(
+        async move { tracker.webhook(&name, event).await }.instrument(info_span!("webhook")),
+    );
+    StatusCode::NO_CONTENT
+}
+
+/// Start following requests in the background, and the webhook server when
+/// one is configured
+pub async fn spawn(
+    config: Option<&Tracking>,
+    backends: BackendRegistry,
+    store: Arc<Store>,
+    announcer: Announcer,
+) -> anyhow::Result<()> {
+    let tracker = Arc::new(Tracker {
+        backends,
+        store,
+        announcer,
+        admin_channel: config.and_then(|c| c.admin_channel_id).map(Id::new),
+        research_after: config
+            .and_then(|c| c.research_hours)
+            .map(|hours| Duration::from_secs(hours.max(1) * 60 * 60)),
+        research_limit: config
+            .and_then(|c| c.research_limit)
+            .unwrap_or(DEFAULT_RESEARCH_LIMIT),
+        stalled_after: config
+            .and_then(|c| c.stalled_minutes)
+            .map(|minutes| Duration::from_secs(minutes.max(1) * 60)),
+        stalls: Mutex::default(),
+        webhook_password: config.and_then(|c| c.webhook_password.clone()),
+    });
+
+    if let Some(listen) = config.and_then(|c| c.webhook_listen.as_deref()) {
+        anyhow::ensure!(
+            tracker.webhook_password.is_some(),
+            "`webhook_listen` needs a `webhook_password` under [tracking], for the Radarr/Sonarr Webhook connections to send"
+        );
+        let listener = tokio::net::TcpListener::bind(listen)
+            .await
+            .with_context(|| format!("Failed to listen for webhooks on {listen}"))?;
+        info!(listen, "Receiving backend webhooks");
+        let app = Router::new()
+            .route("/webhook/{backend}", post(receive_webhook))
+            .with_state(Arc::clone(&tracker));
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                warn!(error = ?e, "Webhook server stopped");
+            }
+        });
+    }
+
+    let every = config
+        .and_then(|c| c.poll_minutes)
+        .map_or(DEFAULT_POLL_INTERVAL, |minutes| {
+            Duration::from_secs(minutes.max(1) * 60)
+        });
+    let mut poll = interval(every);
+    let mut reconcile = interval(RECONCILE_INTERVAL);
+    tokio::spawn(async move {
+        loop {
+            let since = tokio::select! {
+                _ = poll.tick() => store::now().saturating_sub(TRACKING_WINDOW_SECS),
+                _ = reconcile.tick() => 0,
+            };
+            tracker.poll(since).await;
+            tracker.escalate().await;
+            tracker.check_queues().await;
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn need_by_dates_end_with_their_day() {
+        // A Wednesday
+        let today = Date::from_calendar_date(2024, time::Month::March, 6).unwrap();
+        let end_of = |day| {
+            let date = Date::from_calendar_date(2024, time::Month::March, day).unwrap();
+            date.next_day()
+                .unwrap()
+                .midnight()
+                .assume_utc()
+                .unix_timestamp() as u64
+        };
+        assert_eq!(parse_need_by("today", today), Some(end_of(6)));
+        assert_eq!(parse_need_by("Tomorrow", today), Some(end_of(7)));
+        assert_eq!(parse_need_by("friday", today), Some(end_of(8)));
+        assert_eq!(parse_need_by("Wed", today), Some(end_of(6)));
+        assert_eq!(parse_need_by("tue", today), Some(end_of(12)));
+        assert_eq!(parse_need_by(" 2024-03-20 ", today), Some(end_of(20)));
+        assert_eq!(parse_need_by("2024-03-05", today), None);
+        assert_eq!(parse_need_by("soon", today), None);
+    }
+
+    #[test]
+    fn webhooks_need_the_password() {
+        let with = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(AUTHORIZATION, value.parse().unwrap());
+            headers
+        };
+        let basic = |credentials: &str| with(&format!("Basic {}", STANDARD.encode(credentials)));
+        assert!(authorized(&basic("radarr:hunter2"), "hunter2"));
+        assert!(authorized(&basic(":hunter2"), "hunter2"));
+        assert!(authorized(&basic("user:pass:word"), "pass:word"));
+        assert!(!authorized(&basic("radarr:hunter"), "hunter2"));
+        assert!(!authorized(&basic("radarr:hunter22"), "hunter2"));
+        assert!(!authorized(&basic("hunter2"), "hunter2"));
+        assert!(!authorized(&with("Bearer hunter2"), "hunter2"));
+        assert!(!authorized(&with("Basic not base64!"), "hunter2"));
+        assert!(!authorized(&HeaderMap::new(), "hunter2"));
+    }
+
+    #[test]
+    fn stalled_and_flagged_downloads_are_reported_once() {
+        let item = |id, size_left, problem: Option<&str>| QueueItem {
+            id,
+            title: format!("Release {id}"),
+            size_left,
+            downloading: true,
+            problem: problem.map(String::from),
+        };
+        let mut watch = StallWatch::default();
+        let ids = |alerts: Vec<(QueueItem, String)>| -> Vec<i32> {
+            alerts.into_iter().map(|(item, _)| item.id).collect()
+        };
+
+        let first = watch.observe(
+            "radarr",
+            vec![item(1, 500, None), item(2, 0, Some("Import failed"))],
+            0,
+            600,
+        );
+        assert_eq!(first[0].1, "Import failed");
+        assert_eq!(ids(first), [2]);
+        // No progress, but not for long enough yet
+        assert!(
+            watch
+                .observe(
+                    "radarr",
+                    vec![item(1, 500, None), item(2, 0, Some("Import failed"))],
+                    300,
+                    600
+                )
+                .is_empty()
+        );
+        let stalled = watch.observe("radarr", vec![item(1, 500, None)], 600, 600);
+        assert_eq!(stalled[0].1, "no progress for 10 minutes");
+        assert!(
+            watch
+                .observe("radarr", vec![item(1, 500, None)], 1200, 600)
+                .is_empty()
+        );
+        // Moving again, then stuck again
+        assert!(
+            watch
+                .observe("radarr", vec![item(1, 400, None)], 1300, 600)
+                .is_empty()
+        );
+        assert_eq!(
+            ids(watch.observe("radarr", vec![item(1, 400, None)], 1900, 600)),
+            [1]
+        );
+        // Another backend's queue doesn't touch this one's
+        watch.observe("sonarr", vec![], 1900, 600);
+        assert!(watch.downloads.contains_key(&("radarr".to_string(), 1)));
+        // Waiting its turn isn't stalling
+        let queued = QueueItem {
+            downloading: false,
+            ..item(3, 100, None)
+        };
+        assert!(
+            watch
+                .observe("sonarr", vec![queued.clone()], 0, 600)
+                .is_empty()
+        );
+        assert!(watch.observe("sonarr", vec![queued], 5000, 600).is_empty());
+    }
+
+    #[test]
+    fn webhooks_name_their_title() {
+        let radarr: ArrWebhook = serde_json::from_str(
+            r#"{"eventType": "Grab", "movie": {"id": 1, "tmdbId": 438631, "imdbId": "tt1160419"}}"#,
+        )
+        .unwrap();
+        let dune = radarr.media_id().unwrap();
+        assert_eq!(dune.tmdb, Some(438631));
+        assert_eq!(dune.imdb.as_deref(), Some("tt1160419"));
+        let sonarr: ArrWebhook = serde_json::from_str(
+            r#"{"eventType": "ManualInteractionRequired", "series": {"tvdbId": 81189}}"#,
+        )
+        .unwrap();
+        assert_eq!(sonarr.media_id().and_then(|id| id.tvdb), Some(81189));
+        assert!(sonarr.is_failure());
+        let test: ArrWebhook = serde_json::from_str(r#"{"eventType": "Test"}"#).unwrap();
+        assert_eq!(test.media_id(), None);
+
+        assert_eq!(status_of(&DownloadStatus::Searching), None);
+        assert_eq!(
+            status_of(&DownloadStatus::Grabbed {
+                eta: Some(Duration::from_secs(60))
+            }),
+            Some(RequestStatus::Downloading)
+        );
+    }
+}
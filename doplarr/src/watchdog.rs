@@ -0,0 +1,195 @@
+//! Sweeping abandoned request flows, and noticing when they pile up
+//!
+//! Every request flow in progress has an entry in the [InteractionMap] until
+//! it finishes. A background task drops entries older than a flow can live,
+//! and warns (in the log, and in `[watchdog]`'s channel when set) when the map
+//! grows unusually large or a sweep finds many abandoned flows at once: both
+//! are early signs of stuck flows or Discord not delivering clicks.
+use crate::{announcer::Announcer, config::Watchdog, handlers::InteractionMap};
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+use tokio::time::interval;
+use tracing::{debug, info, warn};
+use twilight_model::id::Id;
+
+/// Flows older than this have been abandoned: every step times out sooner
+const INTERACTION_TIMEOUT: Duration = Duration::from_secs(300);
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Flows in progress at once before it's worth a warning, when not configured
+const DEFAULT_MAX_IN_PROGRESS: usize = 50;
+
+/// Flows one sweep can drop before it's worth a warning, when not configured
+const DEFAULT_MAX_ABANDONED: usize = 10;
+
+/// How often admins are notified at most, however long the trouble lasts
+const NOTIFY_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+
+/// Upper bounds of the age buckets in [FlowAges::buckets]; the last bucket
+/// holds everything older
+const AGE_BUCKETS: [Duration; 2] = [Duration::from_secs(60), Duration::from_secs(180)];
+
+/// How many request flows are in progress, and for how long
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FlowAges {
+    pub count: usize,
+    /// Under a minute, one to three minutes, and older
+    pub buckets: [usize; 3],
+    pub oldest: Option<Duration>,
+}
+
+impl FlowAges {
+    pub fn new(ages: impl IntoIterator<Item = Duration>) -> Self {
+        let mut summary = Self::default();
+        for age in ages {
+            summary.count += 1;
+            let bucket = AGE_BUCKETS.iter().take_while(|b| age >= **b).count();
+            summary.buckets[bucket] += 1;
+            summary.oldest = summary.oldest.max(Some(age));
+        }
+        summary
+    }
+
+    /// The flows in `map` right now
+    pub async fn of(map: &InteractionMap) -> Self {
+        let now = Instant::now();
+        let map = map.lock().await;
+        Self::new(
+            map.values()
+                .map(|(_, _, started)| now.duration_since(*started)),
+        )
+    }
+}
+
+impl fmt::Display for FlowAges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [fresh, waiting, old] = self.buckets;
+        write!(
+            f,
+            "{} in progress ({fresh} under 1 min, {waiting} 1-3 min, {old} older)",
+            self.count
+        )?;
+        if let Some(oldest) = self.oldest {
+            write!(f, ", oldest {}s", oldest.as_secs())?;
+        }
+        Ok(())
+    }
+}
+
+/// What's unusual about one sweep, if anything
+fn trouble(
+    ages: &FlowAges,
+    abandoned: usize,
+    max_in_progress: usize,
+    max_abandoned: usize,
+) -> Option<String> {
+    if ages.count > max_in_progress {
+        Some(format!(
+            "{} request flows are in progress at once (more than {max_in_progress})",
+            ages.count
+        ))
+    } else if abandoned > max_abandoned {
+        Some(format!(
+            "{abandoned} request flows were abandoned in the last minute (more than {max_abandoned})"
+        ))
+    } else {
+        None
+    }
+}
+
+/// Start sweeping `interactions` in the background
+pub fn spawn(interactions: InteractionMap, config: Option<&Watchdog>, announcer: Announcer) {
+    let channel = config.and_then(|c| c.channel_id).map(Id::new);
+    let max_in_progress = config
+        .and_then(|c| c.max_in_progress)
+        .unwrap_or(DEFAULT_MAX_IN_PROGRESS);
+    let max_abandoned = config
+        .and_then(|c| c.max_abandoned)
+        .unwrap_or(DEFAULT_MAX_ABANDONED);
+    tokio::spawn(async move {
+        let mut ticker = interval(CLEANUP_INTERVAL);
+        let mut notified: Option<Instant> = None;
+        loop {
+            ticker.tick().await;
+            let now = Instant::now();
+            let (abandoned, ages) = {
+                let mut map = interactions.lock().await;
+                let before_count = map.len();
+                map.retain(|uuid, (_tx, _user_id, timestamp)| {
+                    let age = now.duration_since(*timestamp);
+                    if age > INTERACTION_TIMEOUT {
+                        debug!(uuid = %uuid, age_secs = age.as_secs(), "Cleaning up abandoned interaction");
+                        false
+                    } else {
+                        true
+                    }
+                });
+                let ages = FlowAges::new(map.values().map(|(_, _, t)| now.duration_since(*t)));
+                (before_count - map.len(), ages)
+            };
+
+            if abandoned > 0 {
+                info!("Cleaned up {} abandoned interaction(s)", abandoned);
+            }
+            debug!(flows = %ages, "Request flows");
+
+            let Some(trouble) = trouble(&ages, abandoned, max_in_progress, max_abandoned) else {
+                continue;
+            };
+            warn!(flows = %ages, "{trouble}");
+            if let Some(channel) = channel
+                && notified.is_none_or(|at| at.elapsed() >= NOTIFY_COOLDOWN)
+            {
+                announcer.send(
+                    channel,
+                    format!(
+                        "⚠️ {trouble}. Flows may be stuck, or Discord may not be \
+                         delivering clicks.\n-# {ages}"
+                    ),
+                );
+                notified = Some(now);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flows_are_bucketed_by_age_and_pileups_flagged() {
+        let secs = Duration::from_secs;
+        let ages = FlowAges::new([secs(5), secs(59), secs(60), secs(200)]);
+        assert_eq!(
+            ages,
+            FlowAges {
+                count: 4,
+                buckets: [2, 1, 1],
+                oldest: Some(secs(200)),
+            }
+        );
+        assert_eq!(
+            ages.to_string(),
+            "4 in progress (2 under 1 min, 1 1-3 min, 1 older), oldest 200s"
+        );
+        assert_eq!(
+            FlowAges::new([]).to_string(),
+            "0 in progress (0 under 1 min, 0 1-3 min, 0 older)"
+        );
+
+        assert_eq!(trouble(&ages, 2, 4, 2), None, "at the limits is fine");
+        assert!(
+            trouble(&ages, 2, 3, 2)
+                .unwrap()
+                .contains("4 request flows are in progress")
+        );
+        assert!(
+            trouble(&ages, 3, 4, 2)
+                .unwrap()
+                .contains("3 request flows were abandoned")
+        );
+    }
+}
@@ -213,6 +213,67 @@ pub async fn api_v3_command_id_get(
     }
 }
 
+/// Post a command with a custom payload (for commands with dynamic fields like movieIds)
+/// This is needed because CommandResource doesn't capture command-specific fields
+pub async fn api_v3_command_post_custom<T: serde::Serialize>(
+    configuration: &configuration::Configuration,
+    command_payload: &T,
+) -> Result<models::CommandResource, Error<ApiV3CommandPostError>> {
+    let uri_str = format!("{}/api/v3/command", configuration.base_path);
+    let mut req_builder = configuration
+        .client
+        .request(reqwest::Method::POST, &uri_str);
+
+    if let Some(ref apikey) = configuration.api_key {
+        let key = apikey.key.clone();
+        let value = match apikey.prefix {
+            Some(ref prefix) => format!("{} {}", prefix, key),
+            None => key,
+        };
+        req_builder = req_builder.query(&[("apikey", value)]);
+    }
+    if let Some(ref user_agent) = configuration.user_agent {
+        req_builder = req_builder.header(reqwest::header::USER_AGENT, user_agent.clone());
+    }
+    if let Some(ref apikey) = configuration.api_key {
+        let key = apikey.key.clone();
+        let value = match apikey.prefix {
+            Some(ref prefix) => format!("{} {}", prefix, key),
+            None => key,
+        };
+        req_builder = req_builder.header("X-Api-Key", value);
+    };
+    req_builder = req_builder.json(command_payload);
+
+    let req = req_builder.build()?;
+    let resp = configuration.client.execute(req).await?;
+
+    let status = resp.status();
+    let content_type = resp
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream");
+    let content_type = super::ContentType::from(content_type);
+
+    if !status.is_client_error() && !status.is_server_error() {
+        let content = resp.text().await?;
+        match content_type {
+            ContentType::Json => serde_json::from_str(&content).map_err(Error::from),
+            ContentType::Text => return Err(Error::from(serde_json::Error::custom("Received `text/plain` content type response that cannot be converted to `models::CommandResource`"))),
+            ContentType::Unsupported(unknown_type) => return Err(Error::from(serde_json::Error::custom(format!("Received `{unknown_type}` content type response that cannot be converted to `models::CommandResource`")))),
+        }
+    } else {
+        let content = resp.text().await?;
+        let entity: Option<ApiV3CommandPostError> = serde_json::from_str(&content).ok();
+        Err(Error::ResponseError(ResponseContent {
+            status,
+            content,
+            entity,
+        }))
+    }
+}
+
 pub async fn api_v3_command_post(
     configuration: &configuration::Configuration,
     command_resource: Option<models::CommandResource>,
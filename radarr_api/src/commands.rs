@@ -0,0 +1,81 @@
+/// Command payloads for Radarr API
+/// Reference: https://github.com/Radarr/Radarr/tree/develop/src/NzbDrone.Core
+use serde::Serialize;
+
+/// Minimal RefreshMovie command payload. No ids refreshes the whole library.
+/// Reference: https://github.com/Radarr/Radarr/blob/develop/src/NzbDrone.Core/Movies/Commands/RefreshMovieCommand.cs
+#[derive(Debug, Clone, Serialize)]
+pub struct RefreshMovieCommand {
+    name: String,
+    #[serde(rename = "movieIds")]
+    pub movie_ids: Vec<i32>,
+}
+
+impl RefreshMovieCommand {
+    pub fn new(movie_ids: Vec<i32>) -> Self {
+        Self {
+            name: "RefreshMovie".to_string(),
+            movie_ids,
+        }
+    }
+}
+
+/// RssSync command payload
+/// Reference: https://github.com/Radarr/Radarr/blob/develop/src/NzbDrone.Core/Indexers/RssSyncCommand.cs
+#[derive(Debug, Clone, Serialize)]
+pub struct RssSyncCommand {
+    name: String,
+}
+
+impl RssSyncCommand {
+    pub fn new() -> Self {
+        Self {
+            name: "RssSync".to_string(),
+        }
+    }
+}
+
+impl Default for RssSyncCommand {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// MissingMoviesSearch command payload
+/// Reference: https://github.com/Radarr/Radarr/blob/develop/src/NzbDrone.Core/IndexerSearch/MissingMoviesSearchCommand.cs
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingMoviesSearchCommand {
+    name: String,
+}
+
+impl MissingMoviesSearchCommand {
+    pub fn new() -> Self {
+        Self {
+            name: "MissingMoviesSearch".to_string(),
+        }
+    }
+}
+
+impl Default for MissingMoviesSearchCommand {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// MoviesSearch command payload, searching the indexers for specific movies
+/// Reference: https://github.com/Radarr/Radarr/blob/develop/src/NzbDrone.Core/IndexerSearch/MoviesSearchCommand.cs
+#[derive(Debug, Clone, Serialize)]
+pub struct MoviesSearchCommand {
+    name: String,
+    #[serde(rename = "movieIds")]
+    pub movie_ids: Vec<i32>,
+}
+
+impl MoviesSearchCommand {
+    pub fn new(movie_ids: Vec<i32>) -> Self {
+        Self {
+            name: "MoviesSearch".to_string(),
+            movie_ids,
+        }
+    }
+}
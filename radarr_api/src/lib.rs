@@ -12,4 +12,5 @@ extern crate serde_repr;
 extern crate url;
 
 pub mod apis;
+pub mod commands;
 pub mod models;
@@ -40,3 +40,63 @@ impl SeasonSearchCommand {
         }
     }
 }
+
+/// Minimal RefreshSeries command payload. No id refreshes every series.
+/// Reference: https://github.com/Sonarr/Sonarr/blob/develop/src/NzbDrone.Core/Tv/Commands/RefreshSeriesCommand.cs
+#[derive(Debug, Clone, Serialize)]
+pub struct RefreshSeriesCommand {
+    name: String,
+    #[serde(rename = "seriesId", skip_serializing_if = "Option::is_none")]
+    pub series_id: Option<i32>,
+}
+
+impl RefreshSeriesCommand {
+    pub fn new(series_id: Option<i32>) -> Self {
+        Self {
+            name: "RefreshSeries".to_string(),
+            series_id,
+        }
+    }
+}
+
+/// RssSync command payload
+/// Reference: https://github.com/Sonarr/Sonarr/blob/develop/src/NzbDrone.Core/Indexers/RssSyncCommand.cs
+#[derive(Debug, Clone, Serialize)]
+pub struct RssSyncCommand {
+    name: String,
+}
+
+impl RssSyncCommand {
+    pub fn new() -> Self {
+        Self {
+            name: "RssSync".to_string(),
+        }
+    }
+}
+
+impl Default for RssSyncCommand {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// MissingEpisodeSearch command payload
+/// Reference: https://github.com/Sonarr/Sonarr/blob/develop/src/NzbDrone.Core/IndexerSearch/MissingEpisodeSearchCommand.cs
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingEpisodeSearchCommand {
+    name: String,
+}
+
+impl MissingEpisodeSearchCommand {
+    pub fn new() -> Self {
+        Self {
+            name: "MissingEpisodeSearch".to_string(),
+        }
+    }
+}
+
+impl Default for MissingEpisodeSearchCommand {
+    fn default() -> Self {
+        Self::new()
+    }
+}